@@ -0,0 +1,51 @@
+/*
+ * C interface to the KULIM analyzer (build with `--features ffi`).
+ *
+ * Strings are NUL-terminated UTF-8. Everything returned is owned by the
+ * caller until passed back to its free function. An analyzer may be shared
+ * by threads analyzing at the same time.
+ */
+#ifndef KULIM_H
+#define KULIM_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct KulimAnalyzer KulimAnalyzer;
+
+typedef struct {
+    char *surface;
+    char *pos;
+    char *lemma;
+    /* Character (code point) offsets into the analyzed text */
+    size_t start;
+    size_t end;
+} KulimMorpheme;
+
+typedef struct {
+    KulimMorpheme *morphemes;
+    size_t len;
+    /* Why the analysis failed, or NULL */
+    char *error;
+} KulimAnalysis;
+
+/* Opens a compiled (compile_trie) or saved (save_trie) dictionary. Returns
+ * NULL on failure and sets *error, when error is not NULL. */
+KulimAnalyzer *kulim_open(const char *path, char **error);
+
+KulimAnalysis *kulim_analyze(const KulimAnalyzer *analyzer, const char *text);
+
+void kulim_analysis_free(KulimAnalysis *analysis);
+
+void kulim_string_free(char *s);
+
+void kulim_close(KulimAnalyzer *analyzer);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* KULIM_H */
@@ -0,0 +1,101 @@
+use crate::{Analysis, Morpheme};
+
+// -----------------------------------------------------------------------------
+// Ambiguous Spans
+// -----------------------------------------------------------------------------
+//
+// Two analyses of the same text agree on the morphemes they share (same
+// span, tag and lemma) and disagree on the rest, which is the same stretches
+// of text in both since each spells out the whole input. Those stretches,
+// over every analysis close enough to the best one, are the ambiguous spans.
+
+/// `(start, end, readings)`: a span in character offsets and how the close
+/// analyses read it, as `(morphemes, cost above the best)`.
+pub(crate) type Ambiguity = (usize, usize, Vec<Analysis>);
+
+/// The spans where an analysis in `analyses` (best first) costing at most
+/// `margin` more than the first differs from it, in text order. Spans
+/// where several of them differ are merged when they overlap; each reading
+/// appears once, at the lowest cost it was found with, the best first.
+pub(crate) fn find(analyses: &[Analysis], margin: f64) -> Vec<Ambiguity> {
+    let Some(((best, best_cost), rest)) = analyses.split_first() else {
+        return Vec::new();
+    };
+    let best = spans(best);
+    let mut regions: Vec<(usize, usize, usize, f64)> = Vec::new();
+    for (i, (morphs, cost)) in rest.iter().enumerate() {
+        let gap = cost - best_cost;
+        if gap.is_nan() || gap > margin {
+            continue;
+        }
+        let alt = spans(morphs);
+        let mut open: Option<usize> = None;
+        for (start, end, m) in &best {
+            // Both are in text order with one morpheme per start
+            let shared = alt
+                .binary_search_by_key(start, |&(s, ..)| s)
+                .is_ok_and(|k| (alt[k].1, alt[k].2) == (*end, *m));
+            match shared {
+                true => {
+                    if let Some(from) = open.take() {
+                        regions.push((from, *start, i, gap));
+                    }
+                }
+                false => {
+                    open.get_or_insert(*start);
+                }
+            }
+        }
+        if let (Some(from), Some(&(_, end, _))) = (open, best.last()) {
+            regions.push((from, end, i, gap));
+        }
+    }
+    regions.sort_by_key(|&(start, end, ..)| (start, end));
+
+    let mut out: Vec<Ambiguity> = Vec::new();
+    let mut members: Vec<Vec<(usize, f64)>> = Vec::new();
+    for (start, end, i, gap) in regions {
+        match out.last_mut() {
+            Some(last) if start < last.1 => {
+                last.1 = last.1.max(end);
+                members.last_mut().unwrap().push((i, gap));
+            }
+            _ => {
+                out.push((start, end, Vec::new()));
+                members.push(vec![(i, gap)]);
+            }
+        }
+    }
+    for ((start, end, readings), members) in out.iter_mut().zip(members) {
+        let within = |morphs: &[Morpheme]| -> Vec<Morpheme> {
+            spans(morphs)
+                .into_iter()
+                .filter(|&(s, e, _)| *start <= s && e <= *end)
+                .map(|(_, _, m)| m.clone())
+                .collect()
+        };
+        readings.push((within(&analyses[0].0), 0.0));
+        for (i, gap) in members {
+            let reading = within(&rest[i].0);
+            match readings.iter_mut().find(|(m, _)| *m == reading) {
+                Some(seen) => seen.1 = seen.1.min(gap),
+                None => readings.push((reading, gap)),
+            }
+        }
+        readings[1..].sort_by(|a, b| a.1.total_cmp(&b.1));
+    }
+    out
+}
+
+/// `morphs` with the character span of each.
+fn spans(morphs: &[Morpheme]) -> Vec<(usize, usize, &Morpheme)> {
+    let mut at = 0;
+    morphs
+        .iter()
+        .map(|m| {
+            let start = at;
+            at += m.0.chars().count();
+            (start, at, m)
+        })
+        .collect()
+}
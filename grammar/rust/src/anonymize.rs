@@ -0,0 +1,69 @@
+// -----------------------------------------------------------------------------
+// Anonymization
+// -----------------------------------------------------------------------------
+//
+// Personal data found by analysis replaced by placeholders: person names
+// from the gazetteers (type PER) or, failing those, proper nouns shaped
+// like a Korean name, and the structured tokens of `structured.rs`. The
+// offset map records where each replaced span was and where its
+// placeholder is, so that positions in the redacted text can be traced
+// back.
+
+/// Common surnames, compound ones first.
+const SURNAMES: &[&str] = &[
+    "남궁", "황보", "제갈", "선우", "독고", "서문", "사공", "김", "이", "박", "최", "정", "강",
+    "조", "윤", "장", "임", "한", "오", "서", "신", "권", "황", "안", "송", "류", "전", "홍", "고",
+    "문", "양", "손", "배", "백", "허", "유", "남", "심", "노", "하", "곽", "성", "차", "주", "우",
+    "구", "민", "진", "나", "지", "엄", "채", "원", "천", "방", "공", "현", "함", "변", "염", "여",
+    "추", "도", "소", "석", "선", "설", "마", "길", "연", "위", "표", "명", "기", "반", "왕", "금",
+    "옥", "육", "인", "맹", "제", "모", "탁", "국", "어", "은", "편", "용", "예", "경", "봉", "승",
+];
+
+/// Whether a proper noun looks like a person's name: a surname and a
+/// two-syllable given name (김철수), or a compound surname and one or two
+/// (남궁민). Two-syllable names like 김구 are left out, being as often
+/// places or brands (서울, 한라).
+pub(crate) fn is_person_name(surface: &str) -> bool {
+    if !surface.chars().all(|c| ('가'..='힣').contains(&c)) {
+        return false;
+    }
+    SURNAMES.iter().any(|surname| {
+        let given = surface
+            .strip_prefix(surname)
+            .map_or(0, |g| g.chars().count());
+        match surname.chars().count() {
+            1 => given == 2,
+            _ => (1..=2).contains(&given),
+        }
+    })
+}
+
+/// A replaced span and its placeholder: `(start, end, new start, new end,
+/// type)`.
+pub(crate) type Replacement = (usize, usize, usize, usize, String);
+
+/// `chars` with each of `spans` (`(start, end, type)`, in order and
+/// non-overlapping) replaced by `placeholder`, its `{}` filled with the
+/// type; and the replacement of each span.
+pub(crate) fn redact(
+    chars: &[char],
+    spans: &[(usize, usize, String)],
+    placeholder: &str,
+) -> (String, Vec<Replacement>) {
+    let mut out = String::with_capacity(chars.len());
+    let mut map = Vec::with_capacity(spans.len());
+    let mut next = 0;
+    let mut written = 0;
+    for (start, end, kind) in spans {
+        out.extend(&chars[next..*start]);
+        written += start - next;
+        let replacement = placeholder.replace("{}", kind);
+        let len = replacement.chars().count();
+        out.push_str(&replacement);
+        map.push((*start, *end, written, written + len, kind.clone()));
+        written += len;
+        next = *end;
+    }
+    out.extend(&chars[next..]);
+    (out, map)
+}
@@ -0,0 +1,486 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::{Located, RustTrie};
+
+// -----------------------------------------------------------------------------
+// Arrow IPC Output
+// -----------------------------------------------------------------------------
+//
+// Analyses written as an Arrow IPC file (Feather v2), one row per morpheme,
+// so that analysts can `pyarrow.memory_map` the result instead of building
+// a Python tuple per morpheme:
+//
+//     doc: int64, surface: utf8, pos: utf8, lemma: utf8,
+//     start: int64, end: int64          character offsets into the document
+//
+// No column is nullable. The file is the magic `ARROW1`, the schema message,
+// one record batch message per `batch_rows` rows, the end-of-stream marker
+// and a footer locating the batches. Messages are flatbuffers (built by the
+// small back-to-front `Builder` below) prefixed with 0xFFFFFFFF and their
+// length, each followed by its body of 8-byte aligned buffers.
+
+const MAGIC: &[u8] = b"ARROW1";
+// MetadataVersion.V5
+const METADATA_VERSION: i16 = 4;
+// MessageHeader union members
+const HEADER_SCHEMA: u8 = 1;
+const HEADER_RECORD_BATCH: u8 = 3;
+// Type union members
+const TYPE_INT: u8 = 2;
+const TYPE_UTF8: u8 = 5;
+
+/// Column names, and whether each holds strings (or else int64s).
+const COLUMNS: [(&str, bool); 6] = [
+    ("doc", false),
+    ("surface", true),
+    ("pos", true),
+    ("lemma", true),
+    ("start", false),
+    ("end", false),
+];
+
+/// A table field: a little-endian scalar or an offset to an object.
+enum Field {
+    Scalar(Vec<u8>),
+    Offset(usize),
+}
+
+/// A flatbuffer built back to front, as the format expects: objects are
+/// written before the tables that refer to them, and a position is the
+/// distance from the end of the finished buffer. Metadata is small, so
+/// bytes are simply prepended.
+#[derive(Default)]
+struct Builder {
+    buf: Vec<u8>,
+}
+
+impl Builder {
+    fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn prepend(&mut self, bytes: &[u8]) {
+        self.buf.splice(0..0, bytes.iter().copied());
+    }
+
+    /// Pads so that `size` bytes prepended next end up aligned to `align`.
+    fn align(&mut self, size: usize, align: usize) {
+        let pad = (align - (self.len() + size) % align) % align;
+        self.prepend(&vec![0; pad]);
+    }
+
+    fn offset(&mut self, target: usize) -> usize {
+        self.align(4, 4);
+        let at = self.len() + 4;
+        self.prepend(&((at - target) as u32).to_le_bytes());
+        at
+    }
+
+    fn string(&mut self, s: &str) -> usize {
+        self.align(s.len() + 1, 4);
+        self.prepend(&[0]);
+        self.prepend(s.as_bytes());
+        self.prepend(&(s.len() as u32).to_le_bytes());
+        self.len()
+    }
+
+    fn offsets(&mut self, targets: &[usize]) -> usize {
+        self.align(targets.len() * 4, 4);
+        for &target in targets.iter().rev() {
+            self.offset(target);
+        }
+        self.prepend(&(targets.len() as u32).to_le_bytes());
+        self.len()
+    }
+
+    /// A vector of `count` structs of 8-byte alignment, laid out in `data`.
+    fn structs(&mut self, data: &[u8], count: usize) -> usize {
+        self.align(data.len(), 8);
+        self.prepend(data);
+        self.prepend(&(count as u32).to_le_bytes());
+        self.len()
+    }
+
+    /// A table of `(slot, field)`; absent slots take their defaults.
+    fn table(&mut self, fields: &[(usize, Field)]) -> usize {
+        let start = self.len();
+        let slots = fields.iter().map(|&(slot, _)| slot + 1).max().unwrap_or(0);
+        let mut at = vec![0usize; slots];
+        // Widest scalars first, so that none needs padding inside the table
+        let mut order: Vec<&(usize, Field)> = fields.iter().collect();
+        order.sort_by_key(|(_, field)| match field {
+            Field::Scalar(bytes) => std::cmp::Reverse(bytes.len()),
+            Field::Offset(_) => std::cmp::Reverse(4),
+        });
+        for (slot, field) in order {
+            at[*slot] = match field {
+                Field::Scalar(bytes) => {
+                    self.align(bytes.len(), bytes.len());
+                    self.prepend(bytes);
+                    self.len()
+                }
+                Field::Offset(target) => self.offset(*target),
+            };
+        }
+        self.align(4, 4);
+        self.prepend(&[0; 4]);
+        let table = self.len();
+
+        let mut vtable = Vec::with_capacity(4 + slots * 2);
+        vtable.extend_from_slice(&((4 + slots * 2) as u16).to_le_bytes());
+        vtable.extend_from_slice(&((table - start) as u16).to_le_bytes());
+        for &field in &at {
+            let offset = if field == 0 { 0 } else { table - field };
+            vtable.extend_from_slice(&(offset as u16).to_le_bytes());
+        }
+        self.align(vtable.len(), 2);
+        self.prepend(&vtable);
+        // The table starts with the distance back to its vtable
+        let (vtable, at) = (self.len(), self.len() - table);
+        self.buf[at..at + 4].copy_from_slice(&((vtable - table) as i32).to_le_bytes());
+        table
+    }
+
+    fn finish(mut self, root: usize) -> Vec<u8> {
+        self.align(4, 8);
+        self.offset(root);
+        self.buf
+    }
+}
+
+fn scalar<const N: usize>(bytes: [u8; N]) -> Field {
+    Field::Scalar(bytes.to_vec())
+}
+
+fn schema(b: &mut Builder) -> usize {
+    let fields: Vec<usize> = COLUMNS
+        .iter()
+        .map(|&(name, utf8)| {
+            let name = b.string(name);
+            let (type_id, ty) = match utf8 {
+                true => (TYPE_UTF8, b.table(&[])),
+                false => (
+                    TYPE_INT,
+                    b.table(&[(0, scalar(64i32.to_le_bytes())), (1, scalar([1]))]),
+                ),
+            };
+            let children = b.offsets(&[]);
+            b.table(&[
+                (0, Field::Offset(name)),
+                (1, scalar([0])),
+                (2, scalar([type_id])),
+                (3, Field::Offset(ty)),
+                (5, Field::Offset(children)),
+            ])
+        })
+        .collect();
+    let fields = b.offsets(&fields);
+    b.table(&[(0, scalar(0i16.to_le_bytes())), (1, Field::Offset(fields))])
+}
+
+fn message(
+    header_type: u8,
+    header: impl FnOnce(&mut Builder) -> usize,
+    body_len: usize,
+) -> Vec<u8> {
+    let mut b = Builder::default();
+    let header = header(&mut b);
+    let root = b.table(&[
+        (0, scalar(METADATA_VERSION.to_le_bytes())),
+        (1, scalar([header_type])),
+        (2, Field::Offset(header)),
+        (3, scalar((body_len as i64).to_le_bytes())),
+    ]);
+    b.finish(root)
+}
+
+/// Where a record batch lies in the file, for the footer.
+struct Block {
+    offset: u64,
+    meta_len: usize,
+    body_len: usize,
+}
+
+#[derive(Default)]
+struct StringColumn {
+    offsets: Vec<i32>,
+    data: Vec<u8>,
+}
+
+impl StringColumn {
+    fn push(&mut self, s: &str) {
+        if self.offsets.is_empty() {
+            self.offsets.push(0);
+        }
+        self.data.extend_from_slice(s.as_bytes());
+        self.offsets.push(self.data.len() as i32);
+    }
+}
+
+/// The rows of the record batch being filled.
+#[derive(Default)]
+struct Rows {
+    doc: Vec<i64>,
+    strings: [StringColumn; 3],
+    start: Vec<i64>,
+    end: Vec<i64>,
+}
+
+impl Rows {
+    fn len(&self) -> usize {
+        self.doc.len()
+    }
+
+    /// The batch's body and its record batch header: a node per column,
+    /// then for each column an empty validity buffer and its data.
+    fn encode(&self) -> (Vec<u8>, Vec<u8>) {
+        let mut body = Vec::new();
+        let mut buffers = Vec::new();
+        let mut push = |bytes: &[u8]| {
+            buffers.extend_from_slice(&(body.len() as i64).to_le_bytes());
+            buffers.extend_from_slice(&(bytes.len() as i64).to_le_bytes());
+            body.extend_from_slice(bytes);
+            body.resize(body.len().div_ceil(8) * 8, 0);
+        };
+        let ints =
+            |column: &[i64]| -> Vec<u8> { column.iter().flat_map(|v| v.to_le_bytes()).collect() };
+        let mut strings = self.strings.iter();
+        for &(name, utf8) in &COLUMNS {
+            push(&[]);
+            if utf8 {
+                let column = strings.next().expect("a column per string field");
+                let offsets: Vec<u8> = match column.offsets.is_empty() {
+                    true => 0i32.to_le_bytes().to_vec(),
+                    false => column
+                        .offsets
+                        .iter()
+                        .flat_map(|v| v.to_le_bytes())
+                        .collect(),
+                };
+                push(&offsets);
+                push(&column.data);
+                continue;
+            }
+            push(&ints(match name {
+                "doc" => &self.doc,
+                "start" => &self.start,
+                _ => &self.end,
+            }));
+        }
+        let rows = self.len() as i64;
+        let nodes: Vec<u8> = COLUMNS
+            .iter()
+            .flat_map(|_| rows.to_le_bytes().into_iter().chain(0i64.to_le_bytes()))
+            .collect();
+        let meta = message(
+            HEADER_RECORD_BATCH,
+            |b| {
+                let nodes = b.structs(&nodes, COLUMNS.len());
+                let buffers = b.structs(&buffers, buffers.len() / 16);
+                b.table(&[
+                    (0, scalar(rows.to_le_bytes())),
+                    (1, Field::Offset(nodes)),
+                    (2, Field::Offset(buffers)),
+                ])
+            },
+            body.len(),
+        );
+        (meta, body)
+    }
+}
+
+/// An Arrow IPC file being written, a record batch at a time.
+pub(crate) struct ArrowFile {
+    out: BufWriter<File>,
+    pos: u64,
+    blocks: Vec<Block>,
+    rows: Rows,
+    batch_rows: usize,
+}
+
+impl ArrowFile {
+    pub fn create(path: &str, batch_rows: usize) -> io::Result<Self> {
+        let mut file = ArrowFile {
+            out: BufWriter::new(File::create(path)?),
+            pos: 0,
+            blocks: Vec::new(),
+            rows: Rows::default(),
+            batch_rows: batch_rows.max(1),
+        };
+        file.write(MAGIC)?;
+        file.write(&[0, 0])?;
+        file.write_message(&message(HEADER_SCHEMA, schema, 0), &[])?;
+        Ok(file)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.pos += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn write_message(&mut self, meta: &[u8], body: &[u8]) -> io::Result<Block> {
+        let offset = self.pos;
+        let padded = meta.len().div_ceil(8) * 8;
+        self.write(&u32::MAX.to_le_bytes())?;
+        self.write(&(padded as i32).to_le_bytes())?;
+        self.write(meta)?;
+        self.write(&vec![0; padded - meta.len()])?;
+        self.write(body)?;
+        Ok(Block {
+            offset,
+            meta_len: 8 + padded,
+            body_len: body.len(),
+        })
+    }
+
+    /// Adds a row for morpheme `m` of document `doc`.
+    pub fn push(&mut self, doc: usize, m: &Located) -> io::Result<()> {
+        let (surface, pos, lemma, start, end) = m;
+        let rows = &mut self.rows;
+        rows.doc.push(doc as i64);
+        for (column, s) in rows.strings.iter_mut().zip([surface, pos, lemma]) {
+            column.push(s);
+        }
+        rows.start.push(*start as i64);
+        rows.end.push(*end as i64);
+        // String offsets are 32-bit; a batch never holds 2 GiB of text
+        let full = rows
+            .strings
+            .iter()
+            .any(|c| c.data.len() > i32::MAX as usize / 2);
+        if rows.len() >= self.batch_rows || full {
+            self.flush_batch()?;
+        }
+        Ok(())
+    }
+
+    fn flush_batch(&mut self) -> io::Result<()> {
+        if self.rows.len() == 0 {
+            return Ok(());
+        }
+        let (meta, body) = std::mem::take(&mut self.rows).encode();
+        let block = self.write_message(&meta, &body)?;
+        self.blocks.push(block);
+        Ok(())
+    }
+
+    /// Writes the last batch and the footer.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.flush_batch()?;
+        self.write(&u32::MAX.to_le_bytes())?;
+        self.write(&0u32.to_le_bytes())?;
+
+        let mut blocks = Vec::with_capacity(self.blocks.len() * 24);
+        for block in &self.blocks {
+            blocks.extend_from_slice(&block.offset.to_le_bytes());
+            blocks.extend_from_slice(&(block.meta_len as i32).to_le_bytes());
+            blocks.extend_from_slice(&[0; 4]);
+            blocks.extend_from_slice(&(block.body_len as i64).to_le_bytes());
+        }
+        let mut b = Builder::default();
+        let schema = schema(&mut b);
+        let dictionaries = b.structs(&[], 0);
+        let batches = b.structs(&blocks, self.blocks.len());
+        let root = b.table(&[
+            (0, scalar(METADATA_VERSION.to_le_bytes())),
+            (1, Field::Offset(schema)),
+            (2, Field::Offset(dictionaries)),
+            (3, Field::Offset(batches)),
+        ]);
+        let footer = b.finish(root);
+        self.write(&footer)?;
+        self.write(&(footer.len() as i32).to_le_bytes())?;
+        self.write(MAGIC)?;
+        self.out.flush()
+    }
+}
+
+/// Streams analyses into an Arrow IPC (Feather v2) file, one row per
+/// morpheme: `doc`, `surface`, `pos`, `lemma`, `start`, `end`. Whitespace
+/// is left out; `doc` numbers the texts of every `write` in order. The file
+/// is complete once `close` is called, or the `with` block exits.
+#[pyclass]
+pub(crate) struct ArrowResultWriter {
+    file: Option<ArrowFile>,
+    path: String,
+    docs: usize,
+}
+
+impl ArrowResultWriter {
+    fn file(&mut self) -> PyResult<&mut ArrowFile> {
+        self.file
+            .as_mut()
+            .ok_or_else(|| PyValueError::new_err(format!("{}: writer is closed", self.path)))
+    }
+}
+
+#[pymethods]
+impl ArrowResultWriter {
+    #[new]
+    #[pyo3(signature = (path, batch_rows=65536))]
+    fn new(path: String, batch_rows: usize) -> PyResult<Self> {
+        let file = ArrowFile::create(&path, batch_rows)
+            .map_err(|e| PyValueError::new_err(format!("{}: {}", path, e)))?;
+        Ok(ArrowResultWriter {
+            file: Some(file),
+            path,
+            docs: 0,
+        })
+    }
+
+    /// Analyzes `texts` with `trie` and appends their morphemes; returns the
+    /// number of rows added. A text that fails to analyze raises, keeping
+    /// the rows of the texts before it.
+    #[pyo3(signature = (trie, texts, layers=None))]
+    fn write(
+        &mut self,
+        trie: &RustTrie,
+        texts: Vec<String>,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<usize> {
+        trie.view(layers.as_deref())?;
+        let path = self.path.clone();
+        let io = |e: io::Error| PyValueError::new_err(format!("{}: {}", path, e));
+        let mut rows = 0;
+        for text in &texts {
+            crate::check_signals()?;
+            let (morphs, _) =
+                trie.analyze_located(&trie.prepare(text, false), layers.as_deref())?;
+            let doc = self.docs;
+            let file = self.file()?;
+            for m in morphs
+                .iter()
+                .filter(|m| !m.0.chars().all(char::is_whitespace))
+            {
+                file.push(doc, m).map_err(io)?;
+                rows += 1;
+            }
+            self.docs += 1;
+        }
+        Ok(rows)
+    }
+
+    /// Writes the footer; the writer takes no more rows.
+    fn close(&mut self) -> PyResult<()> {
+        match self.file.take() {
+            Some(file) => file
+                .finish()
+                .map_err(|e| PyValueError::new_err(format!("{}: {}", self.path, e))),
+            None => Ok(()),
+        }
+    }
+
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __exit__(&mut self, _ty: &PyAny, _value: &PyAny, _traceback: &PyAny) -> PyResult<bool> {
+        self.close()?;
+        Ok(false)
+    }
+}
@@ -0,0 +1,51 @@
+use pyo3::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Per-Item Batch Errors
+// -----------------------------------------------------------------------------
+
+/// Stands in for the result of a batch item that failed, so the other items
+/// still come back. Interrupts are not caught and abort the whole batch.
+#[pyclass]
+pub(crate) struct BatchError {
+    /// Position of the failed item in the input list
+    #[pyo3(get)]
+    index: usize,
+    /// Name of the exception type, e.g. `"TypeError"`
+    #[pyo3(get)]
+    kind: String,
+    #[pyo3(get)]
+    message: String,
+    /// The exception itself, for re-raising
+    #[pyo3(get)]
+    error: PyObject,
+}
+
+impl BatchError {
+    pub fn from_err(py: Python, index: usize, err: PyErr) -> PyResult<PyObject> {
+        let kind = err.get_type(py).name()?.to_string();
+        let message = err.value(py).to_string();
+        let error = err.into_value(py).into_py(py);
+        let item = BatchError {
+            index,
+            kind,
+            message,
+            error,
+        };
+        Ok(Py::new(py, item)?.into_py(py))
+    }
+}
+
+#[pymethods]
+impl BatchError {
+    fn __repr__(&self) -> String {
+        format!(
+            "BatchError(index={}, kind={:?}, message={:?})",
+            self.index, self.kind, self.message
+        )
+    }
+
+    fn __str__(&self) -> String {
+        format!("item {}: {}: {}", self.index, self.kind, self.message)
+    }
+}
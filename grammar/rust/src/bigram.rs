@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::Morpheme;
+
+// -----------------------------------------------------------------------------
+// POS Bigram Statistics
+// -----------------------------------------------------------------------------
+
+pub(crate) const BOS: &str = "BOS";
+pub(crate) const EOS: &str = "EOS";
+
+/// POS-transition counts over analyzed sentences, with `BOS`/`EOS` marking
+/// sentence edges. Whitespace morphemes are skipped, so transitions across
+/// a space count like any other.
+#[derive(Default)]
+pub(crate) struct BigramCounts {
+    pub counts: HashMap<(String, String), u64>,
+}
+
+impl BigramCounts {
+    pub fn add(&mut self, morphs: &[Morpheme]) {
+        let tags = morphs
+            .iter()
+            .filter(|m| !m.0.chars().all(char::is_whitespace))
+            .map(|m| m.1.as_str());
+        let mut prev = BOS;
+        for tag in tags.chain(std::iter::once(EOS)) {
+            *self
+                .counts
+                .entry((prev.to_string(), tag.to_string()))
+                .or_default() += 1;
+            prev = tag;
+        }
+    }
+
+    /// Rows as `(prev, next, count)`, most frequent first.
+    pub fn rows(&self) -> Vec<(&str, &str, u64)> {
+        let mut rows: Vec<_> = self
+            .counts
+            .iter()
+            .map(|((a, b), &c)| (a.as_str(), b.as_str(), c))
+            .collect();
+        rows.sort_by(|x, y| y.2.cmp(&x.2).then_with(|| (x.0, x.1).cmp(&(y.0, y.1))));
+        rows
+    }
+
+    /// Writes `prev<TAB>next<TAB>count` lines, most frequent first.
+    pub fn write_tsv(&self, path: &str) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        let mut w = BufWriter::new(file);
+        for (a, b, c) in self.rows() {
+            writeln!(w, "{}\t{}\t{}", a, b, c).map_err(|e| e.to_string())?;
+        }
+        w.flush().map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,188 @@
+//! `kulim-dictc`: compiles raw lexicon files into the binary dictionary
+//! format read by `load_compiled`, without a Python environment.
+//!
+//!     kulim-dictc -o dict.kfz lexicon.tsv user.csv mecab/NNG.csv
+
+use std::process::ExitCode;
+
+use kulim_rust::compile::{Compiler, Format, Profile, TagSet, UnknownTag, STANDARD_TAGS};
+
+const USAGE: &str = "\
+usage: kulim-dictc [options] -o OUTPUT INPUT...
+
+Compiles lexicon files into a KULIM compiled dictionary.
+
+options:
+  -o, --output PATH   where to write the compiled dictionary
+  -f, --format FMT    tsv, csv or mecab (default: from the extension; .csv
+                      files with mecab-ko-dic rows are read as mecab)
+      --no-expand     do not add stems and contracted or irregular forms
+                      (갔, 들) for predicates given as 가다/VV
+      --source TAG    record TAG as the source of every entry
+      --keep-going    skip invalid lines instead of failing
+      --tags PATH     extra POS tags to accept, one per line, besides the
+                      standard TTA tag set
+      --unknown-tags POLICY
+                      warn (default), reject or allow entries whose tag is
+                      not in the tag set
+      --profile NAME  full (default), or small for devices short of memory:
+                      quantized costs and no entry metadata
+      --max-cost COST leave out entries costing more than COST, by their own
+                      cost or the default one for their length and tag
+  -h, --help          show this help";
+
+struct Args {
+    output: String,
+    format: Option<Format>,
+    expand: bool,
+    source: Option<String>,
+    keep_going: bool,
+    tags: TagSet,
+    profile: Profile,
+    max_cost: Option<f64>,
+    inputs: Vec<String>,
+}
+
+fn parse_args() -> Result<Option<Args>, String> {
+    let mut args = std::env::args().skip(1);
+    let mut output = None;
+    let mut format = None;
+    let mut expand = true;
+    let mut source = None;
+    let mut keep_going = false;
+    let mut extra_tags = Vec::new();
+    let mut unknown = UnknownTag::Warn;
+    let mut profile = Profile::Full;
+    let mut max_cost = None;
+    let mut inputs = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-o" | "--output" => {
+                output = Some(args.next().ok_or("missing value for --output")?);
+            }
+            "-f" | "--format" => {
+                let name = args.next().ok_or("missing value for --format")?;
+                format = Some(Format::parse(&name).ok_or(format!("unknown format '{}'", name))?);
+            }
+            "--no-expand" => expand = false,
+            "--source" => source = Some(args.next().ok_or("missing value for --source")?),
+            "--keep-going" => keep_going = true,
+            "--tags" => {
+                let path = args.next().ok_or("missing value for --tags")?;
+                let text =
+                    std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+                extra_tags.extend(
+                    text.lines()
+                        .map(str::trim)
+                        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                        .map(str::to_string),
+                );
+            }
+            "--unknown-tags" => {
+                let name = args.next().ok_or("missing value for --unknown-tags")?;
+                unknown =
+                    UnknownTag::parse(&name).ok_or(format!("unknown tag policy '{}'", name))?;
+            }
+            "--profile" => {
+                let name = args.next().ok_or("missing value for --profile")?;
+                profile = Profile::parse(&name).ok_or(format!("unknown profile '{}'", name))?;
+            }
+            "--max-cost" => {
+                let value = args.next().ok_or("missing value for --max-cost")?;
+                let cost = value.parse::<f64>().ok().filter(|c| c.is_finite());
+                max_cost = Some(cost.ok_or(format!("bad cost '{}'", value))?);
+            }
+            flag if flag.starts_with('-') && flag != "-" => {
+                return Err(format!("unknown option '{}'", flag));
+            }
+            _ => inputs.push(arg),
+        }
+    }
+    let output = output.ok_or("no output given (-o)")?;
+    if inputs.is_empty() {
+        return Err("no input files given".to_string());
+    }
+    Ok(Some(Args {
+        output,
+        format,
+        expand,
+        source,
+        keep_going,
+        tags: TagSet::new(
+            STANDARD_TAGS
+                .iter()
+                .map(|t| t.to_string())
+                .chain(extra_tags),
+            unknown,
+        ),
+        profile,
+        max_cost,
+        inputs,
+    }))
+}
+
+fn run(args: &Args) -> Result<bool, String> {
+    let mut compiler = Compiler::default();
+    let mut failed = false;
+    for path in &args.inputs {
+        for err in compiler.load(
+            path,
+            args.format,
+            args.expand,
+            args.source.as_deref(),
+            &args.tags,
+        )? {
+            eprintln!("{}", err);
+            failed = true;
+        }
+    }
+    if failed && !args.keep_going {
+        eprintln!(
+            "kulim-dictc: invalid lines found, nothing written (use --keep-going to skip them)"
+        );
+        return Ok(false);
+    }
+    for (tag, count) in &compiler.stats.unknown_tags {
+        eprintln!(
+            "kulim-dictc: warning: {} entries with unknown POS tag '{}' (add it with --tags)",
+            count, tag
+        );
+    }
+    let pruned = args.max_cost.map_or(0, |max| compiler.prune(max));
+    compiler.write(&args.output, args.profile)?;
+    let stats = &compiler.stats;
+    eprintln!(
+        "kulim-dictc: {} surfaces, {} patterns from {} entries ({} expanded, {} skipped, {} pruned) -> {}",
+        compiler.surfaces(),
+        compiler.patterns(),
+        stats.entries,
+        stats.expanded,
+        stats.skipped,
+        pruned,
+        args.output
+    );
+    Ok(true)
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("kulim-dictc: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("kulim-dictc: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
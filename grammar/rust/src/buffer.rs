@@ -0,0 +1,97 @@
+use std::ffi::{c_char, c_int, c_void};
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+
+use pyo3::ffi::PyObject;
+use pyo3::prelude::*;
+
+// -----------------------------------------------------------------------------
+// Buffer Protocol
+// -----------------------------------------------------------------------------
+//
+// Direct access to the memory of any object exporting a buffer (bytes,
+// bytearray, memoryview, mmap, NumPy arrays), so that dictionaries are
+// serialized into and read from it in place. pyo3's `PyBuffer` is not
+// available under the 3.10 stable ABI this module is built for, so the two
+// calls are declared here; `Py_buffer` has kept this layout since Python
+// 3.3 and is in the stable ABI from 3.11.
+
+#[repr(C)]
+struct RawBuffer {
+    buf: *mut c_void,
+    obj: *mut PyObject,
+    len: isize,
+    itemsize: isize,
+    readonly: c_int,
+    ndim: c_int,
+    format: *mut c_char,
+    shape: *mut isize,
+    strides: *mut isize,
+    suboffsets: *mut isize,
+    internal: *mut c_void,
+}
+
+extern "C" {
+    fn PyObject_GetBuffer(obj: *mut PyObject, view: *mut RawBuffer, flags: c_int) -> c_int;
+    fn PyBuffer_Release(view: *mut RawBuffer);
+}
+
+// A C-contiguous run of bytes, optionally writable
+const PYBUF_SIMPLE: c_int = 0;
+const PYBUF_WRITABLE: c_int = 0x0001;
+
+/// The exported bytes of a Python object, released on drop. Objects that
+/// cannot give one contiguous run of bytes raise `BufferError`.
+pub(crate) struct Buffer<'py> {
+    raw: RawBuffer,
+    // Held with the GIL, which release needs
+    gil: PhantomData<Python<'py>>,
+}
+
+impl<'py> Buffer<'py> {
+    pub fn get(obj: &'py PyAny) -> PyResult<Self> {
+        Self::with_flags(obj, PYBUF_SIMPLE)
+    }
+
+    pub fn get_mut(obj: &'py PyAny) -> PyResult<Self> {
+        Self::with_flags(obj, PYBUF_WRITABLE)
+    }
+
+    fn with_flags(obj: &'py PyAny, flags: c_int) -> PyResult<Self> {
+        let mut raw = MaybeUninit::<RawBuffer>::zeroed();
+        // SAFETY: the GIL is held and `raw` has room for a Py_buffer
+        if unsafe { PyObject_GetBuffer(obj.as_ptr(), raw.as_mut_ptr(), flags) } != 0 {
+            return Err(PyErr::fetch(obj.py()));
+        }
+        Ok(Buffer {
+            raw: unsafe { raw.assume_init() },
+            gil: PhantomData,
+        })
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        match self.raw.len {
+            0 => &[],
+            // SAFETY: the exporter keeps `len` bytes at `buf` until release
+            len => unsafe { std::slice::from_raw_parts(self.raw.buf as *const u8, len as usize) },
+        }
+    }
+
+    /// Writable only for buffers from `get_mut`.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        assert!(self.raw.readonly == 0, "buffer is read-only");
+        match self.raw.len {
+            0 => &mut [],
+            // SAFETY: as above, and the exporter granted write access
+            len => unsafe { std::slice::from_raw_parts_mut(self.raw.buf as *mut u8, len as usize) },
+        }
+    }
+}
+
+impl Drop for Buffer<'_> {
+    fn drop(&mut self) {
+        // SAFETY: `raw` came from a successful PyObject_GetBuffer, and the
+        // GIL is held for 'py
+        unsafe { PyBuffer_Release(&mut self.raw) }
+    }
+}
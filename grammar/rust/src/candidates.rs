@@ -0,0 +1,242 @@
+use std::borrow::Cow;
+
+use crate::casefold::CaseIndex;
+use crate::chartype::{CharClass, OovGrouping};
+use crate::decoder::{Edge, EdgeKind};
+use crate::dict::MAX_WORD_LEN;
+use crate::entropy::EntropyStats;
+use crate::grammar::{Grammar, Unknown};
+use crate::scoring;
+use crate::view::{DictView, PatternRef};
+
+// -----------------------------------------------------------------------------
+// Candidate Sources
+// -----------------------------------------------------------------------------
+//
+// The lattice asks each source in turn for the edges starting at a position:
+// dictionary matches, unknown script runs, the per-cluster fallback, corpus
+// spans. A new kind of candidate (a pattern matcher, an irregular-conjugation
+// rule, edges computed in Python) is one more source in the list given to
+// `Lattice::build_from`, without touching the others.
+
+/// The text being analyzed, as every source sees it.
+pub(crate) struct Text<'t> {
+    pub source: &'t str,
+    pub chars: &'t [char],
+    /// `classes[i]`: the class of `chars[i]`
+    pub classes: &'t [CharClass],
+    /// `bytes[i]`: offset of `chars[i]` in `source`, and its length last
+    pub bytes: &'t [usize],
+    /// `clusters[i]`: a grapheme cluster boundary falls before `chars[i]`
+    pub clusters: &'t [bool],
+    pub grammar: &'t dyn Grammar,
+}
+
+impl<'t> Text<'t> {
+    /// `chars[start..end]` as a slice of the source, for lookups
+    pub fn slice(&self, start: usize, end: usize) -> &'t str {
+        &self.source[self.bytes[start]..self.bytes[end]]
+    }
+
+    pub fn surface(&self, start: usize, end: usize) -> String {
+        self.slice(start, end).to_string()
+    }
+
+    pub fn unknown<'a>(&self, start: usize, end: usize, u: Unknown) -> Edge<'a> {
+        Edge {
+            start,
+            end,
+            pos: Cow::Borrowed(u.pos),
+            lemma: match u.lemma {
+                Some(lemma) => Cow::Borrowed(lemma),
+                None => Cow::Owned(self.surface(start, end)),
+            },
+            cost: u.cost,
+            kind: EdgeKind::Oov,
+        }
+    }
+}
+
+/// Byte offset of each character of `text`, then `text.len()`.
+pub(crate) fn char_offsets(text: &str) -> Vec<usize> {
+    let mut offsets: Vec<usize> = text.char_indices().map(|(at, _)| at).collect();
+    offsets.push(text.len());
+    offsets
+}
+
+pub(crate) trait CandidateSource<'a> {
+    /// Appends edges starting at `i`, a cluster boundary. Edges must end
+    /// on a cluster boundary no later than `limit`, where the next protected
+    /// span starts; the lattice drops any that do not.
+    fn candidates(&self, text: &Text, i: usize, limit: usize, out: &mut Vec<Edge<'a>>);
+}
+
+/// Entries of a dictionary view, optionally matching Latin letters in
+/// either case.
+pub(crate) struct Dictionary<'v, 'a> {
+    pub view: &'v DictView<'a>,
+    pub case_fold: Option<&'a CaseIndex>,
+}
+
+impl<'a> CandidateSource<'a> for Dictionary<'_, 'a> {
+    fn candidates(&self, text: &Text, i: usize, limit: usize, out: &mut Vec<Edge<'a>>) {
+        for len in 1..=MAX_WORD_LEN {
+            if i + len > limit {
+                break;
+            }
+            if !text.clusters[i + len] {
+                continue;
+            }
+            let surface = text.slice(i, i + len);
+            let mut push = |pat: PatternRef<'a>| {
+                out.push(Edge {
+                    start: i,
+                    end: i + len,
+                    pos: Cow::Borrowed(pat.pos),
+                    lemma: Cow::Borrowed(pat.lemma),
+                    cost: pat
+                        .cost
+                        .unwrap_or_else(|| text.grammar.word_cost(len, pat.pos)),
+                    kind: EdgeKind::Dict,
+                })
+            };
+            self.view.for_each(surface, &mut push);
+            // The edge spans the text as written; the lemma is the entry's
+            if let Some(index) = self.case_fold {
+                for key in index.variants(surface) {
+                    self.view.for_each(key, &mut push);
+                }
+            }
+        }
+    }
+}
+
+/// Unknown runs of one script (Hangul, Latin, digits, ...), so script
+/// changes become boundaries.
+pub(crate) struct ScriptRuns<'g>(pub &'g OovGrouping);
+
+impl<'a> CandidateSource<'a> for ScriptRuns<'_> {
+    fn candidates(&self, text: &Text, i: usize, limit: usize, out: &mut Vec<Edge<'a>>) {
+        let class = text.classes[i];
+        let end = self.0.run_end(text.classes, i, limit.min(i + MAX_WORD_LEN));
+        for len in (1..=end - i).filter(|&len| text.clusters[i + len]) {
+            if let Some(u) = text.grammar.script_run(class, len) {
+                out.push(text.unknown(i, i + len, u));
+            }
+        }
+    }
+}
+
+/// Whole Latin words (with inner apostrophes and hyphens: don't, e-mail)
+/// as SL tokens that take part in tag transitions like dictionary entries,
+/// so that an English word in Korean text takes particles and suffixes
+/// (PPT/SL 를/JKO, check/SL 하/XSV). An entry for the same span wins.
+pub(crate) struct LatinWords;
+
+impl<'a> CandidateSource<'a> for LatinWords {
+    fn candidates(&self, text: &Text, i: usize, limit: usize, out: &mut Vec<Edge<'a>>) {
+        let chars = text.chars;
+        let letter = |k: usize| text.classes[k] == CharClass::Latin;
+        let joiner = |k: usize| matches!(chars[k], '\'' | '\u{2019}' | '-');
+        // Only where a word starts
+        if !letter(i) || (i > 0 && (letter(i - 1) || (i > 1 && joiner(i - 1) && letter(i - 2)))) {
+            return;
+        }
+        let mut end = i + 1;
+        while end < limit {
+            if letter(end) {
+                end += 1;
+            } else if joiner(end) && end + 1 < limit && letter(end + 1) {
+                end += 2;
+            } else {
+                break;
+            }
+        }
+        if text.clusters[end] {
+            let len = end - i;
+            out.push(Edge {
+                start: i,
+                end,
+                pos: Cow::Borrowed("SL"),
+                lemma: Cow::Owned(text.surface(i, end)),
+                cost: text.grammar.word_cost(len, "SL") + scoring::PENALTY_FOREIGN_WORD,
+                kind: EdgeKind::Dict,
+            });
+        }
+    }
+}
+
+/// One unknown cluster, so every input has a path.
+pub(crate) struct Fallback;
+
+impl<'a> CandidateSource<'a> for Fallback {
+    fn candidates(&self, text: &Text, i: usize, _limit: usize, out: &mut Vec<Edge<'a>>) {
+        let n = text.chars.len();
+        let next = (i + 1..=n).find(|&j| text.clusters[j]).unwrap_or(n);
+        out.push(text.unknown(i, next, text.grammar.fallback(1)));
+    }
+}
+
+/// Letter runs seen in the corpus, cheaper the more their edges look like
+/// word boundaries.
+pub(crate) struct EntropySpans<'s> {
+    pub stats: &'s EntropyStats,
+    pub weight: f64,
+}
+
+impl<'a> CandidateSource<'a> for EntropySpans<'_> {
+    fn candidates(&self, text: &Text, i: usize, limit: usize, out: &mut Vec<Edge<'a>>) {
+        let chars = text.chars;
+        let max = self.stats.max_len.min(limit - i);
+        for len in 2..=max {
+            if !chars[i + len - 1].is_alphanumeric() || !chars[i].is_alphanumeric() {
+                break;
+            }
+            if !text.clusters[i + len] {
+                continue;
+            }
+            if let Some((left, right)) = self.stats.entropies(text.slice(i, i + len)) {
+                let u = text.grammar.fallback(len);
+                let cost = u.cost - self.weight * (left + right);
+                out.push(text.unknown(i, i + len, Unknown { cost, ..u }));
+            }
+        }
+    }
+}
+
+/// A tagged span supplied from outside, in characters of the text.
+#[derive(Clone, Debug)]
+pub(crate) struct Candidate {
+    pub start: usize,
+    pub end: usize,
+    pub pos: String,
+    pub lemma: String,
+    pub cost: f64,
+}
+
+/// Edges computed ahead of the lattice (by a Python callback); they take
+/// part in tag transitions like dictionary entries.
+pub(crate) struct FixedEdges(Vec<Candidate>);
+
+impl FixedEdges {
+    pub fn new(mut candidates: Vec<Candidate>) -> Self {
+        candidates.sort_by_key(|c| c.start);
+        FixedEdges(candidates)
+    }
+}
+
+impl<'a> CandidateSource<'a> for FixedEdges {
+    fn candidates(&self, _text: &Text, i: usize, _limit: usize, out: &mut Vec<Edge<'a>>) {
+        let from = self.0.partition_point(|c| c.start < i);
+        for c in self.0[from..].iter().take_while(|c| c.start == i) {
+            out.push(Edge {
+                start: c.start,
+                end: c.end,
+                pos: Cow::Owned(c.pos.clone()),
+                lemma: Cow::Owned(c.lemma.clone()),
+                cost: c.cost,
+                kind: EdgeKind::Dict,
+            });
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use crate::chartype::CharClass;
+use crate::view::DictView;
+
+// -----------------------------------------------------------------------------
+// Case-Folded Lookup of Latin Keys
+// -----------------------------------------------------------------------------
+//
+// Dictionary keys containing Latin letters, grouped by their lower-cased
+// form, so "IPHONE" and "iphone" in the text find the "iPhone" entry. Only
+// Latin letters are folded; the rest of a key (갤럭시S24) must match as is.
+
+fn is_latin(c: char) -> bool {
+    CharClass::of(c) == CharClass::Latin
+}
+
+pub(crate) fn has_latin(s: &str) -> bool {
+    s.chars().any(is_latin)
+}
+
+/// `s` with its Latin letters lower-cased; the character count is unchanged.
+pub(crate) fn fold(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if is_latin(c) {
+                c.to_lowercase().next().unwrap_or(c)
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+pub(crate) struct CaseIndex {
+    // Folded form -> the keys with that form
+    keys: HashMap<String, Vec<String>>,
+}
+
+impl CaseIndex {
+    /// Indexes the Latin keys of every dictionary in `view`. Keys hidden by
+    /// a ban are included; looking them up finds nothing.
+    pub fn build(view: &DictView) -> Self {
+        let mut keys: HashMap<String, Vec<String>> = HashMap::new();
+        for key in view.keys().filter(|k| has_latin(k)) {
+            let variants = keys.entry(fold(&key)).or_default();
+            if !variants.iter().any(|v| *v == key) {
+                variants.push(key.to_string());
+            }
+        }
+        CaseIndex { keys }
+    }
+
+    /// The keys, other than `surface` itself, that `surface` matches
+    /// ignoring the case of Latin letters.
+    pub fn variants<'s>(&'s self, surface: &'s str) -> impl Iterator<Item = &'s str> + 's {
+        let found = if has_latin(surface) {
+            self.keys.get(&fold(surface)).map_or(&[][..], Vec::as_slice)
+        } else {
+            &[]
+        };
+        found
+            .iter()
+            .map(String::as_str)
+            .filter(move |k| *k != surface)
+    }
+}
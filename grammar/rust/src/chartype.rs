@@ -0,0 +1,157 @@
+// -----------------------------------------------------------------------------
+// Character Types
+// -----------------------------------------------------------------------------
+//
+// Unknown text is grouped into runs of one script, so 갤럭시S24 falls back to
+// 갤럭시 / S / 24 rather than one unknown per character. Script changes are
+// natural token boundaries. The lattice runs a single classification
+// pre-pass over the text (`classify`, a scalar loop rather than SIMD) and
+// its sources read the classes instead of testing code point ranges at
+// every position they look at.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CharClass {
+    Hangul,
+    Latin,
+    Digit,
+    Han,
+    Kana,
+    Space,
+    /// ASCII, general and CJK punctuation
+    Punct,
+    Other,
+}
+
+// Classes of the ASCII characters, by code
+const ASCII: [CharClass; 128] = {
+    let mut table = [CharClass::Other; 128];
+    let mut c = 0;
+    while c < 128 {
+        let b = c as u8;
+        table[c] = if b.is_ascii_alphabetic() {
+            CharClass::Latin
+        } else if b.is_ascii_digit() {
+            CharClass::Digit
+        } else if b.is_ascii_whitespace() || b == 0x0B {
+            CharClass::Space
+        } else if b.is_ascii_punctuation() {
+            CharClass::Punct
+        } else {
+            CharClass::Other
+        };
+        c += 1;
+    }
+    table
+};
+
+impl CharClass {
+    pub fn of(c: char) -> CharClass {
+        if c.is_ascii() {
+            return ASCII[c as usize];
+        }
+        match c {
+            '가'..='힣' | 'ㄱ'..='ㆎ' | 'ᄀ'..='ᇿ' => CharClass::Hangul,
+            // Accented letters too (café, naïve, Tiếng), but not × and ÷
+            'a'..='z'
+            | 'A'..='Z'
+            | 'Ａ'..='Ｚ'
+            | 'ａ'..='ｚ'
+            | '\u{00C0}'..='\u{00D6}'
+            | '\u{00D8}'..='\u{00F6}'
+            | '\u{00F8}'..='\u{024F}'
+            | '\u{1E00}'..='\u{1EFF}' => CharClass::Latin,
+            '0'..='9' | '０'..='９' => CharClass::Digit,
+            '一'..='鿿' | '㐀'..='䶿' | '豈'..='﫿' => CharClass::Han,
+            'ぁ'..='ゟ' | '゠'..='ヿ' => CharClass::Kana,
+            c if c.is_whitespace() => CharClass::Space,
+            '\u{2010}'..='\u{2027}'
+            | '\u{2030}'..='\u{205E}'
+            | '\u{3001}'..='\u{3003}'
+            | '\u{3008}'..='\u{3011}'
+            | '\u{3014}'..='\u{301F}'
+            | '！'..='／'
+            | '：'..='＠'
+            | '［'..='｀'
+            | '｛'..='･' => CharClass::Punct,
+            _ => CharClass::Other,
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<CharClass> {
+        Some(match name {
+            "hangul" => CharClass::Hangul,
+            "latin" => CharClass::Latin,
+            "digit" => CharClass::Digit,
+            "han" => CharClass::Han,
+            "kana" => CharClass::Kana,
+            _ => return None,
+        })
+    }
+
+    /// Tag of an unknown run of this class (Sejong SL/SN/SH for foreign
+    /// letters, numbers and hanja).
+    pub fn tag(self) -> &'static str {
+        match self {
+            CharClass::Latin | CharClass::Kana => "SL",
+            CharClass::Digit => "SN",
+            CharClass::Han => "SH",
+            _ => "NNG",
+        }
+    }
+
+    fn groups(self) -> bool {
+        !matches!(self, CharClass::Space | CharClass::Punct | CharClass::Other)
+    }
+}
+
+/// The class of every character of `chars`, Hangul syllables (most of
+/// Korean text) first. Combining diacritics take the class of the
+/// character they follow, so a decomposed é stays within its word.
+pub(crate) fn classify(chars: &[char]) -> Vec<CharClass> {
+    let mut classes: Vec<CharClass> = Vec::with_capacity(chars.len());
+    for &c in chars {
+        let class = match c {
+            '가'..='힣' => CharClass::Hangul,
+            '\u{0300}'..='\u{036F}' => classes.last().copied().unwrap_or(CharClass::Other),
+            c => CharClass::of(c),
+        };
+        classes.push(class);
+    }
+    classes
+}
+
+/// Which classes form unknown runs, and which of them run together.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OovGrouping {
+    /// Class pairs treated as one script, e.g. (Latin, Digit) keeps S24 whole
+    pub merge: Vec<(CharClass, CharClass)>,
+}
+
+impl OovGrouping {
+    fn joins(&self, a: CharClass, b: CharClass) -> bool {
+        a == b
+            || self
+                .merge
+                .iter()
+                .any(|&(x, y)| (x, y) == (a, b) || (x, y) == (b, a))
+    }
+
+    /// The end of the run of joinable characters starting at `i`, cut at
+    /// `limit` (`i` itself when the character does not group), given the
+    /// classes of the text.
+    pub fn run_end(&self, classes: &[CharClass], i: usize, limit: usize) -> usize {
+        let mut class = classes[i];
+        if !class.groups() {
+            return i;
+        }
+        let mut end = i + 1;
+        while end < limit {
+            let next = classes[end];
+            if !self.joins(class, next) {
+                break;
+            }
+            (class, end) = (next, end + 1);
+        }
+        end
+    }
+}
@@ -0,0 +1,81 @@
+use crate::Morpheme;
+
+// -----------------------------------------------------------------------------
+// Noun-Phrase Chunking
+// -----------------------------------------------------------------------------
+//
+// A small finite-state grammar over POS tags:
+//
+//     NP := (MM | VA ETM)* N+
+//
+// Whitespace morphemes are transparent, so modifiers and compound nouns may
+// span eojeols ("예쁜 꽃", "학교 도서관").
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Start,
+    // saw VA, waiting for ETM
+    Adjective,
+    // saw a complete modifier
+    Modifier,
+    // inside the noun run
+    Noun,
+}
+
+fn is_space(m: &Morpheme) -> bool {
+    !m.0.is_empty() && m.0.chars().all(char::is_whitespace)
+}
+
+/// Morpheme index ranges `[start, end)` of every noun phrase.
+pub(crate) fn noun_phrases(morphs: &[Morpheme]) -> Vec<(usize, usize)> {
+    let mut chunks = Vec::new();
+    let mut state = State::Start;
+    let mut start = 0;
+    let mut last_noun = 0;
+
+    let mut i = 0;
+    while i < morphs.len() {
+        let m = &morphs[i];
+        if is_space(m) {
+            i += 1;
+            continue;
+        }
+        let pos = m.1.as_str();
+        let next = match (state, pos) {
+            (State::Adjective, "ETM") => Some(State::Modifier),
+            (State::Adjective, _) => None,
+            (State::Noun, p) if p.starts_with('N') => Some(State::Noun),
+            (State::Noun, _) => None,
+            (_, "MM") => Some(State::Modifier),
+            (_, "VA") => Some(State::Adjective),
+            (_, p) if p.starts_with('N') => Some(State::Noun),
+            _ => None,
+        };
+        match next {
+            Some(s) => {
+                if state == State::Start {
+                    start = i;
+                }
+                if s == State::Noun {
+                    last_noun = i;
+                }
+                state = s;
+                i += 1;
+            }
+            None => {
+                if state == State::Noun {
+                    chunks.push((start, last_noun + 1));
+                }
+                // Re-read this morpheme as a possible chunk start
+                if state == State::Start {
+                    i += 1;
+                }
+                state = State::Start;
+            }
+        }
+    }
+    if state == State::Noun {
+        chunks.push((start, last_noun + 1));
+    }
+    chunks
+}
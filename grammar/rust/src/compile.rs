@@ -0,0 +1,60 @@
+use crate::{frozen, lexicon, scoring, TrieData};
+
+pub use crate::frozen::Profile;
+pub use crate::lexicon::{Format, LexiconError, Stats};
+pub use crate::tags::{TagSet, UnknownTag, STANDARD_TAGS};
+
+// -----------------------------------------------------------------------------
+// Lexicon Compilation
+// -----------------------------------------------------------------------------
+//
+// What `kulim-dictc` links against: raw lexicon files in, the compiled
+// format `load_compiled` reads out, without a Python environment.
+
+/// A dictionary being built from lexicon files.
+#[derive(Default)]
+pub struct Compiler {
+    data: TrieData,
+    pub stats: Stats,
+}
+
+impl Compiler {
+    /// Adds the entries of the lexicon file at `path` (see `lexicon::load`)
+    /// and returns its rejected lines.
+    pub fn load(
+        &mut self,
+        path: &str,
+        format: Option<Format>,
+        expand: bool,
+        source: Option<&str>,
+        tags: &TagSet,
+    ) -> Result<Vec<LexiconError>, String> {
+        lexicon::load(
+            path,
+            format,
+            expand,
+            source,
+            tags,
+            &mut self.data,
+            &mut self.stats,
+        )
+    }
+
+    /// Drops the entries costing more than `max_cost`, by their own cost or
+    /// else the default one for their length and tag; returns how many.
+    pub fn prune(&mut self, max_cost: f64) -> usize {
+        self.data.prune(max_cost, scoring::word_cost)
+    }
+
+    pub fn write(&self, path: &str, profile: Profile) -> Result<(), String> {
+        frozen::write(&self.data, path, profile)
+    }
+
+    pub fn surfaces(&self) -> usize {
+        self.data.dict.len()
+    }
+
+    pub fn patterns(&self) -> usize {
+        self.data.dict.values().map(Vec::len).sum()
+    }
+}
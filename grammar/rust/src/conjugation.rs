@@ -0,0 +1,288 @@
+use crate::Morpheme;
+
+// -----------------------------------------------------------------------------
+// Conjugation Expansion
+// -----------------------------------------------------------------------------
+//
+// Lexicons list predicates in their dictionary form (가다/VV), while text
+// has them conjugated. The lattice joins a stem and its endings when both
+// are separate entries (먹/VV 었/EP 다/EF), which leaves the forms where
+// the stem and a vowel ending fuse into one syllable (가+았 → 갔, 보+아 →
+// 봐, 하+여 → 해) and the irregular stems (듣 → 들, 가깝 → 가까우). Those
+// are added as entries of their own, as `conjugation.py` and
+// `irregular.py` restore them: the fused forms with compound tags
+// and lemmas listing their morphemes (갔/VV+EP/가+았), the irregular
+// stems under the dictionary form like the plain stem.
+
+// The irregular stems of `irregular.py`, by class
+const B_IRREGULAR: &[&str] = &[
+    "돕",
+    "곱",
+    "눕",
+    "줍",
+    "굽",
+    "가깝",
+    "고맙",
+    "즐겁",
+    "아름답",
+    "무겁",
+    "차갑",
+    "뜨겁",
+    "반갑",
+    "어렵",
+    "쉽",
+    "더럽",
+    "무섭",
+    "귀엽",
+    "부끄럽",
+];
+const D_IRREGULAR: &[&str] = &["듣", "걷", "묻", "싣", "깨닫"];
+const S_IRREGULAR: &[&str] = &["짓", "낫", "잇", "붓"];
+const H_IRREGULAR: &[&str] = &[
+    "그렇", "이렇", "저렇", "어떻", "하얗", "까맣", "빨갛", "파랗", "노랗",
+];
+// Stems in 르 drop only their ㅡ, as other ㅡ stems do (따르 → 따라)
+const EU_REGULAR: &[&str] = &["따르", "치르", "들르", "우러르"];
+
+// Jamo indices within a syllable block
+const A: u32 = 0; // ㅏ
+const AE: u32 = 1; // ㅐ
+const YA: u32 = 2; // ㅑ
+const YAE: u32 = 3; // ㅒ
+const EO: u32 = 4; // ㅓ
+const E: u32 = 5; // ㅔ
+const YEO: u32 = 6; // ㅕ
+const YE: u32 = 7; // ㅖ
+const O: u32 = 8; // ㅗ
+const WA: u32 = 9; // ㅘ
+const WAE: u32 = 10; // ㅙ
+const OE: u32 = 11; // ㅚ
+const U: u32 = 13; // ㅜ
+const WEO: u32 = 14; // ㅝ
+const EU: u32 = 18; // ㅡ
+const I: u32 = 20; // ㅣ
+const DIGEUT: u32 = 7; // final ㄷ
+const RIEUL: u32 = 8; // final ㄹ
+const BIEUP: u32 = 17; // final ㅂ
+const SIOS: u32 = 19; // final ㅅ
+const SSANG_SIOS: u32 = 20; // final ㅆ
+const HIEUT: u32 = 27; // final ㅎ
+
+#[derive(Clone, Copy)]
+struct Syllable {
+    initial: u32,
+    vowel: u32,
+    last: u32,
+}
+
+impl Syllable {
+    fn parse(c: char) -> Option<Self> {
+        let code = (c as u32)
+            .checked_sub(0xAC00)
+            .filter(|&code| code < 11172)?;
+        Some(Syllable {
+            initial: code / 588,
+            vowel: code % 588 / 28,
+            last: code % 28,
+        })
+    }
+
+    fn to_char(self) -> char {
+        char::from_u32(0xAC00 + self.initial * 588 + self.vowel * 28 + self.last).unwrap_or('?')
+    }
+
+    fn with(self, vowel: u32, last: u32) -> Self {
+        Syllable {
+            vowel,
+            last,
+            ..self
+        }
+    }
+
+    /// Whether an ending after this vowel takes 아 rather than 어.
+    fn bright(self) -> bool {
+        matches!(self.vowel, A | YA | O)
+    }
+}
+
+/// A stem with its last syllable replaced.
+fn respell(stem: &[char], last: Syllable) -> String {
+    let mut out: String = stem[..stem.len() - 1].iter().collect();
+    out.push(last.to_char());
+    out
+}
+
+/// The stem fused with the vowel ending 아/어/여, as `(surface, ending)`,
+/// when the two share a syllable, and the stem alternant irregular
+/// predicates take before vowels, if any.
+fn fuse(stem: &str) -> (Option<(String, &'static str)>, Option<String>) {
+    let chars: Vec<char> = stem.chars().collect();
+    let Some(last) = chars.last().and_then(|&c| Syllable::parse(c)) else {
+        return (None, None);
+    };
+    let prev = chars
+        .len()
+        .checked_sub(2)
+        .and_then(|i| Syllable::parse(chars[i]));
+    let ending = |bright: bool| if bright { "아" } else { "어" };
+    let listed = |list: &[&str]| list.contains(&stem);
+
+    if last.last == 0 && chars[chars.len() - 1] == '하' {
+        return (Some((respell(&chars, last.with(AE, 0)), "여")), None);
+    }
+    if last.last == BIEUP && listed(B_IRREGULAR) {
+        // 돕 → 도와, 가깝 → 가까워; before 으 the ㅂ becomes 우
+        let open = respell(&chars, last.with(last.vowel, 0));
+        let alternant = format!("{}우", open);
+        let bright = chars.len() == 1 && last.vowel == O;
+        let fused = format!("{}{}", open, if bright { "와" } else { "워" });
+        return (Some((fused, ending(bright))), Some(alternant));
+    }
+    if last.last == DIGEUT && listed(D_IRREGULAR) {
+        // 듣 → 들어: the ending keeps its own syllable
+        return (None, Some(respell(&chars, last.with(last.vowel, RIEUL))));
+    }
+    if last.last == SIOS && listed(S_IRREGULAR) {
+        // 짓 → 지어
+        return (None, Some(respell(&chars, last.with(last.vowel, 0))));
+    }
+    if last.last == HIEUT && listed(H_IRREGULAR) {
+        // 그렇 → 그래, 하얗 → 하얘; before 으 the ㅎ drops (그러니)
+        let vowel = if last.vowel == YA { YAE } else { AE };
+        let fused = respell(&chars, last.with(vowel, 0));
+        let alternant = respell(&chars, last.with(last.vowel, 0));
+        return (Some((fused, ending(last.bright()))), Some(alternant));
+    }
+    if last.last != 0 {
+        return (None, None);
+    }
+    match last.vowel {
+        EU => {
+            let bright = prev.is_some_and(Syllable::bright);
+            let vowel = if bright { A } else { EO };
+            let reu = chars[chars.len() - 1] == '르' && !listed(EU_REGULAR);
+            match (reu, prev) {
+                // 부르 → 불러, 모르 → 몰라
+                (true, Some(p)) if p.last == 0 => {
+                    let mut fused: String = chars[..chars.len() - 2].iter().collect();
+                    fused.push(p.with(p.vowel, RIEUL).to_char());
+                    fused.push(last.with(vowel, 0).to_char());
+                    (Some((fused, ending(bright))), None)
+                }
+                (true, _) => (None, None),
+                // 쓰 → 써, 바쁘 → 바빠
+                (false, _) => (
+                    Some((respell(&chars, last.with(vowel, 0)), ending(bright))),
+                    None,
+                ),
+            }
+        }
+        // 가 → 가, 서 → 서, 보내 → 보내, 켜 → 켜
+        A | EO | AE | E | YEO | YE => (Some((stem.to_string(), ending(last.vowel == A))), None),
+        O => (Some((respell(&chars, last.with(WA, 0)), "아")), None),
+        U => (Some((respell(&chars, last.with(WEO, 0)), "어")), None),
+        I => (Some((respell(&chars, last.with(YEO, 0)), "어")), None),
+        OE => (Some((respell(&chars, last.with(WAE, 0)), "어")), None),
+        _ => (None, None),
+    }
+}
+
+/// Whether entries tagged `pos` are predicates, listed in dictionary form.
+pub(crate) fn is_predicate(pos: &str) -> bool {
+    matches!(pos, "VV" | "VA" | "VX" | "VCP" | "VCN")
+}
+
+/// The entries a predicate in dictionary form (`surface` ending in 다,
+/// tagged `pos`, with `lemma`) adds besides itself: its stem, and unless
+/// it is the copula (이다, 아니다) the irregular stem if it has one and
+/// its stem fused with the vowel ending (`pos`+EC) and with the past 았/었
+/// (`pos`+EP) where the two share a syllable. Empty for anything else.
+pub(crate) fn expand(surface: &str, pos: &str, lemma: &str) -> Vec<Morpheme> {
+    let Some(stem) = surface.strip_suffix('다').filter(|s| !s.is_empty()) else {
+        return Vec::new();
+    };
+    if !is_predicate(pos) {
+        return Vec::new();
+    }
+    let mut out = vec![(stem.to_string(), pos.to_string(), lemma.to_string())];
+    if matches!(pos, "VCP" | "VCN") {
+        return out;
+    }
+    let (fused, alternant) = fuse(stem);
+    if let Some(alternant) = alternant {
+        out.push((alternant, pos.to_string(), lemma.to_string()));
+    }
+    if let Some((fused, ending)) = fused {
+        let mut chars: Vec<char> = fused.chars().collect();
+        let last = Syllable::parse(chars[chars.len() - 1]).expect("fused forms end in a syllable");
+        let past = match ending {
+            "아" => "았",
+            "여" => "였",
+            _ => "었",
+        };
+        *chars.last_mut().unwrap() = last.with(last.vowel, SSANG_SIOS).to_char();
+        out.push((
+            chars.iter().collect(),
+            format!("{}+EP", pos),
+            format!("{}+{}", stem, past),
+        ));
+        out.push((fused, format!("{}+EC", pos), format!("{}+{}", stem, ending)));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forms(dictionary_form: &str, pos: &str) -> Vec<String> {
+        expand(dictionary_form, pos, dictionary_form)
+            .into_iter()
+            .map(|(surface, pos, lemma)| format!("{}/{}/{}", surface, pos, lemma))
+            .collect()
+    }
+
+    #[test]
+    fn expands_fused_and_irregular_forms() {
+        assert_eq!(
+            forms("가다", "VV"),
+            ["가/VV/가다", "갔/VV+EP/가+았", "가/VV+EC/가+아"]
+        );
+        assert_eq!(forms("먹다", "VV"), ["먹/VV/먹다"]);
+        assert_eq!(forms("이다", "VCP"), ["이/VCP/이다"]);
+        assert!(forms("학교", "NNG").is_empty() && forms("다", "VV").is_empty());
+        for (dictionary_form, pos, fused, past) in [
+            ("보다", "VV", "봐", "봤"),
+            ("주다", "VV", "줘", "줬"),
+            ("마시다", "VV", "마셔", "마셨"),
+            ("되다", "VV", "돼", "됐"),
+            ("공부하다", "VV", "공부해", "공부했"),
+            ("쓰다", "VV", "써", "썼"),
+            ("바쁘다", "VA", "바빠", "바빴"),
+            ("따르다", "VV", "따라", "따랐"),
+            ("부르다", "VV", "불러", "불렀"),
+            ("모르다", "VV", "몰라", "몰랐"),
+            ("돕다", "VV", "도와", "도왔"),
+            ("가깝다", "VA", "가까워", "가까웠"),
+            ("그렇다", "VA", "그래", "그랬"),
+            ("하얗다", "VA", "하얘", "하얬"),
+        ] {
+            let out = forms(dictionary_form, pos);
+            assert!(
+                out.iter()
+                    .any(|f| f.starts_with(&format!("{}/{}+EC/", fused, pos))),
+                "{:?}",
+                out
+            );
+            assert!(
+                out.iter()
+                    .any(|f| f.starts_with(&format!("{}/{}+EP/", past, pos))),
+                "{:?}",
+                out
+            );
+        }
+        assert_eq!(forms("듣다", "VV"), ["듣/VV/듣다", "들/VV/듣다"]);
+        assert_eq!(forms("짓다", "VV"), ["짓/VV/짓다", "지/VV/짓다"]);
+        assert!(forms("가깝다", "VA").contains(&"가까우/VA/가깝다".to_string()));
+    }
+}
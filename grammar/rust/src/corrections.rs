@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use crate::Morpheme;
+
+// -----------------------------------------------------------------------------
+// Online Learning from Corrections
+// -----------------------------------------------------------------------------
+
+/// A morpheme placed on the input: (start, end) in characters.
+pub(crate) type Span = (usize, usize, Morpheme);
+
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Counts {
+    /// Times the entry was in the gold analysis but not predicted
+    pub missed: u32,
+    /// Times the entry was predicted but contradicted by the gold analysis
+    pub spurious: u32,
+}
+
+/// Accumulated disagreement between the decoder and annotator fixes.
+#[derive(Clone, Default)]
+pub(crate) struct Corrections {
+    pub counts: HashMap<Morpheme, Counts>,
+}
+
+/// Places gold morphemes on `text`, skipping whitespace between them.
+pub(crate) fn align(text: &str, gold: &[Morpheme]) -> Result<Vec<Span>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut at = 0;
+    let mut spans = Vec::with_capacity(gold.len());
+    for m in gold {
+        while at < chars.len() && chars[at].is_whitespace() {
+            at += 1;
+        }
+        let surface: Vec<char> = m.0.chars().collect();
+        if surface.is_empty() || !chars[at..].starts_with(&surface) {
+            return Err(format!(
+                "gold morpheme '{}' does not match the text at character {}",
+                m.0, at
+            ));
+        }
+        spans.push((at, at + surface.len(), m.clone()));
+        at += surface.len();
+    }
+    if chars[at..].iter().any(|c| !c.is_whitespace()) {
+        return Err(format!("gold analysis stops at character {}", at));
+    }
+    Ok(spans)
+}
+
+impl Corrections {
+    /// Records one correction given the predicted dictionary spans and the
+    /// aligned gold spans. Whitespace and unknown predictions are not entries
+    /// and are never counted as spurious.
+    pub fn record(&mut self, predicted: &[Span], gold: &[Span]) {
+        for g in gold {
+            if !predicted.contains(g) {
+                self.counts.entry(g.2.clone()).or_default().missed += 1;
+            }
+        }
+        for p in predicted {
+            if !gold.contains(p) {
+                self.counts.entry(p.2.clone()).or_default().spurious += 1;
+            }
+        }
+    }
+
+    /// Net adjustment per entry: negative deltas make it more attractive.
+    pub fn deltas(&self, step: f64, min_count: u32) -> Vec<(Morpheme, f64)> {
+        let mut out: Vec<(Morpheme, f64)> = self
+            .counts
+            .iter()
+            .filter_map(|(m, c)| {
+                let net = c.missed as i64 - c.spurious as i64;
+                if net == 0 || net.unsigned_abs() < min_count as u64 {
+                    return None;
+                }
+                Some((m.clone(), -(net as f64) * step))
+            })
+            .collect();
+        out.sort_by(|a, b| a.0.cmp(&b.0));
+        out
+    }
+}
@@ -0,0 +1,214 @@
+use std::borrow::Cow;
+
+use crate::candidates::{CandidateSource, Text};
+use crate::decoder::{Edge, EdgeKind};
+use crate::scoring;
+use crate::Morpheme;
+
+// -----------------------------------------------------------------------------
+// Numeral + Counter Constructions
+// -----------------------------------------------------------------------------
+//
+// Korean counts things with a numeral and a bound noun of measure: 3개,
+// 세 명, 스무 마리, 이십 번. After a numeral, a counter word gets an NNB
+// edge even when the dictionary only has its homograph (개 "dog", 명
+// "name"), and a numeral before a counter gets its own edge: SN for digits,
+// MM for native determiners (한, 두, 세, 스무, 열두), NR for Sino-Korean
+// numerals of two syllables or more (이십, 백오십). The numeral-counter
+// transition bonus (`scoring::BONUS_NUMERAL_COUNTER`) then binds them. A
+// counter after a space or a native numeral gets the bonus in its own cost
+// instead: the whitespace edge between them hides the numeral's tag from
+// the transition, and MM is followed by any noun at the same bonus.
+
+/// The built-in counter words.
+pub(crate) const COUNTERS: &[&str] = &[
+    "개", "명", "마리", "번", "권", "대", "살", "장", "잔", "병", "벌", "켤레", "채", "그루",
+    "송이", "곳", "군데", "가지", "분", "시간", "초", "년", "개월", "달", "주", "일", "월", "원",
+    "층", "쪽", "줄", "회", "차", "배", "척", "통", "알", "쌍", "인분", "그릇", "칸", "점", "위",
+    "등",
+];
+
+const NATIVE_TENS: &[&str] = &[
+    "열", "스물", "서른", "마흔", "쉰", "예순", "일흔", "여든", "아흔",
+];
+const NATIVE_UNITS: &[&str] = &[
+    "한", "두", "세", "네", "다섯", "여섯", "일곱", "여덟", "아홉",
+];
+// Determiner forms that stand alone
+const NATIVE_WORDS: &[&str] = &["스무", "서", "너", "몇", "여러", "수십", "수백", "수천"];
+const SINO_DIGITS: &str = "영일이삼사오육칠팔구십백천만억조";
+
+/// Counter words to recognize, and whether a numeral and its counter are
+/// merged into one quantity token.
+#[derive(Clone, Debug)]
+pub(crate) struct Counters {
+    words: Vec<Vec<char>>,
+    pub composite: bool,
+}
+
+impl Counters {
+    pub fn new(words: Vec<String>, composite: bool) -> Self {
+        let mut words: Vec<Vec<char>> = words.iter().map(|w| w.chars().collect()).collect();
+        words.retain(|w| !w.is_empty());
+        words.sort();
+        words.dedup();
+        Counters { words, composite }
+    }
+
+    fn is_counter(&self, surface: &str) -> bool {
+        self.words
+            .iter()
+            .any(|w| w.iter().copied().eq(surface.chars()))
+    }
+
+    /// Lengths of the counter words starting at `i`.
+    fn counters_at<'s>(&'s self, chars: &'s [char], i: usize) -> impl Iterator<Item = usize> + 's {
+        self.words
+            .iter()
+            .filter(move |w| chars[i..].starts_with(w))
+            .map(|w| w.len())
+    }
+
+    /// Whether a counter word starts at `i`, after any whitespace.
+    fn counter_follows(&self, chars: &[char], mut i: usize) -> bool {
+        while chars.get(i).is_some_and(|c| c.is_whitespace()) {
+            i += 1;
+        }
+        i < chars.len() && self.counters_at(chars, i).next().is_some()
+    }
+}
+
+fn is_digit(c: char) -> bool {
+    matches!(c, '0'..='9' | '０'..='９')
+}
+
+/// The tag of `s` as a numeral before a counter: SN, MM or NR.
+fn numeral_tag(s: &[char]) -> Option<&'static str> {
+    let first = *s.first()?;
+    if is_digit(first) {
+        // Digits with inner separators: 1,000 and 2.5
+        let last = s[s.len() - 1];
+        let ok = is_digit(last) && s.iter().all(|&c| is_digit(c) || c == ',' || c == '.');
+        return ok.then_some("SN");
+    }
+    let word: String = s.iter().collect();
+    if NATIVE_WORDS.contains(&word.as_str()) {
+        return Some("MM");
+    }
+    let (tens, rest) = match NATIVE_TENS.iter().find(|t| word.starts_with(*t)) {
+        Some(t) => (true, &word[t.len()..]),
+        None => (false, word.as_str()),
+    };
+    if (tens && rest.is_empty()) || NATIVE_UNITS.contains(&rest) {
+        return Some("MM");
+    }
+    (s.len() >= 2 && s.iter().all(|&c| SINO_DIGITS.contains(c))).then_some("NR")
+}
+
+/// Where the numeral ending at `end` would start: the digit run, or the
+/// Hangul word, that ends there.
+fn numeral_start(chars: &[char], end: usize) -> usize {
+    let digits = is_digit(chars[end - 1]);
+    let mut start = end;
+    while start > 0 {
+        let c = chars[start - 1];
+        let same = match digits {
+            true => {
+                is_digit(c) || ((c == ',' || c == '.') && start >= 2 && is_digit(chars[start - 2]))
+            }
+            false => ('가'..='힣').contains(&c),
+        };
+        if !same {
+            break;
+        }
+        start -= 1;
+    }
+    start
+}
+
+impl<'a> CandidateSource<'a> for Counters {
+    fn candidates(&self, text: &Text, i: usize, limit: usize, out: &mut Vec<Edge<'a>>) {
+        let chars = text.chars;
+        // A counter after a numeral
+        let mut end = i;
+        while end > 0 && chars[end - 1].is_whitespace() {
+            end -= 1;
+        }
+        let numeral = (end > 0)
+            .then(|| numeral_tag(&chars[numeral_start(chars, end)..end]))
+            .flatten();
+        if let Some(tag) = numeral {
+            // The transition rules bind SN and NR to a counter right after
+            // them; MM only gets the determiner bonus, which any noun does
+            let bound = end == i && tag != "MM";
+            for len in self.counters_at(chars, i).filter(|&len| i + len <= limit) {
+                let mut cost = text.grammar.word_cost(len, "NNB");
+                if !bound {
+                    cost -= scoring::BONUS_NUMERAL_COUNTER;
+                }
+                out.push(Edge {
+                    start: i,
+                    end: i + len,
+                    pos: Cow::Borrowed("NNB"),
+                    lemma: Cow::Owned(text.surface(i, i + len)),
+                    cost,
+                    kind: EdgeKind::Dict,
+                });
+            }
+        }
+        // A numeral before a counter, starting a digit run or a word
+        let starts = match chars[i] {
+            c if is_digit(c) => i == 0 || !is_digit(chars[i - 1]),
+            _ => i == 0 || !chars[i - 1].is_alphanumeric(),
+        };
+        if !starts {
+            return;
+        }
+        for end in (i + 1)..=limit.min(i + 16) {
+            let Some(tag) = numeral_tag(&chars[i..end]) else {
+                continue;
+            };
+            if self.counter_follows(chars, end) {
+                out.push(Edge {
+                    start: i,
+                    end,
+                    pos: Cow::Borrowed(tag),
+                    lemma: Cow::Owned(text.surface(i, end)),
+                    cost: text.grammar.word_cost(end - i, tag),
+                    kind: EdgeKind::Dict,
+                });
+            }
+        }
+    }
+}
+
+/// Merges each numeral and the counter after it, with any whitespace
+/// between, into one quantity token tagged and lemmatized like compound
+/// entries: 세 명 as `세 명/MM+NNB/세+명`.
+pub(crate) fn compose(counters: &Counters, morphs: &mut Vec<Morpheme>) {
+    let is_numeral = |m: &Morpheme| {
+        matches!(m.1.as_str(), "SN" | "MM" | "NR")
+            && numeral_tag(&m.0.chars().collect::<Vec<_>>()) == Some(m.1.as_str())
+    };
+    let is_space = |m: &Morpheme| !m.0.is_empty() && m.0.chars().all(char::is_whitespace);
+    let mut i = 0;
+    while i < morphs.len() {
+        if !is_numeral(&morphs[i]) {
+            i += 1;
+            continue;
+        }
+        let mut k = i + 1;
+        while k < morphs.len() && is_space(&morphs[k]) {
+            k += 1;
+        }
+        if k < morphs.len() && morphs[k].1 == "NNB" && counters.is_counter(&morphs[k].0) {
+            let merged: Vec<Morpheme> = morphs.drain(i..=k).collect();
+            let surface = merged.iter().map(|m| m.0.as_str()).collect();
+            let (first, last) = (&merged[0], &merged[merged.len() - 1]);
+            let pos = format!("{}+{}", first.1, last.1);
+            let lemma = format!("{}+{}", first.2, last.2);
+            morphs.insert(i, (surface, pos, lemma));
+        }
+        i += 1;
+    }
+}
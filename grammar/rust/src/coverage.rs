@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use crate::Morpheme;
+
+// -----------------------------------------------------------------------------
+// Dictionary Coverage
+// -----------------------------------------------------------------------------
+
+/// Where the analysis of a token came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Source {
+    Dictionary,
+    /// A name of a gazetteer, with the gazetteer's tag
+    Gazetteer,
+    Unknown,
+}
+
+/// How much of an analyzed corpus the dictionary covers. A token is a
+/// non-whitespace morpheme of a best analysis; it is unknown when no
+/// dictionary entry has its surface, tag and lemma and no gazetteer has it
+/// as a name (unknown-word edges, script runs, recognized tokens such as
+/// URLs).
+#[derive(Default)]
+pub(crate) struct Coverage {
+    pub lines: u64,
+    pub tokens: u64,
+    pub unknown: u64,
+    /// Tokens found in a gazetteer rather than the dictionary
+    pub entities: u64,
+    /// Tokens per tag
+    pub pos: HashMap<String, u64>,
+    /// Unknown tokens per tag
+    pub unknown_pos: HashMap<String, u64>,
+    /// Unknown tokens per surface
+    pub unknown_surfaces: HashMap<String, u64>,
+}
+
+impl Coverage {
+    /// Counts the morphemes of one line, `source` telling where each came
+    /// from.
+    pub fn add(&mut self, morphs: Vec<Morpheme>, mut source: impl FnMut(&Morpheme) -> Source) {
+        self.lines += 1;
+        for m in morphs {
+            if m.0.chars().all(char::is_whitespace) {
+                continue;
+            }
+            self.tokens += 1;
+            match source(&m) {
+                Source::Dictionary => {}
+                Source::Gazetteer => self.entities += 1,
+                Source::Unknown => {
+                    self.unknown += 1;
+                    *self.unknown_pos.entry(m.1.clone()).or_default() += 1;
+                    *self.unknown_surfaces.entry(m.0).or_default() += 1;
+                }
+            }
+            *self.pos.entry(m.1).or_default() += 1;
+        }
+    }
+
+    /// Unknown tokens over all tokens, 0.0 for an empty corpus.
+    pub fn oov_rate(&self) -> f64 {
+        match self.tokens {
+            0 => 0.0,
+            n => self.unknown as f64 / n as f64,
+        }
+    }
+
+    /// The `n` most frequent unknown surfaces with their counts, ties in
+    /// surface order.
+    pub fn top_unknown(&self, n: usize) -> Vec<(String, u64)> {
+        let mut top: Vec<(&String, &u64)> = self.unknown_surfaces.iter().collect();
+        top.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        top.into_iter()
+            .take(n)
+            .map(|(s, &c)| (s.clone(), c))
+            .collect()
+    }
+}
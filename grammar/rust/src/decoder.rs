@@ -0,0 +1,763 @@
+use std::borrow::Cow;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use crate::candidates::{
+    char_offsets, CandidateSource, Dictionary, EntropySpans, Fallback, FixedEdges, LatinWords,
+    ScriptRuns, Text,
+};
+use crate::casefold::CaseIndex;
+use crate::chartype::{self, OovGrouping};
+use crate::counter::Counters;
+use crate::dict::MAX_WORD_LEN;
+use crate::entropy::EntropyStats;
+use crate::gazetteer::Gazetteer;
+use crate::grammar::{Grammar, Term};
+use crate::grapheme;
+use crate::language::{self, LanguageFilter};
+use crate::social::{self, SocialTags};
+use crate::structured::{self, StructuredTokens};
+use crate::view::{DictView, PatternRef};
+use crate::Morpheme;
+
+// -----------------------------------------------------------------------------
+// Lattice
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum EdgeKind {
+    Dict,
+    Oov,
+    /// A pre-recognized span (hashtag, mention) taken as one unit
+    Token,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Edge<'a> {
+    pub start: usize,
+    pub end: usize,
+    pub pos: Cow<'a, str>,
+    pub lemma: Cow<'a, str>,
+    pub cost: f64,
+    pub kind: EdgeKind,
+}
+
+/// The grammar scoring the lattice, and optional features beyond
+/// dictionary lookup.
+#[derive(Clone, Copy)]
+pub(crate) struct BuildOptions<'a> {
+    pub grammar: &'a Arc<dyn Grammar>,
+    pub social: Option<&'a SocialTags>,
+    /// Phone numbers, RRNs, business numbers and emails as single tokens
+    pub structured: Option<&'a StructuredTokens>,
+    /// Paragraphs of other languages passed through or skipped
+    pub language: Option<&'a LanguageFilter>,
+    /// Script-run grouping of unknown characters; per-character when unset
+    pub grouping: Option<&'a OovGrouping>,
+    /// Corpus entropy and its weight, for multi-character unknown spans
+    pub entropy: Option<(&'a EntropyStats, f64)>,
+    /// Latin keys by folded case, for case-insensitive dictionary matches
+    pub case_fold: Option<&'a CaseIndex>,
+    /// Edges from the candidate sources registered in Python
+    pub extra: Option<&'a FixedEdges>,
+    /// Counter words after numerals, and numerals before them
+    pub counters: Option<&'a Counters>,
+    /// Name lists searched besides the dictionary
+    pub gazetteers: &'a [Arc<Gazetteer>],
+    /// Whole Latin words as SL tokens taking part in transitions
+    pub code_switching: bool,
+}
+
+/// The recognizers of spans taken as single tokens.
+#[derive(Clone, Copy)]
+pub(crate) struct Protected<'a> {
+    pub social: Option<&'a SocialTags>,
+    pub structured: Option<&'a StructuredTokens>,
+    pub language: Option<&'a LanguageFilter>,
+}
+
+impl<'a> Protected<'a> {
+    /// The protected spans of `chars`, in order and non-overlapping; where
+    /// spans of different recognizers overlap, the one starting first (the
+    /// longer one on a tie) wins.
+    fn spans(&self, chars: &[char]) -> Vec<(usize, usize, &'a str)> {
+        let mut spans = self
+            .social
+            .map_or_else(Vec::new, |tags| social::find_spans(chars, tags));
+        if let Some(tokens) = self.structured {
+            spans.extend(structured::find_spans(chars, tokens));
+        }
+        if let Some(filter) = self.language {
+            spans.extend(language::find_spans(chars, filter));
+        }
+        spans.sort_by_key(|&(start, end, _)| (start, std::cmp::Reverse(end)));
+        let mut covered = 0;
+        spans.retain(|&(start, end, _)| {
+            let keep = start >= covered;
+            if keep {
+                covered = end;
+            }
+            keep
+        });
+        spans
+    }
+}
+
+/// Every candidate edge for one input, grouped by start position.
+pub(crate) struct Lattice<'a> {
+    pub chars: Vec<char>,
+    pub edges: Vec<Edge<'a>>,
+    pub grammar: Arc<dyn Grammar>,
+    // edges[offsets[i]..offsets[i + 1]] start at character i
+    offsets: Vec<usize>,
+}
+
+impl<'a> Lattice<'a> {
+    /// Builds the lattice of `text`. With `opts.social` set, hashtag and
+    /// mention spans become single edges and nothing else may start inside or
+    /// cross into them. With `opts.entropy` set, letter runs seen in the
+    /// corpus also get unknown edges longer than one character, cheaper the
+    /// more their edges look like word boundaries. With `opts.grouping` set,
+    /// unknown text also gets edges along runs of one script (Hangul, Latin,
+    /// digits, ...), so script changes become boundaries.
+    ///
+    /// Edges start and end only at grapheme cluster boundaries, so no
+    /// morpheme splits a combining sequence or a ZWJ emoji; positions
+    /// inside a cluster have no edges.
+    pub fn build(view: &DictView<'a>, text: &str, opts: BuildOptions<'a>) -> Self {
+        match Self::build_with(view, text, opts, || Ok::<(), Infallible>(())) {
+            Ok(lattice) => lattice,
+            Err(never) => match never {},
+        }
+    }
+
+    /// `build`, calling `check` every `CHECK_INTERVAL` positions and giving
+    /// up with its error.
+    pub fn build_with<E>(
+        view: &DictView<'a>,
+        text: &str,
+        opts: BuildOptions<'a>,
+        check: impl FnMut() -> Result<(), E>,
+    ) -> Result<Self, E> {
+        let dictionary = Dictionary {
+            view,
+            case_fold: opts.case_fold,
+        };
+        let runs = opts.grouping.map(ScriptRuns);
+        let entropy = opts
+            .entropy
+            .map(|(stats, weight)| EntropySpans { stats, weight });
+        let mut sources: Vec<&dyn CandidateSource<'a>> = vec![&dictionary];
+        if let Some(runs) = &runs {
+            sources.push(runs);
+        }
+        sources.push(&Fallback);
+        if let Some(entropy) = &entropy {
+            sources.push(entropy);
+        }
+        if let Some(extra) = opts.extra {
+            sources.push(extra);
+        }
+        if let Some(counters) = opts.counters {
+            sources.push(counters);
+        }
+        for gazetteer in opts.gazetteers {
+            sources.push(&**gazetteer);
+        }
+        if opts.code_switching {
+            sources.push(&LatinWords);
+        }
+        let protected = Protected {
+            social: opts.social,
+            structured: opts.structured,
+            language: opts.language,
+        };
+        Self::build_from(text, opts.grammar, protected, &sources, check)
+    }
+
+    /// Builds the lattice of `text` from the edges of `sources`, asked in
+    /// order at every cluster boundary. The spans `protected` finds are each
+    /// a single edge that no other edge enters or crosses.
+    pub fn build_from<E>(
+        text: &str,
+        grammar: &Arc<dyn Grammar>,
+        protected: Protected<'a>,
+        sources: &[&dyn CandidateSource<'a>],
+        mut check: impl FnMut() -> Result<(), E>,
+    ) -> Result<Self, E> {
+        let chars: Vec<char> = text.chars().collect();
+        let n = chars.len();
+        let mut edges = Vec::new();
+        let mut offsets = Vec::with_capacity(n + 1);
+
+        let spans = protected.spans(&chars);
+        let mut spans = spans.into_iter().peekable();
+        let mut inside = 0;
+        let clusters = grapheme::boundaries(&chars);
+        let bytes = char_offsets(text);
+        let classes = chartype::classify(&chars);
+        let text = Text {
+            source: text,
+            chars: &chars,
+            classes: &classes,
+            bytes: &bytes,
+            clusters: &clusters,
+            grammar: &**grammar,
+        };
+
+        for i in 0..n {
+            if i % CHECK_INTERVAL == CHECK_INTERVAL - 1 {
+                check()?;
+            }
+            offsets.push(edges.len());
+            if i < inside {
+                continue;
+            }
+            if let Some(&(start, end, tag)) = spans.peek() {
+                if start == i {
+                    spans.next();
+                    inside = end;
+                    edges.push(Edge {
+                        start,
+                        end,
+                        pos: Cow::Borrowed(tag),
+                        lemma: Cow::Owned(text.surface(start, end)),
+                        cost: grammar.word_cost(end - start, tag),
+                        kind: EdgeKind::Token,
+                    });
+                    continue;
+                }
+            }
+            // Edges may end at, but not cross, the next protected span
+            let limit = spans.peek().map_or(n, |&(start, _, _)| start);
+            if !clusters[i] {
+                continue;
+            }
+            let first = edges.len();
+            for source in sources {
+                source.candidates(&text, i, limit, &mut edges);
+            }
+            let mut kept = first;
+            for k in first..edges.len() {
+                let end = edges[k].end;
+                if end > i && end <= limit && clusters[end] {
+                    edges.swap(kept, k);
+                    kept += 1;
+                }
+            }
+            edges.truncate(kept);
+        }
+        offsets.push(edges.len());
+
+        Ok(Lattice {
+            chars,
+            edges,
+            grammar: Arc::clone(grammar),
+            offsets,
+        })
+    }
+
+    /// Detaches the lattice from the dictionary it was built from.
+    pub fn into_owned(self) -> Lattice<'static> {
+        let edges = self
+            .edges
+            .into_iter()
+            .map(|e| Edge {
+                pos: Cow::Owned(e.pos.into_owned()),
+                lemma: Cow::Owned(e.lemma.into_owned()),
+                ..e
+            })
+            .collect();
+        Lattice {
+            chars: self.chars,
+            edges,
+            grammar: self.grammar,
+            offsets: self.offsets,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    /// Index in `edges` of the first edge starting at `i`.
+    pub fn first_edge(&self, i: usize) -> usize {
+        self.offsets[i]
+    }
+
+    pub fn edges_from(&self, i: usize) -> &[Edge<'a>] {
+        &self.edges[self.offsets[i]..self.offsets[i + 1]]
+    }
+
+    pub fn surface(&self, edge: &Edge) -> String {
+        self.chars[edge.start..edge.end].iter().collect()
+    }
+
+    /// Cost of taking `edge` after a hypothesis whose last edge is tagged
+    /// `prev_pos`, or `None` when the grammar forbids the transition.
+    pub fn step_cost(&self, prev_pos: Option<&str>, edge: &Edge) -> Option<f64> {
+        if edge.kind != EdgeKind::Dict {
+            return Some(edge.cost);
+        }
+        match prev_pos {
+            Some(pp) => {
+                if !self.grammar.allows(pp, &edge.pos) {
+                    return None;
+                }
+                Some(edge.cost - self.grammar.transition_bonus(pp, &edge.pos))
+            }
+            None => Some(edge.cost),
+        }
+    }
+
+    /// `step_cost` with the tag before `prev_pos` known as well, adding the
+    /// grammar's bonus for the three-tag sequence.
+    pub fn trigram_step_cost(
+        &self,
+        prev2_pos: Option<&str>,
+        prev_pos: Option<&str>,
+        edge: &Edge,
+    ) -> Option<f64> {
+        let step = self.step_cost(prev_pos, edge)?;
+        match (prev2_pos, prev_pos) {
+            (Some(p2), Some(p)) if edge.kind == EdgeKind::Dict => {
+                Some(step - self.grammar.trigram_bonus(p2, p, &edge.pos))
+            }
+            _ => Some(step),
+        }
+    }
+
+    pub fn morphemes(&self, path: &Path) -> Vec<Morpheme> {
+        path.edges
+            .iter()
+            .map(|&e| {
+                let edge = &self.edges[e];
+                (
+                    self.surface(edge),
+                    edge.pos.to_string(),
+                    edge.lemma.to_string(),
+                )
+            })
+            .collect()
+    }
+
+    /// The named terms making up the cost of each edge of `path`, with its
+    /// surface and POS; they add up to `path.cost`. `second_order` counts
+    /// the three-tag bonuses as well.
+    pub fn breakdown(&self, path: &Path, second_order: bool) -> Vec<Step> {
+        let mut steps = Vec::with_capacity(path.edges.len());
+        let (mut prev2_pos, mut prev_pos): (Option<&str>, Option<&str>) = (None, None);
+        for &e in &path.edges {
+            let edge = &self.edges[e];
+            let mut terms = match edge.kind {
+                EdgeKind::Dict => {
+                    let len = edge.end - edge.start;
+                    // An entry's own cost replaces the length-based one
+                    if edge.cost == self.grammar.word_cost(len, &edge.pos) {
+                        self.grammar.word_cost_terms(len, &edge.pos)
+                    } else {
+                        vec![("entry cost".to_string(), edge.cost)]
+                    }
+                }
+                EdgeKind::Oov => vec![("unknown".to_string(), edge.cost)],
+                EdgeKind::Token => vec![("token".to_string(), edge.cost)],
+            };
+            if edge.kind == EdgeKind::Dict {
+                if let Some(prev) = prev_pos {
+                    terms.extend(self.grammar.transition_term(prev, &edge.pos));
+                    if let Some(prev2) = prev2_pos.filter(|_| second_order) {
+                        terms.extend(self.grammar.trigram_term(prev2, prev, &edge.pos));
+                    }
+                }
+            }
+            steps.push((self.surface(edge), edge.pos.to_string(), terms));
+            (prev2_pos, prev_pos) = (prev_pos, Some(edge.pos.as_ref()));
+        }
+        steps
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Viterbi / N-best Decoding
+// -----------------------------------------------------------------------------
+
+/// One edge of an analysis as `(surface, pos, terms)`, for score breakdowns.
+pub(crate) type Step = (String, String, Vec<Term>);
+
+/// A complete analysis: indices into `Lattice::edges`, left to right.
+#[derive(Clone, Debug)]
+pub(crate) struct Path {
+    pub cost: f64,
+    pub edges: Vec<usize>,
+}
+
+#[derive(Clone, Copy)]
+struct Hyp {
+    cost: f64,
+    // (edge index, hypothesis index at edge.start); None for the start state
+    back: Option<(usize, usize)>,
+}
+
+/// Positions decoded between two calls of the interruption check.
+pub(crate) const CHECK_INTERVAL: usize = 1024;
+
+/// Keeps the `beam` cheapest hypotheses per position and returns up to `beam`
+/// complete paths, best first. With `beam == 1` this is the classic Viterbi pass.
+pub(crate) fn decode(lattice: &Lattice, beam: usize) -> Vec<Path> {
+    match decode_with(lattice, beam, || Ok::<(), Infallible>(())) {
+        Ok(paths) => paths,
+        Err(never) => match never {},
+    }
+}
+
+/// `decode`, calling `check` every `CHECK_INTERVAL` positions and giving up
+/// with its error, so long inputs can be interrupted.
+pub(crate) fn decode_with<E>(
+    lattice: &Lattice,
+    beam: usize,
+    mut check: impl FnMut() -> Result<(), E>,
+) -> Result<Vec<Path>, E> {
+    let n = lattice.len();
+    let beam = beam.max(1);
+    let mut cells: Vec<Vec<Hyp>> = vec![Vec::new(); n + 1];
+    cells[0].push(Hyp {
+        cost: 0.0,
+        back: None,
+    });
+
+    for i in 0..n {
+        if i % CHECK_INTERVAL == CHECK_INTERVAL - 1 {
+            check()?;
+        }
+        // edges always move forward, so cell i is final once we reach it
+        let (done, pending) = cells.split_at_mut(i + 1);
+        let hyps = &done[i];
+
+        for (h, hyp) in hyps.iter().enumerate() {
+            let prev_pos = hyp.back.map(|(e, _)| lattice.edges[e].pos.as_ref());
+
+            for (k, edge) in lattice.edges_from(i).iter().enumerate() {
+                let Some(step) = lattice.step_cost(prev_pos, edge) else {
+                    continue;
+                };
+                let total_cost = hyp.cost + step;
+                let cell = &mut pending[edge.end - i - 1];
+                // Ties keep the earlier hypothesis, matching a strict `<` relaxation.
+                let at = cell.partition_point(|other| other.cost <= total_cost);
+                if at < beam {
+                    cell.insert(
+                        at,
+                        Hyp {
+                            cost: total_cost,
+                            back: Some((lattice.first_edge(i) + k, h)),
+                        },
+                    );
+                    cell.truncate(beam);
+                }
+            }
+        }
+    }
+
+    Ok(cells[n]
+        .iter()
+        .enumerate()
+        .map(|(h, hyp)| Path {
+            cost: hyp.cost,
+            edges: backtrace(lattice, &cells, n, h),
+        })
+        .collect())
+}
+
+// A second-order hypothesis, told apart from others at its position by the
+// tags of its last two edges
+#[derive(Clone, Copy)]
+struct Hyp2<'l> {
+    cost: f64,
+    back: Option<(usize, usize)>,
+    context: (Option<&'l str>, Option<&'l str>),
+}
+
+/// Second-order decoding: hypotheses at a position are kept apart by the
+/// tags of their last two edges, up to `beam` per pair, so a path is not
+/// dropped for a cheaper one that differs only in its earlier context, and
+/// the grammar's three-tag bonuses apply. Each position keeps its
+/// `max_states` cheapest hypotheses. Returns up to `beam` paths, best first.
+pub(crate) fn decode_second_order_with<E>(
+    lattice: &Lattice,
+    beam: usize,
+    max_states: usize,
+    mut check: impl FnMut() -> Result<(), E>,
+) -> Result<Vec<Path>, E> {
+    let n = lattice.len();
+    let (beam, max_states) = (beam.max(1), max_states.max(1));
+    let mut cells: Vec<Vec<Hyp2>> = vec![Vec::new(); n + 1];
+    cells[0].push(Hyp2 {
+        cost: 0.0,
+        back: None,
+        context: (None, None),
+    });
+
+    for i in 0..n {
+        if i % CHECK_INTERVAL == CHECK_INTERVAL - 1 {
+            check()?;
+        }
+        let (done, pending) = cells.split_at_mut(i + 1);
+        let hyps = &mut done[i];
+        // Final now: prune to the cheapest states, earlier first on ties
+        hyps.sort_by(|a, b| a.cost.total_cmp(&b.cost));
+        hyps.truncate(max_states);
+
+        for (h, hyp) in hyps.iter().enumerate() {
+            let (prev2_pos, prev_pos) = hyp.context;
+            for (k, edge) in lattice.edges_from(i).iter().enumerate() {
+                let Some(step) = lattice.trigram_step_cost(prev2_pos, prev_pos, edge) else {
+                    continue;
+                };
+                let next = Hyp2 {
+                    cost: hyp.cost + step,
+                    back: Some((lattice.first_edge(i) + k, h)),
+                    context: (prev_pos, Some(edge.pos.as_ref())),
+                };
+                let cell = &mut pending[edge.end - i - 1];
+                let same = |other: &&mut Hyp2| other.context == next.context;
+                if cell.iter_mut().filter(same).count() < beam {
+                    cell.push(next);
+                } else if let Some(worst) = cell
+                    .iter_mut()
+                    .filter(same)
+                    .max_by(|a, b| a.cost.total_cmp(&b.cost))
+                {
+                    if next.cost < worst.cost {
+                        *worst = next;
+                    }
+                }
+            }
+        }
+    }
+
+    let last = &mut cells[n];
+    last.sort_by(|a, b| a.cost.total_cmp(&b.cost));
+    last.truncate(beam);
+    Ok((0..cells[n].len())
+        .map(|h| {
+            let mut edges = Vec::new();
+            let mut curr = (n, h);
+            while let Some((e, back)) = cells[curr.0][curr.1].back {
+                edges.push(e);
+                curr = (lattice.edges[e].start, back);
+            }
+            edges.reverse();
+            Path {
+                cost: cells[n][h].cost,
+                edges,
+            }
+        })
+        .collect())
+}
+
+/// The furthest position a path from the start reaches under the
+/// transition constraints; `lattice.len()` when some path is complete.
+/// Explains where decoding got stuck when it finds no path.
+pub(crate) fn furthest_reach(lattice: &Lattice) -> usize {
+    let n = lattice.len();
+    // The POS of the last edge of the paths reaching each position
+    let mut reached: Vec<Vec<Option<&str>>> = vec![Vec::new(); n + 1];
+    reached[0].push(None);
+    let mut furthest = 0;
+    for i in 0..n {
+        let prevs = std::mem::take(&mut reached[i]);
+        if prevs.is_empty() {
+            continue;
+        }
+        furthest = i;
+        for edge in lattice.edges_from(i) {
+            let pos = Some(edge.pos.as_ref());
+            if prevs.iter().any(|&p| lattice.step_cost(p, edge).is_some())
+                && !reached[edge.end].contains(&pos)
+            {
+                reached[edge.end].push(pos);
+            }
+        }
+    }
+    if reached[n].is_empty() {
+        furthest
+    } else {
+        n
+    }
+}
+
+/// Forward-backward decoding: a right-to-left pass computes, for every edge,
+/// the cheapest completion given that edge's POS; a left-to-right walk then
+/// picks each next edge by its step cost plus that completion. Unlike the
+/// forward beam, which keeps one history per position, nothing is pruned
+/// before the end of the input is seen, so the result is the cheapest path.
+pub(crate) fn decode_bidirectional_with<E>(
+    lattice: &Lattice,
+    mut check: impl FnMut() -> Result<(), E>,
+) -> Result<Option<Path>, E> {
+    let n = lattice.len();
+    let rest = completions(lattice, &mut check)?;
+    let mut path = Path {
+        cost: 0.0,
+        edges: Vec::new(),
+    };
+    let (mut i, mut prev_pos) = (0, None);
+    while i < n {
+        let Some((e, total)) = best_next(lattice, &rest, i, prev_pos) else {
+            return Ok(None);
+        };
+        if path.edges.is_empty() {
+            path.cost = total;
+        }
+        path.edges.push(e);
+        i = lattice.edges[e].end;
+        prev_pos = Some(&lattice.edges[e].pos);
+    }
+    Ok(Some(path))
+}
+
+/// For every edge, the cheapest cost from its end to the end of the input
+/// after it; `None` when no path continues from it.
+fn completions<E>(
+    lattice: &Lattice,
+    mut check: impl FnMut() -> Result<(), E>,
+) -> Result<Vec<Option<f64>>, E> {
+    let n = lattice.len();
+    let mut rest: Vec<Option<f64>> = vec![None; lattice.edges.len()];
+    for i in (0..n).rev() {
+        if i % CHECK_INTERVAL == CHECK_INTERVAL - 1 {
+            check()?;
+        }
+        for (k, edge) in lattice.edges_from(i).iter().enumerate() {
+            rest[lattice.first_edge(i) + k] = if edge.end == n {
+                Some(0.0)
+            } else {
+                best_next(lattice, &rest, edge.end, Some(&edge.pos)).map(|(_, c)| c)
+            };
+        }
+    }
+    Ok(rest)
+}
+
+/// The margin of each decision along `path`: at every edge's start, after
+/// the edge before it, the cheapest complete cost through any other edge
+/// from there minus the cost through the edge taken. Edges with the same
+/// end, POS and lemma make the same decision and do not compete. Infinite
+/// when nothing else leads to the end; negative when `path` is not the
+/// cheapest first-order continuation (second-order decoding).
+pub(crate) fn margins<E>(
+    lattice: &Lattice,
+    path: &Path,
+    check: impl FnMut() -> Result<(), E>,
+) -> Result<Vec<f64>, E> {
+    let rest = completions(lattice, check)?;
+    let mut prev_pos = None;
+    let mut out = Vec::with_capacity(path.edges.len());
+    for &e in &path.edges {
+        let taken = &lattice.edges[e];
+        let first = lattice.first_edge(taken.start);
+        let total =
+            |k: usize, edge: &Edge| Some(lattice.step_cost(prev_pos, edge)? + rest[first + k]?);
+        let chosen = total(e - first, taken).unwrap_or(f64::INFINITY);
+        let rival = lattice
+            .edges_from(taken.start)
+            .iter()
+            .enumerate()
+            .filter(|(_, edge)| {
+                (edge.end, &edge.pos, &edge.lemma) != (taken.end, &taken.pos, &taken.lemma)
+            })
+            .filter_map(|(k, edge)| total(k, edge))
+            .fold(f64::INFINITY, f64::min);
+        out.push(rival - chosen);
+        prev_pos = Some(&*taken.pos);
+    }
+    Ok(out)
+}
+
+// Cheapest edge from `i` after `prev_pos`, counting its completion cost
+fn best_next(
+    lattice: &Lattice,
+    rest: &[Option<f64>],
+    i: usize,
+    prev_pos: Option<&str>,
+) -> Option<(usize, f64)> {
+    let mut best: Option<(usize, f64)> = None;
+    for (k, edge) in lattice.edges_from(i).iter().enumerate() {
+        let e = lattice.first_edge(i) + k;
+        let (Some(step), Some(after)) = (lattice.step_cost(prev_pos, edge), rest[e]) else {
+            continue;
+        };
+        let total = step + after;
+        if best.is_none_or(|(_, c)| total < c) {
+            best = Some((e, total));
+        }
+    }
+    best
+}
+
+fn backtrace(lattice: &Lattice, cells: &[Vec<Hyp>], end: usize, hyp: usize) -> Vec<usize> {
+    let mut edges = Vec::new();
+    let mut curr = (end, hyp);
+    while let Some((e, back)) = cells[curr.0][curr.1].back {
+        edges.push(e);
+        curr = (lattice.edges[e].start, back);
+    }
+    edges.reverse();
+    edges
+}
+
+// -----------------------------------------------------------------------------
+// Greedy Segmentation
+// -----------------------------------------------------------------------------
+
+/// Longest-match segmentation of `text` without a lattice: from each
+/// cluster boundary, the longest dictionary surface within the eojeol (as
+/// its cheapest entry), else one cluster as the grammar's fallback unknown.
+/// For text there is no time left to decode; stopping at whitespace keeps
+/// it several times cheaper than building a lattice.
+pub(crate) fn greedy(view: &DictView, text: &str, grammar: &dyn Grammar) -> Vec<Morpheme> {
+    let chars: Vec<char> = text.chars().collect();
+    let clusters = grapheme::boundaries(&chars);
+    let bytes = char_offsets(text);
+    let n = chars.len();
+    let mut morphs = Vec::new();
+    let mut i = 0;
+    while i < n {
+        let mut found = None;
+        let eojeol = chars[i..]
+            .iter()
+            .take(MAX_WORD_LEN)
+            .take_while(|c| !c.is_whitespace())
+            .count();
+        for len in (1..=MAX_WORD_LEN.min(eojeol)).rev() {
+            if !clusters[i + len] {
+                continue;
+            }
+            let surface = &text[bytes[i]..bytes[i + len]];
+            let mut cheapest: Option<(f64, PatternRef)> = None;
+            view.for_each(surface, |pat| {
+                let cost = pat.cost.unwrap_or_else(|| grammar.word_cost(len, pat.pos));
+                if cheapest.is_none_or(|(c, _)| cost < c) {
+                    cheapest = Some((cost, pat));
+                }
+            });
+            if let Some((_, pat)) = cheapest {
+                found = Some((
+                    surface.to_string(),
+                    pat.pos.to_string(),
+                    pat.lemma.to_string(),
+                ));
+                break;
+            }
+        }
+        let morph = found.unwrap_or_else(|| {
+            let end = (i + 1..=n).find(|&j| clusters[j]).unwrap_or(n);
+            let surface: String = chars[i..end].iter().collect();
+            let u = grammar.fallback(1);
+            let lemma = u.lemma.map_or_else(|| surface.clone(), str::to_string);
+            (surface, u.pos.to_string(), lemma)
+        });
+        i += morph.0.chars().count();
+        morphs.push(morph);
+    }
+    morphs
+}
@@ -0,0 +1,382 @@
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq, SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::hashing::KeyMap;
+use crate::smallstr::SmallStr;
+use crate::view::{Ban, PatternRef};
+
+/// Longest dictionary surface (in characters) considered during lookup.
+pub(crate) const MAX_WORD_LEN: usize = 16;
+
+// Cap on pre-sizing from a serialized entry count, so a corrupt count
+// cannot request an absurd allocation up front
+const MAX_PRESIZE: usize = 1 << 24;
+
+// -----------------------------------------------------------------------------
+// Data Structures
+// -----------------------------------------------------------------------------
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct TriePattern {
+    // Ids in the dictionary's string pool
+    pub(crate) pos: u32,
+    pub(crate) lemma: u32,
+    // Replaces the length-based word cost when set
+    pub(crate) cost: Option<f64>,
+    // Provenance; boxed since most entries carry none
+    pub(crate) meta: Option<Box<EntryMeta>>,
+}
+
+/// Where an entry came from, for tracing bad entries back to their source.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub(crate) struct EntryMeta {
+    /// Source tag, e.g. the user dictionary it was loaded from
+    pub(crate) source: Option<String>,
+    /// Priority tier
+    pub(crate) priority: Option<u8>,
+    pub(crate) note: Option<String>,
+}
+
+impl EntryMeta {
+    /// `self` with the fields set in `other` replaced.
+    pub(crate) fn merged(mut self, other: EntryMeta) -> Self {
+        if other.source.is_some() {
+            self.source = other.source;
+        }
+        if other.priority.is_some() {
+            self.priority = other.priority;
+        }
+        if other.note.is_some() {
+            self.note = other.note;
+        }
+        self
+    }
+}
+
+/// POS tags and lemmas, stored once however many entries share them
+/// (하다 alone backs tens of thousands of conjugated entries).
+#[derive(Default, Clone)]
+pub(crate) struct StringPool {
+    strings: Vec<SmallStr>,
+    ids: KeyMap<SmallStr, u32>,
+}
+
+impl StringPool {
+    pub(crate) fn intern(&mut self, s: &str) -> u32 {
+        if let Some(&id) = self.ids.get(s) {
+            return id;
+        }
+        let id = self.strings.len() as u32;
+        self.strings.push(SmallStr::new(s));
+        self.ids.insert(SmallStr::new(s), id);
+        id
+    }
+
+    /// The id of `s`, if any entry has used it.
+    pub(crate) fn id(&self, s: &str) -> Option<u32> {
+        self.ids.get(s).copied()
+    }
+
+    pub(crate) fn get(&self, id: u32) -> &str {
+        &self.strings[id as usize]
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.strings.len()
+    }
+}
+
+// Inner data struct that is Pure Rust and Serializable
+#[derive(Deserialize, Default, Clone)]
+#[serde(try_from = "Stored")]
+pub(crate) struct TrieData {
+    pub(crate) dict: KeyMap<SmallStr, Vec<TriePattern>>,
+    pub(crate) strings: StringPool,
+}
+
+/// The serialized layout: the pool, then the map with ids into it.
+#[derive(Deserialize)]
+struct Stored {
+    strings: Vec<SmallStr>,
+    #[serde(deserialize_with = "presized")]
+    dict: KeyMap<SmallStr, Vec<TriePattern>>,
+}
+
+impl TryFrom<Stored> for TrieData {
+    type Error = String;
+
+    fn try_from(stored: Stored) -> Result<Self, String> {
+        let n = stored.strings.len();
+        let mut patterns = stored.dict.values().flatten();
+        if patterns.any(|p| p.pos as usize >= n || p.lemma as usize >= n) {
+            return Err(format!("pattern refers past the {} pooled strings", n));
+        }
+        let ids = (0..n as u32)
+            .map(|id| (stored.strings[id as usize].clone(), id))
+            .collect();
+        Ok(TrieData {
+            dict: stored.dict,
+            strings: StringPool {
+                strings: stored.strings,
+                ids,
+            },
+        })
+    }
+}
+
+/// Serializes the pool with only the strings in use, sorted, and the map
+/// in key order rather than hash order, with each key's patterns in
+/// `TrieData::sorted` order, so identical dictionaries save to identical
+/// bytes whatever their insertion history.
+impl Serialize for TrieData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut used = vec![false; self.strings.len()];
+        for p in self.dict.values().flatten() {
+            used[p.pos as usize] = true;
+            used[p.lemma as usize] = true;
+        }
+        let mut order: Vec<u32> = (0..self.strings.len() as u32)
+            .filter(|&id| used[id as usize])
+            .collect();
+        order.sort_unstable_by_key(|&id| self.strings.get(id));
+        let mut remap = vec![0; self.strings.len()];
+        for (new, &old) in order.iter().enumerate() {
+            remap[old as usize] = new as u32;
+        }
+        let strings: Vec<&str> = order.iter().map(|&id| self.strings.get(id)).collect();
+        let dict = Entries {
+            data: self,
+            remap: &remap,
+        };
+
+        let mut stored = serializer.serialize_struct("TrieData", 2)?;
+        stored.serialize_field("strings", &strings)?;
+        stored.serialize_field("dict", &dict)?;
+        stored.end()
+    }
+}
+
+struct Entries<'a> {
+    data: &'a TrieData,
+    remap: &'a [u32],
+}
+
+impl Serialize for Entries<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut keys: Vec<&SmallStr> = self.data.dict.keys().collect();
+        keys.sort_unstable();
+        let mut map = serializer.serialize_map(Some(keys.len()))?;
+        for key in keys {
+            let patterns = self.data.sorted(&self.data.dict[key]);
+            map.serialize_entry(key, &Patterns(&patterns, self.remap))?;
+        }
+        map.end()
+    }
+}
+
+struct Patterns<'a>(&'a [&'a TriePattern], &'a [u32]);
+
+#[derive(Serialize)]
+struct StoredPattern<'a> {
+    pos: u32,
+    lemma: u32,
+    cost: Option<f64>,
+    meta: Option<&'a EntryMeta>,
+}
+
+impl Serialize for Patterns<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Patterns(patterns, remap) = self;
+        let mut seq = serializer.serialize_seq(Some(patterns.len()))?;
+        for p in patterns.iter() {
+            seq.serialize_element(&StoredPattern {
+                pos: remap[p.pos as usize],
+                lemma: remap[p.lemma as usize],
+                cost: p.cost,
+                meta: p.meta.as_deref(),
+            })?;
+        }
+        seq.end()
+    }
+}
+
+/// Deserializes the map at the capacity given by its stored entry count
+/// instead of growing it by doubling (serde caps its own pre-sizing).
+fn presized<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<KeyMap<SmallStr, Vec<TriePattern>>, D::Error> {
+    struct Presized;
+
+    impl<'de> Visitor<'de> for Presized {
+        type Value = KeyMap<SmallStr, Vec<TriePattern>>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a map of surfaces to patterns")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let capacity = map.size_hint().unwrap_or(0).min(MAX_PRESIZE);
+            let mut dict = KeyMap::with_capacity_and_hasher(capacity, Default::default());
+            while let Some((surface, patterns)) = map.next_entry()? {
+                dict.insert(surface, patterns);
+            }
+            Ok(dict)
+        }
+    }
+
+    deserializer.deserialize_map(Presized)
+}
+
+impl TrieData {
+    pub(crate) fn insert(&mut self, word: String, pos: String, lemma: String, cost: Option<f64>) {
+        let (pos, lemma) = (self.strings.intern(&pos), self.strings.intern(&lemma));
+        // Most surfaces have a single pattern; `or_default` would grow to four
+        let entry = self
+            .dict
+            .entry(word.into())
+            .or_insert_with(|| Vec::with_capacity(1));
+        match entry.iter_mut().find(|p| p.pos == pos && p.lemma == lemma) {
+            Some(existing) => {
+                if cost.is_some() {
+                    existing.cost = cost;
+                }
+            }
+            None => entry.push(TriePattern {
+                pos,
+                lemma,
+                cost,
+                meta: None,
+            }),
+        }
+    }
+
+    /// Inserts `(word, pos, lemma)` entries in one pass, reserving room for
+    /// them up front. Entries already present are left as they are; returns
+    /// how many were new.
+    pub(crate) fn insert_batch(
+        &mut self,
+        entries: impl IntoIterator<Item = (String, String, String)>,
+    ) -> usize {
+        let entries = entries.into_iter();
+        self.dict.reserve(entries.size_hint().0);
+        let mut added = 0;
+        for (word, pos, lemma) in entries {
+            let (pos, lemma) = (self.strings.intern(&pos), self.strings.intern(&lemma));
+            let patterns = self
+                .dict
+                .entry(word.into())
+                .or_insert_with(|| Vec::with_capacity(1));
+            if !patterns.iter().any(|p| p.pos == pos && p.lemma == lemma) {
+                patterns.push(TriePattern {
+                    pos,
+                    lemma,
+                    cost: None,
+                    meta: None,
+                });
+                added += 1;
+            }
+        }
+        added
+    }
+
+    /// Appends `pat` to the patterns of `word` without checking for an
+    /// existing (pos, lemma).
+    pub(crate) fn push(&mut self, word: &str, pat: PatternRef) {
+        let pattern = TriePattern {
+            pos: self.strings.intern(pat.pos),
+            lemma: self.strings.intern(pat.lemma),
+            cost: pat.cost,
+            meta: pat.meta.map(|m| Box::new(m.to_owned())),
+        };
+        match self.dict.get_mut(word) {
+            Some(patterns) => patterns.push(pattern),
+            None => {
+                self.dict.insert(word.into(), vec![pattern]);
+            }
+        }
+    }
+
+    /// `patterns` by tag, then lemma, then cost (unset first): the order
+    /// saved and compiled dictionaries list them in, whatever order they
+    /// were inserted in.
+    pub(crate) fn sorted<'a>(&self, patterns: &'a [TriePattern]) -> Vec<&'a TriePattern> {
+        let mut sorted: Vec<&TriePattern> = patterns.iter().collect();
+        sorted.sort_by(|a, b| {
+            let strings = |p: &TriePattern| (self.strings.get(p.pos), self.strings.get(p.lemma));
+            strings(a)
+                .cmp(&strings(b))
+                .then_with(|| match (a.cost, b.cost) {
+                    (Some(x), Some(y)) => x.total_cmp(&y),
+                    (x, y) => x.is_some().cmp(&y.is_some()),
+                })
+        });
+        sorted
+    }
+
+    /// The entry `pat` of this dictionary, with its strings.
+    pub(crate) fn pattern<'a>(&'a self, pat: &'a TriePattern) -> PatternRef<'a> {
+        pat.as_ref(&self.strings)
+    }
+
+    /// Releases the spare capacity of the map and of every pattern list
+    /// (a list grown by one push holds room for four).
+    /// Drops the entries costing more than `max_cost`, by their own cost or
+    /// else `default_cost` of their length and tag; returns how many.
+    pub(crate) fn prune(
+        &mut self,
+        max_cost: f64,
+        default_cost: impl Fn(usize, &str) -> f64,
+    ) -> usize {
+        let strings = &self.strings;
+        let mut dropped = 0;
+        self.dict.retain(|surface, patterns| {
+            let before = patterns.len();
+            patterns.retain(|p| {
+                p.cost
+                    .unwrap_or_else(|| default_cost(surface.chars().count(), strings.get(p.pos)))
+                    <= max_cost
+            });
+            dropped += before - patterns.len();
+            !patterns.is_empty()
+        });
+        dropped
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.dict.shrink_to_fit();
+        self.dict.values_mut().for_each(Vec::shrink_to_fit);
+    }
+
+    /// Merges `meta` into the metadata of an existing entry; false if the
+    /// entry is absent.
+    pub(crate) fn set_meta(&mut self, word: &str, pos: &str, lemma: &str, meta: EntryMeta) -> bool {
+        let (Some(pos), Some(lemma)) = (self.strings.id(pos), self.strings.id(lemma)) else {
+            return false;
+        };
+        let Some(pat) = self
+            .dict
+            .get_mut(word)
+            .and_then(|ps| ps.iter_mut().find(|p| p.pos == pos && p.lemma == lemma))
+        else {
+            return false;
+        };
+        let current = pat.meta.take().map(|m| *m).unwrap_or_default();
+        pat.meta = Some(Box::new(current.merged(meta)));
+        true
+    }
+
+    /// Removes the entries of `word` matching `ban`, returning how many went.
+    pub(crate) fn remove(&mut self, word: &str, ban: &Ban) -> usize {
+        let Some(patterns) = self.dict.get_mut(word) else {
+            return 0;
+        };
+        let before = patterns.len();
+        patterns.retain(|p| !ban.matches(p.as_ref(&self.strings)));
+        let removed = before - patterns.len();
+        if patterns.is_empty() {
+            self.dict.remove(word);
+        }
+        removed
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+// -----------------------------------------------------------------------------
+// Word Discovery
+// -----------------------------------------------------------------------------
+//
+// Cohesion of a word w = c1..cn, counted over the left substrings of the
+// corpus tokens: (count(w) / count(c1)) ^ (1 / (n - 1)). It rises while the
+// characters keep appearing together and drops once w runs past a word
+// boundary into its particles, so a local maximum marks a likely word.
+
+/// Left-substring counts of a raw corpus.
+pub(crate) struct CorpusStats {
+    max_len: usize,
+    prefixes: HashMap<String, u32>,
+}
+
+/// Runs of letters and digits; punctuation and spaces end a token.
+pub(crate) fn tokens(text: &str) -> impl Iterator<Item = Vec<char>> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.chars().collect())
+}
+
+impl CorpusStats {
+    pub fn new(max_len: usize) -> Self {
+        CorpusStats {
+            max_len: max_len.max(1),
+            prefixes: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, text: &str) {
+        for token in tokens(text) {
+            for len in 1..=token.len().min(self.max_len) {
+                let prefix: String = token[..len].iter().collect();
+                *self.prefixes.entry(prefix).or_default() += 1;
+            }
+        }
+    }
+
+    pub fn count(&self, word: &str) -> u32 {
+        self.prefixes.get(word).copied().unwrap_or(0)
+    }
+
+    pub fn cohesion(&self, word: &str) -> f64 {
+        let n = word.chars().count();
+        let Some(first) = word.chars().next() else {
+            return 0.0;
+        };
+        if n < 2 {
+            return 0.0;
+        }
+        let base = self.count(first.encode_utf8(&mut [0; 4]));
+        if base == 0 {
+            return 0.0;
+        }
+        (self.count(word) as f64 / base as f64).powf(1.0 / (n - 1) as f64)
+    }
+
+    /// Words of two or more characters seen `min_count` times whose cohesion
+    /// is at least `min_cohesion` and higher than every one-character
+    /// extension of them, as `(word, cohesion, count)`, most cohesive first.
+    /// A peak that extends an earlier peak (카카오 → 카카오에서) is the word
+    /// plus a particle and is left out.
+    pub fn candidates(&self, min_count: u32, min_cohesion: f64) -> Vec<(String, f64, u32)> {
+        // Best cohesion among the extensions w + c, keyed by w
+        let mut best_ext: HashMap<&str, f64> = HashMap::new();
+        for word in self.prefixes.keys() {
+            if let Some((cut, _)) = word.char_indices().last() {
+                if cut > 0 {
+                    let slot = best_ext.entry(&word[..cut]).or_insert(0.0);
+                    *slot = slot.max(self.cohesion(word));
+                }
+            }
+        }
+        let peaks: HashMap<&str, (f64, u32)> = self
+            .prefixes
+            .iter()
+            .filter(|(word, &count)| count >= min_count && word.chars().nth(1).is_some())
+            .filter_map(|(word, &count)| {
+                let score = self.cohesion(word);
+                let peak = best_ext.get(word.as_str()).is_none_or(|&ext| score > ext);
+                (score >= min_cohesion && peak).then_some((word.as_str(), (score, count)))
+            })
+            .collect();
+        let mut out: Vec<(String, f64, u32)> = peaks
+            .iter()
+            .filter(|(word, _)| {
+                !word
+                    .char_indices()
+                    .skip(2)
+                    .any(|(cut, _)| peaks.contains_key(&word[..cut]))
+            })
+            .map(|(word, &(score, count))| (word.to_string(), score, count))
+            .collect();
+        out.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        out
+    }
+}
@@ -0,0 +1,58 @@
+use std::fmt::Write;
+
+use crate::decoder::{EdgeKind, Lattice, Path};
+
+// -----------------------------------------------------------------------------
+// Graphviz Export
+// -----------------------------------------------------------------------------
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Renders the lattice as a DOT digraph: one node per character boundary,
+/// one edge per candidate labeled with surface/POS and cost. Edges of `best`
+/// are drawn bold red; unknown-word edges are dashed.
+pub(crate) fn render(lattice: &Lattice, best: Option<&Path>) -> String {
+    let on_path: Vec<bool> = {
+        let mut v = vec![false; lattice.edges.len()];
+        best.iter()
+            .flat_map(|p| &p.edges)
+            .for_each(|&e| v[e] = true);
+        v
+    };
+    let mut out = String::from("digraph lattice {\n  rankdir=LR;\n  node [shape=circle];\n");
+    for i in 0..=lattice.len() {
+        let _ = writeln!(out, "  n{} [label=\"{}\"];", i, i);
+    }
+    for (idx, edge) in lattice.edges.iter().enumerate() {
+        let mut attrs = format!(
+            "label=\"{}/{}\\n{:.1}\"",
+            escape(&lattice.surface(edge)),
+            escape(&edge.pos),
+            edge.cost
+        );
+        if edge.kind == EdgeKind::Oov {
+            attrs.push_str(", style=dashed");
+        }
+        if on_path[idx] {
+            attrs.push_str(", color=red, penwidth=2.5, fontcolor=red");
+        } else if edge.kind == EdgeKind::Oov {
+            attrs.push_str(", color=gray");
+        }
+        let _ = writeln!(out, "  n{} -> n{} [{}];", edge.start, edge.end, attrs);
+    }
+    out.push_str("}\n");
+    out
+}
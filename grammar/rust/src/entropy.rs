@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use pyo3::prelude::*;
+
+use crate::discovery;
+
+// -----------------------------------------------------------------------------
+// Branching Entropy
+// -----------------------------------------------------------------------------
+//
+// The entropy of the characters seen right after (or before) a substring.
+// Inside a word the next character is predictable; at a word boundary it
+// varies, so high entropy marks a likely boundary. Token edges count as a
+// fresh symbol per occurrence, which makes them maximally uncertain.
+
+#[derive(Default)]
+struct Neighbors {
+    chars: HashMap<char, u32>,
+    edges: u32,
+}
+
+impl Neighbors {
+    fn entropy(&self) -> f64 {
+        let total = self.chars.values().sum::<u32>() + self.edges;
+        if total == 0 {
+            return 0.0;
+        }
+        let total = total as f64;
+        let h: f64 = self
+            .chars
+            .values()
+            .map(|&c| {
+                let p = c as f64 / total;
+                -p * p.ln()
+            })
+            .sum();
+        h + self.edges as f64 / total * total.ln()
+    }
+}
+
+#[derive(Default)]
+struct Context {
+    left: Neighbors,
+    right: Neighbors,
+}
+
+pub(crate) struct EntropyStats {
+    pub max_len: usize,
+    contexts: HashMap<String, Context>,
+}
+
+impl EntropyStats {
+    pub fn new(max_len: usize) -> Self {
+        EntropyStats {
+            max_len: max_len.max(1),
+            contexts: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, text: &str) {
+        for token in discovery::tokens(text) {
+            let n = token.len();
+            for i in 0..n {
+                for j in i + 1..=n.min(i + self.max_len) {
+                    let ctx = self
+                        .contexts
+                        .entry(token[i..j].iter().collect())
+                        .or_default();
+                    match i.checked_sub(1) {
+                        Some(l) => *ctx.left.chars.entry(token[l]).or_default() += 1,
+                        None => ctx.left.edges += 1,
+                    }
+                    match token.get(j) {
+                        Some(&r) => *ctx.right.chars.entry(r).or_default() += 1,
+                        None => ctx.right.edges += 1,
+                    }
+                }
+            }
+        }
+    }
+
+    /// `(left, right)` entropy of `word`, or `None` if it was never seen.
+    pub fn entropies(&self, word: &str) -> Option<(f64, f64)> {
+        self.contexts
+            .get(word)
+            .map(|c| (c.left.entropy(), c.right.entropy()))
+    }
+}
+
+/// Left/right branching entropy of the substrings of a corpus.
+///
+/// Attach one to a trie with `RustTrie.set_boundary_entropy` to let it place
+/// unknown-word boundaries where the corpus suggests them.
+#[pyclass]
+pub(crate) struct BranchingEntropy {
+    pub stats: Arc<EntropyStats>,
+}
+
+#[pymethods]
+impl BranchingEntropy {
+    #[new]
+    #[pyo3(signature = (texts, max_len=6))]
+    fn new(texts: Vec<String>, max_len: usize) -> PyResult<Self> {
+        let mut stats = EntropyStats::new(max_len);
+        for text in &texts {
+            crate::check_signals()?;
+            stats.add(text);
+        }
+        Ok(BranchingEntropy {
+            stats: Arc::new(stats),
+        })
+    }
+
+    /// Entropy of the characters preceding `word` (0.0 if unseen).
+    fn left(&self, word: &str) -> f64 {
+        self.stats.entropies(word).map_or(0.0, |e| e.0)
+    }
+
+    /// Entropy of the characters following `word` (0.0 if unseen).
+    fn right(&self, word: &str) -> f64 {
+        self.stats.entropies(word).map_or(0.0, |e| e.1)
+    }
+
+    fn __len__(&self) -> usize {
+        self.stats.contexts.len()
+    }
+}
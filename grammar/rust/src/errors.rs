@@ -0,0 +1,86 @@
+use pyo3::prelude::*;
+use pyo3::PyTypeInfo;
+
+// -----------------------------------------------------------------------------
+// Located Errors
+// -----------------------------------------------------------------------------
+//
+// Failures that can be pinned to a place in the input are raised with
+// `stage`, `offset` and `substring` attributes on the exception, so a bad
+// spot in a large document or lexicon can be found without bisecting it.
+
+/// Longest `substring` reported, in characters.
+const SNIPPET_CHARS: usize = 16;
+
+/// The step of the pipeline that failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Stage {
+    /// Checks on the input before analysis (length limits)
+    Input,
+    /// No path through the lattice
+    Decode,
+    /// Adding entries to a dictionary
+    Import,
+    /// Checking an analysis against the input (`set_verify_round_trip`)
+    Verify,
+}
+
+impl Stage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Stage::Input => "input",
+            Stage::Decode => "decode",
+            Stage::Import => "import",
+            Stage::Verify => "verify",
+        }
+    }
+}
+
+/// A failure at `offset` in the input: a character offset for analysis,
+/// the entry's position for imports.
+#[derive(Clone, Debug)]
+pub(crate) struct ErrorSite {
+    pub stage: Stage,
+    pub offset: usize,
+    pub substring: String,
+    pub message: String,
+}
+
+impl ErrorSite {
+    /// A failure at character `offset` of `chars`, quoting what follows it.
+    pub fn in_text(
+        stage: Stage,
+        chars: &[char],
+        offset: usize,
+        message: impl Into<String>,
+    ) -> Self {
+        let end = (offset + SNIPPET_CHARS).min(chars.len());
+        ErrorSite {
+            stage,
+            offset,
+            substring: chars[offset.min(end)..end].iter().collect(),
+            message: message.into(),
+        }
+    }
+
+    /// Raises `T` with the location as attributes.
+    pub fn into_err<T: PyTypeInfo>(self) -> PyErr {
+        let err = PyErr::new::<T, _>(format!(
+            "{} at {} {:?} ({})",
+            self.message,
+            self.offset,
+            self.substring,
+            self.stage.as_str()
+        ));
+        let located = Python::with_gil(|py| {
+            let value = err.value(py);
+            value.setattr("stage", self.stage.as_str())?;
+            value.setattr("offset", self.offset)?;
+            value.setattr("substring", &self.substring)
+        });
+        match located {
+            Ok(()) => err,
+            Err(e) => e,
+        }
+    }
+}
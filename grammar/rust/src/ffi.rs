@@ -0,0 +1,285 @@
+use std::convert::Infallible;
+use std::ffi::{c_char, CStr, CString};
+use std::path::Path;
+use std::ptr;
+use std::sync::Arc;
+
+use crate::chartype::OovGrouping;
+use crate::decoder::{self, BuildOptions, Lattice};
+use crate::frozen::FrozenDict;
+use crate::grammar::{Grammar, Korean};
+use crate::view::{DictRef, DictView};
+use crate::{sentence, serial, Morpheme, TrieData};
+
+// -----------------------------------------------------------------------------
+// C ABI (feature `ffi`)
+// -----------------------------------------------------------------------------
+//
+// A plain C interface to the analyzer for hosts without Python: Swift
+// imports `include/kulim.h` through a module map, Kotlin through JNA or
+// Kotlin/Native cinterop. Results are flat arrays of NUL-terminated UTF-8
+// strings and character offsets, owned by the caller until they are passed
+// back to their free function. Analysis uses the default settings of a new
+// `RustTrie` over one dictionary file.
+//
+// Not yet usable on Android or iOS: the library still links the Python
+// bindings, which those builds cannot resolve, and no Kotlin or Swift
+// wrappers ship. The interface is meant for a core crate without pyo3,
+// which does not exist yet.
+
+/// An analyzer over one dictionary: compiled (`compile_trie`) or saved
+/// (`save_trie`).
+pub struct KulimAnalyzer {
+    frozen: Option<FrozenDict>,
+    data: TrieData,
+    grammar: Arc<dyn Grammar>,
+    grouping: OovGrouping,
+}
+
+#[repr(C)]
+pub struct KulimMorpheme {
+    pub surface: *mut c_char,
+    pub pos: *mut c_char,
+    pub lemma: *mut c_char,
+    /// Character offsets into the analyzed text
+    pub start: usize,
+    pub end: usize,
+}
+
+#[repr(C)]
+pub struct KulimAnalysis {
+    pub morphemes: *mut KulimMorpheme,
+    pub len: usize,
+    /// Why the analysis failed, or null
+    pub error: *mut c_char,
+}
+
+fn c_string(s: &str) -> *mut c_char {
+    // Interior NULs cannot cross the boundary; they read as U+FFFD
+    CString::new(s.replace('\0', "\u{FFFD}"))
+        .unwrap_or_default()
+        .into_raw()
+}
+
+impl KulimAnalyzer {
+    fn open(path: &str) -> Result<Self, String> {
+        let (frozen, data) = match FrozenDict::open(path, true, true) {
+            Ok(frozen) => (Some(frozen), TrieData::default()),
+            Err(_) => match serial::read(Path::new(path)) {
+                Ok((data, _)) => (None, data),
+                Err(serial::LoadError::Io(e)) => return Err(format!("{}: {}", path, e)),
+                Err(serial::LoadError::Format(e)) => return Err(format!("{}: {}", path, e)),
+            },
+        };
+        Ok(KulimAnalyzer {
+            frozen,
+            data,
+            grammar: Arc::new(Korean::default()),
+            grouping: OovGrouping::default(),
+        })
+    }
+
+    fn view(&self) -> DictView<'_> {
+        let mut dicts: Vec<DictRef> = self.frozen.iter().map(DictRef::Frozen).collect();
+        dicts.push(DictRef::Map(&self.data));
+        DictView::new(dicts, None)
+    }
+
+    /// The best analysis of `text`, chunk by chunk; a chunk no path crosses
+    /// is segmented greedily.
+    fn analyze(&self, text: &str) -> Vec<Morpheme> {
+        let view = self.view();
+        let never = || Ok::<(), Infallible>(());
+        let mut morphs = Vec::new();
+        for chunk in sentence::chunks(text, crate::InputLimits::default().chunk_chars) {
+            let opts = BuildOptions {
+                grammar: &self.grammar,
+                social: None,
+                structured: None,
+                language: None,
+                grouping: Some(&self.grouping),
+                entropy: None,
+                case_fold: None,
+                extra: None,
+                counters: None,
+                gazetteers: &[],
+                code_switching: false,
+            };
+            let Ok(lattice) = Lattice::build_with(&view, chunk, opts, never);
+            let Ok(paths) = decoder::decode_with(&lattice, 1, never);
+            match paths.first() {
+                Some(path) => morphs.extend(lattice.morphemes(path)),
+                None => morphs.extend(decoder::greedy(&view, chunk, &*self.grammar)),
+            }
+        }
+        morphs
+    }
+}
+
+/// Opens the dictionary at `path`, or returns null and sets `*error` (when
+/// not null) to why; free the message with `kulim_string_free`.
+///
+/// # Safety
+/// `path` must be a NUL-terminated string; `error` null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn kulim_open(
+    path: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut KulimAnalyzer {
+    let opened = match CStr::from_ptr(path).to_str() {
+        Ok(path) => KulimAnalyzer::open(path),
+        Err(_) => Err("path is not UTF-8".to_string()),
+    };
+    match opened {
+        Ok(analyzer) => Box::into_raw(Box::new(analyzer)),
+        Err(e) => {
+            if !error.is_null() {
+                *error = c_string(&e);
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Analyzes `text`; free the result with `kulim_analysis_free`.
+///
+/// # Safety
+/// `analyzer` must come from `kulim_open`; `text` must be NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn kulim_analyze(
+    analyzer: *const KulimAnalyzer,
+    text: *const c_char,
+) -> *mut KulimAnalysis {
+    let mut analysis = KulimAnalysis {
+        morphemes: ptr::null_mut(),
+        len: 0,
+        error: ptr::null_mut(),
+    };
+    match CStr::from_ptr(text).to_str() {
+        Ok(text) => {
+            let mut at = 0;
+            let morphs: Box<[KulimMorpheme]> = (*analyzer)
+                .analyze(text)
+                .iter()
+                .map(|(surface, pos, lemma)| {
+                    let start = at;
+                    at += surface.chars().count();
+                    KulimMorpheme {
+                        surface: c_string(surface),
+                        pos: c_string(pos),
+                        lemma: c_string(lemma),
+                        start,
+                        end: at,
+                    }
+                })
+                .collect();
+            analysis.len = morphs.len();
+            analysis.morphemes = Box::into_raw(morphs) as *mut KulimMorpheme;
+        }
+        Err(_) => analysis.error = c_string("text is not UTF-8"),
+    }
+    Box::into_raw(Box::new(analysis))
+}
+
+/// Frees a result of `kulim_analyze`.
+///
+/// # Safety
+/// `analysis` must come from `kulim_analyze` (or be null), freed once.
+#[no_mangle]
+pub unsafe extern "C" fn kulim_analysis_free(analysis: *mut KulimAnalysis) {
+    if analysis.is_null() {
+        return;
+    }
+    let analysis = Box::from_raw(analysis);
+    if !analysis.morphemes.is_null() {
+        let morphs = Box::from_raw(ptr::slice_from_raw_parts_mut(
+            analysis.morphemes,
+            analysis.len,
+        ));
+        for m in morphs.iter() {
+            kulim_string_free(m.surface);
+            kulim_string_free(m.pos);
+            kulim_string_free(m.lemma);
+        }
+    }
+    kulim_string_free(analysis.error);
+}
+
+/// Frees a string returned through the interface.
+///
+/// # Safety
+/// `s` must come from this library (or be null), freed once.
+#[no_mangle]
+pub unsafe extern "C" fn kulim_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Closes an analyzer from `kulim_open`.
+///
+/// # Safety
+/// `analyzer` must come from `kulim_open` (or be null), closed once, with
+/// no analysis running on it.
+#[no_mangle]
+pub unsafe extern "C" fn kulim_close(analyzer: *mut KulimAnalyzer) {
+    if !analyzer.is_null() {
+        drop(Box::from_raw(analyzer));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn text(s: *const c_char) -> String {
+        CStr::from_ptr(s).to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn analyzes_through_the_c_interface() {
+        let path = std::env::temp_dir().join(format!("kulim-ffi-{}.bin", std::process::id()));
+        let mut data = TrieData::default();
+        for (word, pos) in [("학교", "NNG"), ("에", "JKB")] {
+            data.insert(word.into(), pos.into(), word.into(), None);
+        }
+        serial::write(&data, &path).unwrap();
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+
+        unsafe {
+            let mut error = ptr::null_mut();
+            let analyzer = kulim_open(c_path.as_ptr(), &mut error);
+            std::fs::remove_file(&path).unwrap();
+            assert!(!analyzer.is_null() && error.is_null());
+
+            let input = CString::new("학교에 x").unwrap();
+            let analysis = kulim_analyze(analyzer, input.as_ptr());
+            assert!((*analysis).error.is_null());
+            let morphs = std::slice::from_raw_parts((*analysis).morphemes, (*analysis).len);
+            let got: Vec<(String, String, String, usize, usize)> = morphs
+                .iter()
+                .map(|m| (text(m.surface), text(m.pos), text(m.lemma), m.start, m.end))
+                .collect();
+            assert_eq!(
+                got[..2],
+                [
+                    ("학교".into(), "NNG".into(), "학교".into(), 0, 2),
+                    ("에".into(), "JKB".into(), "에".into(), 2, 3),
+                ]
+            );
+            assert_eq!(got.last().map(|m| m.4), Some(5));
+            kulim_analysis_free(analysis);
+
+            let invalid = [0xFFu8 as c_char, 0];
+            let analysis = kulim_analyze(analyzer, invalid.as_ptr());
+            assert_eq!(text((*analysis).error), "text is not UTF-8");
+            kulim_analysis_free(analysis);
+            kulim_close(analyzer);
+
+            let missing = CString::new("/nonexistent/kulim.bin").unwrap();
+            assert!(kulim_open(missing.as_ptr(), &mut error).is_null());
+            assert!(text(error).starts_with("/nonexistent/kulim.bin: "));
+            kulim_string_free(error);
+        }
+    }
+}
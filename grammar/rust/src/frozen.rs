@@ -0,0 +1,1084 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::sync::OnceLock;
+
+use crate::smallstr::SmallStr;
+use crate::view::{MetaRef, PatternRef};
+use crate::TrieData;
+
+// -----------------------------------------------------------------------------
+// Compiled (Frozen) Dictionary
+// -----------------------------------------------------------------------------
+//
+// A read-only, flat layout that is used in place from a memory-mapped file:
+// nothing is deserialized, so every process mapping the same file shares one
+// physical copy through the page cache. All integers are little-endian.
+//
+//     header          magic[8] version:u32 keys:u32 patterns:u32 strings:u32
+//                     metas:u32 buckets:u32 hash_seed:u32 hash_groups:u32
+//                     profile:u32 cost_base:f32 cost_step:f32 blocks:u32
+//     index           buckets x (first:u32 key_start:u32)   one per first
+//                     character, ascending; kept in memory once opened
+//     displacements   hash_groups x (d1:u32 d2:u32)
+//     slots           keys x u32            key index of each hash slot
+//     key_offsets     (blocks + 1) x u64    per key block, keys sorted
+//     pattern_starts  (keys + 1) x u32      into the pattern records
+//     patterns        patterns x (pos:u32 lemma:u32 cost:f64)   NaN = default
+//                     small: patterns x (lemma:u32 pos:u16 cost:u8 0:u8),
+//                     cost = cost_base + cost * cost_step, 255 = default
+//     metas           metas x (pattern:u32 source:u32 note:u32 priority:u32)
+//                     sorted by pattern; string ids, u32::MAX = unset
+//     string_offsets  (strings + 1) x u64   into the string blob
+//     key blob, string blob
+//
+// Keys sharing a first character form a bucket, the unit of lazy checking:
+// a lookup only ever touches the pages of its own bucket. Within it, the key
+// is found through a minimal perfect hash over all keys (hash and displace:
+// a key's group picks the displacement that sends it to its own slot), so
+// a lookup compares a single key instead of binary searching. Version 1
+// files (no metas), version 2 files (no index), version 3 files (no hash,
+// keys are binary searched), version 4 files (no profile) and version 5
+// files (one offset per key, keys spelled out) are still read.
+//
+// Keys are front coded: each bucket's keys are cut into blocks of
+// KEY_BLOCK, and within a block every key after the first stores only the
+// length of the prefix it shares with the one before and the rest, as
+//
+//     first key       len:varint bytes
+//     later keys      shared:varint len:varint bytes
+//
+// (LEB128 varints, byte lengths). Sorted Korean surfaces share long
+// prefixes (먹, 먹고, 먹었다, ...), so this stores a fraction of the key
+// bytes and one offset per block instead of one per key; a lookup decodes
+// at most one block.
+//
+// The small profile is for devices short of memory: half-size pattern
+// records with costs quantized to 255 steps over their range, and no
+// metadata. POS tags are pooled ahead of lemmas so their ids fit in 16 bits.
+//
+// Fork safety: the buffer is never written, and the only lazy state is the
+// per-bucket check flags on the heap, so a dictionary loaded before `fork()`
+// is read by every child without copying a single mapped page.
+
+const MAGIC: &[u8; 8] = b"KULIMFZ\0";
+const VERSION: u32 = 6;
+const HEADER_LEN: usize = 56;
+const HEADER_LEN_V1: usize = 24;
+const HEADER_LEN_V2: usize = 28;
+const HEADER_LEN_V3: usize = 32;
+const HEADER_LEN_V4: usize = 40;
+const HEADER_LEN_V5: usize = 52;
+// Keys per front-coded block
+const KEY_BLOCK: usize = 16;
+const BUCKET_LEN: usize = 8;
+const DISPLACEMENT_LEN: usize = 8;
+// Average keys per hash group
+const GROUP_SIZE: usize = 5;
+const PATTERN_LEN: usize = 16;
+const SMALL_PATTERN_LEN: usize = 8;
+// Quantized cost of an entry without one
+const DEFAULT_COST: u8 = u8::MAX;
+const META_LEN: usize = 16;
+const UNSET: u32 = u32::MAX;
+
+enum Backing {
+    #[cfg(unix)]
+    Mapped {
+        ptr: *mut libc::c_void,
+        len: usize,
+    },
+    Owned(Vec<u8>),
+}
+
+// The mapping is private to this struct and never written through.
+unsafe impl Send for Backing {}
+unsafe impl Sync for Backing {}
+
+impl Drop for Backing {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Backing::Mapped { ptr, len } = *self {
+            unsafe {
+                libc::munmap(ptr, len);
+            }
+        }
+    }
+}
+
+impl Backing {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            #[cfg(unix)]
+            Backing::Mapped { ptr, len } => unsafe {
+                std::slice::from_raw_parts(*ptr as *const u8, *len)
+            },
+            Backing::Owned(v) => v,
+        }
+    }
+
+    #[cfg(unix)]
+    fn map(file: &File) -> Result<Backing, String> {
+        use std::os::unix::io::AsRawFd;
+
+        let len = file.metadata().map_err(|e| e.to_string())?.len() as usize;
+        if len == 0 {
+            return Ok(Backing::Owned(Vec::new()));
+        }
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(Backing::Mapped { ptr, len })
+    }
+
+    #[cfg(not(unix))]
+    fn map(file: &File) -> Result<Backing, String> {
+        Backing::read(file)
+    }
+
+    fn read(mut file: &File) -> Result<Backing, String> {
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf).map_err(|e| e.to_string())?;
+        Ok(Backing::Owned(buf))
+    }
+}
+
+/// How much a compiled dictionary keeps of each entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    /// Exact costs and metadata
+    Full,
+    /// Quantized costs, no metadata
+    Small,
+}
+
+impl Profile {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "full" => Some(Profile::Full),
+            "small" => Some(Profile::Small),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::Full => "full",
+            Profile::Small => "small",
+        }
+    }
+
+    fn id(self) -> u32 {
+        match self {
+            Profile::Full => 0,
+            Profile::Small => 1,
+        }
+    }
+
+    fn pattern_len(self) -> usize {
+        match self {
+            Profile::Full => PATTERN_LEN,
+            Profile::Small => SMALL_PATTERN_LEN,
+        }
+    }
+}
+
+pub(crate) struct FrozenDict {
+    backing: Backing,
+    version: u32,
+    profile: Profile,
+    // Dequantization of small-profile costs: base + code * step
+    cost_base: f64,
+    cost_step: f64,
+    keys: usize,
+    patterns: usize,
+    strings: usize,
+    metas: usize,
+    hash_seed: u32,
+    hash_groups: usize,
+    // Section offsets into the buffer
+    displacements: usize,
+    slots: usize,
+    key_offsets: usize,
+    pattern_starts: usize,
+    records: usize,
+    meta_records: usize,
+    string_offsets: usize,
+    key_blob: usize,
+    string_blob: usize,
+    // Keys are front coded (version 6 on), with key_offsets per block
+    front_coded: bool,
+    // (first character, first key) per bucket, then a (char::MAX, keys) sentinel
+    index: Vec<(char, usize)>,
+    // First block of each bucket, then the block count; front-coded only
+    bucket_blocks: Vec<usize>,
+    // Whether each bucket's offsets are sound, settled on first access
+    checked: Box<[OnceLock<bool>]>,
+}
+
+// Shared across threads and forked workers as-is
+const _: fn() = || {
+    fn assert_shareable<T: Send + Sync>() {}
+    assert_shareable::<FrozenDict>();
+};
+
+fn read_u32(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(buf[at..at + 4].try_into().unwrap())
+}
+
+fn read_u64(buf: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(buf[at..at + 8].try_into().unwrap())
+}
+
+impl FrozenDict {
+    /// Opens a compiled dictionary, memory-mapping it when `shared` is set.
+    ///
+    /// Every bucket is checked up front unless `lazy` is set; then opening
+    /// reads only the header and the index, and a bucket is checked (and
+    /// its pages faulted in) the first time a lookup reaches it. A corrupt
+    /// bucket then reads as empty instead of failing the open.
+    pub fn open(path: &str, shared: bool, lazy: bool) -> Result<FrozenDict, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let backing = if shared {
+            Backing::map(&file)?
+        } else {
+            Backing::read(&file)?
+        };
+        FrozenDict::from_backing(backing, lazy)
+    }
+
+    fn from_backing(backing: Backing, lazy: bool) -> Result<FrozenDict, String> {
+        let buf = backing.bytes();
+        let truncated = || Err("compiled dictionary is truncated".to_string());
+        if buf.len() < HEADER_LEN_V1 || &buf[..8] != MAGIC {
+            return Err("not a compiled KULIM dictionary".to_string());
+        }
+        let version = read_u32(buf, 8);
+        let header_len = match version {
+            1 => HEADER_LEN_V1,
+            2 => HEADER_LEN_V2,
+            3 => HEADER_LEN_V3,
+            4 => HEADER_LEN_V4,
+            5 => HEADER_LEN_V5,
+            VERSION => HEADER_LEN,
+            _ => {
+                return Err(format!(
+                    "unsupported compiled dictionary version {}",
+                    version
+                ))
+            }
+        };
+        if buf.len() < header_len {
+            return truncated();
+        }
+        let keys = read_u32(buf, 12) as usize;
+        let patterns = read_u32(buf, 16) as usize;
+        let strings = read_u32(buf, 20) as usize;
+        let metas = if version >= 2 {
+            read_u32(buf, 24) as usize
+        } else {
+            0
+        };
+        let buckets = if version >= 3 {
+            read_u32(buf, 28) as usize
+        } else {
+            0
+        };
+        let (hash_seed, hash_groups) = if version >= 4 {
+            (read_u32(buf, 32), read_u32(buf, 36) as usize)
+        } else {
+            (0, 0)
+        };
+        if version >= 4 && (keys > 0) != (hash_groups > 0) {
+            return Err("compiled dictionary has a corrupt hash table".to_string());
+        }
+        let profile = match if version >= 5 { read_u32(buf, 40) } else { 0 } {
+            0 => Profile::Full,
+            1 => Profile::Small,
+            p => return Err(format!("unknown compiled dictionary profile {}", p)),
+        };
+        let (cost_base, cost_step) = if version >= 5 {
+            (
+                f32::from_bits(read_u32(buf, 44)) as f64,
+                f32::from_bits(read_u32(buf, 48)) as f64,
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        let front_coded = version >= 6;
+        let offsets = if front_coded {
+            read_u32(buf, 52) as usize
+        } else {
+            keys
+        };
+
+        let displacements = header_len + buckets * BUCKET_LEN;
+        let slots = displacements + hash_groups * DISPLACEMENT_LEN;
+        let key_offsets = if hash_groups > 0 {
+            slots + keys * 4
+        } else {
+            slots
+        };
+        let pattern_starts = key_offsets + (offsets + 1) * 8;
+        let records = pattern_starts + (keys + 1) * 4;
+        let meta_records = records + patterns * profile.pattern_len();
+        let string_offsets = meta_records + metas * META_LEN;
+        let key_blob = string_offsets + (strings + 1) * 8;
+        if buf.len() < key_blob {
+            return truncated();
+        }
+        let key_blob_len = read_u64(buf, key_offsets + offsets * 8) as usize;
+        let string_blob = key_blob.saturating_add(key_blob_len);
+        let string_blob_len = read_u64(buf, string_offsets + strings * 8) as usize;
+        if string_blob.checked_add(string_blob_len) != Some(buf.len()) {
+            return truncated();
+        }
+
+        let mut dict = FrozenDict {
+            backing,
+            version,
+            profile,
+            cost_base,
+            cost_step,
+            keys,
+            patterns,
+            strings,
+            metas,
+            hash_seed,
+            hash_groups,
+            displacements,
+            slots,
+            key_offsets,
+            pattern_starts,
+            records,
+            meta_records,
+            string_offsets,
+            key_blob,
+            string_blob,
+            front_coded,
+            index: Vec::new(),
+            bucket_blocks: Vec::new(),
+            checked: Box::new([]),
+        };
+        dict.index = if version >= 3 {
+            dict.read_index(header_len, buckets)?
+        } else {
+            // Older files carry no index; derive it, checking every key
+            dict.derive_index()?
+        };
+        if front_coded {
+            let mut first = 0;
+            for b in dict.index.windows(2) {
+                dict.bucket_blocks.push(first);
+                first += (b[1].1 - b[0].1).div_ceil(KEY_BLOCK);
+            }
+            if first != offsets {
+                return Err("compiled dictionary has a corrupt block count".to_string());
+            }
+            dict.bucket_blocks.push(first);
+        }
+        dict.checked = (1..dict.index.len()).map(|_| OnceLock::new()).collect();
+        if !lazy && !(0..dict.checked.len()).all(|b| dict.bucket_ok(b)) {
+            return Err("compiled dictionary has corrupt offsets".to_string());
+        }
+        Ok(dict)
+    }
+
+    fn read_index(&self, at: usize, buckets: usize) -> Result<Vec<(char, usize)>, String> {
+        let buf = self.buf();
+        let mut index = Vec::with_capacity(buckets + 1);
+        for b in 0..buckets {
+            let first = char::from_u32(read_u32(buf, at + b * BUCKET_LEN));
+            let start = read_u32(buf, at + b * BUCKET_LEN + 4) as usize;
+            match (first, index.last()) {
+                (Some(c), None) if start == 0 => index.push((c, start)),
+                (Some(c), Some(&(prev, prev_start))) if c > prev && start >= prev_start => {
+                    index.push((c, start))
+                }
+                _ => return Err("compiled dictionary has a corrupt index".to_string()),
+            }
+        }
+        if index.last().is_some_and(|&(_, start)| start > self.keys)
+            || (buckets == 0 && self.keys > 0)
+        {
+            return Err("compiled dictionary has a corrupt index".to_string());
+        }
+        index.push((char::MAX, self.keys));
+        Ok(index)
+    }
+
+    fn derive_index(&self) -> Result<Vec<(char, usize)>, String> {
+        let buf = self.buf();
+        let blob = &buf[self.key_blob..self.string_blob];
+        let text = std::str::from_utf8(blob).map_err(|e| e.to_string())?;
+        let mut index: Vec<(char, usize)> = Vec::new();
+        let key_at = |i: usize| read_u64(buf, self.key_offsets + i * 8) as usize;
+        for i in 0..self.keys {
+            let key = text.get(key_at(i)..key_at(i + 1));
+            let first = key.map(|k| k.chars().next().unwrap_or('\0'));
+            match (first, index.last()) {
+                (Some(c), Some(&(prev, _))) if c == prev => {}
+                (Some(c), last) if last.is_none_or(|&(prev, _)| c > prev) => index.push((c, i)),
+                _ => return Err("compiled dictionary has corrupt offsets".to_string()),
+            }
+        }
+        index.push((char::MAX, self.keys));
+        Ok(index)
+    }
+
+    fn bucket_ok(&self, b: usize) -> bool {
+        *self.checked[b].get_or_init(|| self.check_bucket(b))
+    }
+
+    /// Checks that every offset and string id reachable from bucket `b`
+    /// stays in bounds on UTF-8 boundaries, so reading it needs no checks.
+    fn check_bucket(&self, b: usize) -> bool {
+        let buf = self.buf();
+        let (lo, hi) = (self.index[b].1, self.index[b + 1].1);
+        let start_at = |i: usize| read_u32(buf, self.pattern_starts + i * 4) as usize;
+        let mut prev_start = start_at(lo);
+        for i in lo..=hi {
+            let start = start_at(i);
+            if start < prev_start || start > self.patterns {
+                return false;
+            }
+            prev_start = start;
+        }
+        if self.front_coded {
+            if !self.blocks_ok(b) {
+                return false;
+            }
+        } else if !self.key_offsets_ok(lo, hi) {
+            return false;
+        }
+
+        let (p_lo, p_hi) = (start_at(lo), start_at(hi));
+        for p in p_lo..p_hi {
+            let (pos, lemma) = self.pattern_strings(p);
+            if !self.string_ok(pos) || !self.string_ok(lemma) {
+                return false;
+            }
+        }
+        let mut prev = None;
+        for m in self.first_meta(p_lo)..self.metas {
+            let at = self.meta_records + m * META_LEN;
+            let pattern = read_u32(buf, at) as usize;
+            if pattern >= p_hi {
+                break;
+            }
+            let priority = read_u32(buf, at + 12);
+            let optional = |v: u32| v == UNSET || self.string_ok(v);
+            if prev.is_some_and(|p| p >= pattern)
+                || !optional(read_u32(buf, at + 4))
+                || !optional(read_u32(buf, at + 8))
+                || (priority != UNSET && priority > u8::MAX as u32)
+            {
+                return false;
+            }
+            prev = Some(pattern);
+        }
+        true
+    }
+
+    /// Whether the keys `lo..hi`, spelled out, lie in the key blob on UTF-8
+    /// boundaries.
+    fn key_offsets_ok(&self, lo: usize, hi: usize) -> bool {
+        let buf = self.buf();
+        let key_blob_len = self.string_blob - self.key_blob;
+        let key_at = |i: usize| read_u64(buf, self.key_offsets + i * 8) as usize;
+        let (from, to) = (key_at(lo), key_at(hi));
+        if from > to || to > key_blob_len {
+            return false;
+        }
+        let Ok(text) = std::str::from_utf8(&buf[self.key_blob + from..self.key_blob + to]) else {
+            return false;
+        };
+        let mut prev = from;
+        for i in lo..=hi {
+            let off = key_at(i);
+            if off < prev || off > to || !text.is_char_boundary(off - from) {
+                return false;
+            }
+            prev = off;
+        }
+        true
+    }
+
+    /// Whether the blocks of bucket `b` decode into as many UTF-8 keys as
+    /// the bucket holds.
+    fn blocks_ok(&self, b: usize) -> bool {
+        let (lo, hi) = (self.index[b].1, self.index[b + 1].1);
+        let mut key = Vec::new();
+        (self.bucket_blocks[b]..self.bucket_blocks[b + 1])
+            .zip((lo..hi).step_by(KEY_BLOCK))
+            .all(|(block, first)| {
+                let n = KEY_BLOCK.min(hi - first);
+                let Some(bytes) = self.block(block) else {
+                    return false;
+                };
+                let mut keys = FrontCoded::new(bytes, &mut key);
+                (0..n).all(|_| {
+                    keys.next_key()
+                        .is_some_and(|k| std::str::from_utf8(k).is_ok())
+                }) && keys.rest.is_empty()
+            })
+    }
+
+    /// The encoded bytes of front-coded key block `block`.
+    fn block(&self, block: usize) -> Option<&[u8]> {
+        let buf = self.buf();
+        let start = read_u64(buf, self.key_offsets + block * 8) as usize;
+        let end = read_u64(buf, self.key_offsets + (block + 1) * 8) as usize;
+        (start <= end && end <= self.string_blob - self.key_blob)
+            .then(|| &buf[self.key_blob + start..self.key_blob + end])
+    }
+
+    /// Decodes key `i` of bucket `b` of a front-coded file into `out`.
+    fn decode_key<'k>(&self, b: usize, i: usize, out: &'k mut Vec<u8>) -> Option<&'k [u8]> {
+        let at = i - self.index[b].1;
+        let bytes = self.block(self.bucket_blocks[b] + at / KEY_BLOCK)?;
+        let mut keys = FrontCoded::new(bytes, out);
+        for _ in 0..at % KEY_BLOCK {
+            keys.next_key()?;
+        }
+        keys.next_key()?;
+        Some(out.as_slice())
+    }
+
+    /// The POS and lemma string ids of pattern `p`.
+    fn pattern_strings(&self, p: usize) -> (u32, u32) {
+        let buf = self.buf();
+        let at = self.records + p * self.profile.pattern_len();
+        match self.profile {
+            Profile::Full => (read_u32(buf, at), read_u32(buf, at + 4)),
+            Profile::Small => {
+                let pos = u16::from_le_bytes(buf[at + 4..at + 6].try_into().unwrap());
+                (u32::from(pos), read_u32(buf, at))
+            }
+        }
+    }
+
+    fn pattern_cost(&self, p: usize) -> Option<f64> {
+        let buf = self.buf();
+        let at = self.records + p * self.profile.pattern_len();
+        match self.profile {
+            Profile::Full => {
+                let cost = f64::from_le_bytes(buf[at + 8..at + 16].try_into().unwrap());
+                (!cost.is_nan()).then_some(cost)
+            }
+            Profile::Small => match buf[at + 6] {
+                DEFAULT_COST => None,
+                code => Some(self.cost_base + f64::from(code) * self.cost_step),
+            },
+        }
+    }
+
+    fn string_ok(&self, id: u32) -> bool {
+        let id = id as usize;
+        if id >= self.strings {
+            return false;
+        }
+        let buf = self.buf();
+        let start = read_u64(buf, self.string_offsets + id * 8) as usize;
+        let end = read_u64(buf, self.string_offsets + (id + 1) * 8) as usize;
+        start <= end
+            && end <= buf.len() - self.string_blob
+            && std::str::from_utf8(&buf[self.string_blob + start..self.string_blob + end]).is_ok()
+    }
+
+    fn buf(&self) -> &[u8] {
+        self.backing.bytes()
+    }
+
+    /// Whether the buffer is a shared file mapping rather than a private copy.
+    pub fn is_mapped(&self) -> bool {
+        !matches!(self.backing, Backing::Owned(_))
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys
+    }
+
+    pub fn pattern_count(&self) -> usize {
+        self.patterns
+    }
+
+    /// The layout version the file was written in.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    fn blob_str(&self, blob: usize, start: usize, end: usize) -> &str {
+        // Only reached through buckets that passed `check_bucket`
+        unsafe { std::str::from_utf8_unchecked(&self.buf()[blob + start..blob + end]) }
+    }
+
+    fn key(&self, i: usize) -> &str {
+        let buf = self.buf();
+        let start = read_u64(buf, self.key_offsets + i * 8) as usize;
+        let end = read_u64(buf, self.key_offsets + (i + 1) * 8) as usize;
+        self.blob_str(self.key_blob, start, end)
+    }
+
+    /// Every key of a sound bucket, in order.
+    pub fn keys(&self) -> impl Iterator<Item = Cow<'_, str>> + '_ {
+        (0..self.checked.len())
+            .filter(|&b| self.bucket_ok(b))
+            .flat_map(move |b| self.bucket_keys(b))
+    }
+
+    /// The keys of sound bucket `b`, in order.
+    fn bucket_keys(&self, b: usize) -> Vec<Cow<'_, str>> {
+        let (lo, hi) = (self.index[b].1, self.index[b + 1].1);
+        if !self.front_coded {
+            return (lo..hi).map(|i| Cow::Borrowed(self.key(i))).collect();
+        }
+        let mut out = Vec::with_capacity(hi - lo);
+        let mut key = Vec::new();
+        for (block, first) in (self.bucket_blocks[b]..).zip((lo..hi).step_by(KEY_BLOCK)) {
+            let mut keys = FrontCoded::new(self.block(block).unwrap_or_default(), &mut key);
+            for _ in first..hi.min(first + KEY_BLOCK) {
+                if let Some(k) = keys.next_key() {
+                    out.push(Cow::Owned(String::from_utf8_lossy(k).into_owned()));
+                }
+            }
+        }
+        out
+    }
+
+    fn string(&self, id: usize) -> &str {
+        let buf = self.buf();
+        let start = read_u64(buf, self.string_offsets + id * 8) as usize;
+        let end = read_u64(buf, self.string_offsets + (id + 1) * 8) as usize;
+        self.blob_str(self.string_blob, start, end)
+    }
+
+    /// Key index of `surface`. Only the bucket of its first character is
+    /// checked and read; older files without a hash binary search it.
+    pub fn find(&self, surface: &str) -> Option<usize> {
+        let first = surface.chars().next().unwrap_or('\0');
+        let b = self.index[..self.index.len() - 1]
+            .binary_search_by(|&(c, _)| c.cmp(&first))
+            .ok()?;
+        if !self.bucket_ok(b) {
+            return None;
+        }
+        let (mut lo, mut hi) = (self.index[b].1, self.index[b + 1].1);
+        if self.hash_groups > 0 {
+            // A slot pointing outside the bucket is a miss (or corruption)
+            let i = self.slot(surface);
+            if !(lo..hi).contains(&i) {
+                return None;
+            }
+            let found = match self.front_coded {
+                true => self.decode_key(b, i, &mut Vec::new()) == Some(surface.as_bytes()),
+                false => self.key(i) == surface,
+            };
+            return found.then_some(i);
+        }
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            match self.key(mid).as_bytes().cmp(surface.as_bytes()) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => return Some(mid),
+            }
+        }
+        None
+    }
+
+    /// The key index in the hash slot of `surface`; the key there is
+    /// `surface` if it is in the dictionary at all.
+    fn slot(&self, surface: &str) -> usize {
+        let buf = self.buf();
+        let h = KeyHash::new(self.hash_seed, surface);
+        let at = self.displacements + (h.group as usize % self.hash_groups) * DISPLACEMENT_LEN;
+        let slot = h.displace(read_u32(buf, at), read_u32(buf, at + 4)) as usize % self.keys;
+        read_u32(buf, self.slots + slot * 4) as usize
+    }
+
+    /// First metadata record for pattern `start` or later.
+    fn first_meta(&self, start: usize) -> usize {
+        let (mut m, mut hi) = (0, self.metas);
+        while m < hi {
+            let mid = (m + hi) / 2;
+            if self.meta_pattern(mid) < start {
+                m = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        m
+    }
+
+    fn meta_pattern(&self, m: usize) -> usize {
+        read_u32(self.buf(), self.meta_records + m * META_LEN) as usize
+    }
+
+    fn meta(&self, m: usize) -> MetaRef<'_> {
+        let buf = self.buf();
+        let at = self.meta_records + m * META_LEN;
+        let string = |v: u32| (v != UNSET).then(|| self.string(v as usize));
+        let priority = read_u32(buf, at + 12);
+        MetaRef {
+            source: string(read_u32(buf, at + 4)),
+            note: string(read_u32(buf, at + 8)),
+            priority: (priority != UNSET).then_some(priority as u8),
+        }
+    }
+
+    /// Calls `f` with every pattern stored for key `i`.
+    pub fn for_each_pattern<'a>(&'a self, i: usize, mut f: impl FnMut(PatternRef<'a>)) {
+        let buf = self.buf();
+        let start = read_u32(buf, self.pattern_starts + i * 4) as usize;
+        let end = read_u32(buf, self.pattern_starts + (i + 1) * 4) as usize;
+        let mut m = self.first_meta(start);
+        for p in start..end {
+            let (pos, lemma) = self.pattern_strings(p);
+            let meta = (m < self.metas && self.meta_pattern(m) == p).then(|| {
+                m += 1;
+                self.meta(m - 1)
+            });
+            f(PatternRef {
+                pos: self.string(pos as usize),
+                lemma: self.string(lemma as usize),
+                cost: self.pattern_cost(p),
+                meta,
+            });
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Perfect Hash
+// -----------------------------------------------------------------------------
+
+/// The three hashes of a key: its group and the two values displaced into
+/// a slot. Fixed here (FNV-1a, then a SplitMix64 finalizer) so that files
+/// read the same on every platform and build.
+struct KeyHash {
+    group: u32,
+    f1: u32,
+    f2: u32,
+}
+
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl KeyHash {
+    fn new(seed: u32, key: &str) -> Self {
+        let mut h = 0xcbf2_9ce4_8422_2325u64 ^ u64::from(seed);
+        for &b in key.as_bytes() {
+            h = (h ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        let (a, b) = (mix(h), mix(h ^ 0x9e37_79b9_7f4a_7c15));
+        KeyHash {
+            group: (a >> 32) as u32,
+            f1: a as u32,
+            f2: b as u32,
+        }
+    }
+
+    fn displace(&self, d1: u32, d2: u32) -> u32 {
+        self.f2
+            .wrapping_add(self.f1.wrapping_mul(d1))
+            .wrapping_add(d2)
+    }
+}
+
+/// A minimal perfect hash over `keys`: `(seed, displacements, slots)` where
+/// `slots[s]` is the index of the key hashed to slot `s`. Groups are placed
+/// largest first; a seed whose groups cannot all be placed is replaced.
+fn perfect_hash(keys: &[&SmallStr]) -> (u32, Vec<(u32, u32)>, Vec<u32>) {
+    let n = keys.len();
+    let groups = n.div_ceil(GROUP_SIZE).max(1);
+    'seed: for seed in 0u32.. {
+        let hashes: Vec<KeyHash> = keys.iter().map(|k| KeyHash::new(seed, k)).collect();
+        let mut members: Vec<Vec<usize>> = vec![Vec::new(); groups];
+        for (i, h) in hashes.iter().enumerate() {
+            members[h.group as usize % groups].push(i);
+        }
+        let mut order: Vec<usize> = (0..groups).collect();
+        order.sort_by_key(|&g| std::cmp::Reverse(members[g].len()));
+
+        let mut displacements = vec![(0u32, 0u32); groups];
+        let mut slots = vec![UNSET; n];
+        // Slots taken while trying one displacement, marked by attempt
+        let mut tried = vec![0u64; n];
+        let mut attempt = 0u64;
+        let mut taken = Vec::new();
+        for g in order {
+            if members[g].is_empty() {
+                break;
+            }
+            let mut candidates = (0..n as u32).flat_map(|d1| (0..n as u32).map(move |d2| (d1, d2)));
+            let placed = candidates.find(|&(d1, d2)| {
+                attempt += 1;
+                taken.clear();
+                for &i in &members[g] {
+                    let s = hashes[i].displace(d1, d2) as usize % n;
+                    if slots[s] != UNSET || tried[s] == attempt {
+                        return false;
+                    }
+                    tried[s] = attempt;
+                    taken.push((s, i));
+                }
+                true
+            });
+            let Some(d) = placed else {
+                continue 'seed;
+            };
+            displacements[g] = d;
+            for &(s, i) in &taken {
+                slots[s] = i as u32;
+            }
+        }
+        return (seed, displacements, slots);
+    }
+    unreachable!("a seed always places every group eventually")
+}
+
+/// Reads the keys of one front-coded block in turn.
+struct FrontCoded<'a, 'k> {
+    rest: &'a [u8],
+    key: &'k mut Vec<u8>,
+    first: bool,
+}
+
+impl<'a, 'k> FrontCoded<'a, 'k> {
+    fn new(block: &'a [u8], key: &'k mut Vec<u8>) -> Self {
+        key.clear();
+        FrontCoded {
+            rest: block,
+            key,
+            first: true,
+        }
+    }
+
+    fn varint(&mut self) -> Option<usize> {
+        let mut v = 0usize;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.rest.split_first()?;
+            self.rest = rest;
+            v |= usize::from(byte & 0x7f).checked_shl(shift)?;
+            if byte & 0x80 == 0 {
+                return Some(v);
+            }
+        }
+        None
+    }
+
+    /// The next key's bytes, or None past the end of a corrupt block.
+    fn next_key(&mut self) -> Option<&[u8]> {
+        let shared = if std::mem::take(&mut self.first) {
+            0
+        } else {
+            self.varint()?
+        };
+        let len = self.varint()?;
+        if shared > self.key.len() || len > self.rest.len() {
+            return None;
+        }
+        let (suffix, rest) = self.rest.split_at(len);
+        self.key.truncate(shared);
+        self.key.extend_from_slice(suffix);
+        self.rest = rest;
+        Some(self.key)
+    }
+}
+
+fn put_varint(out: &mut Vec<u8>, mut v: usize) {
+    while v >= 0x80 {
+        out.push(v as u8 | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn intern<'d>(s: &'d str, ids: &mut HashMap<&'d str, u32>, strings: &mut Vec<&'d str>) -> u32 {
+    *ids.entry(s).or_insert_with(|| {
+        strings.push(s);
+        (strings.len() - 1) as u32
+    })
+}
+
+/// The base and step mapping 0..=254 onto the range of the costs set in
+/// `data`.
+fn quantization(data: &TrieData) -> (f32, f32) {
+    let costs = data.dict.values().flatten().filter_map(|p| p.cost);
+    let (lo, hi) = costs.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), c| {
+        (lo.min(c), hi.max(c))
+    });
+    if lo > hi {
+        return (0.0, 1.0);
+    }
+    let step = (hi - lo) / f64::from(DEFAULT_COST - 1);
+    (lo as f32, if step > 0.0 { step as f32 } else { 1.0 })
+}
+
+/// Writes `data` in the compiled layout of `profile`, sharing repeated
+/// POS/lemma strings.
+pub(crate) fn write<'d>(data: &'d TrieData, path: &str, profile: Profile) -> Result<(), String> {
+    let mut keys: Vec<&SmallStr> = data.dict.keys().collect();
+    keys.sort();
+
+    let mut string_ids: HashMap<&str, u32> = HashMap::new();
+    let mut strings: Vec<&str> = Vec::new();
+    let (cost_base, cost_step) = quantization(data);
+    if profile == Profile::Small {
+        // Tags first, so that their ids fit the small records' 16 bits
+        for key in &keys {
+            for pat in data.sorted(&data.dict[key.as_str()]) {
+                intern(data.strings.get(pat.pos), &mut string_ids, &mut strings);
+            }
+        }
+        if strings.len() > usize::from(u16::MAX) + 1 {
+            return Err(format!(
+                "{} POS tags do not fit the small profile",
+                strings.len()
+            ));
+        }
+    }
+    let pattern_len = profile.pattern_len();
+
+    let mut key_offsets = Vec::with_capacity(keys.len() / KEY_BLOCK + 1);
+    let mut key_blob = Vec::new();
+    let mut pattern_starts = Vec::with_capacity(keys.len() + 1);
+    let mut records = Vec::new();
+    let mut metas = Vec::new();
+    let mut index = Vec::new();
+    let mut block_start = 0;
+    for (i, key) in keys.iter().enumerate() {
+        let first = key.chars().next().map_or(0, u32::from);
+        let bucket = i == 0 || index[index.len() - 2] != first;
+        if bucket {
+            index.extend([first, i as u32]);
+        }
+        // Blocks restart at every bucket, so a lookup stays within one
+        if bucket || i - block_start == KEY_BLOCK {
+            block_start = i;
+            key_offsets.push(key_blob.len() as u64);
+            put_varint(&mut key_blob, key.len());
+            key_blob.extend_from_slice(key.as_bytes());
+        } else {
+            let prev = keys[i - 1].as_bytes();
+            let shared = prev
+                .iter()
+                .zip(key.as_bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            put_varint(&mut key_blob, shared);
+            put_varint(&mut key_blob, key.len() - shared);
+            key_blob.extend_from_slice(&key.as_bytes()[shared..]);
+        }
+        pattern_starts.push((records.len() / pattern_len) as u32);
+        for pat in data.sorted(&data.dict[key.as_str()]) {
+            let pos = intern(data.strings.get(pat.pos), &mut string_ids, &mut strings);
+            let lemma = intern(data.strings.get(pat.lemma), &mut string_ids, &mut strings);
+            if profile == Profile::Small {
+                let cost = pat.cost.map_or(DEFAULT_COST, |c| {
+                    let code = (c - f64::from(cost_base)) / f64::from(cost_step);
+                    code.round().clamp(0.0, f64::from(DEFAULT_COST - 1)) as u8
+                });
+                records.extend_from_slice(&lemma.to_le_bytes());
+                records.extend_from_slice(&(pos as u16).to_le_bytes());
+                records.extend_from_slice(&[cost, 0]);
+                continue;
+            }
+            records.extend_from_slice(&pos.to_le_bytes());
+            records.extend_from_slice(&lemma.to_le_bytes());
+            records.extend_from_slice(&pat.cost.unwrap_or(f64::NAN).to_le_bytes());
+            if let Some(meta) = &pat.meta {
+                let mut string = |s: &'d Option<String>| {
+                    s.as_deref()
+                        .map_or(UNSET, |s| intern(s, &mut string_ids, &mut strings))
+                };
+                let pattern = (records.len() / PATTERN_LEN - 1) as u32;
+                for v in [
+                    pattern,
+                    string(&meta.source),
+                    string(&meta.note),
+                    meta.priority.map_or(UNSET, u32::from),
+                ] {
+                    metas.extend_from_slice(&v.to_le_bytes());
+                }
+            }
+        }
+    }
+    key_offsets.push(key_blob.len() as u64);
+    pattern_starts.push((records.len() / pattern_len) as u32);
+
+    let (hash_seed, displacements, slots) = if keys.is_empty() {
+        (0, Vec::new(), Vec::new())
+    } else {
+        perfect_hash(&keys)
+    };
+
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut w = BufWriter::new(file);
+    let io = |e: std::io::Error| e.to_string();
+    w.write_all(MAGIC).map_err(io)?;
+    for v in [
+        VERSION,
+        keys.len() as u32,
+        (records.len() / pattern_len) as u32,
+        strings.len() as u32,
+        (metas.len() / META_LEN) as u32,
+        (index.len() / 2) as u32,
+        hash_seed,
+        displacements.len() as u32,
+        profile.id(),
+        cost_base.to_bits(),
+        cost_step.to_bits(),
+        (key_offsets.len() - 1) as u32,
+    ] {
+        w.write_all(&v.to_le_bytes()).map_err(io)?;
+    }
+    for v in &index {
+        w.write_all(&v.to_le_bytes()).map_err(io)?;
+    }
+    for (d1, d2) in &displacements {
+        w.write_all(&d1.to_le_bytes()).map_err(io)?;
+        w.write_all(&d2.to_le_bytes()).map_err(io)?;
+    }
+    for slot in &slots {
+        w.write_all(&slot.to_le_bytes()).map_err(io)?;
+    }
+    for off in &key_offsets {
+        w.write_all(&off.to_le_bytes()).map_err(io)?;
+    }
+    for start in &pattern_starts {
+        w.write_all(&start.to_le_bytes()).map_err(io)?;
+    }
+    w.write_all(&records).map_err(io)?;
+    w.write_all(&metas).map_err(io)?;
+    let mut off = 0u64;
+    for s in &strings {
+        w.write_all(&off.to_le_bytes()).map_err(io)?;
+        off += s.len() as u64;
+    }
+    w.write_all(&off.to_le_bytes()).map_err(io)?;
+    w.write_all(&key_blob).map_err(io)?;
+    for s in &strings {
+        w.write_all(s.as_bytes()).map_err(io)?;
+    }
+    w.flush().map_err(io)
+}
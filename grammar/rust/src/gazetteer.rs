@@ -0,0 +1,226 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use crate::candidates::{CandidateSource, Text};
+use crate::decoder::{Edge, EdgeKind};
+
+// -----------------------------------------------------------------------------
+// Gazetteers
+// -----------------------------------------------------------------------------
+//
+// Name lists (people, organizations, places) too large for per-entry
+// dictionary records. A gazetteer is one string of its names in sorted
+// order plus the offset of each, about four bytes per name beyond its
+// text, and is searched by binary search: extending a match one character
+// at a time stops as soon as no name has it as a prefix. The longest name
+// at a position becomes an edge tagged as the gazetteer says (NNP by
+// default), cheaper than the length-based cost by the gazetteer's bonus
+// per character, so that a name outbids its split into dictionary words.
+// Each gazetteer also carries an entity type (PER, ORG, LOC, ...), or one
+// per name, for `extract_entities`.
+
+pub(crate) struct Gazetteer {
+    names: String,
+    // names[offsets[k]..offsets[k + 1]] is the k-th name
+    offsets: Vec<u32>,
+    max_chars: usize,
+    pub tag: String,
+    /// Per character of a match
+    pub bonus: f64,
+    /// Entity type of the names, for `entities`
+    pub label: String,
+    /// Whether some name contains whitespace
+    pub spaced: bool,
+    // Index into `labels` per name, when some line gives its own type
+    kinds: Vec<u16>,
+    labels: Vec<String>,
+}
+
+impl Gazetteer {
+    /// Builds a gazetteer from `source`, one name per line, optionally
+    /// followed by a tab and the name's own entity type (otherwise
+    /// `label`). Further fields are ignored, and blank lines and lines
+    /// starting with `#` are skipped.
+    pub fn parse(source: &str, tag: String, bonus: f64, label: String) -> Result<Self, String> {
+        let mut lines: Vec<(&str, &str)> = Vec::new();
+        for line in source.lines() {
+            let mut fields = line.split('\t').map(str::trim);
+            let name = fields.next().unwrap_or("");
+            if name.is_empty() || name.starts_with('#') {
+                continue;
+            }
+            lines.push((
+                name,
+                fields.next().filter(|kind| !kind.is_empty()).unwrap_or(""),
+            ));
+        }
+        // The first line of a repeated name decides its type
+        lines.sort_by(|a, b| a.0.cmp(b.0));
+        lines.dedup_by(|a, b| a.0 == b.0);
+        let total: usize = lines.iter().map(|(name, _)| name.len()).sum();
+        if u32::try_from(total).is_err() {
+            return Err(format!(
+                "gazetteer of {} bytes is over the 4 GiB limit",
+                total
+            ));
+        }
+        let typed = lines.iter().any(|(_, kind)| !kind.is_empty());
+        let mut labels = vec![label.clone()];
+        let mut kinds = Vec::new();
+        let mut names = String::with_capacity(total);
+        let mut offsets = Vec::with_capacity(lines.len() + 1);
+        let mut max_chars = 0;
+        let spaced = lines
+            .iter()
+            .any(|(name, _)| name.contains(char::is_whitespace));
+        for (name, kind) in lines {
+            offsets.push(names.len() as u32);
+            names.push_str(name);
+            max_chars = max_chars.max(name.chars().count());
+            if typed {
+                let kind = if kind.is_empty() {
+                    label.as_str()
+                } else {
+                    kind
+                };
+                let id = match labels.iter().position(|l| l == kind) {
+                    Some(id) => id,
+                    None => {
+                        labels.push(kind.to_string());
+                        labels.len() - 1
+                    }
+                };
+                kinds.push(
+                    u16::try_from(id).map_err(|_| "more than 65536 entity types".to_string())?,
+                );
+            }
+        }
+        offsets.push(names.len() as u32);
+        Ok(Gazetteer {
+            names,
+            offsets,
+            max_chars,
+            tag,
+            bonus,
+            label,
+            spaced,
+            kinds,
+            labels,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    fn name(&self, k: usize) -> &str {
+        &self.names[self.offsets[k] as usize..self.offsets[k + 1] as usize]
+    }
+
+    /// The entity type of the `k`-th name.
+    fn kind(&self, k: usize) -> &str {
+        match self.kinds.get(k) {
+            Some(&id) => &self.labels[id as usize],
+            None => &self.label,
+        }
+    }
+
+    /// Whether `name` is one of the names.
+    pub fn contains(&self, name: &str) -> bool {
+        let k = partition(self.len(), |k| self.name(k) < name);
+        k < self.len() && self.name(k) == name
+    }
+
+    /// `(character length, index)` of the names starting at `chars[i]`,
+    /// shortest first, none longer than `limit - i`.
+    pub fn matches(&self, chars: &[char], i: usize, limit: usize) -> Vec<(usize, usize)> {
+        let mut out = Vec::new();
+        let mut key = String::new();
+        // Names before `lo` sort before the key, and so before any longer one
+        let mut lo = 0;
+        for (len, &c) in chars[i..limit.min(i + self.max_chars)].iter().enumerate() {
+            key.push(c);
+            let from = lo;
+            lo = from + partition(self.len() - from, |k| self.name(from + k) < key.as_str());
+            if lo == self.len() || !self.name(lo).starts_with(key.as_str()) {
+                break;
+            }
+            if self.name(lo) == key {
+                out.push((len + 1, lo));
+            }
+        }
+        out
+    }
+}
+
+/// The number of leading indices in `0..count` for which `before` holds,
+/// given that it holds for a prefix of them.
+fn partition(count: usize, before: impl Fn(usize) -> bool) -> usize {
+    let (mut lo, mut hi) = (0, count);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if before(mid) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+impl<'a> CandidateSource<'a> for Gazetteer {
+    fn candidates(&self, text: &Text, i: usize, limit: usize, out: &mut Vec<Edge<'a>>) {
+        // Only the longest name, so that a long name is not outbid by a
+        // shorter one and the dictionary word after it
+        if let Some(&(len, _)) = self.matches(text.chars, i, limit).last() {
+            out.push(Edge {
+                start: i,
+                end: i + len,
+                pos: Cow::Owned(self.tag.clone()),
+                lemma: Cow::Owned(text.surface(i, i + len)),
+                cost: text.grammar.word_cost(len, &self.tag) - self.bonus * len as f64,
+                kind: EdgeKind::Dict,
+            });
+        }
+    }
+}
+
+/// Entity spans `(start, end, type)` in `chars`, found by longest match
+/// over all gazetteers (the first loaded wins a tie) from left to right.
+/// `boundaries[i]` tells whether a morpheme starts or ends at `i`; a name
+/// must start and end on one, so entities never split a morpheme.
+pub(crate) fn entities<'g>(
+    gazetteers: &'g [Arc<Gazetteer>],
+    chars: &[char],
+    boundaries: &[bool],
+) -> Vec<(usize, usize, &'g str)> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if !boundaries[i] {
+            i += 1;
+            continue;
+        }
+        let mut best: Option<(usize, &str)> = None;
+        for gazetteer in gazetteers {
+            let aligned = gazetteer
+                .matches(chars, i, chars.len())
+                .into_iter()
+                .rev()
+                .find(|&(len, _)| boundaries[i + len]);
+            if let Some((len, k)) = aligned {
+                if best.is_none_or(|(top, _)| len > top) {
+                    best = Some((len, gazetteer.kind(k)));
+                }
+            }
+        }
+        match best {
+            Some((len, kind)) => {
+                out.push((i, i + len, kind));
+                i += len;
+            }
+            None => i += 1,
+        }
+    }
+    out
+}
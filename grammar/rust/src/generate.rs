@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use crate::grammar::Grammar;
+use crate::tuning::SplitMix;
+use crate::Morpheme;
+
+// -----------------------------------------------------------------------------
+// Synthetic Sentences
+// -----------------------------------------------------------------------------
+//
+// Sentences for fuzzing the decoder and for benchmarks. Each eojeol is a
+// content morpheme (noun, predicate stem, adverb or determiner) followed by
+// the particles and endings the grammar's transition rules reward after
+// it; stems and pre-final endings always take an ending, and the last
+// eojeol is a predicate closed by a final ending when the dictionary has
+// both. The same dictionary and seed give the same sentences.
+
+/// Tag prefixes an eojeol starts with.
+const HEADS: [&str; 4] = ["N", "V", "MAG", "MM"];
+
+/// Tag prefixes that may follow within an eojeol.
+const SUFFIXES: [&str; 3] = ["J", "E", "XS"];
+
+/// Most morphemes added after the head of an eojeol.
+const MAX_SUFFIXES: usize = 3;
+
+/// Chance that an eojeol free to end does so before its next suffix.
+const STOP: f64 = 0.3;
+
+/// Dictionary entries by tag, in tag and surface order.
+pub(crate) struct Lexicon {
+    tags: BTreeMap<String, Vec<(String, String)>>,
+    heads: Vec<String>,
+    suffixes: Vec<String>,
+}
+
+impl Lexicon {
+    /// The lexicon of `entries`, or `None` when none of them can start an
+    /// eojeol. Compound entries and surfaces with whitespace are left out.
+    pub fn new(entries: impl IntoIterator<Item = Morpheme>) -> Option<Self> {
+        let mut tags: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+        for (surface, pos, lemma) in entries {
+            if pos.contains('+') || surface.is_empty() || surface.chars().any(char::is_whitespace) {
+                continue;
+            }
+            tags.entry(pos).or_default().push((surface, lemma));
+        }
+        for words in tags.values_mut() {
+            words.sort();
+            words.dedup();
+        }
+        let starting = |prefixes: &[&str]| -> Vec<String> {
+            tags.keys()
+                .filter(|tag| prefixes.iter().any(|p| tag.starts_with(p)))
+                .cloned()
+                .collect()
+        };
+        let heads = starting(&HEADS);
+        let suffixes = starting(&SUFFIXES);
+        (!heads.is_empty()).then_some(Lexicon {
+            tags,
+            heads,
+            suffixes,
+        })
+    }
+
+    /// `n` sentences of one to `max_words` eojeols, each with its morphemes.
+    pub fn sentences(
+        &self,
+        grammar: &dyn Grammar,
+        n: usize,
+        seed: u64,
+        max_words: usize,
+    ) -> Vec<(String, Vec<Morpheme>)> {
+        let mut rng = SplitMix(seed);
+        (0..n)
+            .map(|_| self.sentence(grammar, &mut rng, max_words))
+            .collect()
+    }
+
+    fn sentence(
+        &self,
+        grammar: &dyn Grammar,
+        rng: &mut SplitMix,
+        max_words: usize,
+    ) -> (String, Vec<Morpheme>) {
+        let words = 1 + (rng.next() % max_words as u64) as usize;
+        let mut text = String::new();
+        let mut morphs = Vec::new();
+        for i in 0..words {
+            if i > 0 {
+                text.push(' ');
+            }
+            for m in self.eojeol(grammar, rng, i + 1 == words) {
+                text.push_str(&m.0);
+                morphs.push(m);
+            }
+        }
+        if let Some(m) = self.word(rng, "SF") {
+            text.push_str(&m.0);
+            morphs.push(m);
+        }
+        (text, morphs)
+    }
+
+    fn eojeol(&self, grammar: &dyn Grammar, rng: &mut SplitMix, last: bool) -> Vec<Morpheme> {
+        let closes = last && self.tags.contains_key("EF");
+        let verbs: Vec<&String> = self.heads.iter().filter(|t| t.starts_with('V')).collect();
+        let mut tag = match closes && !verbs.is_empty() {
+            true => verbs[pick(rng, verbs.len())].as_str(),
+            false => self.heads[pick(rng, self.heads.len())].as_str(),
+        };
+        let mut out: Vec<Morpheme> = self.word(rng, tag).into_iter().collect();
+        for _ in 0..MAX_SUFFIXES {
+            let bound = tag.starts_with('V') || tag == "EP";
+            let ended = tag.starts_with('E') && tag != "EP";
+            if ended || (!bound && rng.unit() < STOP) {
+                break;
+            }
+            let candidates: Vec<(&str, f64)> = self
+                .suffixes
+                .iter()
+                .map(String::as_str)
+                .filter(|&next| next != tag && grammar.allows(tag, next))
+                .filter(|&next| match closes {
+                    true => next == "EP" || next == "EF",
+                    false => next != "EF",
+                })
+                .map(|next| (next, grammar.transition_bonus(tag, next)))
+                .filter(|&(_, bonus)| bonus > 0.0)
+                .collect();
+            if candidates.is_empty() {
+                break;
+            }
+            tag = weighted(rng, &candidates);
+            out.extend(self.word(rng, tag));
+        }
+        out
+    }
+
+    /// A random entry tagged `tag`, if there is one.
+    fn word(&self, rng: &mut SplitMix, tag: &str) -> Option<Morpheme> {
+        let words = self.tags.get(tag)?;
+        let (surface, lemma) = &words[pick(rng, words.len())];
+        Some((surface.clone(), tag.to_string(), lemma.clone()))
+    }
+}
+
+fn pick(rng: &mut SplitMix, len: usize) -> usize {
+    (rng.next() % len as u64) as usize
+}
+
+/// One of `items`, chosen in proportion to its weight.
+fn weighted<'a>(rng: &mut SplitMix, items: &[(&'a str, f64)]) -> &'a str {
+    let total: f64 = items.iter().map(|&(_, w)| w).sum();
+    let mut at = rng.unit() * total;
+    for &(item, w) in items {
+        if at < w {
+            return item;
+        }
+        at -= w;
+    }
+    items[items.len() - 1].0
+}
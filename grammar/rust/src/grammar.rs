@@ -0,0 +1,288 @@
+use std::sync::Arc;
+
+use crate::chartype::CharClass;
+use crate::scoring;
+
+// -----------------------------------------------------------------------------
+// Grammar Modules
+// -----------------------------------------------------------------------------
+//
+// The dictionary views, lattice and decoders know nothing about Korean:
+// what an entry costs, which tag sequences are allowed or preferred and how
+// unknown text is tagged all come from a `Grammar`. Another agglutinative
+// language plugs in by implementing the trait; `Korean` is the built-in one.
+
+/// An edge for text the dictionary does not cover.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Unknown {
+    pub pos: &'static str,
+    /// `None` takes the surface as the lemma
+    pub lemma: Option<&'static str>,
+    pub cost: f64,
+}
+
+/// A tag in a transition rule: exact, a prefix ending in `*`, or `*` for
+/// any tag.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum TagPattern {
+    Exact(String),
+    Prefix(String),
+}
+
+impl TagPattern {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (stem, wild) = match s.strip_suffix('*') {
+            Some(stem) => (stem, true),
+            None => (s, false),
+        };
+        if stem.contains('*') || (stem.is_empty() && !wild) {
+            return Err(format!("bad tag pattern '{}'", s));
+        }
+        Ok(if wild {
+            TagPattern::Prefix(stem.to_string())
+        } else {
+            TagPattern::Exact(stem.to_string())
+        })
+    }
+
+    pub fn matches(&self, tag: &str) -> bool {
+        match self {
+            TagPattern::Exact(t) => t == tag,
+            TagPattern::Prefix(p) => tag.starts_with(p.as_str()),
+        }
+    }
+}
+
+impl std::fmt::Display for TagPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TagPattern::Exact(t) => f.write_str(t),
+            TagPattern::Prefix(p) => write!(f, "{}*", p),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct TransitionRule {
+    pub prev: TagPattern,
+    pub curr: TagPattern,
+    pub allowed: bool,
+    /// Subtracted from the cost of the second edge
+    pub bonus: f64,
+    /// Shown in score breakdowns; rules set from Python have none
+    pub name: Option<&'static str>,
+}
+
+impl TransitionRule {
+    pub fn label(&self) -> String {
+        match self.name {
+            Some(name) => name.to_string(),
+            None => format!("{} -> {}", self.prev, self.curr),
+        }
+    }
+}
+
+/// A named contribution to the cost of an edge; bonuses are negative.
+pub(crate) type Term = (String, f64);
+
+/// Which tag may follow which, and at what bonus. Rules are tried in order
+/// and the first whose patterns match a pair decides it; unmatched pairs are
+/// allowed with no bonus.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Transitions {
+    rules: Vec<TransitionRule>,
+}
+
+impl Transitions {
+    pub fn new(rules: Vec<TransitionRule>) -> Self {
+        Transitions { rules }
+    }
+
+    pub fn rules(&self) -> &[TransitionRule] {
+        &self.rules
+    }
+
+    pub fn lookup(&self, prev: &str, curr: &str) -> Option<&TransitionRule> {
+        self.rules
+            .iter()
+            .find(|r| r.prev.matches(prev) && r.curr.matches(curr))
+    }
+
+    /// Replaces the rule for the same pair of patterns, or puts `rule`
+    /// first so it takes precedence over broader ones.
+    pub fn set(&mut self, rule: TransitionRule) {
+        match self
+            .rules
+            .iter_mut()
+            .find(|r| r.prev == rule.prev && r.curr == rule.curr)
+        {
+            Some(existing) => *existing = rule,
+            None => self.rules.insert(0, rule),
+        }
+    }
+}
+
+pub(crate) trait Grammar: Send + Sync {
+    /// Cost of a dictionary edge of `len` characters tagged `pos`, for
+    /// entries without a cost of their own.
+    fn word_cost(&self, len: usize, pos: &str) -> f64;
+
+    /// The tag transition rules behind `allows` and `transition_bonus`.
+    fn transitions(&self) -> &Transitions;
+
+    /// This grammar with its transition rules replaced.
+    fn with_transitions(&self, transitions: Transitions) -> Arc<dyn Grammar>;
+
+    /// Whether an edge tagged `curr` may follow one tagged `prev`.
+    fn allows(&self, prev: &str, curr: &str) -> bool {
+        self.transitions()
+            .lookup(prev, curr)
+            .is_none_or(|r| r.allowed)
+    }
+
+    /// Subtracted from the cost of an edge tagged `curr` following `prev`.
+    fn transition_bonus(&self, prev: &str, curr: &str) -> f64 {
+        self.transitions()
+            .lookup(prev, curr)
+            .map_or(0.0, |r| r.bonus)
+    }
+
+    /// Subtracted from the cost of an edge tagged `curr` following `prev`,
+    /// itself following `prev2`; used by second-order decoding only.
+    fn trigram_bonus(&self, _prev2: &str, _prev: &str, _curr: &str) -> f64 {
+        0.0
+    }
+
+    /// The terms `word_cost` adds up, for score breakdowns.
+    fn word_cost_terms(&self, len: usize, pos: &str) -> Vec<Term> {
+        vec![("word cost".to_string(), self.word_cost(len, pos))]
+    }
+
+    /// The transition bonus of `curr` after `prev` as a term, if any.
+    fn transition_term(&self, prev: &str, curr: &str) -> Option<Term> {
+        let bonus = self.transition_bonus(prev, curr);
+        let label = match self.transitions().lookup(prev, curr) {
+            Some(rule) => rule.label(),
+            None => "transition".to_string(),
+        };
+        (bonus != 0.0).then_some((label, -bonus))
+    }
+
+    /// The three-tag bonus of `curr` after `prev2 prev` as a term, if any.
+    fn trigram_term(&self, prev2: &str, prev: &str, curr: &str) -> Option<Term> {
+        let bonus = self.trigram_bonus(prev2, prev, curr);
+        (bonus != 0.0).then(|| ("trigram".to_string(), -bonus))
+    }
+
+    /// The edge for an unknown run of `len` characters of one script, or
+    /// `None` when such a run gets no edge of its own.
+    fn script_run(&self, class: CharClass, len: usize) -> Option<Unknown>;
+
+    /// The edge for `len` unknown characters taken one cluster at a time
+    /// (or as a corpus-attested span).
+    fn fallback(&self, len: usize) -> Unknown;
+}
+
+/// Sejong-tagged Korean: the constraints and bonuses ported from
+/// constraints.py and the length-based costs of `scoring`.
+#[derive(Clone, Debug)]
+pub(crate) struct Korean {
+    transitions: Transitions,
+    trigrams: Vec<([TagPattern; 3], f64, &'static str)>,
+}
+
+impl Default for Korean {
+    fn default() -> Self {
+        let pattern = |tag: &str| TagPattern::parse(tag).expect("built-in tag pattern");
+        let impossible = scoring::IMPOSSIBLE_TRANSITIONS
+            .iter()
+            .map(|&(prev, curr)| (prev, curr, false, 0.0, None));
+        let bonuses = scoring::TRANSITION_BONUSES
+            .iter()
+            .map(|&(prev, curr, bonus, name)| (prev, curr, true, bonus, Some(name)));
+        let rules = impossible
+            .chain(bonuses)
+            .map(|(prev, curr, allowed, bonus, name)| TransitionRule {
+                prev: pattern(prev),
+                curr: pattern(curr),
+                allowed,
+                bonus,
+                name,
+            })
+            .collect();
+        let trigrams = scoring::TRIGRAM_BONUSES
+            .iter()
+            .map(|&(a, b, c, bonus, name)| ([pattern(a), pattern(b), pattern(c)], bonus, name))
+            .collect();
+        Korean {
+            transitions: Transitions::new(rules),
+            trigrams,
+        }
+    }
+}
+
+impl Korean {
+    fn trigram(
+        &self,
+        prev2: &str,
+        prev: &str,
+        curr: &str,
+    ) -> Option<&([TagPattern; 3], f64, &'static str)> {
+        self.trigrams
+            .iter()
+            .find(|([a, b, c], _, _)| a.matches(prev2) && b.matches(prev) && c.matches(curr))
+    }
+}
+
+impl Grammar for Korean {
+    fn word_cost(&self, len: usize, pos: &str) -> f64 {
+        scoring::word_cost(len, pos)
+    }
+
+    fn transitions(&self) -> &Transitions {
+        &self.transitions
+    }
+
+    fn with_transitions(&self, transitions: Transitions) -> Arc<dyn Grammar> {
+        Arc::new(Korean {
+            transitions,
+            ..self.clone()
+        })
+    }
+
+    fn trigram_bonus(&self, prev2: &str, prev: &str, curr: &str) -> f64 {
+        self.trigram(prev2, prev, curr)
+            .map_or(0.0, |&(_, bonus, _)| bonus)
+    }
+
+    fn word_cost_terms(&self, len: usize, pos: &str) -> Vec<Term> {
+        scoring::word_cost_terms(len, pos)
+            .map(|(name, cost)| (name.to_string(), cost))
+            .collect()
+    }
+
+    fn trigram_term(&self, prev2: &str, prev: &str, curr: &str) -> Option<Term> {
+        self.trigram(prev2, prev, curr)
+            .map(|&(_, bonus, name)| (name.to_string(), -bonus))
+    }
+
+    fn script_run(&self, class: CharClass, len: usize) -> Option<Unknown> {
+        // A lone unknown Hangul syllable stays the plain fallback
+        if class == CharClass::Hangul && len < 2 {
+            return None;
+        }
+        Some(Unknown {
+            pos: class.tag(),
+            lemma: (class == CharClass::Hangul).then_some("UNKNOWN"),
+            cost: scoring::oov_cost(len) - scoring::BONUS_SCRIPT_RUN,
+        })
+    }
+
+    fn fallback(&self, len: usize) -> Unknown {
+        Unknown {
+            pos: "NNG",
+            lemma: Some("UNKNOWN"),
+            cost: len as f64 * scoring::oov_cost(1),
+        }
+    }
+}
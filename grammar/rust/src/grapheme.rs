@@ -0,0 +1,100 @@
+// -----------------------------------------------------------------------------
+// Grapheme Clusters
+// -----------------------------------------------------------------------------
+//
+// An approximation of extended grapheme clusters (UAX #29), close enough to
+// keep tokens from splitting what a reader sees as one character: combining
+// marks, variation selectors, emoji modifiers and ZWJ sequences, flag pairs
+// and decomposed Hangul (conjoining jamo).
+
+const ZWJ: char = '\u{200D}';
+
+/// Characters that attach to the preceding one (Grapheme_Cluster_Break=Extend).
+fn extends(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}'     // combining diacritics
+        | '\u{0483}'..='\u{0489}'
+        | '\u{0591}'..='\u{05BD}'
+        | '\u{0610}'..='\u{061A}'
+        | '\u{064B}'..='\u{065F}'
+        | '\u{0E31}' | '\u{0E34}'..='\u{0E3A}' | '\u{0E47}'..='\u{0E4E}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{200C}' | ZWJ
+        | '\u{20D0}'..='\u{20FF}'   // combining marks for symbols (keycaps)
+        | '\u{302A}'..='\u{302F}'   // ideographic / Hangul tone marks
+        | '\u{3099}'..='\u{309A}'   // kana voicing marks
+        | '\u{FE00}'..='\u{FE0F}'   // variation selectors
+        | '\u{FE20}'..='\u{FE2F}'
+        | '\u{1F3FB}'..='\u{1F3FF}' // emoji skin tones
+        | '\u{E0020}'..='\u{E007F}' // emoji tag sequences
+        | '\u{E0100}'..='\u{E01EF}')
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c, '\u{1F1E6}'..='\u{1F1FF}')
+}
+
+/// Emoji that continue a ZWJ sequence (approximate Extended_Pictographic).
+fn is_pictographic(c: char) -> bool {
+    matches!(c,
+        '\u{00A9}' | '\u{00AE}'
+        | '\u{2190}'..='\u{21FF}'
+        | '\u{2300}'..='\u{23FF}'
+        | '\u{2600}'..='\u{27BF}'
+        | '\u{2B00}'..='\u{2BFF}'
+        | '\u{1F000}'..='\u{1FAFF}')
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Jamo {
+    Lead,
+    Vowel,
+    Tail,
+    // Precomposed syllables without / with a final consonant
+    Lv,
+    Lvt,
+}
+
+fn jamo(c: char) -> Option<Jamo> {
+    Some(match c {
+        '\u{1100}'..='\u{115F}' | '\u{A960}'..='\u{A97C}' => Jamo::Lead,
+        '\u{1160}'..='\u{11A7}' | '\u{D7B0}'..='\u{D7C6}' => Jamo::Vowel,
+        '\u{11A8}'..='\u{11FF}' | '\u{D7CB}'..='\u{D7FB}' => Jamo::Tail,
+        '가'..='힣' if (c as u32 - 0xAC00).is_multiple_of(28) => Jamo::Lv,
+        '가'..='힣' => Jamo::Lvt,
+        _ => return None,
+    })
+}
+
+fn hangul_joins(prev: Jamo, next: Jamo) -> bool {
+    use Jamo::*;
+    matches!(
+        (prev, next),
+        (Lead, Lead | Vowel | Lv | Lvt) | (Lv | Vowel, Vowel | Tail) | (Lvt | Tail, Tail)
+    )
+}
+
+/// `b[i]` tells whether a cluster boundary falls before `chars[i]`; the text
+/// edges (0 and `chars.len()`) are always boundaries.
+pub(crate) fn boundaries(chars: &[char]) -> Vec<bool> {
+    let n = chars.len();
+    let mut b = vec![true; n + 1];
+    // Regional indicators pair up left to right
+    let mut flags = 0usize;
+    for i in 1..n {
+        let (prev, next) = (chars[i - 1], chars[i]);
+        flags = if is_regional_indicator(prev) {
+            flags + 1
+        } else {
+            0
+        };
+        b[i] = !(extends(next)
+            || (prev == ZWJ && is_pictographic(next))
+            || (is_regional_indicator(next) && !flags.is_multiple_of(2))
+            || jamo(prev)
+                .zip(jamo(next))
+                .is_some_and(|(p, n)| hangul_joins(p, n)));
+    }
+    b
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::hash::Hasher;
+
+// -----------------------------------------------------------------------------
+// Dictionary Key Hashing
+// -----------------------------------------------------------------------------
+//
+// Dictionary maps are looked up for every substring of the input, mostly
+// keys of a few Hangul syllables, and SipHash, the standard library's
+// default, is a large share of a lookup that short. Its keys are random
+// per process so that an attacker choosing the keys cannot force
+// collisions; dictionary keys come from the lexicon, not the input, so by
+// default the maps use the Fx hash (a multiply and rotate per 8 bytes, as
+// in rustc) instead. Building with the `siphash` feature restores SipHash
+// for deployments that insert entries from untrusted sources.
+
+const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+/// The Fx hash: fast and not collision-resistant.
+#[cfg_attr(feature = "siphash", allow(dead_code))]
+#[derive(Clone, Copy, Default)]
+pub(crate) struct FxHasher {
+    hash: u64,
+}
+
+impl FxHasher {
+    #[inline]
+    fn add(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    #[inline]
+    fn write(&mut self, mut bytes: &[u8]) {
+        while let Some((chunk, rest)) = bytes.split_first_chunk::<8>() {
+            self.add(u64::from_le_bytes(*chunk));
+            bytes = rest;
+        }
+        if let Some((chunk, rest)) = bytes.split_first_chunk::<4>() {
+            self.add(u64::from(u32::from_le_bytes(*chunk)));
+            bytes = rest;
+        }
+        for &b in bytes {
+            self.add(u64::from(b));
+        }
+    }
+
+    #[inline]
+    fn write_u8(&mut self, i: u8) {
+        self.add(u64::from(i));
+    }
+
+    #[inline]
+    fn write_u32(&mut self, i: u32) {
+        self.add(u64::from(i));
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.add(i);
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        self.add(i as u64);
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// The hasher of dictionary maps.
+#[cfg(not(feature = "siphash"))]
+pub(crate) type KeyHasher = std::hash::BuildHasherDefault<FxHasher>;
+#[cfg(feature = "siphash")]
+pub(crate) type KeyHasher = std::collections::hash_map::RandomState;
+
+/// A map keyed by dictionary surfaces or pooled strings.
+pub(crate) type KeyMap<K, V> = HashMap<K, V, KeyHasher>;
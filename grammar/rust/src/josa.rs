@@ -0,0 +1,100 @@
+use crate::suffix::EndingMatch;
+
+// -----------------------------------------------------------------------------
+// Josa Splitting
+// -----------------------------------------------------------------------------
+
+// Allomorphs chosen by the stem's final consonant (batchim)
+const AFTER_CONSONANT: &[&str] = &[
+    "이",
+    "을",
+    "은",
+    "과",
+    "아",
+    "이나",
+    "이랑",
+    "이며",
+    "이여",
+    "이야",
+    "이란",
+    "이라고",
+    "이라도",
+    "이든",
+    "이든지",
+    "으로",
+    "으로서",
+    "으로써",
+    "으로부터",
+];
+const AFTER_VOWEL: &[&str] = &[
+    "가",
+    "를",
+    "는",
+    "와",
+    "야",
+    "나",
+    "랑",
+    "며",
+    "여",
+    "란",
+    "라고",
+    "라도",
+    "든",
+    "든지",
+    "로",
+    "로서",
+    "로써",
+    "로부터",
+];
+
+/// Final consonant of a Hangul syllable: `Some(0)` for an open syllable,
+/// `None` for anything else.
+pub(crate) fn batchim(c: char) -> Option<u32> {
+    let code = c as u32;
+    (0xAC00..=0xD7A3)
+        .contains(&code)
+        .then(|| (code - 0xAC00) % 28)
+}
+
+const RIEUL: u32 = 8;
+
+/// Whether `josa` agrees with a stem ending in `last`. Non-Hangul stems
+/// (digits, Latin) take either form.
+pub(crate) fn agrees(last: char, josa: &str) -> bool {
+    let Some(jong) = batchim(last) else {
+        return true;
+    };
+    if AFTER_CONSONANT.contains(&josa) {
+        // ㄹ-final stems take 로, not 으로
+        jong != 0 && !(jong == RIEUL && josa.starts_with('으'))
+    } else if AFTER_VOWEL.contains(&josa) {
+        jong == 0 || (jong == RIEUL && josa.starts_with('로'))
+    } else {
+        true
+    }
+}
+
+/// Picks the split of `chars` into stem + josa among `endings` (longest
+/// first): the longest agreeing josa whose stem `is_noun`, else the longest
+/// agreeing one. Returns the josa length.
+pub(crate) fn choose(
+    chars: &[char],
+    endings: &[EndingMatch],
+    is_noun: impl Fn(&[char]) -> bool,
+) -> Option<usize> {
+    let candidates: Vec<usize> = endings
+        .iter()
+        .filter(|m| m.pos.starts_with('J') && m.len < chars.len())
+        .filter(|m| {
+            let cut = chars.len() - m.len;
+            let josa: String = chars[cut..].iter().collect();
+            agrees(chars[cut - 1], &josa)
+        })
+        .map(|m| m.len)
+        .collect();
+    candidates
+        .iter()
+        .copied()
+        .find(|&len| is_noun(&chars[..chars.len() - len]))
+        .or_else(|| candidates.first().copied())
+}
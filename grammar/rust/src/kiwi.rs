@@ -0,0 +1,115 @@
+// -----------------------------------------------------------------------------
+// Kiwi Dictionary Import
+// -----------------------------------------------------------------------------
+//
+// Kiwi (kiwipiepy) user dictionaries are tab-separated, one word per line:
+//
+//     형태소<TAB>품사<TAB>점수
+//     사겼다<TAB>사귀/VV + 었/EP + 다/EF<TAB>-1.5
+//
+// The score is optional (0 by default); a second column of `form/TAG`
+// pairs is a pre-analyzed word. The word lists Kiwi builds its models from
+// share the first two columns and carry counts after them, which are not
+// scores. Lines starting with `#` are comments.
+
+/// Which of Kiwi's files a source is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Format {
+    /// A user dictionary: the third column is a score
+    User,
+    /// A model word list: columns after the tag are counts, ignored
+    Model,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "user" => Some(Format::User),
+            "model" => Some(Format::Model),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) struct KiwiEntry {
+    pub surface: String,
+    /// The tag, `+`-joined for a pre-analyzed word
+    pub pos: String,
+    /// The surface, or the `+`-joined morphemes of a pre-analyzed word
+    pub lemma: String,
+    /// Kiwi's score, higher preferred; 0 when absent
+    pub score: f64,
+}
+
+/// The entries of `source`, or the first line that is not one.
+pub(crate) fn parse(source: &str, format: Format) -> Result<Vec<KiwiEntry>, String> {
+    let mut entries = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let bad = |what: &str| format!("line {}: {}", i + 1, what);
+        let mut columns = line.split('\t');
+        let surface = columns.next().unwrap_or_default().trim();
+        let analysis = columns.next().map(str::trim).unwrap_or_default();
+        if surface.is_empty() || analysis.is_empty() {
+            return Err(bad("expected 'word<TAB>tag'"));
+        }
+        let score = match (format, columns.next().map(str::trim)) {
+            (Format::User, Some(score)) if !score.is_empty() => score
+                .parse::<f64>()
+                .ok()
+                .filter(|s| s.is_finite())
+                .ok_or_else(|| bad(&format!("bad score '{}'", score)))?,
+            _ => 0.0,
+        };
+        let (pos, lemma) = if analysis.contains('/') {
+            let morphs =
+                morphemes(analysis).ok_or_else(|| bad(&format!("bad analysis '{}'", analysis)))?;
+            let pos: Vec<String> = morphs.iter().map(|(_, tag)| tag.clone()).collect();
+            let forms: Vec<&str> = morphs.iter().map(|(form, _)| *form).collect();
+            (pos.join("+"), forms.join("+"))
+        } else {
+            (tag(analysis), surface.to_string())
+        };
+        entries.push(KiwiEntry {
+            surface: surface.to_string(),
+            pos,
+            lemma,
+            score,
+        });
+    }
+    Ok(entries)
+}
+
+// `form/TAG` pairs separated by spaces or `+`
+fn morphemes(analysis: &str) -> Option<Vec<(&str, String)>> {
+    analysis
+        .split(|c: char| c == '+' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let (form, tag_name) = part.rsplit_once('/')?;
+            (!form.is_empty() && !tag_name.is_empty()).then(|| (form, tag(tag_name)))
+        })
+        .collect::<Option<Vec<_>>>()
+        .filter(|morphs| !morphs.is_empty())
+}
+
+/// Kiwi's tag as the TTA tag set has it. Kiwi marks (ir)regular
+/// conjugation on predicate tags (VV-R, VA-I) and splits a few classes
+/// further; those collapse to the class. Other tags pass through.
+pub(crate) fn tag(kiwi: &str) -> String {
+    let base = kiwi
+        .strip_suffix("-R")
+        .or_else(|| kiwi.strip_suffix("-I"))
+        .unwrap_or(kiwi);
+    match base {
+        "SSO" | "SSC" => "SS",
+        "SB" | "W_URL" | "W_EMAIL" | "W_HASHTAG" | "W_MENTION" | "W_SERIAL" => "SW",
+        "UN" => "NA",
+        t if t.starts_with("USER") => "NNP",
+        t => t,
+    }
+    .to_string()
+}
@@ -0,0 +1,176 @@
+use crate::chartype::CharClass;
+
+// -----------------------------------------------------------------------------
+// Language Pre-Filter
+// -----------------------------------------------------------------------------
+//
+// Each paragraph (line) is assigned a language by the scripts of its
+// letters, before the lattice is built: Korean when enough of them are
+// Hangul, otherwise Japanese with kana, Chinese for hanja, English for
+// Latin letters. A paragraph that is not Korean is analyzed as usual,
+// passed through with every whitespace-separated word as one token, or
+// skipped as a single token, which costs next to nothing to decode.
+// Passed-through and skipped tokens are tagged SL, or SH for Chinese.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Language {
+    Korean,
+    English,
+    Japanese,
+    Chinese,
+    /// No letters at all: digits, symbols, emoji
+    Other,
+}
+
+impl Language {
+    pub const FOREIGN: [Language; 3] = [Language::English, Language::Japanese, Language::Chinese];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::Korean => "ko",
+            Language::English => "en",
+            Language::Japanese => "ja",
+            Language::Chinese => "zh",
+            Language::Other => "und",
+        }
+    }
+
+    pub fn parse(code: &str) -> Option<Self> {
+        [
+            Language::Korean,
+            Language::English,
+            Language::Japanese,
+            Language::Chinese,
+            Language::Other,
+        ]
+        .into_iter()
+        .find(|l| l.code() == code)
+    }
+
+    fn tag(self) -> &'static str {
+        match self {
+            Language::Chinese => "SH",
+            _ => "SL",
+        }
+    }
+}
+
+/// What is done with a paragraph of a foreign language.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+    Analyze,
+    Passthrough,
+    Skip,
+}
+
+impl Action {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "analyze" => Some(Action::Analyze),
+            "passthrough" => Some(Action::Passthrough),
+            "skip" => Some(Action::Skip),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct LanguageFilter {
+    /// Per language of `Language::FOREIGN`
+    pub actions: [Action; 3],
+    /// Share of Hangul among the letters from which a paragraph is Korean
+    pub hangul_ratio: f64,
+}
+
+impl LanguageFilter {
+    fn action(&self, language: Language) -> Action {
+        match Language::FOREIGN.iter().position(|&l| l == language) {
+            Some(k) => self.actions[k],
+            None => Action::Analyze,
+        }
+    }
+}
+
+/// The language of `chars`.
+pub(crate) fn detect(chars: &[char], hangul_ratio: f64) -> Language {
+    let (mut hangul, mut latin, mut kana, mut han) = (0usize, 0usize, 0usize, 0usize);
+    for &c in chars {
+        match CharClass::of(c) {
+            CharClass::Hangul => hangul += 1,
+            CharClass::Latin => latin += 1,
+            CharClass::Kana => kana += 1,
+            CharClass::Han => han += 1,
+            _ => {}
+        }
+    }
+    let letters = hangul + latin + kana + han;
+    if letters == 0 {
+        return Language::Other;
+    }
+    if hangul > 0 && hangul as f64 >= hangul_ratio * letters as f64 {
+        return Language::Korean;
+    }
+    if kana > 0 && kana + han >= latin {
+        Language::Japanese
+    } else if han > latin {
+        Language::Chinese
+    } else if latin > 0 {
+        Language::English
+    } else {
+        Language::Korean
+    }
+}
+
+/// Paragraphs of `chars` as `(start, end, language)`, line breaks left
+/// out.
+pub(crate) fn paragraphs(chars: &[char], hangul_ratio: f64) -> Vec<(usize, usize, Language)> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    for end in 0..=chars.len() {
+        if end == chars.len() || matches!(chars[end], '\n' | '\r') {
+            if end > start {
+                out.push((start, end, detect(&chars[start..end], hangul_ratio)));
+            }
+            start = end + 1;
+        }
+    }
+    out
+}
+
+/// Protected spans `(start, end, tag)` of the paragraphs passed through or
+/// skipped, in order.
+pub(crate) fn find_spans(
+    chars: &[char],
+    filter: &LanguageFilter,
+) -> Vec<(usize, usize, &'static str)> {
+    let mut spans = Vec::new();
+    for (start, end, language) in paragraphs(chars, filter.hangul_ratio) {
+        let is_space = |i: &usize| chars[*i].is_whitespace();
+        match filter.action(language) {
+            Action::Analyze => {}
+            Action::Skip => {
+                // Without the whitespace around it
+                let Some(first) = (start..end).find(|i| !is_space(i)) else {
+                    continue;
+                };
+                let last = (start..end).rfind(|i| !is_space(i)).unwrap_or(first);
+                spans.push((first, last + 1, language.tag()));
+            }
+            Action::Passthrough => {
+                let mut i = start;
+                while i < end {
+                    if is_space(&i) {
+                        i += 1;
+                        continue;
+                    }
+                    let word = i;
+                    while i < end && !is_space(&i) {
+                        i += 1;
+                    }
+                    spans.push((word, i, language.tag()));
+                }
+            }
+        }
+    }
+    spans
+}
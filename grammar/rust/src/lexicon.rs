@@ -0,0 +1,373 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+
+use crate::conjugation::{self, is_predicate};
+use crate::dict::{EntryMeta, MAX_WORD_LEN};
+use crate::tags::{TagSet, UnknownTag};
+use crate::TrieData;
+
+// -----------------------------------------------------------------------------
+// Raw Lexicon Files
+// -----------------------------------------------------------------------------
+//
+// tsv / csv:  surface, pos[, lemma[, cost]]   lemma defaults to the surface
+// mecab:      mecab-ko-dic rows
+//             surface,left_id,right_id,cost,pos,semantic,coda,reading,type,...
+//
+// Blank lines and lines starting with `#` are skipped in tsv / csv files.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Tsv,
+    Csv,
+    Mecab,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "tsv" => Some(Format::Tsv),
+            "csv" => Some(Format::Csv),
+            "mecab" => Some(Format::Mecab),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the extension and, for `.csv`, the first row:
+    /// numeric context ids in columns 2-4 mean mecab-ko-dic.
+    pub fn detect(path: &str, text: &str) -> Self {
+        if !path.ends_with(".csv") {
+            return Format::Tsv;
+        }
+        let first = text
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty() && !l.starts_with('#'));
+        match first.map(split_csv) {
+            Some(fields)
+                if fields.len() >= 8 && fields[1..4].iter().all(|f| f.parse::<i64>().is_ok()) =>
+            {
+                Format::Mecab
+            }
+            _ => Format::Csv,
+        }
+    }
+}
+
+/// A rejected line of a lexicon file.
+#[derive(Debug)]
+pub struct LexiconError {
+    pub path: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for LexiconError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.path, self.line, self.message)
+    }
+}
+
+/// One validated dictionary entry.
+#[derive(Debug)]
+pub(crate) struct Entry {
+    pub surface: String,
+    pub pos: String,
+    pub lemma: String,
+    pub cost: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub entries: usize,
+    /// Stems and fused forms added by conjugation expansion
+    pub expanded: usize,
+    /// Rows that are valid but cannot be a single entry (mecab Inflect / Preanalysis)
+    pub skipped: usize,
+    /// Entries kept despite a tag outside the inventory, by tag
+    pub unknown_tags: BTreeMap<String, usize>,
+}
+
+/// Splits one CSV row, honoring double-quoted fields.
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+fn validate(surface: &str, pos: &str, cost: Option<&str>) -> Result<Option<f64>, String> {
+    if surface.is_empty() {
+        return Err("empty surface".to_string());
+    }
+    if surface.chars().any(char::is_whitespace) {
+        return Err(format!("surface '{}' contains whitespace", surface));
+    }
+    if surface.chars().count() > MAX_WORD_LEN {
+        return Err(format!(
+            "surface '{}' is longer than {} characters and can never match",
+            surface, MAX_WORD_LEN
+        ));
+    }
+    // Contracted forms carry one tag per morpheme: 갔다/VV+EP+EF
+    let valid_tag = pos.split('+').all(|part| {
+        let mut tag = part.chars();
+        tag.next().is_some_and(|c| c.is_ascii_uppercase())
+            && tag.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+    });
+    if !valid_tag {
+        return Err(format!("invalid POS tag '{}'", pos));
+    }
+    match cost.map(str::trim).filter(|c| !c.is_empty()) {
+        None => Ok(None),
+        Some(c) => match c.parse::<f64>() {
+            Ok(v) if v.is_finite() => Ok(Some(v)),
+            _ => Err(format!("invalid cost '{}'", c)),
+        },
+    }
+}
+
+/// Parses one row; `Ok(None)` is a row to skip.
+fn parse_row(line: &str, format: Format) -> Result<Option<Entry>, String> {
+    let fields: Vec<String> = match format {
+        Format::Tsv => line.split('\t').map(str::to_string).collect(),
+        Format::Csv | Format::Mecab => split_csv(line),
+    };
+    let field = |i: usize| fields.get(i).map(|f| f.trim());
+
+    if format == Format::Mecab {
+        if fields.len() < 9 {
+            return Err(format!(
+                "expected at least 9 mecab fields, found {}",
+                fields.len()
+            ));
+        }
+        let (surface, pos) = (field(0).unwrap(), field(4).unwrap());
+        if matches!(field(8), Some("Inflect" | "Preanalysis")) || pos.contains('+') {
+            return Ok(None);
+        }
+        validate(surface, pos, None)?;
+        // mecab stems are bare; KULIM stem entries carry the dictionary form
+        let lemma = if is_predicate(pos) {
+            format!("{}다", surface)
+        } else {
+            surface.to_string()
+        };
+        return Ok(Some(Entry {
+            surface: surface.to_string(),
+            pos: pos.to_string(),
+            lemma,
+            cost: None,
+        }));
+    }
+
+    if fields.len() < 2 || fields.len() > 4 {
+        return Err(format!("expected 2 to 4 fields, found {}", fields.len()));
+    }
+    let (surface, pos) = (field(0).unwrap(), field(1).unwrap());
+    let cost = validate(surface, pos, field(3))?;
+    let lemma = field(2).filter(|l| !l.is_empty()).unwrap_or(surface);
+    Ok(Some(Entry {
+        surface: surface.to_string(),
+        pos: pos.to_string(),
+        lemma: lemma.to_string(),
+        cost,
+    }))
+}
+
+/// Reads `path` into `data`. With `expand`, a predicate listed in its
+/// dictionary form (가다/VV) also gets its stem (가/VV/가다) and its
+/// contracted and irregular forms (갔/VV+EP/가+았, see `conjugation`), as
+/// the Python dictionary build restores them. Entries are tagged with
+/// `source` when given.
+/// Tags outside `tags` are rejected or counted as its policy says.
+/// Returns the rejected lines.
+pub(crate) fn load(
+    path: &str,
+    format: Option<Format>,
+    expand: bool,
+    source: Option<&str>,
+    tags: &TagSet,
+    data: &mut TrieData,
+    stats: &mut Stats,
+) -> Result<Vec<LexiconError>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let text = text.strip_prefix('\u{FEFF}').unwrap_or(&text);
+    let format = format.unwrap_or_else(|| Format::detect(path, text));
+    let mut errors = Vec::new();
+    let mut add = |(surface, pos, lemma): (String, String, String), cost: Option<f64>| {
+        if let Some(source) = source {
+            let meta = EntryMeta {
+                source: Some(source.to_string()),
+                ..EntryMeta::default()
+            };
+            data.insert(surface.clone(), pos.clone(), lemma.clone(), cost);
+            data.set_meta(&surface, &pos, &lemma, meta);
+        } else {
+            data.insert(surface, pos, lemma, cost);
+        }
+    };
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || (format != Format::Mecab && trimmed.starts_with('#')) {
+            continue;
+        }
+        let entry = match parse_row(line.trim_end_matches('\r'), format) {
+            Ok(Some(entry)) => entry,
+            Ok(None) => {
+                stats.skipped += 1;
+                continue;
+            }
+            Err(message) => {
+                errors.push(LexiconError {
+                    path: path.to_string(),
+                    line: i + 1,
+                    message,
+                });
+                continue;
+            }
+        };
+        if tags.unknown != UnknownTag::Allow {
+            if let Some(message) = tags.problem(&entry.pos) {
+                if tags.unknown == UnknownTag::Reject {
+                    errors.push(LexiconError {
+                        path: path.to_string(),
+                        line: i + 1,
+                        message,
+                    });
+                    continue;
+                }
+                *stats.unknown_tags.entry(entry.pos.clone()).or_insert(0) += 1;
+            }
+        }
+        if expand {
+            for form in conjugation::expand(&entry.surface, &entry.pos, &entry.lemma) {
+                add(form, entry.cost);
+                stats.expanded += 1;
+            }
+        }
+        add((entry.surface, entry.pos, entry.lemma), entry.cost);
+        stats.entries += 1;
+    }
+    Ok(errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(line: &str, format: Format) -> (String, String, String, Option<f64>) {
+        let e = parse_row(line, format).unwrap().unwrap();
+        (e.surface, e.pos, e.lemma, e.cost)
+    }
+
+    #[test]
+    fn parses_rows_of_each_format() {
+        let plain = |s: &str, p: &str, l: &str| (s.into(), p.into(), l.into(), None);
+        assert_eq!(
+            entry("학교\tNNG", Format::Tsv),
+            plain("학교", "NNG", "학교")
+        );
+        assert_eq!(
+            entry("갔다\tVV+EF\t가+았다\t1.5", Format::Tsv),
+            ("갔다".into(), "VV+EF".into(), "가+았다".into(), Some(1.5))
+        );
+        assert_eq!(
+            entry("\"a,b\",SL,\"say \"\"hi\"\"\"", Format::Csv),
+            plain("a,b", "SL", "say \"hi\"")
+        );
+        let mecab = "먹,2421,3398,1640,VV,*,T,먹,*,*,*,*";
+        assert_eq!(entry(mecab, Format::Mecab), plain("먹", "VV", "먹다"));
+        let inflected = "갔,2421,3398,1640,VV+EP,*,T,갔,Inflect,VV,EP,가/VV/*+았/EP/*";
+        assert!(parse_row(inflected, Format::Mecab).unwrap().is_none());
+        assert_eq!(
+            Format::detect("dic/NNG.csv", "학교,1,2,3,NNG,*,F,학교,*,*,*,*"),
+            Format::Mecab
+        );
+        assert_eq!(Format::detect("user.csv", "학교,NNG"), Format::Csv);
+    }
+
+    #[test]
+    fn rejects_invalid_rows() {
+        let long = format!("{}\tNNG", "가".repeat(MAX_WORD_LEN + 1));
+        for line in [
+            "학교",
+            "학교\tNNG\t학교\t1\textra",
+            "\tNNG",
+            "학 교\tNNG",
+            &long,
+            "학교\tnng",
+            "학교\tNN G",
+            "갔다\tVV+\t가+았다",
+            "학교\tNNG\t학교\tcheap",
+            "학교\tNNG\t학교\tinf",
+        ] {
+            assert!(parse_row(line, Format::Tsv).is_err(), "{:?}", line);
+        }
+        assert!(parse_row("먹,1,2,3,VV", Format::Mecab).is_err());
+    }
+
+    #[test]
+    fn loads_expanded_predicates_and_collects_errors() {
+        let path = std::env::temp_dir().join(format!("kulim-lexicon-{}.tsv", std::process::id()));
+        std::fs::write(
+            &path,
+            "\u{FEFF}# comment\n가다\tVV\n\n듣다\tVV\n학교\tXX\n학 교\tNNG\n",
+        )
+        .unwrap();
+        let path = path.to_str().unwrap().to_string();
+        let tags = TagSet::new(["VV".to_string()], UnknownTag::Reject);
+        let (mut data, mut stats) = (TrieData::default(), Stats::default());
+        let errors = load(
+            &path,
+            None,
+            true,
+            Some("test"),
+            &tags,
+            &mut data,
+            &mut stats,
+        )
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, [5, 6]);
+        assert_eq!((stats.entries, stats.expanded), (2, 5));
+        let patterns = |surface: &str| -> Vec<(String, String)> {
+            let mut out: Vec<(String, String)> = data.dict[surface]
+                .iter()
+                .map(|p| {
+                    let p = data.pattern(p);
+                    (p.pos.to_string(), p.lemma.to_string())
+                })
+                .collect();
+            out.sort();
+            out
+        };
+        assert_eq!(patterns("갔"), [("VV+EP".into(), "가+았".into())]);
+        assert_eq!(
+            patterns("가"),
+            [
+                ("VV".into(), "가다".into()),
+                ("VV+EC".into(), "가+아".into())
+            ]
+        );
+        assert_eq!(patterns("들"), [("VV".into(), "듣다".into())]);
+        let ga = &data.dict["가"][0];
+        assert_eq!(data.pattern(ga).meta.and_then(|m| m.source), Some("test"));
+    }
+}
@@ -1,275 +1,4270 @@
+#![allow(non_local_definitions)] // pyo3 0.20 macro expansion
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyUserWarning, PyValueError};
 use pyo3::prelude::*;
-use pyo3::exceptions::PyValueError;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use pyo3::types::{PyBytes, PySlice, PyTuple};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
-// -----------------------------------------------------------------------------
-// Scoring Constants
-// -----------------------------------------------------------------------------
-const COST_LONG_WORD: f64 = -40.0;
-const COST_MEDIUM_WORD: f64 = -30.0;
-const COST_SHORT_WORD: f64 = -5.0;
-const PENALTY_SINGLE_VERB_IC: f64 = 20.0;
-const COST_OOV: f64 = 50.0;
-const BONUS_NOUN_2PLUS: f64 = 5.0;
-const BONUS_ADVERB_2PLUS: f64 = 10.0;
-
-const BONUS_NOUN_JOSA: f64 = 20.0;
-const BONUS_VERB_EOMI: f64 = 15.0;
-const BONUS_EOMI_EOMI: f64 = 10.0;
-const BONUS_ADVERB_NOUN: f64 = 15.0;
-const BONUS_ADVERB_VERB: f64 = 10.0;
-const BONUS_DETERMINER_NOUN: f64 = 10.0;
-
-const COST_CONJUGATION_BASE: f64 = -25.0;
-const BONUS_CONJ_CONTEXT: f64 = 10.0;
+mod ambiguity;
+mod anonymize;
+mod arrow;
+mod batch;
+mod bigram;
+mod buffer;
+mod candidates;
+mod casefold;
+mod chartype;
+mod chunk;
+pub mod compile;
+mod conjugation;
+mod corrections;
+mod counter;
+mod coverage;
+mod decoder;
+mod dict;
+mod discovery;
+mod dot;
+mod entropy;
+mod errors;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod frozen;
+mod gazetteer;
+mod generate;
+mod grammar;
+mod grapheme;
+mod hashing;
+mod josa;
+mod kiwi;
+mod language;
+mod lexicon;
+mod matches;
+mod mecabdic;
+mod normalize;
+mod options;
+mod output;
+mod parquet;
+mod profanity;
+mod reading;
+mod regression;
+mod rescoring;
+mod rewrite;
+mod scoring;
+mod segment;
+mod sentence;
+mod serial;
+mod slang;
+mod smallstr;
+mod social;
+mod structured;
+mod subword;
+mod suffix;
+mod tags;
+mod tuning;
+mod view;
 
-// -----------------------------------------------------------------------------
-// Data Structures
-// -----------------------------------------------------------------------------
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct TriePattern {
-    pos: String,
-    lemma: String,
+use arrow::ArrowResultWriter;
+use batch::BatchError;
+use bigram::BigramCounts;
+use buffer::Buffer;
+use candidates::{Candidate, FixedEdges};
+use casefold::CaseIndex;
+use chartype::{CharClass, OovGrouping};
+use corrections::{Corrections, Span};
+use counter::Counters;
+use decoder::{BuildOptions, EdgeKind, Lattice};
+pub(crate) use dict::{EntryMeta, StringPool, TrieData, TriePattern, MAX_WORD_LEN};
+use discovery::CorpusStats;
+use entropy::{BranchingEntropy, EntropyStats};
+use errors::{ErrorSite, Stage};
+use frozen::FrozenDict;
+use gazetteer::Gazetteer;
+use generate::Lexicon;
+use grammar::{Grammar, Korean, TagPattern, TransitionRule};
+use language::{Action, Language, LanguageFilter};
+use matches::{PatternMatches, Scanner};
+use normalize::{Folding, Normalized, Sanitize};
+use options::AnalyzeOptions;
+use output::Sentence;
+use parquet::Column;
+use profanity::Profanity;
+use reading::Readings;
+use rescoring::{PyCallableScorer, ScoreWeights};
+use rewrite::RewriteRule;
+use segment::Segmentations;
+use serial::{Legacy, LoadError};
+use social::SocialTags;
+use structured::StructuredTokens;
+use subword::{Piece, VocabCounts};
+use suffix::SuffixIndex;
+use tags::{TagSet, UnknownTag};
+use tuning::{Counts, Param, Values};
+use view::{Ban, DictRef, DictView, Overlay};
+
+/// (surface, pos, lemma)
+pub(crate) type Morpheme = (String, String, String);
+/// (pos, lemma)
+pub(crate) type Pattern = (String, String);
+/// (pos id, lemma id) into `get_symbols()`
+type PatternIds = (u32, u32);
+/// (morphemes, cost)
+pub(crate) type Analysis = (Vec<Morpheme>, f64);
+// An analysis and the score breakdown of its edges
+type Explained = (Analysis, Vec<decoder::Step>);
+/// (surface, pos, lemma, start, end) with character offsets into the input
+pub(crate) type Located = (String, String, String, usize, usize);
+/// (surface, pos, lemma, start, end, uncertain)
+type Flagged = (String, String, String, usize, usize, bool);
+/// (pos, lemma, source, priority, note)
+pub(crate) type EntryInfo = (String, String, Option<String>, Option<u8>, Option<String>);
+
+// A named domain dictionary consulted alongside the base dictionary
+#[derive(Clone)]
+struct Layer {
+    name: String,
+    frozen: Option<Arc<FrozenDict>>,
+    data: Arc<TrieData>,
+    enabled: bool,
+}
+
+impl Layer {
+    fn push_dicts<'a>(&'a self, dicts: &mut Vec<DictRef<'a>>) {
+        if let Some(frozen) = &self.frozen {
+            dicts.push(DictRef::Frozen(frozen));
+        }
+        dicts.push(DictRef::Map(&self.data));
+    }
+}
+
+/// Raises `KeyboardInterrupt` (or whatever a signal handler raised) in the
+/// middle of a long Rust loop.
+pub(crate) fn check_signals() -> PyResult<()> {
+    Python::with_gil(|py| py.check_signals())
+}
+
+create_exception!(kulim_rust, AnalysisError, PyValueError);
+create_exception!(kulim_rust, InputTooLongError, AnalysisError);
+create_exception!(kulim_rust, DictFormatError, PyValueError);
+create_exception!(kulim_rust, DictEntryError, PyValueError);
+
+/// The file the base dictionary was loaded from, for `describe`.
+#[derive(Clone, Debug)]
+struct DictSource {
+    path: String,
+    // "saved" (`load_trie`) or "compiled" (`load_compiled`)
+    format: &'static str,
+    // Of the file layout; `None` for headerless legacy files
+    version: Option<u32>,
+    // Last modification of the file, in seconds since the epoch
+    modified: Option<u64>,
 }
 
-// Inner data struct that is Pure Rust and Serializable
-#[derive(Serialize, Deserialize, Default)]
-struct TrieData {
-    dict: HashMap<String, Vec<TriePattern>>,
+impl DictSource {
+    fn new(path: &Path, format: &'static str, version: Option<u32>) -> Self {
+        let modified = std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs());
+        DictSource {
+            path: path.to_string_lossy().into_owned(),
+            format,
+            version,
+            modified,
+        }
+    }
+}
+
+// Chunk size and hard cap on analyzed inputs, in characters
+#[derive(Clone, Copy)]
+struct InputLimits {
+    chunk_chars: usize,
+    max_chars: Option<usize>,
+}
+
+impl Default for InputLimits {
+    fn default() -> Self {
+        InputLimits {
+            chunk_chars: 10_000,
+            max_chars: None,
+        }
+    }
 }
 
 // PyO3 Wrapper
 #[pyclass]
 struct RustTrie {
-    data: TrieData,
+    // Compiled read-only base (possibly memory-mapped) under `data`
+    frozen: Option<Arc<FrozenDict>>,
+    // Shared with every fork; only cloned when a shared base is mutated
+    data: Arc<TrieData>,
+    // Present on forks: per-fork inserts and bans over `data`
+    overlay: Option<Overlay>,
+    // Costs, tag constraints and unknown-word rules of the language
+    grammar: Arc<dyn Grammar>,
+    rules: Vec<RewriteRule>,
+    layers: Vec<Layer>,
+    // Emoticon / slang table, consulted after the layers when enabled
+    slang: Option<Arc<TrieData>>,
+    // Hashtag / mention recognition, off unless enabled
+    social: Option<SocialTags>,
+    // Phone number / ID / email recognition, off unless enabled
+    structured: Option<StructuredTokens>,
+    // Foreign paragraphs passed through or skipped, off unless enabled
+    language: Option<LanguageFilter>,
+    limits: InputLimits,
+    // Single-best analyses use forward-backward decoding (experimental)
+    bidirectional: bool,
+    // Hypotheses kept per position by second-order decoding, when enabled
+    second_order: Option<usize>,
+    // Dead-end chunks are segmented greedily instead of raising
+    dead_end_fallback: bool,
+    // Analyses are checked to spell out their input before being returned
+    verify: bool,
+    // Script-run grouping of unknown text; `None` falls back per character
+    grouping: Option<OovGrouping>,
+    // Endings of the default view, built on first use; reset on any change
+    suffixes: OnceLock<SuffixIndex>,
+    // Corpus branching entropy and weight for unknown-word boundaries
+    entropy: Option<(Arc<EntropyStats>, f64)>,
+    // Invisible / control character cleaning before analysis, off by default
+    sanitize: Option<Sanitize>,
+    // Width folding, Hangul composition and repeat collapsing, off by default
+    folding: Option<Folding>,
+    // Tag inventory checked by the insert methods
+    tags: TagSet,
+    // Case-insensitive matching of Latin keys, off by default
+    case_fold: bool,
+    // Latin keys of every dictionary including disabled layers, built on
+    // first use; reset when entries are added
+    folded: OnceLock<CaseIndex>,
+    // Whether a key of those dictionaries contains whitespace, found on
+    // first use; reset with `folded` and by inserting such a key
+    spaced: OnceLock<bool>,
+    corrections: Corrections,
+    // Python callbacks adding candidate edges (`add_candidate_source`)
+    sources: Vec<PyObject>,
+    // Dictionary readings for `analyze_readings`
+    readings: Readings,
+    // Numeral + counter constructions (`set_counters`), off by default
+    counters: Option<Counters>,
+    // Name lists from `load_gazetteer`, shared by forks
+    gazetteers: Vec<Arc<Gazetteer>>,
+    // Words `mask_profanity` masks; the built-in list until one is loaded
+    profanity: Option<Arc<Profanity>>,
+    // Whole Latin words as SL tokens (`set_code_switching`)
+    code_switching: bool,
+    // Tags and lemmas numbered by the `*_ids` methods; only ever grows
+    symbols: Mutex<StringPool>,
+    // Where the base dictionary came from, when loaded from a file
+    source: Option<DictSource>,
 }
 
-// -----------------------------------------------------------------------------
-// Constraint Validator
-// -----------------------------------------------------------------------------
-fn is_valid_transition(prev_pos: &str, curr_pos: &str) -> bool {
-    // Ported from constraints.py
-    match (prev_pos, curr_pos) {
-        ("JKS", "JKS") => false,
-        ("JKO", "JKO") => false,
-        ("EF", "JKS") => false,
-        ("EF", "JKO") => false,
-        ("EF", "EF") => false,
-        ("SF", "JKS") => false,
-        _ => true,
-    }
+thread_local! {
+    // Set on the calling thread for the duration of a call with
+    // `AnalyzeOptions(strict=True)`, so concurrent calls do not see it
+    static STRICT: Cell<bool> = const { Cell::new(false) };
 }
 
-#[pymethods]
 impl RustTrie {
-    #[new]
-    fn new() -> Self {
+    fn from_data(data: TrieData) -> Self {
         RustTrie {
-            data: TrieData::default(),
+            frozen: None,
+            data: Arc::new(data),
+            overlay: None,
+            grammar: Arc::new(Korean::default()),
+            rules: Vec::new(),
+            layers: Vec::new(),
+            slang: None,
+            social: None,
+            structured: None,
+            language: None,
+            limits: InputLimits::default(),
+            bidirectional: false,
+            second_order: None,
+            dead_end_fallback: false,
+            verify: false,
+            grouping: Some(OovGrouping::default()),
+            suffixes: OnceLock::new(),
+            entropy: None,
+            sanitize: None,
+            folding: None,
+            tags: TagSet::default(),
+            case_fold: false,
+            folded: OnceLock::new(),
+            spaced: OnceLock::new(),
+            corrections: Corrections::default(),
+            sources: Vec::new(),
+            readings: Readings::default(),
+            counters: None,
+            gazetteers: Vec::new(),
+            profanity: None,
+            code_switching: false,
+            symbols: Mutex::default(),
+            source: None,
         }
     }
 
-    fn insert(&mut self, word: String, pos: String, lemma: String) {
-        let entry = self.data.dict.entry(word).or_insert_with(Vec::new);
-        if !entry.iter().any(|p| p.pos == pos && p.lemma == lemma) {
-            entry.push(TriePattern { pos, lemma });
+    fn layer_mut(&mut self, name: &str) -> PyResult<&mut Layer> {
+        self.layers
+            .iter_mut()
+            .find(|l| l.name == name)
+            .ok_or_else(|| PyValueError::new_err(format!("unknown layer '{}'", name)))
+    }
+
+    /// This trie's own dictionaries, compiled base first.
+    fn base_dicts(&self) -> Vec<DictRef<'_>> {
+        let mut dicts: Vec<DictRef> = self.frozen.iter().map(|f| DictRef::Frozen(f)).collect();
+        dicts.push(DictRef::Map(&self.data));
+        dicts
+    }
+
+    /// The base dictionary (plus fork overlay) without any layers.
+    fn own_view(&self) -> DictView<'_> {
+        DictView::new(self.base_dicts(), self.overlay.as_ref())
+    }
+
+    /// The base dictionary followed by the requested layers, or by the
+    /// enabled ones when `layers` is `None`.
+    fn view(&self, layers: Option<&[String]>) -> PyResult<DictView<'_>> {
+        let mut dicts = self.base_dicts();
+        match layers {
+            Some(names) => {
+                for name in names {
+                    let layer = self
+                        .layers
+                        .iter()
+                        .find(|l| &l.name == name)
+                        .ok_or_else(|| {
+                            PyValueError::new_err(format!("unknown layer '{}'", name))
+                        })?;
+                    layer.push_dicts(&mut dicts);
+                }
+            }
+            None => self
+                .layers
+                .iter()
+                .filter(|l| l.enabled)
+                .for_each(|l| l.push_dicts(&mut dicts)),
         }
+        dicts.extend(self.slang.iter().map(|s| DictRef::Map(s)));
+        Ok(DictView::new(dicts, self.overlay.as_ref()))
     }
 
-    fn exists(&self, word: String) -> bool {
-        self.data.dict.contains_key(&word)
+    fn default_view(&self) -> DictView<'_> {
+        let mut dicts = self.base_dicts();
+        self.layers
+            .iter()
+            .filter(|l| l.enabled)
+            .for_each(|l| l.push_dicts(&mut dicts));
+        dicts.extend(self.slang.iter().map(|s| DictRef::Map(s)));
+        DictView::new(dicts, self.overlay.as_ref())
     }
 
-    fn search(&self, word: String) -> Vec<(String, String)> {
-        match self.data.dict.get(&word) {
-            Some(patterns) => patterns.iter()
-                .map(|p| (p.pos.clone(), p.lemma.clone()))
-                .collect(),
-            None => Vec::new(),
+    /// This trie's own entries as a standalone dictionary; free unless forked
+    /// or compiled.
+    fn snapshot(&self) -> Arc<TrieData> {
+        match (&self.overlay, &self.frozen) {
+            (None, None) => Arc::clone(&self.data),
+            _ => Arc::new(self.own_view().materialize()),
         }
     }
-    
-    fn search_batch(&self, words: Vec<String>) -> Vec<Vec<(String, String)>> {
-         words.into_iter().map(|w| self.search(w)).collect()
+
+    /// `insert`, merging `meta` into the entry's metadata when given.
+    fn insert_entry(
+        &mut self,
+        word: String,
+        pos: String,
+        lemma: String,
+        cost: Option<f64>,
+        meta: Option<EntryMeta>,
+    ) {
+        if suffix::is_ending(&pos) {
+            self.suffixes = OnceLock::new();
+        }
+        if casefold::has_latin(&word) {
+            self.folded = OnceLock::new();
+        }
+        if word.chars().any(char::is_whitespace) {
+            self.spaced = OnceLock::new();
+        }
+        let data = self.edit_data();
+        match meta {
+            Some(meta) => {
+                data.insert(word.clone(), pos.clone(), lemma.clone(), cost);
+                data.set_meta(&word, &pos, &lemma, meta);
+            }
+            None => data.insert(word, pos, lemma, cost),
+        }
     }
 
-    fn get_stats(&self) -> (usize, usize) {
-        let nodes = self.data.dict.len();
-        let patterns = self.data.dict.values().map(|v| v.len()).sum();
-        (nodes, patterns)
+    /// Removes the entries of the base dictionary (and the fork's additions)
+    /// for which `drop` holds, returning them sorted.
+    fn prune_where(
+        &mut self,
+        mut drop: impl FnMut(&str, &str, &str) -> PyResult<bool>,
+    ) -> PyResult<Vec<Morpheme>> {
+        if self.overlay.is_none() && self.frozen.is_some() {
+            return Err(PyValueError::new_err(
+                "cannot prune a compiled dictionary; fork() it first",
+            ));
+        }
+        let mut dropped = Vec::new();
+        {
+            let view = self.own_view();
+            for surface in view.surfaces() {
+                for p in view.lookup(&surface) {
+                    if drop(&surface, p.pos, p.lemma)? {
+                        dropped.push((surface.to_string(), p.pos.to_string(), p.lemma.to_string()));
+                    }
+                }
+            }
+        }
+        for (surface, pos, lemma) in &dropped {
+            self.ban(surface.clone(), Some(pos.clone()), Some(lemma.clone()))?;
+        }
+        self.shrink_to_fit();
+        dropped.sort();
+        Ok(dropped)
     }
 
-    fn search_all_patterns(&self, text: String) -> Vec<(usize, usize, Vec<(String, String)>)> {
-        let chars: Vec<char> = text.chars().collect();
-        let n = chars.len();
-        let mut results = Vec::new();
+    /// Applies the `set_tag_inventory` policy to the entries about to be
+    /// inserted, warning once per distinct unknown tag. A rejected entry is
+    /// reported at its index in the input, counted from `first`.
+    fn check_tags(&self, py: Python<'_>, first: usize, entries: &[Morpheme]) -> PyResult<()> {
+        if self.tags.unknown == UnknownTag::Allow {
+            return Ok(());
+        }
+        let mut seen = std::collections::HashSet::new();
+        for (k, (word, tag, _)) in entries.iter().enumerate() {
+            if !seen.insert(tag) {
+                continue;
+            }
+            let Some(problem) = self.tags.problem(tag) else {
+                continue;
+            };
+            match self.tags.unknown {
+                UnknownTag::Reject => {
+                    return Err(ErrorSite {
+                        stage: Stage::Import,
+                        offset: first + k,
+                        substring: word.clone(),
+                        message: problem,
+                    }
+                    .into_err::<DictEntryError>())
+                }
+                _ => PyErr::warn(py, py.get_type::<PyUserWarning>(), &problem, 1)?,
+            }
+        }
+        Ok(())
+    }
 
-        for i in 0..n {
-            for len in 1..=16 {
-                if i + len > n {
-                    break;
+    /// Bulk counterpart of `insert_entry`; returns how many entries were new.
+    fn insert_entries(&mut self, entries: Vec<Morpheme>) -> usize {
+        if entries.iter().any(|(_, pos, _)| suffix::is_ending(pos)) {
+            self.suffixes = OnceLock::new();
+        }
+        if entries.iter().any(|(word, _, _)| casefold::has_latin(word)) {
+            self.folded = OnceLock::new();
+        }
+        if entries
+            .iter()
+            .any(|(word, _, _)| word.chars().any(char::is_whitespace))
+        {
+            self.spaced = OnceLock::new();
+        }
+        self.edit_data().insert_batch(entries)
+    }
+
+    /// The map that inserts go to: the fork's overlay or the base dictionary.
+    fn edit_data(&mut self) -> &mut TrieData {
+        match &mut self.overlay {
+            Some(overlay) => &mut overlay.added,
+            None => Arc::make_mut(&mut self.data),
+        }
+    }
+
+    fn case_index(&self) -> &CaseIndex {
+        self.folded.get_or_init(|| {
+            let mut dicts = self.base_dicts();
+            self.layers.iter().for_each(|l| l.push_dicts(&mut dicts));
+            dicts.extend(self.slang.iter().map(|s| DictRef::Map(s)));
+            CaseIndex::build(&DictView::new(dicts, self.overlay.as_ref()))
+        })
+    }
+
+    /// Whether some dictionary key, in any layer or the slang table,
+    /// contains whitespace, and so may cross an eojeol boundary.
+    fn has_spaced_keys(&self) -> bool {
+        *self.spaced.get_or_init(|| {
+            let mut dicts = self.base_dicts();
+            self.layers.iter().for_each(|l| l.push_dicts(&mut dicts));
+            dicts.extend(self.slang.iter().map(|s| DictRef::Map(s)));
+            DictView::new(dicts, self.overlay.as_ref())
+                .keys()
+                .any(|key| key.contains(char::is_whitespace))
+        })
+    }
+
+    fn suffix_index(&self) -> &SuffixIndex {
+        self.suffixes
+            .get_or_init(|| SuffixIndex::build(&self.default_view()))
+    }
+
+    /// `text` as the decoder sees it: sanitized and folded when enabled,
+    /// then without whitespace for `ignore_spaces`.
+    fn prepare(&self, text: &str, ignore_spaces: bool) -> Normalized {
+        let mut norm = Normalized::identity(text);
+        if let Some(opts) = &self.sanitize {
+            norm = normalize::sanitize(norm, opts);
+        }
+        if let Some(opts) = &self.folding {
+            norm = normalize::fold(norm, opts);
+        }
+        if ignore_spaces {
+            norm = normalize::strip_spaces(norm);
+        }
+        norm
+    }
+
+    fn build_options<'a>(&'a self, extra: Option<&'a FixedEdges>) -> BuildOptions<'a> {
+        BuildOptions {
+            grammar: &self.grammar,
+            social: self.social.as_ref(),
+            structured: self.structured.as_ref(),
+            language: self.language.as_ref(),
+            grouping: self.grouping.as_ref(),
+            entropy: self.entropy.as_ref().map(|(stats, w)| (&**stats, *w)),
+            case_fold: self.case_fold.then(|| self.case_index()),
+            extra,
+            counters: self.counters.as_ref(),
+            gazetteers: &self.gazetteers,
+            code_switching: self.code_switching,
+        }
+    }
+
+    /// The edges the registered candidate sources give for `text`, or
+    /// `None` with no sources.
+    fn extra_candidates(&self, text: &str) -> PyResult<Option<FixedEdges>> {
+        if self.sources.is_empty() {
+            return Ok(None);
+        }
+        let len = text.chars().count();
+        let mut candidates = Vec::new();
+        Python::with_gil(|py| {
+            for source in &self.sources {
+                let spans: Vec<(usize, usize, String, String, f64)> =
+                    source.call1(py, (text,))?.extract(py)?;
+                for (start, end, pos, lemma, cost) in spans {
+                    if start >= end || end > len {
+                        return Err(PyValueError::new_err(format!(
+                            "candidate span {}..{} outside the {} characters of the text",
+                            start, end, len
+                        )));
+                    }
+                    if !cost.is_finite() {
+                        return Err(PyValueError::new_err(format!(
+                            "candidate cost {} is not finite",
+                            cost
+                        )));
+                    }
+                    candidates.push(Candidate {
+                        start,
+                        end,
+                        pos,
+                        lemma,
+                        cost,
+                    });
                 }
-                let sub: String = chars[i..i+len].iter().collect();
-                if let Some(patterns) = self.data.dict.get(&sub) {
-                    let pat_vec: Vec<(String, String)> = patterns.iter()
-                        .map(|p| (p.pos.clone(), p.lemma.clone()))
-                        .collect();
-                    results.push((i, len, pat_vec));
+            }
+            Ok(())
+        })?;
+        Ok(Some(FixedEdges::new(candidates)))
+    }
+
+    /// Up to `n` paths through `lattice`, from the second-order decoder when
+    /// `set_second_order` is on; otherwise single-best requests use the
+    /// forward-backward decoder when `set_bidirectional` is on.
+    fn decode_paths(&self, lattice: &Lattice, n: usize) -> PyResult<Vec<decoder::Path>> {
+        self.decode_paths_with(lattice, n, check_signals)
+    }
+
+    /// `decode_paths` calling `check` instead of only checking for signals.
+    fn decode_paths_with<E>(
+        &self,
+        lattice: &Lattice,
+        n: usize,
+        mut check: impl FnMut() -> Result<(), E>,
+    ) -> Result<Vec<decoder::Path>, E> {
+        if let Some(max_states) = self.second_order {
+            return decoder::decode_second_order_with(lattice, n, max_states, check);
+        }
+        if self.bidirectional && n <= 1 {
+            return Ok(decoder::decode_bidirectional_with(lattice, &mut check)?
+                .into_iter()
+                .collect());
+        }
+        decoder::decode_with(lattice, n, check)
+    }
+
+    /// The best analysis of `norm`, empty for empty text.
+    fn analyze_best(
+        &self,
+        norm: &Normalized,
+        layers: Option<&[String]>,
+    ) -> PyResult<Vec<Morpheme>> {
+        Ok(self
+            .decode_nbest(norm, 1, layers)?
+            .pop()
+            .map(|(morphs, _)| morphs)
+            .unwrap_or_default())
+    }
+
+    /// The best analysis of `norm` if it can be found by `deadline`, else
+    /// the analyses of the chunks decoded in time followed by a greedy
+    /// segmentation of the rest, and `true`. A dead-end chunk is segmented
+    /// greedily too when `set_dead_end_fallback` is on.
+    fn decode_within(
+        &self,
+        norm: &Normalized,
+        layers: Option<&[String]>,
+        deadline: Instant,
+    ) -> PyResult<(Vec<Morpheme>, bool)> {
+        let text = norm.text();
+        self.check_length(&text)?;
+        let view = self.view(layers)?;
+        let mut check = || {
+            check_signals()?;
+            match Instant::now() < deadline {
+                true => Ok(()),
+                false => Err(Interrupt::Deadline),
+            }
+        };
+        let (mut morphs, mut truncated) = (Vec::new(), false);
+        let mut offset = 0;
+        for chunk in sentence::chunks(&text, self.limits.chunk_chars) {
+            let decoded = match Instant::now() >= deadline {
+                true => None,
+                false => {
+                    let extra = self.extra_candidates(chunk)?;
+                    let opts = self.build_options(extra.as_ref());
+                    match Lattice::build_with(&view, chunk, opts, &mut check).and_then(|lattice| {
+                        Ok((self.decode_paths_with(&lattice, 1, &mut check)?, lattice))
+                    }) {
+                        Ok((paths, lattice)) => match paths.first() {
+                            Some(path) => Some(lattice.morphemes(path)),
+                            None => {
+                                self.dead_end(norm, offset, &lattice)?;
+                                None
+                            }
+                        },
+                        Err(Interrupt::Deadline) => None,
+                        Err(Interrupt::Error(e)) => return Err(e),
+                    }
                 }
+            };
+            let mut part = decoded.unwrap_or_else(|| {
+                truncated = true;
+                decoder::greedy(&view, chunk, &*self.grammar)
+            });
+            self.postprocess(&mut part);
+            offset += chunk.chars().count();
+            morphs.extend(part);
+        }
+        self.verify_round_trip(norm, &morphs)?;
+        Ok((morphs, truncated))
+    }
+
+    /// Fails with `InputTooLongError` when `text` exceeds the hard cap.
+    fn check_length(&self, text: &str) -> PyResult<()> {
+        if let Some(max) = self.limits.max_chars {
+            let len = text.chars().count();
+            if len > max {
+                let chars: Vec<char> = text.chars().collect();
+                let message = format!("input of {} characters exceeds the limit of {}", len, max);
+                let err = ErrorSite::in_text(Stage::Input, &chars, max, message)
+                    .into_err::<InputTooLongError>();
+                Python::with_gil(|py| {
+                    err.value(py).setattr("length", len)?;
+                    err.value(py).setattr("limit", max)
+                })?;
+                return Err(err);
             }
         }
-        results
+        Ok(())
     }
 
-    fn analyze(&self, text: String) -> PyResult<Vec<(String, String, String)>> {
-        let chars: Vec<char> = text.chars().collect();
-        let n = chars.len();
-        
-        let mut dp = vec![f64::INFINITY; n + 1];
-        let mut path: Vec<Option<(String, String, String, usize)>> = vec![None; n + 1];
-        let mut prev_pos_table: Vec<Option<String>> = vec![None; n + 1];
+    /// Decodes `text` into up to `n` paths and applies the rewrite rules.
+    ///
+    /// Long inputs are decoded chunk by chunk (see `set_input_limits`), with
+    /// no transition context carried across chunks; the n-best lists of the
+    /// chunks are then combined.
+    fn decode_nbest(
+        &self,
+        norm: &Normalized,
+        n: usize,
+        layers: Option<&[String]>,
+    ) -> PyResult<Vec<Analysis>> {
+        Ok(self
+            .decode_explained(norm, n, layers, false)?
+            .into_iter()
+            .map(|(analysis, _)| analysis)
+            .collect())
+    }
 
-        dp[0] = 0.0;
+    /// Quantity tokens (`set_counters`), then the rewrite rules.
+    fn postprocess(&self, morphs: &mut Vec<Morpheme>) {
+        if let Some(counters) = self.counters.as_ref().filter(|c| c.composite) {
+            counter::compose(counters, morphs);
+        }
+        rewrite::apply_rules(&self.rules, morphs);
+    }
 
-        for i in 0..n {
-            if dp[i] == f64::INFINITY {
+    /// `decode_nbest` with the score breakdown of every analysis when
+    /// `breakdown` is set (and an empty one otherwise).
+    fn decode_explained(
+        &self,
+        norm: &Normalized,
+        n: usize,
+        layers: Option<&[String]>,
+        breakdown: bool,
+    ) -> PyResult<Vec<Explained>> {
+        let text = norm.text();
+        self.check_length(&text)?;
+        let view = self.view(layers)?;
+        let mut combined: Vec<Explained> = vec![((Vec::new(), 0.0), Vec::new())];
+        let mut offset = 0;
+        let mut memo = (n == 1 && !breakdown && self.eojeols_independent()).then(HashMap::new);
+        for chunk in sentence::chunks(&text, self.limits.chunk_chars) {
+            if let Some(memo) = &mut memo {
+                if let Some((mut morphs, cost)) = self.decode_eojeols(&view, chunk, memo)? {
+                    self.postprocess(&mut morphs);
+                    offset += chunk.chars().count();
+                    combined = combine_nbest(combined, vec![((morphs, cost), Vec::new())], n);
+                    continue;
+                }
+            }
+            let extra = self.extra_candidates(chunk)?;
+            let lattice = Lattice::build_with(
+                &view,
+                chunk,
+                self.build_options(extra.as_ref()),
+                check_signals,
+            )?;
+            let paths = self.decode_paths(&lattice, n)?;
+            if paths.is_empty() {
+                self.dead_end(norm, offset, &lattice)?;
+                let mut morphs = decoder::greedy(&view, chunk, &*self.grammar);
+                self.postprocess(&mut morphs);
+                offset += lattice.len();
+                combined = combine_nbest(combined, vec![((morphs, f64::INFINITY), Vec::new())], n);
                 continue;
             }
-            
-            let prev_pos = prev_pos_table[i].clone();
-            let prev_pos_deref = prev_pos.as_deref();
+            offset += lattice.len();
+            let part: Vec<Explained> = paths
+                .iter()
+                .map(|path| {
+                    let mut morphs = lattice.morphemes(path);
+                    self.postprocess(&mut morphs);
+                    let steps = match breakdown {
+                        true => lattice.breakdown(path, self.second_order.is_some()),
+                        false => Vec::new(),
+                    };
+                    ((morphs, path.cost), steps)
+                })
+                .collect();
+            combined = combine_nbest(combined, part, n);
+        }
+        for ((morphs, _), _) in &combined {
+            self.verify_round_trip(norm, morphs)?;
+        }
+        Ok(combined)
+    }
+}
+
+/// Memoized best analyses of eojeols within one call, keyed by the eojeol
+/// with the whitespace before it; `None` when no path spans it.
+type EojeolMemo<'t> = HashMap<&'t str, Option<(Vec<Morpheme>, f64)>>;
+
+impl RustTrie {
+    /// Whether the best path through a chunk is the best paths through its
+    /// eojeols, each with the whitespace before it, one after the other.
+    /// It is when no edge crosses whitespace and the decoder only looks one
+    /// edge back: whitespace is an unknown edge, whose cost ignores the tag
+    /// before it, so an eojeol's best path depends only on the eojeol and
+    /// the whitespace before it (or the chunk start). Protected spans,
+    /// counters and Python sources can look across whitespace.
+    fn eojeols_independent(&self) -> bool {
+        self.second_order.is_none()
+            && !self.bidirectional
+            && self.sources.is_empty()
+            && self.counters.is_none()
+            && self.structured.is_none()
+            && self.language.is_none()
+            && !self.gazetteers.iter().any(|g| g.spaced)
+            && !self.has_spaced_keys()
+    }
 
-            // 1. Dictionary Search
-            for len in 1..=16 {
-                if i + len > n {
-                    break;
+    /// The best analysis of `chunk` and its cost, decoding each distinct
+    /// eojeol (with the whitespace before it) once per call through `memo`;
+    /// `None` when an eojeol has no path, to be decoded as a whole instead.
+    fn decode_eojeols<'t>(
+        &self,
+        view: &DictView,
+        chunk: &'t str,
+        memo: &mut EojeolMemo<'t>,
+    ) -> PyResult<Option<(Vec<Morpheme>, f64)>> {
+        let (mut morphs, mut cost) = (Vec::new(), 0.0);
+        let mut start = 0;
+        let mut prev_space = true;
+        let mut pieces = Vec::new();
+        for (at, c) in chunk.char_indices() {
+            let space = c.is_whitespace();
+            if space && !prev_space {
+                pieces.push(&chunk[start..at]);
+                start = at;
+            }
+            prev_space = space;
+        }
+        pieces.push(&chunk[start..]);
+        for piece in pieces {
+            if !memo.contains_key(piece) {
+                let lattice =
+                    Lattice::build_with(view, piece, self.build_options(None), check_signals)?;
+                let best = decoder::decode_with(&lattice, 1, check_signals)?
+                    .into_iter()
+                    .next()
+                    .map(|path| (lattice.morphemes(&path), path.cost));
+                memo.insert(piece, best);
+            }
+            match &memo[piece] {
+                Some((piece_morphs, piece_cost)) => {
+                    morphs.extend_from_slice(piece_morphs);
+                    cost += piece_cost;
                 }
-                
-                let j = i + len;
-                let surface: String = chars[i..j].iter().collect();
-                
-                if let Some(patterns) = self.data.dict.get(&surface) {
-                    for pat in patterns {
-                        if let Some(pp) = prev_pos_deref {
-                            if !is_valid_transition(pp, &pat.pos) {
-                                continue;
-                            }
-                        }
+                None => return Ok(None),
+            }
+        }
+        Ok(Some((morphs, cost)))
+    }
+}
 
-                        let mut cost = match len {
-                            l if l >= 3 => COST_LONG_WORD,
-                            2 => COST_MEDIUM_WORD,
-                            _ => COST_SHORT_WORD,
-                        };
+impl RustTrie {
+    /// The best analysis of `norm` with offsets into the original text, and
+    /// its cost.
+    fn analyze_located(
+        &self,
+        norm: &Normalized,
+        layers: Option<&[String]>,
+    ) -> PyResult<(Vec<Located>, f64)> {
+        let (morphs, cost) = self
+            .decode_nbest(norm, 1, layers)?
+            .pop()
+            .unwrap_or_default();
+        Ok((locate(norm, morphs), cost))
+    }
 
-                        if len == 1 && (pat.pos.starts_with('V') || pat.pos == "IC") {
-                            cost += PENALTY_SINGLE_VERB_IC;
-                        }
-                        if pat.pos.starts_with('N') && len >= 2 {
-                            cost -= BONUS_NOUN_2PLUS;
-                        }
-                        if pat.pos == "MAG" && len >= 2 {
-                            cost -= BONUS_ADVERB_2PLUS;
-                        }
+    /// The best analysis of `norm` and, per morpheme, the smallest margin
+    /// (`decoder::margins`) of the edges it overlaps, `-inf` for morphemes
+    /// of a dead-end fallback. Decodes chunk by chunk, as `decode_explained`
+    /// does for a single analysis.
+    fn analyze_margins(
+        &self,
+        norm: &Normalized,
+        layers: Option<&[String]>,
+    ) -> PyResult<(Vec<Morpheme>, Vec<f64>)> {
+        let text = norm.text();
+        self.check_length(&text)?;
+        let view = self.view(layers)?;
+        let (mut morphs, mut margins) = (Vec::new(), Vec::new());
+        let mut offset = 0;
+        for chunk in sentence::chunks(&text, self.limits.chunk_chars) {
+            let extra = self.extra_candidates(chunk)?;
+            let lattice = Lattice::build_with(
+                &view,
+                chunk,
+                self.build_options(extra.as_ref()),
+                check_signals,
+            )?;
+            let Some(path) = self.decode_paths(&lattice, 1)?.into_iter().next() else {
+                self.dead_end(norm, offset, &lattice)?;
+                let mut part = decoder::greedy(&view, chunk, &*self.grammar);
+                self.postprocess(&mut part);
+                margins.extend(part.iter().map(|_| f64::NEG_INFINITY));
+                morphs.extend(part);
+                offset += lattice.len();
+                continue;
+            };
+            let spans: Vec<(usize, usize, f64)> = path
+                .edges
+                .iter()
+                .zip(decoder::margins(&lattice, &path, check_signals)?)
+                .map(|(&e, margin)| (lattice.edges[e].start, lattice.edges[e].end, margin))
+                .collect();
+            // Rewrite rules can merge and split morphemes, so they take the
+            // margins of the edges they overlap
+            let mut part = lattice.morphemes(&path);
+            self.postprocess(&mut part);
+            let mut at = 0;
+            for m in &part {
+                let end = at + m.0.chars().count();
+                let margin = spans
+                    .iter()
+                    .filter(|&&(s, e, _)| s < end.max(at + 1) && at < e)
+                    .map(|&(_, _, margin)| margin)
+                    .fold(f64::INFINITY, f64::min);
+                margins.push(margin);
+                at = end;
+            }
+            morphs.extend(part);
+            offset += lattice.len();
+        }
+        self.verify_round_trip(norm, &morphs)?;
+        Ok((morphs, margins))
+    }
 
-                        if let Some(pp) = prev_pos_deref {
-                            if pp.starts_with('N') && pat.pos.starts_with('J') {
-                                cost -= BONUS_NOUN_JOSA;
-                            } else if pp.starts_with('V') && pat.pos.starts_with('E') {
-                                cost -= BONUS_VERB_EOMI;
-                            } else if pp.starts_with('E') && pat.pos.starts_with('E') {
-                                cost -= BONUS_EOMI_EOMI;
-                            } else if pp == "MAG" && pat.pos.starts_with('N') {
-                                cost -= BONUS_ADVERB_NOUN;
-                            } else if pp == "MAG" && pat.pos.starts_with('V') {
-                                cost -= BONUS_ADVERB_VERB;
-                            } else if pp == "MM" && pat.pos.starts_with('N') {
-                                cost -= BONUS_DETERMINER_NOUN;
-                            }
-                        }
+    /// `text` normalized as `opts` asks.
+    fn prepare_with(&self, text: &str, opts: &AnalyzeOptions) -> Normalized {
+        let norm = self.prepare(text, opts.ignore_spaces);
+        match opts.normalize {
+            true => normalize::collapse_repeats(norm, opts.max_repeat, opts.min_elongation),
+            false => norm,
+        }
+    }
 
-                        let total_cost = dp[i] + cost;
-                        if total_cost < dp[j] {
-                            dp[j] = total_cost;
-                            path[j] = Some((surface.clone(), pat.pos.clone(), pat.lemma.clone(), i));
-                            prev_pos_table[j] = Some(pat.pos.clone());
-                        }
-                    }
+    /// `f`, strict (see `AnalyzeOptions`) if `strict` is set.
+    fn with_strict<T>(&self, strict: bool, f: impl FnOnce() -> T) -> T {
+        let before = STRICT.with(|s| s.replace(s.get() || strict));
+        let out = f();
+        STRICT.with(|s| s.set(before));
+        out
+    }
+
+    /// The best analysis of `text` under `opts`, with offsets and filtered
+    /// and tagged as they ask, and whether `timeout_ms` cut it short.
+    fn analyze_options(&self, text: &str, opts: &AnalyzeOptions) -> PyResult<(Vec<Located>, bool)> {
+        let norm = self.prepare_with(text, opts);
+        let layers = opts.layers.as_deref();
+        let (morphs, truncated) = self.with_strict(opts.strict, || match opts.timeout_ms {
+            Some(ms) => {
+                self.decode_within(&norm, layers, Instant::now() + Duration::from_millis(ms))
+            }
+            None => Ok((self.analyze_best(&norm, layers)?, false)),
+        })?;
+        Ok((opts.finish(locate(&norm, morphs)), truncated))
+    }
+
+    /// `analyze` under `opts`: morphemes, and the truncation flag as well
+    /// with `timeout_ms`.
+    fn analyze_options_py(
+        &self,
+        py: Python,
+        text: &str,
+        opts: &AnalyzeOptions,
+    ) -> PyResult<PyObject> {
+        let (morphs, truncated) = self.analyze_options(text, opts)?;
+        let morphs = opts.to_py(py, morphs);
+        Ok(match opts.timeout_ms {
+            Some(_) => (morphs, truncated).into_py(py),
+            None => morphs,
+        })
+    }
+
+    /// Appends the JSON Lines record of `text` under `opts` to `out`; an
+    /// `AnalysisError` becomes an error record, anything else (interrupts)
+    /// propagates.
+    fn write_jsonl(
+        &self,
+        py: Python<'_>,
+        out: &mut String,
+        key: &str,
+        id: usize,
+        text: &str,
+        opts: &AnalyzeOptions,
+    ) -> PyResult<()> {
+        let norm = self.prepare_with(text, opts);
+        match self.with_strict(opts.strict, || {
+            self.analyze_located(&norm, opts.layers.as_deref())
+        }) {
+            Ok((morphs, cost)) => {
+                output::jsonl_document(out, key, id, text, &opts.finish(morphs), cost)
+            }
+            Err(e) if e.is_instance_of::<AnalysisError>(py) => output::jsonl_error(
+                out,
+                key,
+                id,
+                e.get_type(py).name()?,
+                &e.value(py).to_string(),
+            ),
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+
+    /// The top-level sentences of `text`, each analyzed on its own, with
+    /// offsets into `text`.
+    fn analyze_sentences(
+        &self,
+        text: &str,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<Vec<Sentence>> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = Vec::new();
+        for (start, end, depth) in sentence::split(text) {
+            if depth > 0 {
+                continue;
+            }
+            check_signals()?;
+            let sentence: String = chars[start..end].iter().collect();
+            let morphs = self
+                .analyze_with_offsets(sentence, layers.clone(), false, 2, 2, false, None)?
+                .into_iter()
+                .map(|(surface, pos, lemma, s, e)| (surface, pos, lemma, start + s, start + e))
+                .collect();
+            out.push(Sentence { start, end, morphs });
+        }
+        Ok(out)
+    }
+
+    /// `analyze_options` of `chars[start..end]`, offsets into the whole
+    /// text.
+    fn analyze_chunk(
+        &self,
+        chars: &[char],
+        (start, end): (usize, usize),
+        opts: &AnalyzeOptions,
+    ) -> PyResult<Vec<Located>> {
+        let chunk: String = chars[start..end].iter().collect();
+        let (morphs, _) = self.analyze_options(&chunk, opts)?;
+        Ok(morphs
+            .into_iter()
+            .map(|(surface, pos, lemma, s, e)| (surface, pos, lemma, start + s, start + e))
+            .collect())
+    }
+}
+
+/// `morphs`, which spell out the normalized text of `norm`, with offsets
+/// into the original text.
+fn locate(norm: &Normalized, morphs: Vec<Morpheme>) -> Vec<Located> {
+    let mut at = 0;
+    morphs
+        .into_iter()
+        .map(|(surface, pos, lemma)| {
+            let len = surface.chars().count();
+            let (start, end) = norm.original_span(at, at + len);
+            at += len;
+            (surface, pos, lemma, start, end)
+        })
+        .collect()
+}
+
+/// `chars` cut before every top-level sentence of `text` after the first,
+/// so that the pieces cover it: each is a sentence and the whitespace after
+/// it.
+fn sentence_chunks(text: &str, n: usize) -> Vec<(usize, usize)> {
+    let mut cuts: Vec<usize> = sentence::split(text)
+        .into_iter()
+        .filter(|s| s.2 == 0)
+        .map(|s| s.0)
+        .filter(|&start| start > 0)
+        .collect();
+    cuts.dedup();
+    let mut chunks = Vec::with_capacity(cuts.len() + 1);
+    let mut at = 0;
+    for cut in cuts.into_iter().chain(std::iter::once(n)) {
+        if cut > at {
+            chunks.push((at, cut));
+            at = cut;
+        }
+    }
+    chunks
+}
+
+impl RustTrie {
+    /// A copy for a worker thread, sharing the dictionaries and analyzing
+    /// exactly as this trie does (a fork whose overlay is this one's).
+    fn worker_copy(&self) -> RustTrie {
+        let mut copy = self.fork();
+        copy.overlay = self.overlay.clone();
+        copy
+    }
+
+    /// The best analysis of `text` in the regression form (see
+    /// `run_regression`), or `ERROR: <message>` when it fails.
+    fn tagged_line(&self, py: Python, text: &str, layers: Option<&[String]>) -> PyResult<String> {
+        match self.analyze_located(&self.prepare(text, false), layers) {
+            Ok((morphs, _)) => {
+                let chars: Vec<char> = text.chars().collect();
+                Ok(output::tagged_line(&chars, &morphs))
+            }
+            Err(e) if e.is_instance_of::<AnalysisError>(py) => {
+                Ok(format!("ERROR: {}", e.value(py)))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Morpheme counts of the analyses of `cases` against their
+    /// expectations; a case that fails to analyze predicts nothing.
+    fn gold_counts(
+        &self,
+        cases: &[regression::Case],
+        layers: Option<&[String]>,
+    ) -> PyResult<Counts> {
+        let mut counts = Counts::default();
+        for case in cases {
+            let actual = match self.analyze_located(&self.prepare(&case.text, false), layers) {
+                Ok((morphs, _)) => {
+                    let chars: Vec<char> = case.text.chars().collect();
+                    output::tagged_line(&chars, &morphs)
                 }
-                
-                // 2. Simple Conjugation Heuristic
-                // 2. Simple Conjugation Heuristic
-                // (Removed: Standard Viterbi handles regular decomposition naturally)
-
-            }
-
-            // 3. OOV
-            for len in 1..=1 {
-                if i + len > n { break; }
-                let j = i + len;
-                let surface: String = chars[i..j].iter().collect();
-                let cost = COST_OOV + (len as f64 * 10.0);
-                
-                let total_cost = dp[i] + cost;
-                
-                if total_cost < dp[j] {
-                    dp[j] = total_cost;
-                    path[j] = Some((surface, "NNG".to_string(), "UNKNOWN".to_string(), i));
-                    prev_pos_table[j] = Some("NNG".to_string());
+                Err(e) if Python::with_gil(|py| e.is_instance_of::<AnalysisError>(py)) => {
+                    String::new()
                 }
-            }
+                Err(e) => return Err(e),
+            };
+            counts.add(&case.expected, &actual);
         }
+        Ok(counts)
+    }
 
-        if dp[n] == f64::INFINITY {
-            return Ok(Vec::new());
+    /// Fails with `no_path` for a chunk no path crosses, unless
+    /// `set_dead_end_fallback` is on and the chunk is to be segmented
+    /// greedily instead.
+    fn dead_end(&self, norm: &Normalized, offset: usize, lattice: &Lattice) -> PyResult<()> {
+        match self.dead_end_fallback && !STRICT.with(Cell::get) {
+            true => Ok(()),
+            false => Err(no_path(norm, offset, lattice)),
         }
+    }
 
-        let mut results = Vec::new();
-        let mut curr = n;
-        while curr > 0 {
-            if let Some((surf, pos, lemma, prev)) = &path[curr] {
-                results.push((surf.clone(), pos.clone(), lemma.clone()));
-                curr = *prev;
-            } else {
-                break;
+    /// Fails with `AnalysisError` where the surfaces of `morphs` stop
+    /// spelling out the normalized text of `norm`, when `set_verify_round_trip`
+    /// is on.
+    fn verify_round_trip(&self, norm: &Normalized, morphs: &[Morpheme]) -> PyResult<()> {
+        if !self.verify && !STRICT.with(Cell::get) {
+            return Ok(());
+        }
+        let mut at = 0;
+        for (surface, _, _) in morphs {
+            for c in surface.chars() {
+                if norm.chars.get(at) != Some(&c) {
+                    return Err(diverged(norm, at, surface));
+                }
+                at += 1;
             }
         }
-        results.reverse();
-        Ok(results)
+        match at == norm.chars.len() {
+            true => Ok(()),
+            false => Err(diverged(norm, at, "")),
+        }
     }
 }
 
-// -----------------------------------------------------------------------------
-// Serialization Wrappers
-// -----------------------------------------------------------------------------
+/// `AnalysisError` for the lattice of the chunk of `norm` at `offset`, which
+/// no path crosses; the offset points into the original text.
+fn no_path(norm: &Normalized, offset: usize, lattice: &Lattice) -> PyErr {
+    let at = offset + decoder::furthest_reach(lattice);
+    let mut site = ErrorSite::in_text(Stage::Decode, &norm.chars, at, "no analysis continues");
+    site.offset = norm.map[at];
+    site.into_err::<AnalysisError>()
+}
 
-#[pyfunction]
-fn save_trie(trie: &RustTrie, path: String) -> PyResult<()> {
-    let file = File::create(path).map_err(|e| PyValueError::new_err(e.to_string()))?;
-    let writer = BufWriter::new(file);
-    // Serialize inner data
-    bincode::serialize_into(writer, &trie.data).map_err(|e| PyValueError::new_err(e.to_string()))?;
-    Ok(())
+/// `AnalysisError` for an analysis whose surfaces leave the normalized text
+/// of `norm` at `at`, in `surface` (empty when they end early).
+fn diverged(norm: &Normalized, at: usize, surface: &str) -> PyErr {
+    let message = match surface.is_empty() {
+        true => "morpheme surfaces end before the input".to_string(),
+        false => format!("morpheme surface {:?} does not match the input", surface),
+    };
+    let mut site = ErrorSite::in_text(Stage::Verify, &norm.chars, at, message);
+    site.offset = norm.map[at.min(norm.chars.len())];
+    site.into_err::<AnalysisError>()
 }
 
-#[pyfunction]
-fn load_trie(path: String) -> PyResult<RustTrie> {
-    let file = File::open(path).map_err(|e| PyValueError::new_err(e.to_string()))?;
-    let reader = BufReader::new(file);
-    let data: TrieData = bincode::deserialize_from(reader).map_err(|e| PyValueError::new_err(e.to_string()))?;
-    Ok(RustTrie { data })
+/// Why a timed analysis stopped decoding.
+enum Interrupt {
+    Error(PyErr),
+    Deadline,
 }
 
-// -----------------------------------------------------------------------------
-// Module Definition
-// -----------------------------------------------------------------------------
+impl From<PyErr> for Interrupt {
+    fn from(err: PyErr) -> Self {
+        Interrupt::Error(err)
+    }
+}
+
+/// The `n` cheapest concatenations of an analysis from `a` with one from `b`.
+fn combine_nbest(mut a: Vec<Explained>, mut b: Vec<Explained>, n: usize) -> Vec<Explained> {
+    // A single analysis on the right (always for n == 1) is appended in
+    // place, so combining the chunks of a long input stays linear
+    if b.len() == 1 {
+        let ((right, right_cost), right_steps) = b.pop().unwrap();
+        a.truncate(n.max(1));
+        for ((left, left_cost), left_steps) in &mut a {
+            left.extend(right.iter().cloned());
+            *left_cost += right_cost;
+            left_steps.extend(right_steps.iter().cloned());
+        }
+        return a;
+    }
+    let mut out: Vec<Explained> = Vec::with_capacity(a.len() * b.len());
+    for ((left, left_cost), left_steps) in &a {
+        for ((right, right_cost), right_steps) in &b {
+            let mut morphs = left.clone();
+            morphs.extend(right.iter().cloned());
+            let mut steps = left_steps.clone();
+            steps.extend(right_steps.iter().cloned());
+            out.push(((morphs, left_cost + right_cost), steps));
+        }
+    }
+    out.sort_by(|x, y| x.0 .1.total_cmp(&y.0 .1));
+    out.truncate(n.max(1));
+    out
+}
+
+#[pymethods]
+impl RustTrie {
+    #[new]
+    fn new() -> Self {
+        RustTrie::from_data(TrieData::default())
+    }
+
+    /// Returns a copy-on-write view sharing this trie's dictionary.
+    ///
+    /// Inserts, cost changes and bans on the fork are kept in a small overlay
+    /// and never touch the shared base, so forking a large dictionary per
+    /// session is cheap. Rewrite rules and layers are carried over.
+    fn fork(&self) -> RustTrie {
+        RustTrie {
+            frozen: self.frozen.clone(),
+            data: Arc::clone(&self.data),
+            overlay: Some(self.overlay.clone().unwrap_or_default()),
+            grammar: Arc::clone(&self.grammar),
+            rules: self.rules.clone(),
+            layers: self.layers.clone(),
+            slang: self.slang.clone(),
+            social: self.social.clone(),
+            structured: self.structured.clone(),
+            language: self.language.clone(),
+            limits: self.limits,
+            bidirectional: self.bidirectional,
+            second_order: self.second_order,
+            dead_end_fallback: self.dead_end_fallback,
+            verify: self.verify,
+            grouping: self.grouping.clone(),
+            suffixes: OnceLock::new(),
+            entropy: self.entropy.clone(),
+            sanitize: self.sanitize,
+            folding: self.folding,
+            tags: self.tags.clone(),
+            case_fold: self.case_fold,
+            folded: OnceLock::new(),
+            spaced: OnceLock::new(),
+            corrections: Corrections::default(),
+            sources: Python::with_gil(|py| self.sources.iter().map(|s| s.clone_ref(py)).collect()),
+            readings: self.readings.clone(),
+            counters: self.counters.clone(),
+            gazetteers: self.gazetteers.clone(),
+            profanity: self.profanity.clone(),
+            code_switching: self.code_switching,
+            symbols: Mutex::default(),
+            source: self.source.clone(),
+        }
+    }
+
+    fn is_fork(&self) -> bool {
+        self.overlay.is_some()
+    }
+
+    /// Pickles the dictionary as `dumps` does; settings, rules and layers
+    /// are not kept. From protocol 5 the payload is a `PickleBuffer`, so a
+    /// `buffer_callback` can take it out of band without another copy.
+    fn __reduce_ex__(&self, py: Python, protocol: u32) -> PyResult<(PyObject, (PyObject,))> {
+        let module = py.import("kulim_rust")?;
+        let mut payload: PyObject = dumps(py, self)?.into_py(py);
+        if protocol >= 5 {
+            payload = py
+                .import("pickle")?
+                .getattr("PickleBuffer")?
+                .call1((payload,))?
+                .into();
+        }
+        Ok((module.getattr("loads")?.into(), (payload,)))
+    }
+
+    /// Whether the base dictionary is a shared memory mapping (`load_compiled`).
+    fn is_shared(&self) -> bool {
+        self.frozen.as_ref().is_some_and(|f| f.is_mapped())
+    }
+
+    /// Inserts an entry. `cost` overrides the length-based default word cost
+    /// (lower is more attractive); re-inserting an entry updates its cost.
+    ///
+    /// `source`, `priority` (a tier, 0-255) and `note` are kept as metadata
+    /// through `save_trie` and `compile_trie`; re-inserting replaces only the
+    /// fields given.
+    #[pyo3(signature = (word, pos, lemma, cost=None, source=None, priority=None, note=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn insert(
+        &mut self,
+        py: Python<'_>,
+        word: String,
+        pos: String,
+        lemma: String,
+        cost: Option<f64>,
+        source: Option<String>,
+        priority: Option<u8>,
+        note: Option<String>,
+    ) -> PyResult<()> {
+        let entry = (word, pos, lemma);
+        self.check_tags(py, 0, std::slice::from_ref(&entry))?;
+        let (word, pos, lemma) = entry;
+        let meta = EntryMeta {
+            source,
+            priority,
+            note,
+        };
+        let meta = (meta != EntryMeta::default()).then_some(meta);
+        self.insert_entry(word, pos, lemma, cost, meta);
+        Ok(())
+    }
+
+    /// Inserts many `(word, pos, lemma)` entries in one call. Duplicates,
+    /// within the batch or of existing entries, are skipped; returns how many
+    /// entries were new.
+    fn insert_batch(&mut self, py: Python<'_>, entries: Vec<Morpheme>) -> PyResult<usize> {
+        self.check_tags(py, 0, &entries)?;
+        Ok(self.insert_entries(entries))
+    }
+
+    /// `insert_batch` over any iterable of `(word, pos, lemma)` (a generator,
+    /// a file reader), consumed in chunks rather than collected into a list.
+    fn insert_iter(&mut self, py: Python<'_>, entries: &PyAny) -> PyResult<usize> {
+        const CHUNK: usize = 65_536;
+        if let Ok(n) = entries.len() {
+            self.edit_data().dict.reserve(n);
+        }
+        let mut added = 0;
+        let mut chunk = Vec::with_capacity(CHUNK);
+        let mut first = 0;
+        for (index, item) in entries.iter()?.enumerate() {
+            let item = item?;
+            let entry = item.extract::<Morpheme>().map_err(|e| {
+                ErrorSite {
+                    stage: Stage::Import,
+                    offset: index,
+                    substring: item
+                        .repr()
+                        .map_or_else(|_| String::new(), |r| r.to_string()),
+                    message: format!("expected a (word, pos, lemma) tuple: {}", e.value(py)),
+                }
+                .into_err::<DictEntryError>()
+            })?;
+            chunk.push(entry);
+            if chunk.len() == CHUNK {
+                self.check_tags(py, first, &chunk)?;
+                added +=
+                    self.insert_entries(std::mem::replace(&mut chunk, Vec::with_capacity(CHUNK)));
+                first = index + 1;
+                check_signals()?;
+            }
+        }
+        self.check_tags(py, first, &chunk)?;
+        added += self.insert_entries(chunk);
+        Ok(added)
+    }
+
+    /// Writes the dictionary (the default view, or the given layers) as
+    /// mecab-ko-dic CSV files in `dir`: one per tag (`NNG.csv`, ...) and
+    /// `Inflect.csv` for compound entries, with the morphemes of their lemma
+    /// as the expression. Context ids follow the TTA tag order and costs are
+    /// ours scaled into mecab's range (`3000 + 50 * cost`); both are defaults
+    /// for `mecab-dict-index`, which can reassign them from a model. Returns
+    /// `(file name, rows)` per file written.
+    #[pyo3(signature = (dir, layers=None))]
+    fn export_mecab_csv(
+        &self,
+        dir: String,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<Vec<(String, usize)>> {
+        let view = self.view(layers.as_deref())?;
+        let mut surfaces = view.surfaces();
+        surfaces.sort_unstable();
+        let entries = surfaces.iter().flat_map(|surface| {
+            view.lookup(surface)
+                .into_iter()
+                .map(move |pat| (&**surface, pat))
+        });
+        mecabdic::export(std::path::Path::new(&dir), entries, |len, pos| {
+            self.grammar.word_cost(len, pos)
+        })
+        .map_err(PyValueError::new_err)
+    }
+
+    /// Imports a Kiwi (kiwipiepy) dictionary file: a user dictionary
+    /// (`format="user"`: word, tag and optional score per line) or a model
+    /// word list (`format="model"`: word and tag, counts ignored).
+    /// Pre-analyzed words (`사겼다<TAB>사귀/VV + 었/EP + 다/EF`) become compound
+    /// entries. Kiwi's conjugation-marked tags (VV-R, VA-I) and extra
+    /// classes map to the TTA tags. A nonzero score, Kiwi's log-scale
+    /// preference, lowers the entry's default cost by `score_weight` per
+    /// unit. Entries record the file as their source; returns how many were
+    /// read. Malformed lines raise `DictFormatError`.
+    #[pyo3(signature = (path, format="user", score_weight=10.0))]
+    fn load_kiwi_dict(
+        &mut self,
+        py: Python<'_>,
+        path: String,
+        format: &str,
+        score_weight: f64,
+    ) -> PyResult<usize> {
+        let format = kiwi::Format::parse(format)
+            .ok_or_else(|| PyValueError::new_err(format!("unknown Kiwi format '{}'", format)))?;
+        let source = std::fs::read_to_string(&path)
+            .map_err(|e| PyValueError::new_err(format!("{}: {}", path, e)))?;
+        let entries = kiwi::parse(&source, format)
+            .map_err(|e| DictFormatError::new_err(format!("{}: {}", path, e)))?;
+        let morphs: Vec<Morpheme> = entries
+            .iter()
+            .map(|e| (e.surface.clone(), e.pos.clone(), e.lemma.clone()))
+            .collect();
+        self.check_tags(py, 0, &morphs)?;
+        let count = entries.len();
+        for entry in entries {
+            let cost = (entry.score != 0.0).then(|| {
+                self.grammar
+                    .word_cost(entry.surface.chars().count(), &entry.pos)
+                    - score_weight * entry.score
+            });
+            let meta = EntryMeta {
+                source: Some(path.clone()),
+                ..EntryMeta::default()
+            };
+            self.insert_entry(entry.surface, entry.pos, entry.lemma, cost, Some(meta));
+        }
+        self.edit_data().shrink_to_fit();
+        Ok(count)
+    }
+
+    /// Sets the reading of `word` for `analyze_readings`, as `pos` only or as
+    /// any tag: how it is read when that is not what it spells, as for
+    /// abbreviations and text in other scripts (KBS, 케이비에스). A reading
+    /// for the tag wins over one for any tag.
+    #[pyo3(signature = (word, reading, pos=None))]
+    fn add_reading(&mut self, word: &str, reading: &str, pos: Option<&str>) -> PyResult<()> {
+        if word.is_empty() || reading.is_empty() {
+            return Err(PyValueError::new_err("word and reading must not be empty"));
+        }
+        self.readings.insert(word, pos, reading);
+        Ok(())
+    }
+
+    /// Loads readings from a tab-separated file, one `word<TAB>reading` or
+    /// `word<TAB>tag<TAB>reading` per line (`#` starts a comment), as
+    /// `add_reading` would set them. Returns how many were read; malformed
+    /// lines raise `DictFormatError`.
+    fn load_readings(&mut self, path: String) -> PyResult<usize> {
+        let source = std::fs::read_to_string(&path)
+            .map_err(|e| PyValueError::new_err(format!("{}: {}", path, e)))?;
+        let mut readings = self.readings.clone();
+        let count = readings
+            .load(&source)
+            .map_err(|e| DictFormatError::new_err(format!("{}: {}", path, e)))?;
+        self.readings = readings;
+        Ok(count)
+    }
+
+    /// Reserves room for `n` more surfaces ahead of a series of inserts.
+    fn reserve(&mut self, n: usize) {
+        self.edit_data().dict.reserve(n);
+    }
+
+    /// A hex digest of the dictionary's entries (with metadata and costs),
+    /// independent of insertion order and stable across platforms and
+    /// releases, for checking that replicas run the same dictionary or
+    /// keying result caches. Layers and the slang table count only with
+    /// `include_layers=True`, as in the default analysis view.
+    #[pyo3(signature = (include_layers=false))]
+    fn fingerprint(&self, include_layers: bool) -> String {
+        let view = if include_layers {
+            self.default_view()
+        } else {
+            self.own_view()
+        };
+        format!("{:016x}", view.fingerprint())
+    }
+
+    /// Releases spare capacity left by inserts, for after a series of
+    /// `insert_batch` / `insert_iter` calls (loading a file does this
+    /// itself). A base still shared with forks is left alone rather than
+    /// copied.
+    fn shrink_to_fit(&mut self) {
+        match &mut self.overlay {
+            Some(overlay) => overlay.added.shrink_to_fit(),
+            None => {
+                if let Some(data) = Arc::get_mut(&mut self.data) {
+                    data.shrink_to_fit();
+                }
+            }
+        }
+    }
+
+    /// `(pos, lemma, source, priority, note)` for each entry of `word`.
+    fn entry_metadata(&self, word: String) -> Vec<EntryInfo> {
+        self.default_view()
+            .lookup(&word)
+            .into_iter()
+            .map(|p| {
+                let meta = p.meta.unwrap_or_default();
+                (
+                    p.pos.to_string(),
+                    p.lemma.to_string(),
+                    meta.source.map(str::to_string),
+                    meta.priority,
+                    meta.note.map(str::to_string),
+                )
+            })
+            .collect()
+    }
+
+    /// `(surface, pos, lemma)` entries tagged `pos`, sorted by surface and
+    /// paged by `offset` / `limit`. A trailing `*` matches a tag prefix
+    /// ("N*" for every noun, "*" for everything).
+    #[pyo3(signature = (pos, limit=None, offset=0))]
+    fn entries_by_pos(&self, pos: String, limit: Option<usize>, offset: usize) -> Vec<Morpheme> {
+        let matches = |tag: &str| match pos.strip_suffix('*') {
+            Some(prefix) => tag.starts_with(prefix),
+            None => tag == pos,
+        };
+        let view = self.default_view();
+        let mut surfaces = view.surfaces();
+        surfaces.sort_unstable();
+        surfaces
+            .into_iter()
+            .flat_map(|surface| {
+                let mut found: Vec<Morpheme> = view
+                    .lookup(&surface)
+                    .into_iter()
+                    .filter(|p| matches(p.pos))
+                    .map(|p| (surface.to_string(), p.pos.to_string(), p.lemma.to_string()))
+                    .collect();
+                found.sort();
+                found
+            })
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// Every `(surface, pos, lemma)` whose metadata names `source`, sorted.
+    fn entries_from_source(&self, source: String) -> Vec<Morpheme> {
+        let view = self.default_view();
+        let mut out = Vec::new();
+        for surface in view.surfaces() {
+            for p in view.lookup(&surface) {
+                if p.meta.and_then(|m| m.source) == Some(source.as_str()) {
+                    out.push((surface.to_string(), p.pos.to_string(), p.lemma.to_string()));
+                }
+            }
+        }
+        out.sort();
+        out
+    }
+
+    /// Hides the entries of `word` matching `pos`/`lemma` (all when omitted).
+    ///
+    /// On a fork the base entries are only masked; entries inserted into the
+    /// fork afterwards stay visible.
+    /// A compiled base cannot be edited in place, so banning on a compiled
+    /// trie requires a fork.
+    #[pyo3(signature = (word, pos=None, lemma=None))]
+    fn ban(&mut self, word: String, pos: Option<String>, lemma: Option<String>) -> PyResult<()> {
+        let ban = Ban { pos, lemma };
+        self.suffixes = OnceLock::new();
+        match &mut self.overlay {
+            Some(overlay) => {
+                overlay.added.remove(&word, &ban);
+                overlay.banned.entry(word).or_default().push(ban);
+            }
+            None if self.frozen.is_some() => {
+                return Err(PyValueError::new_err(
+                    "cannot ban entries of a compiled dictionary; fork() it first",
+                ));
+            }
+            None => {
+                Arc::make_mut(&mut self.data).remove(&word, &ban);
+            }
+        }
+        Ok(())
+    }
+
+    /// Shifts the cost of every `word`/`pos` entry by `delta` (negative makes
+    /// it more attractive). Returns the number of entries changed.
+    fn set_cost(&mut self, word: String, pos: String, delta: f64) -> usize {
+        let len = word.chars().count();
+        let targets: Vec<(String, f64)> = self
+            .own_view()
+            .lookup(&word)
+            .into_iter()
+            .filter(|p| p.pos == pos)
+            .map(|p| {
+                let base = p.cost.unwrap_or_else(|| self.grammar.word_cost(len, p.pos));
+                (p.lemma.to_string(), base + delta)
+            })
+            .collect();
+        for (lemma, cost) in &targets {
+            self.insert_entry(word.clone(), pos.clone(), lemma.clone(), Some(*cost), None);
+        }
+        targets.len()
+    }
+
+    /// Inserts several weighted lemma candidates for one surface and POS
+    /// (e.g. 나는/VV as 날다 vs 나다). The heaviest keeps the default word
+    /// cost; lighter ones cost more in proportion to `-ln(weight / best)`, so
+    /// context can still prefer them and `analyze_nbest` lists them.
+    fn insert_lemmas(
+        &mut self,
+        word: String,
+        pos: String,
+        candidates: Vec<(String, f64)>,
+    ) -> PyResult<()> {
+        if candidates.iter().any(|(_, w)| !(*w > 0.0 && w.is_finite())) {
+            return Err(PyValueError::new_err(
+                "lemma weights must be positive and finite",
+            ));
+        }
+        let best = candidates.iter().map(|(_, w)| *w).fold(0.0, f64::max);
+        let base = self.grammar.word_cost(word.chars().count(), &pos);
+        for (lemma, weight) in candidates {
+            let cost = (weight < best).then(|| base + scoring::lemma_penalty(weight / best));
+            self.insert_entry(word.clone(), pos.clone(), lemma, cost, None);
+        }
+        Ok(())
+    }
+
+    /// Lemma candidates of `word` tagged `pos` with their relative weights
+    /// (summing to 1), heaviest first.
+    fn lemma_candidates(&self, word: String, pos: String) -> Vec<(String, f64)> {
+        let costs = self.get_costs(word);
+        let (lemmas, costs): (Vec<String>, Vec<f64>) = costs
+            .into_iter()
+            .filter(|(p, _, _)| *p == pos)
+            .map(|(_, lemma, cost)| (lemma, cost))
+            .unzip();
+        let weights = scoring::softmax(&costs, scoring::COST_LEMMA_WEIGHT);
+        let mut out: Vec<(String, f64)> = lemmas.into_iter().zip(weights).collect();
+        out.sort_by(|a, b| b.1.total_cmp(&a.1));
+        out
+    }
+
+    /// Effective word cost of each `word` entry as `(pos, lemma, cost)`.
+    fn get_costs(&self, word: String) -> Vec<(String, String, f64)> {
+        let len = word.chars().count();
+        self.default_view()
+            .lookup(&word)
+            .into_iter()
+            .map(|p| {
+                let cost = p.cost.unwrap_or_else(|| self.grammar.word_cost(len, p.pos));
+                (p.pos.to_string(), p.lemma.to_string(), cost)
+            })
+            .collect()
+    }
+
+    fn exists(&self, word: String) -> bool {
+        self.default_view().contains(&word)
+    }
+
+    fn search(&self, word: String) -> Vec<(String, String)> {
+        let mut found = Vec::new();
+        self.default_view().for_each(&word, |p| {
+            found.push((p.pos.to_string(), p.lemma.to_string()))
+        });
+        found
+    }
+
+    /// Like `search`, but each pattern is `(pos id, lemma id)`, indices into
+    /// `get_symbols()`, so that no strings are built per pattern.
+    fn search_ids(&self, word: &str) -> Vec<PatternIds> {
+        let mut symbols = self.symbols.lock().unwrap();
+        let mut found = Vec::new();
+        self.default_view().for_each(word, |p| {
+            found.push((symbols.intern(p.pos), symbols.intern(p.lemma)))
+        });
+        found
+    }
+
+    /// The tags and lemmas numbered so far by `search_ids` and
+    /// `search_all_pattern_ids`, by id. Ids are never reused or renumbered,
+    /// so the list can be fetched once after scanning.
+    fn get_symbols(&self) -> Vec<String> {
+        let symbols = self.symbols.lock().unwrap();
+        (0..symbols.len() as u32)
+            .map(|id| symbols.get(id).to_string())
+            .collect()
+    }
+
+    /// `search` for each word. An item that is not a string yields a
+    /// `BatchError` in its place instead of failing the batch.
+    fn search_batch(&self, py: Python, words: Vec<&PyAny>) -> PyResult<Vec<PyObject>> {
+        let mut out = Vec::with_capacity(words.len());
+        for (index, word) in words.into_iter().enumerate() {
+            if index % decoder::CHECK_INTERVAL == 0 {
+                check_signals()?;
+            }
+            out.push(match word.extract::<String>() {
+                Ok(w) => self.search(w).into_py(py),
+                Err(e) => BatchError::from_err(py, index, e)?,
+            });
+        }
+        Ok(out)
+    }
+
+    fn __repr__(&self) -> String {
+        let (entries, patterns) = self.get_stats();
+        let mut repr = format!("RustTrie(entries={}, patterns={}", entries, patterns);
+        if let Some(source) = &self.source {
+            repr += &format!(", source={:?}", source.path);
+        }
+        if self.is_fork() {
+            repr += ", fork=True";
+        }
+        repr + ")"
+    }
+
+    /// What the trie holds and where it came from, as a dict: `entries` and
+    /// `patterns` (as `get_stats`), `layers` (names), `fork`, `source` (the
+    /// file the base was loaded from, else `None`), `format` (`"saved"`,
+    /// `"compiled"` or `None`), `format_version` (`None` for legacy files),
+    /// `profile` and `mapped` (compiled files only), `built` (the file's
+    /// modification time, in seconds since the epoch) and `fingerprint` (as
+    /// `fingerprint()`, which reads every entry).
+    fn describe(&self, py: Python) -> HashMap<&'static str, PyObject> {
+        let (entries, patterns) = self.get_stats();
+        let layers: Vec<&str> = self.layers.iter().map(|l| l.name.as_str()).collect();
+        let source = self.source.as_ref();
+        let mut out = HashMap::new();
+        out.insert("entries", entries.into_py(py));
+        out.insert("patterns", patterns.into_py(py));
+        out.insert("layers", layers.into_py(py));
+        out.insert("fork", self.is_fork().into_py(py));
+        out.insert("source", source.map(|s| s.path.as_str()).into_py(py));
+        out.insert("format", source.map(|s| s.format).into_py(py));
+        out.insert("format_version", source.and_then(|s| s.version).into_py(py));
+        out.insert(
+            "profile",
+            self.frozen.as_ref().map(|f| f.profile().name()).into_py(py),
+        );
+        out.insert(
+            "mapped",
+            self.frozen.as_ref().map(|f| f.is_mapped()).into_py(py),
+        );
+        out.insert("built", source.and_then(|s| s.modified).into_py(py));
+        out.insert("fingerprint", self.fingerprint(false).into_py(py));
+        out
+    }
+
+    fn get_stats(&self) -> (usize, usize) {
+        if let (None, Some(frozen)) = (&self.overlay, &self.frozen) {
+            if self.data.dict.is_empty() {
+                return (frozen.len(), frozen.pattern_count());
+            }
+        }
+        if self.overlay.is_none() && self.frozen.is_none() {
+            let nodes = self.data.dict.len();
+            let patterns = self.data.dict.values().map(|v| v.len()).sum();
+            return (nodes, patterns);
+        }
+        let view = self.own_view();
+        let surfaces = view.surfaces();
+        let patterns = surfaces.iter().map(|s| view.lookup(s).len()).sum();
+        (surfaces.len(), patterns)
+    }
+
+    /// Every dictionary word in `text` as `(start, length, patterns)`. With
+    /// `start`/`end` only words lying within that character span are listed;
+    /// offsets stay relative to `text`.
+    #[pyo3(signature = (text, start=0, end=None))]
+    fn search_all_patterns(
+        &self,
+        text: String,
+        start: usize,
+        end: Option<usize>,
+    ) -> Vec<(usize, usize, Vec<Pattern>)> {
+        let view = self.default_view();
+        let mut scanner = Scanner::new(text, start, end);
+        let mut results = Vec::new();
+        while let Some(m) = scanner.next_match(&view) {
+            results.push(m);
+        }
+        results
+    }
+
+    /// `search_all_patterns` with patterns as ids, as `search_ids` gives.
+    #[pyo3(signature = (text, start=0, end=None))]
+    fn search_all_pattern_ids(
+        &self,
+        text: String,
+        start: usize,
+        end: Option<usize>,
+    ) -> Vec<(usize, usize, Vec<PatternIds>)> {
+        let view = self.default_view();
+        let mut symbols = self.symbols.lock().unwrap();
+        let mut scanner = Scanner::new(text, start, end);
+        let mut results = Vec::new();
+        while let Some(m) =
+            scanner.next_match_with(&view, |p| (symbols.intern(p.pos), symbols.intern(p.lemma)))
+        {
+            results.push(m);
+        }
+        results
+    }
+
+    /// Ways to split `eojeol` into a stem and a dictionary ending (eomi or
+    /// josa), as `(stem, ending, pos, lemma)`, longest ending first. Only
+    /// splits leaving a non-empty stem are listed. Endings are looked up in a
+    /// reverse trie built on first use.
+    fn ending_splits(&self, eojeol: String) -> Vec<(String, String, String, String)> {
+        let chars: Vec<char> = eojeol.chars().collect();
+        self.suffix_index()
+            .endings(&chars)
+            .into_iter()
+            .filter(|m| m.len < chars.len())
+            .map(|m| {
+                let cut = chars.len() - m.len;
+                (
+                    chars[..cut].iter().collect(),
+                    chars[cut..].iter().collect(),
+                    m.pos.to_string(),
+                    m.lemma.to_string(),
+                )
+            })
+            .collect()
+    }
+
+    /// Splits a noun+particle eojeol into `(stem, josa)` from the dictionary's
+    /// J* entries and batchim agreement (책이, not 책가), without decoding.
+    /// Stems found as nouns are preferred; `josa` is `None` when no particle
+    /// fits.
+    fn split_josa(&self, eojeol: String) -> (String, Option<String>) {
+        let word = eojeol.trim();
+        let chars: Vec<char> = word.chars().collect();
+        let view = self.default_view();
+        let is_noun = |stem: &[char]| {
+            let stem: String = stem.iter().collect();
+            view.lookup(&stem).iter().any(|p| p.pos.starts_with('N'))
+        };
+        match josa::choose(&chars, &self.suffix_index().endings(&chars), is_noun) {
+            Some(len) => {
+                let cut = chars.len() - len;
+                (
+                    chars[..cut].iter().collect(),
+                    Some(chars[cut..].iter().collect()),
+                )
+            }
+            None => (word.to_string(), None),
+        }
+    }
+
+    /// Lazy variant of `search_all_patterns`, yielding matches one at a time
+    /// instead of building the whole list.
+    #[pyo3(signature = (text, start=0, end=None))]
+    fn iter_patterns(
+        slf: Py<Self>,
+        text: String,
+        start: usize,
+        end: Option<usize>,
+    ) -> PatternMatches {
+        PatternMatches::new(slf, Scanner::new(text, start, end))
+    }
+
+    /// `layers` selects the domain layers for this call; by default every
+    /// enabled layer is used.
+    ///
+    /// With `ignore_spaces=True` whitespace is removed before decoding, for
+    /// OCR/ASR text whose spacing cannot be trusted; surfaces then contain no
+    /// spaces (use `analyze_with_offsets` to map them back).
+    ///
+    /// With `timeout_ms` the result is `(morphemes, truncated)`: when
+    /// decoding has not finished by the deadline it stops, and the text not
+    /// yet decoded is segmented greedily by longest dictionary match, with
+    /// `truncated=True`. Chunks of a long input decoded in time keep their
+    /// analyses. Chunks left to the dead-end fallback (see
+    /// `set_dead_end_fallback`) set it as well. The budget bounds decoding
+    /// only: the greedy segmentation of the rest runs past it, in time
+    /// linear in the text left (about that of decoding memoized eojeols).
+    ///
+    /// `options`, an `AnalyzeOptions`, replaces the other keyword arguments
+    /// of this and the other analyze methods, and adds a few of its own.
+    #[pyo3(signature = (text, layers=None, ignore_spaces=false, timeout_ms=None, options=None))]
+    fn analyze(
+        &self,
+        py: Python<'_>,
+        text: String,
+        layers: Option<Vec<String>>,
+        ignore_spaces: bool,
+        timeout_ms: Option<u64>,
+        options: Option<PyRef<AnalyzeOptions>>,
+    ) -> PyResult<PyObject> {
+        if let Some(opts) = options {
+            return self.analyze_options_py(py, &text, &opts);
+        }
+        let norm = self.prepare(&text, ignore_spaces);
+        if let Some(ms) = timeout_ms {
+            let deadline = Instant::now() + Duration::from_millis(ms);
+            return Ok(self
+                .decode_within(&norm, layers.as_deref(), deadline)?
+                .into_py(py));
+        }
+        Ok(self.analyze_best(&norm, layers.as_deref())?.into_py(py))
+    }
+
+    /// Token boundaries of the best analysis as `(start, end)` character
+    /// spans, without building POS/lemma strings. Whitespace tokens are
+    /// dropped unless `keep_spaces` is set.
+    ///
+    /// With `options` the spans are those of `analyze(text, options=...)`,
+    /// `pos_filter` leaving out the morphemes it does not keep.
+    #[pyo3(signature = (text, layers=None, keep_spaces=false, options=None))]
+    fn tokenize(
+        &self,
+        text: String,
+        layers: Option<Vec<String>>,
+        keep_spaces: bool,
+        options: Option<PyRef<AnalyzeOptions>>,
+    ) -> PyResult<Vec<(usize, usize)>> {
+        if let Some(opts) = options {
+            let (morphs, _) = self.analyze_options(&text, &opts)?;
+            return Ok(morphs
+                .into_iter()
+                .filter(|(surface, ..)| keep_spaces || !surface.chars().all(char::is_whitespace))
+                .map(|(_, _, _, start, end)| (start, end))
+                .collect());
+        }
+        let norm = self.prepare(&text, false);
+        let text = norm.text();
+        let mut spans = Vec::new();
+        let composite = self.counters.as_ref().is_some_and(|c| c.composite);
+        if self.rules.is_empty() && !self.verify && !composite {
+            self.check_length(&text)?;
+            let view = self.view(layers.as_deref())?;
+            let mut offset = 0;
+            let mut memo = self.eojeols_independent().then(HashMap::new);
+            for chunk in sentence::chunks(&text, self.limits.chunk_chars) {
+                if let Some(memo) = &mut memo {
+                    if let Some((morphs, _)) = self.decode_eojeols(&view, chunk, memo)? {
+                        for (surface, _, _) in &morphs {
+                            let len = surface.chars().count();
+                            spans.push((offset, offset + len));
+                            offset += len;
+                        }
+                        continue;
+                    }
+                }
+                let extra = self.extra_candidates(chunk)?;
+                let opts = self.build_options(extra.as_ref());
+                let lattice = Lattice::build_with(&view, chunk, opts, check_signals)?;
+                let Some(path) = self.decode_paths(&lattice, 1)?.into_iter().next() else {
+                    self.dead_end(&norm, offset, &lattice)?;
+                    for (surface, _, _) in decoder::greedy(&view, chunk, &*self.grammar) {
+                        let len = surface.chars().count();
+                        spans.push((offset, offset + len));
+                        offset += len;
+                    }
+                    continue;
+                };
+                spans.extend(path.edges.iter().map(|&e| {
+                    let edge = &lattice.edges[e];
+                    (offset + edge.start, offset + edge.end)
+                }));
+                offset += lattice.len();
+            }
+        } else {
+            // Rewrite rules and quantity tokens can merge or split morphemes,
+            // so they need the tags; verification needs the surfaces
+            let mut at = 0;
+            let best = self.decode_nbest(&norm, 1, layers.as_deref())?.pop();
+            for (surface, _, _) in best.map(|(morphs, _)| morphs).unwrap_or_default() {
+                let len = surface.chars().count();
+                spans.push((at, at + len));
+                at += len;
+            }
+        }
+        if !keep_spaces {
+            spans.retain(|&(s, e)| !norm.chars[s..e].iter().all(|c| c.is_whitespace()));
+        }
+        Ok(spans
+            .into_iter()
+            .map(|(s, e)| norm.original_span(s, e))
+            .collect())
+    }
+
+    /// Morpheme tokens in the shape of a HuggingFace `tokenizers`
+    /// pre-tokenizer: `(substring, (byte_start, byte_end))` with UTF-8 byte
+    /// offsets into `text` and `substring == text[byte_start:byte_end]`
+    /// (the text as written, not a normalized form). Whitespace is dropped.
+    #[pyo3(signature = (text, layers=None, options=None))]
+    fn analyze_pretokenize(
+        &self,
+        text: String,
+        layers: Option<Vec<String>>,
+        options: Option<PyRef<AnalyzeOptions>>,
+    ) -> PyResult<Vec<(String, (usize, usize))>> {
+        let spans = self.tokenize(text.clone(), layers, false, options)?;
+        let bytes: Vec<usize> = text
+            .char_indices()
+            .map(|(b, _)| b)
+            .chain(std::iter::once(text.len()))
+            .collect();
+        Ok(spans
+            .into_iter()
+            .map(|(s, e)| {
+                let (start, end) = (bytes[s], bytes[e]);
+                (text[start..end].to_string(), (start, end))
+            })
+            .collect())
+    }
+
+    /// `text` as CoNLL-U, split into sentences, one token per morpheme.
+    /// FORM is the surface, LEMMA the lemma (`_` when unknown), UPOS mapped
+    /// from the Sejong tag in XPOS. MISC holds `Eojeol=<n>` (the token's
+    /// whitespace-separated word, 1-based per sentence), `TokenRange` in
+    /// characters of `text`, and `SpaceAfter=No` within an eojeol.
+    #[pyo3(signature = (text, layers=None))]
+    fn to_conllu(&self, text: String, layers: Option<Vec<String>>) -> PyResult<String> {
+        let sentences = self.analyze_sentences(&text, layers)?;
+        let chars: Vec<char> = text.chars().collect();
+        Ok(output::conllu(&chars, &sentences))
+    }
+
+    /// `text` in the Sejong corpus format: one `eojeol<TAB>morph/POS+...`
+    /// line per eojeol, sentences separated by a blank line.
+    #[pyo3(signature = (text, layers=None))]
+    fn to_sejong(&self, text: String, layers: Option<Vec<String>>) -> PyResult<String> {
+        let sentences = self.analyze_sentences(&text, layers)?;
+        let chars: Vec<char> = text.chars().collect();
+        Ok(output::sejong(&chars, &sentences))
+    }
+
+    /// The best analysis of `text` as subword pieces `(piece, start, end)`
+    /// that never cross a morpheme boundary, for pretraining segmentation:
+    /// a morpheme is one piece when `vocab` has it (or, without `vocab`,
+    /// when the dictionary does) and one piece per syllable otherwise.
+    /// Pieces after the first of an eojeol start with `continuation`;
+    /// offsets are characters into `text`.
+    #[pyo3(signature = (text, vocab=None, continuation="##", layers=None))]
+    fn subword_tokenize(
+        &self,
+        text: String,
+        vocab: Option<HashSet<String>>,
+        continuation: &str,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<Vec<Piece>> {
+        let (morphs, _) = self.analyze_located(&self.prepare(&text, false), layers.as_deref())?;
+        Ok(match &vocab {
+            Some(vocab) => subword::pieces(&morphs, continuation, |piece, _| vocab.contains(piece)),
+            None => {
+                let view = self.view(layers.as_deref())?;
+                subword::pieces(&morphs, continuation, |_, surface| view.contains(surface))
+            }
+        })
+    }
+
+    /// Writes the `subword_tokenize` vocabulary of `texts` to `path`, one
+    /// piece per line: every syllable piece seen, so any of them can be
+    /// encoded, then the morpheme pieces occurring `min_count` times or
+    /// more, most frequent first, up to `max_size` lines. Returns the
+    /// number of pieces written.
+    #[pyo3(signature = (texts, path, min_count=1, max_size=None, continuation="##", layers=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn export_subword_vocab(
+        &self,
+        texts: Vec<String>,
+        path: String,
+        min_count: usize,
+        max_size: Option<usize>,
+        continuation: &str,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<usize> {
+        let mut counts = VocabCounts::default();
+        for text in &texts {
+            check_signals()?;
+            let (morphs, _) =
+                self.analyze_located(&self.prepare(text, false), layers.as_deref())?;
+            counts.add(&morphs, continuation);
+        }
+        let vocab = counts.vocabulary(min_count, max_size);
+        let mut out = String::new();
+        for piece in &vocab {
+            out.push_str(piece);
+            out.push('\n');
+        }
+        std::fs::write(&path, out)
+            .map_err(|e| PyValueError::new_err(format!("{}: {}", path, e)))?;
+        Ok(vocab.len())
+    }
+
+    /// `text` as HTML with the morphemes whose tag matches `pos_filter`
+    /// (tags, or prefixes ending in `*`) wrapped in `<tag>` elements,
+    /// adjacent matches sharing one and whitespace never included; the rest
+    /// of the text is escaped and otherwise unchanged. Unknown symbols
+    /// (markup, punctuation, emoji) are never marked, whatever their
+    /// fallback tag. Spans follow the character offsets of
+    /// `analyze_with_offsets`, so emoji and `set_normalization` cannot
+    /// shift them.
+    #[pyo3(signature = (text, pos_filter, tag="mark", layers=None))]
+    fn highlight(
+        &self,
+        text: String,
+        pos_filter: Vec<String>,
+        tag: &str,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<String> {
+        let valid = tag.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+            && tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !valid {
+            return Err(PyValueError::new_err(format!(
+                "'{}' is not an element name",
+                tag
+            )));
+        }
+        let filter = pos_filter
+            .iter()
+            .map(|p| TagPattern::parse(p))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(PyValueError::new_err)?;
+        let (morphs, _) = self.analyze_located(&self.prepare(&text, false), layers.as_deref())?;
+        let mut spans: Vec<(usize, usize)> = Vec::new();
+        for (surface, pos, lemma, start, end) in &morphs {
+            let space = surface.chars().all(char::is_whitespace);
+            // Markup, punctuation and emoji fall back to unknown nouns too
+            let symbol = lemma == "UNKNOWN"
+                && !surface
+                    .chars()
+                    .any(|c| matches!(CharClass::of(c), CharClass::Hangul | CharClass::Latin));
+            if space || symbol || !filter.iter().any(|f| f.matches(pos)) {
+                continue;
+            }
+            match spans.last_mut() {
+                Some(last) if last.1 == *start => last.1 = *end,
+                _ => spans.push((*start, *end)),
+            }
+        }
+        let chars: Vec<char> = text.chars().collect();
+        Ok(output::highlight(&chars, &spans, tag))
+    }
+
+    /// `text` in mecab-ko's output format, one `surface<TAB>features` line
+    /// per morpheme and `EOS` after each sentence, for tools that parse
+    /// mecab output.
+    #[pyo3(signature = (text, layers=None))]
+    fn to_mecab(&self, text: String, layers: Option<Vec<String>>) -> PyResult<String> {
+        let sentences = self.analyze_sentences(&text, layers)?;
+        Ok(output::mecab(&sentences))
+    }
+
+    /// Analyzes every case of a regression file (`text<TAB>expected`, see
+    /// `to_sejong` for the analysis form, eojeols separated by spaces) and
+    /// returns `(line, text, expected, actual)` for each case whose analysis
+    /// changed; an empty list means no changes. An analysis that fails is
+    /// reported as `ERROR: <message>`. With `update=True` the file is
+    /// rewritten with the current analyses, to accept intended changes.
+    #[pyo3(signature = (cases_path, update=false, layers=None))]
+    fn run_regression(
+        &self,
+        py: Python,
+        cases_path: String,
+        update: bool,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<Vec<(usize, String, String, String)>> {
+        let io = |e: std::io::Error| PyValueError::new_err(e.to_string());
+        self.view(layers.as_deref())?;
+        let source = std::fs::read_to_string(&cases_path).map_err(io)?;
+        let cases = regression::parse(&source)
+            .map_err(|e| PyValueError::new_err(format!("{}: {}", cases_path, e)))?;
+        let mut diffs = Vec::new();
+        for case in cases {
+            check_signals()?;
+            let actual = self.tagged_line(py, &case.text, layers.as_deref())?;
+            if actual != case.expected {
+                diffs.push((case.line, case.text, case.expected, actual));
+            }
+        }
+        if update && !diffs.is_empty() {
+            let updates: Vec<(usize, String)> = diffs
+                .iter()
+                .map(|(line, _, _, actual)| (*line, actual.clone()))
+                .collect();
+            std::fs::write(&cases_path, regression::rewrite(&source, &updates)).map_err(io)?;
+        }
+        Ok(diffs)
+    }
+
+    /// Searches transition bonuses for the best agreement with a gold
+    /// corpus, a regression file (see `run_regression`), instead of editing
+    /// the scoring constants and rebuilding.
+    ///
+    /// `param_space` maps rule names, as in score breakdowns
+    /// (`BONUS_NOUN_JOSA`, or `prev -> curr` for rules set from Python), to
+    /// a list of bonuses to try or a `(low, high)` range to sample. Without
+    /// `trials` every combination of the lists is tried; with it, `trials`
+    /// random combinations drawn from `seed` (or the whole grid when it is
+    /// no larger). Candidates are scored by morpheme F1 on `workers` threads
+    /// (default: one per core). Returns the best `{name: bonus}` and its F1;
+    /// this trie is left as it is, apply the result with
+    /// `set_transition_rule`.
+    #[pyo3(signature = (gold_corpus, param_space, trials=None, seed=0, workers=None, layers=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn tune_weights(
+        &self,
+        py: Python,
+        gold_corpus: String,
+        param_space: HashMap<String, &PyAny>,
+        trials: Option<usize>,
+        seed: u64,
+        workers: Option<usize>,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<(HashMap<String, f64>, f64)> {
+        self.view(layers.as_deref())?;
+        let source = std::fs::read_to_string(&gold_corpus)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let cases = regression::parse(&source)
+            .map_err(|e| PyValueError::new_err(format!("{}: {}", gold_corpus, e)))?;
+        if cases.is_empty() {
+            return Err(PyValueError::new_err(format!("{}: no cases", gold_corpus)));
+        }
+        let mut space = Vec::with_capacity(param_space.len());
+        for (name, values) in param_space {
+            let values = match values.downcast::<PyTuple>() {
+                Ok(range) => {
+                    let (lo, hi) = range.extract()?;
+                    Values::Range(lo, hi)
+                }
+                Err(_) => Values::Choices(values.extract()?),
+            };
+            space.push(Param { name, values });
+        }
+        space.sort_by(|a, b| a.name.cmp(&b.name));
+        let transitions = self.grammar.transitions();
+        tuning::check(transitions, &space).map_err(PyValueError::new_err)?;
+        let candidates = tuning::candidates(&space, trials, seed).map_err(PyValueError::new_err)?;
+
+        let workers = workers
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+            .clamp(1, candidates.len());
+        let mut shares: Vec<Vec<(usize, RustTrie)>> = (0..workers).map(|_| Vec::new()).collect();
+        for (i, values) in candidates.iter().enumerate() {
+            let mut trial = self.worker_copy();
+            trial.grammar =
+                self.grammar
+                    .with_transitions(tuning::apply(transitions, &space, values));
+            shares[i % workers].push((i, trial));
+        }
+        let (cases, layers) = (&cases, layers.as_deref());
+        let scored: Vec<(usize, PyResult<Counts>)> = py.allow_threads(|| {
+            std::thread::scope(|s| {
+                let handles: Vec<_> = shares
+                    .into_iter()
+                    .map(|share| {
+                        s.spawn(move || {
+                            share
+                                .into_iter()
+                                .map(|(i, trial)| (i, trial.gold_counts(cases, layers)))
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .flat_map(|h| h.join().expect("tuning worker panicked"))
+                    .collect()
+            })
+        });
+        let mut best: Option<(usize, f64)> = None;
+        for (i, counts) in scored {
+            let f1 = counts?.f1();
+            if best.is_none_or(|(j, top)| f1 > top || (f1 == top && i < j)) {
+                best = Some((i, f1));
+            }
+        }
+        let (i, f1) = best.expect("at least one candidate");
+        let params = space
+            .iter()
+            .map(|p| p.name.clone())
+            .zip(candidates[i].iter().copied())
+            .collect();
+        Ok((params, f1))
+    }
+
+    /// `analyze_batch` as JSON Lines, one record per text in order:
+    /// `{"index", "text", "cost", "morphemes": [{"surface", "pos", "lemma",
+    /// "start", "end"}]}` with character offsets and whitespace left out. A
+    /// failing item gives `{"index", "error": {"kind", "message"}}` instead.
+    #[pyo3(signature = (texts, layers=None, options=None))]
+    fn analyze_batch_jsonl(
+        &self,
+        py: Python,
+        texts: Vec<&PyAny>,
+        layers: Option<Vec<String>>,
+        options: Option<PyRef<AnalyzeOptions>>,
+    ) -> PyResult<String> {
+        let opts = match options {
+            Some(opts) => opts.clone(),
+            None => AnalyzeOptions::with_layers(layers),
+        };
+        self.view(opts.layers.as_deref())?;
+        let mut out = String::new();
+        for (index, item) in texts.into_iter().enumerate() {
+            check_signals()?;
+            match item.extract::<&str>() {
+                Ok(text) => self.write_jsonl(py, &mut out, "index", index, text, &opts)?,
+                Err(e) => output::jsonl_error(
+                    &mut out,
+                    "index",
+                    index,
+                    e.get_type(py).name()?,
+                    &e.value(py).to_string(),
+                ),
+            }
+        }
+        Ok(out)
+    }
+
+    /// Analyzes every non-empty line of `input_path` as one document and
+    /// writes `analyze_batch_jsonl` records to `output_path`, keyed by
+    /// `"line"` (1-based) instead of `"index"`. Returns how many records
+    /// were written.
+    #[pyo3(signature = (input_path, output_path, layers=None, options=None))]
+    fn analyze_file(
+        &self,
+        py: Python,
+        input_path: String,
+        output_path: String,
+        layers: Option<Vec<String>>,
+        options: Option<PyRef<AnalyzeOptions>>,
+    ) -> PyResult<usize> {
+        use std::io::{BufRead, BufWriter, Write};
+
+        let io = |e: std::io::Error| PyValueError::new_err(e.to_string());
+        let opts = match options {
+            Some(opts) => opts.clone(),
+            None => AnalyzeOptions::with_layers(layers),
+        };
+        self.view(opts.layers.as_deref())?;
+        let input = File::open(&input_path).map_err(io)?;
+        let mut writer = BufWriter::new(File::create(&output_path).map_err(io)?);
+        let mut record = String::new();
+        let mut written = 0;
+        for (i, line) in BufReader::new(input).lines().enumerate() {
+            let line = line.map_err(io)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            check_signals()?;
+            record.clear();
+            self.write_jsonl(py, &mut record, "line", i + 1, &line, &opts)?;
+            writer.write_all(record.as_bytes()).map_err(io)?;
+            written += 1;
+        }
+        writer.flush().map_err(io)?;
+        Ok(written)
+    }
+
+    /// Analyzes the files matching `input_glob` (wildcards in the file name
+    /// only) as `analyze_file` does, in parallel over files, and writes one
+    /// Parquet row per morpheme, partitioned by input file as
+    /// `output_dir/source=<file name>/part-00000.parquet`. `columns` picks
+    /// and orders the columns among `doc_id` (1-based line), `sentence_id`,
+    /// `surface`, `pos`, `lemma`, `start` and `end` (character offsets into
+    /// the document), all by default. A document failing to analyze is left
+    /// out. Returns `(documents, rows, failed documents)`.
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (input_glob, output_dir, columns=None, row_group_rows=65536, workers=None, layers=None))]
+    fn analyze_corpus_to_parquet(
+        &self,
+        py: Python,
+        input_glob: String,
+        output_dir: String,
+        columns: Option<Vec<String>>,
+        row_group_rows: usize,
+        workers: Option<usize>,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<(usize, usize, usize)> {
+        self.view(layers.as_deref())?;
+        let columns = match columns {
+            None => Column::ALL.to_vec(),
+            Some(names) => {
+                let mut out = Vec::with_capacity(names.len());
+                for name in &names {
+                    let column = Column::parse(name).ok_or_else(|| {
+                        PyValueError::new_err(format!("unknown column '{}'", name))
+                    })?;
+                    if out.contains(&column) {
+                        return Err(PyValueError::new_err(format!(
+                            "column '{}' given twice",
+                            name
+                        )));
+                    }
+                    out.push(column);
+                }
+                out
+            }
+        };
+        if columns.is_empty() {
+            return Err(PyValueError::new_err("at least one column is needed"));
+        }
+        let inputs = parquet::glob(&input_glob).map_err(PyValueError::new_err)?;
+        if inputs.is_empty() {
+            return Err(PyValueError::new_err(format!(
+                "{}: no files match",
+                input_glob
+            )));
+        }
+        let mut jobs = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let name = input
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let dir = std::path::Path::new(&output_dir).join(format!("source={}", name));
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| PyValueError::new_err(format!("{}: {}", dir.display(), e)))?;
+            jobs.push((input, dir.join("part-00000.parquet")));
+        }
+
+        let workers = workers
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+            .clamp(1, jobs.len());
+        let mut shares: Vec<(RustTrie, Vec<_>)> = (0..workers)
+            .map(|_| (self.worker_copy(), Vec::new()))
+            .collect();
+        for (i, job) in jobs.into_iter().enumerate() {
+            shares[i % workers].1.push(job);
+        }
+        let (columns, layers) = (&columns, layers.as_deref());
+        let results: Vec<PyResult<parquet::Counts>> = py.allow_threads(|| {
+            std::thread::scope(|s| {
+                let handles: Vec<_> = shares
+                    .into_iter()
+                    .map(|(trie, share)| {
+                        s.spawn(move || {
+                            share
+                                .iter()
+                                .map(|(input, output)| {
+                                    parquet::analyze_file(
+                                        &trie,
+                                        input,
+                                        output,
+                                        columns,
+                                        row_group_rows,
+                                        layers,
+                                    )
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .flat_map(|h| h.join().expect("corpus worker panicked"))
+                    .collect()
+            })
+        });
+        let mut total = parquet::Counts::default();
+        for counts in results {
+            let counts = counts?;
+            total.documents += counts.documents;
+            total.rows += counts.rows;
+            total.failed += counts.failed;
+        }
+        Ok((total.documents, total.rows, total.failed))
+    }
+
+    /// `analyze` for each text. A failing item (not a string, over the input
+    /// limit, any other `AnalysisError` such as a dead end) yields a
+    /// `BatchError` with its index in place of the result; interrupts and
+    /// invalid `layers` still fail the whole batch.
+    #[pyo3(signature = (texts, layers=None, options=None))]
+    fn analyze_batch(
+        &self,
+        py: Python,
+        texts: Vec<&PyAny>,
+        layers: Option<Vec<String>>,
+        options: Option<PyRef<AnalyzeOptions>>,
+    ) -> PyResult<Vec<PyObject>> {
+        let opts = options.as_deref();
+        self.view(opts.map_or(layers.as_deref(), |o| o.layers.as_deref()))?;
+        let mut out = Vec::with_capacity(texts.len());
+        for (index, item) in texts.into_iter().enumerate() {
+            check_signals()?;
+            let text = match item.extract::<String>() {
+                Ok(text) => text,
+                Err(e) => {
+                    out.push(BatchError::from_err(py, index, e)?);
+                    continue;
+                }
+            };
+            if let Err(e) = self.check_length(&text) {
+                out.push(BatchError::from_err(py, index, e)?);
+                continue;
+            }
+            let result = match opts {
+                Some(opts) => self.analyze_options_py(py, &text, opts),
+                None => self
+                    .analyze_best(&self.prepare(&text, false), layers.as_deref())
+                    .map(|morphs| morphs.into_py(py)),
+            };
+            out.push(match result {
+                Ok(result) => result,
+                Err(e) if e.is_instance_of::<AnalysisError>(py) => {
+                    BatchError::from_err(py, index, e)?
+                }
+                Err(e) => return Err(e),
+            });
+        }
+        Ok(out)
+    }
+
+    /// Like `analyze`, but each morpheme carries its reading as a fourth
+    /// field: `(surface, pos, lemma, reading)`, for TTS front-ends. Readings
+    /// start from the ones set with `add_reading` / `load_readings` (else the
+    /// surface) and then follow the standard pronunciation rules across the
+    /// morphemes of each eojeol: liaison, cluster simplification,
+    /// tensification, nasalization, liquidization, aspiration and
+    /// palatalization (값이 → 갑 씨, 읽다 → 익 따, 같이 → 가 치). Text other
+    /// than Hangul reads as written.
+    #[pyo3(signature = (text, layers=None, ignore_spaces=false, options=None))]
+    fn analyze_readings(
+        &self,
+        text: String,
+        layers: Option<Vec<String>>,
+        ignore_spaces: bool,
+        options: Option<PyRef<AnalyzeOptions>>,
+    ) -> PyResult<Vec<(String, String, String, String)>> {
+        let default = AnalyzeOptions::default();
+        let opts = options.as_deref().unwrap_or(&default);
+        let (norm, layers) = match options {
+            Some(_) => (self.prepare_with(&text, opts), opts.layers.as_deref()),
+            None => (self.prepare(&text, ignore_spaces), layers.as_deref()),
+        };
+        let morphs = self.with_strict(opts.strict, || self.analyze_best(&norm, layers))?;
+        let readings = self.readings.read(&morphs);
+        Ok(morphs
+            .into_iter()
+            .zip(readings)
+            .filter(|((_, pos, _), _)| opts.keeps(pos))
+            .map(|((surface, pos, lemma), reading)| (surface, opts.tag(pos), lemma, reading))
+            .collect())
+    }
+
+    /// Like `analyze`, but each morpheme carries `(start, end)` character
+    /// offsets into `text`.
+    ///
+    /// With `normalize=True`, repeated jamo and stretched vowels are collapsed
+    /// first (see `normalize_text`); surfaces are then the normalized forms
+    /// while offsets still point into the original `text`. `ignore_spaces`
+    /// works as in `analyze`, with offsets skipping the removed spaces;
+    /// characters removed by `set_sanitize` are skipped the same way.
+    #[pyo3(signature = (text, layers=None, normalize=false, max_repeat=2, min_elongation=2, ignore_spaces=false, options=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn analyze_with_offsets(
+        &self,
+        text: String,
+        layers: Option<Vec<String>>,
+        normalize: bool,
+        max_repeat: usize,
+        min_elongation: usize,
+        ignore_spaces: bool,
+        options: Option<PyRef<AnalyzeOptions>>,
+    ) -> PyResult<Vec<Located>> {
+        if let Some(opts) = options {
+            return Ok(self.analyze_options(&text, &opts)?.0);
+        }
+        let mut norm = self.prepare(&text, ignore_spaces);
+        if normalize {
+            norm = normalize::collapse_repeats(norm, max_repeat, min_elongation);
+        }
+        Ok(self.analyze_located(&norm, layers.as_deref())?.0)
+    }
+
+    /// `analyze_with_offsets` of a long document using several threads: the
+    /// document is split into its top-level sentences (as `split_sentences`
+    /// does), runs of consecutive sentences are analyzed in parallel, `workers`
+    /// of them (the available cores by default), and the morphemes are joined
+    /// with offsets into `text`. Each sentence is decoded on its own, as in
+    /// `to_conllu`, so a morpheme never crosses a sentence boundary.
+    #[pyo3(signature = (text, layers=None, workers=None, options=None))]
+    fn analyze_parallel(
+        &self,
+        py: Python,
+        text: String,
+        layers: Option<Vec<String>>,
+        workers: Option<usize>,
+        options: Option<PyRef<AnalyzeOptions>>,
+    ) -> PyResult<Vec<Located>> {
+        let opts = match options {
+            Some(opts) => opts.clone(),
+            None => AnalyzeOptions::with_layers(layers),
+        };
+        self.view(opts.layers.as_deref())?;
+        self.check_length(&text)?;
+        let chars: Vec<char> = text.chars().collect();
+        let chunks = sentence_chunks(&text, chars.len());
+        let workers = workers
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+            .clamp(1, chunks.len().max(1));
+        let opts = &opts;
+        if workers == 1 {
+            let mut out = Vec::new();
+            for &chunk in &chunks {
+                check_signals()?;
+                out.extend(self.analyze_chunk(&chars, chunk, opts)?);
+            }
+            return Ok(out);
+        }
+
+        // Consecutive sentences of about the same total length per worker
+        let share = chars.len().div_ceil(workers);
+        let mut shares: Vec<Vec<(usize, usize)>> = vec![Vec::new(); workers];
+        for &chunk in &chunks {
+            shares[(chunk.0 / share).min(workers - 1)].push(chunk);
+        }
+        let shares: Vec<(RustTrie, Vec<_>)> = shares
+            .into_iter()
+            .filter(|share| !share.is_empty())
+            .map(|share| (self.worker_copy(), share))
+            .collect();
+        let chars = &chars;
+        let results: Vec<PyResult<Vec<Located>>> = py.allow_threads(|| {
+            std::thread::scope(|s| {
+                let handles: Vec<_> = shares
+                    .into_iter()
+                    .map(|(trie, share)| {
+                        s.spawn(move || {
+                            let mut out = Vec::new();
+                            for chunk in share {
+                                out.extend(trie.analyze_chunk(chars, chunk, opts)?);
+                            }
+                            Ok(out)
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|h| h.join().expect("analysis worker panicked"))
+                    .collect()
+            })
+        });
+        let mut out = Vec::new();
+        for morphs in results {
+            out.extend(morphs?);
+        }
+        Ok(out)
+    }
+
+    /// The spans of `text` where analyses costing less than `margin` more
+    /// than the best one disagree, as `(start, end, readings)` with
+    /// character offsets into `text`. Readings are `(morphemes, cost
+    /// above the best)`, the best analysis's reading first at 0.0; spans
+    /// where several analyses disagree are merged when they overlap. The
+    /// analyses compared are the `nbest` cheapest, as from `analyze_nbest`.
+    #[pyo3(signature = (text, margin=5.0, nbest=10, layers=None))]
+    fn ambiguities(
+        &self,
+        text: String,
+        margin: f64,
+        nbest: usize,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<Vec<ambiguity::Ambiguity>> {
+        if nbest < 2 {
+            return Err(PyValueError::new_err(
+                "nbest must be at least 2 to compare analyses",
+            ));
+        }
+        let norm = self.prepare(&text, false);
+        let analyses = self.decode_nbest(&norm, nbest, layers.as_deref())?;
+        Ok(ambiguity::find(&analyses, margin)
+            .into_iter()
+            .map(|(start, end, readings)| {
+                let (start, end) = norm.original_span(start, end);
+                (start, end, readings)
+            })
+            .collect())
+    }
+
+    /// `analyze_with_offsets` with an `uncertain` flag on every morpheme:
+    /// set when some decision behind it was close, the cheapest analysis
+    /// taking another edge where one of its morphemes starts (after the
+    /// same morpheme before it) costing less than `threshold` more. Cost
+    /// units are those of `explain`; morphemes of a chunk segmented by
+    /// `set_dead_end_fallback` are always uncertain.
+    #[pyo3(signature = (text, threshold=5.0, layers=None, options=None))]
+    fn analyze_uncertain(
+        &self,
+        text: String,
+        threshold: f64,
+        layers: Option<Vec<String>>,
+        options: Option<PyRef<AnalyzeOptions>>,
+    ) -> PyResult<Vec<Flagged>> {
+        let opts = match options {
+            Some(opts) => opts.clone(),
+            None => AnalyzeOptions::with_layers(layers),
+        };
+        let norm = self.prepare_with(&text, &opts);
+        let (morphs, margins) = self.with_strict(opts.strict, || {
+            self.analyze_margins(&norm, opts.layers.as_deref())
+        })?;
+        Ok(locate(&norm, morphs)
+            .into_iter()
+            .zip(margins)
+            .filter(|((_, pos, ..), _)| opts.keeps(pos))
+            .map(|((surface, pos, lemma, start, end), margin)| {
+                (
+                    surface,
+                    opts.tag(pos),
+                    lemma,
+                    start,
+                    end,
+                    margin < threshold,
+                )
+            })
+            .collect())
+    }
+
+    /// Up to `n` analyses as `(morphemes, cost)`, cheapest first.
+    ///
+    /// With `probabilities=True` the cost is replaced by a probability from a
+    /// softmax over the returned candidates' negated costs at `temperature`
+    /// (finite and above 0, else `ValueError`), which stays comparable
+    /// across sentence lengths.
+    ///
+    /// With `breakdown=True` each analysis is `(morphemes, cost, steps)`,
+    /// `steps` listing every lattice edge of the path as `(surface, pos,
+    /// terms)` and `terms` the named costs and bonuses that applied to it,
+    /// e.g. `("BONUS_NOUN_JOSA", -20.0)`. The terms add up to the cost. Use
+    /// `n=1` for the best analysis alone.
+    ///
+    /// With `options`, `n` is its `nbest` and each analysis's morphemes are
+    /// filtered, tagged and located as it asks; the costs and steps are
+    /// those of the whole analysis.
+    #[pyo3(signature = (text, n=5, layers=None, probabilities=false, temperature=1.0, breakdown=false, options=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn analyze_nbest(
+        &self,
+        py: Python<'_>,
+        text: String,
+        n: usize,
+        layers: Option<Vec<String>>,
+        probabilities: bool,
+        temperature: f64,
+        breakdown: bool,
+        options: Option<PyRef<AnalyzeOptions>>,
+    ) -> PyResult<PyObject> {
+        if !(temperature.is_finite() && temperature > 0.0) {
+            return Err(PyValueError::new_err(format!(
+                "temperature must be a positive number, got {}",
+                temperature
+            )));
+        }
+        if let Some(opts) = options {
+            let norm = self.prepare_with(&text, &opts);
+            let nbest = self.with_strict(opts.strict, || {
+                self.decode_explained(&norm, opts.nbest, opts.layers.as_deref(), breakdown)
+            })?;
+            let costs: Vec<f64> = nbest.iter().map(|((_, c), _)| *c).collect();
+            let scores = match probabilities {
+                true => scoring::softmax(&costs, temperature),
+                false => costs,
+            };
+            let mut out: Vec<PyObject> = Vec::with_capacity(nbest.len());
+            for (((morphs, _), steps), score) in nbest.into_iter().zip(scores) {
+                let morphs = opts.to_py(py, opts.finish(locate(&norm, morphs)));
+                out.push(match breakdown {
+                    true => (morphs, score, steps).into_py(py),
+                    false => (morphs, score).into_py(py),
+                });
+            }
+            return Ok(out.into_py(py));
+        }
+        let norm = self.prepare(&text, false);
+        let mut nbest = self.decode_explained(&norm, n, layers.as_deref(), breakdown)?;
+        if probabilities {
+            let costs: Vec<f64> = nbest.iter().map(|((_, c), _)| *c).collect();
+            for (((_, score), _), p) in nbest.iter_mut().zip(scoring::softmax(&costs, temperature))
+            {
+                *score = p;
+            }
+        }
+        Ok(match breakdown {
+            true => nbest
+                .into_iter()
+                .map(|((morphs, score), steps)| (morphs, score, steps))
+                .collect::<Vec<_>>()
+                .into_py(py),
+            false => nbest
+                .into_iter()
+                .map(|(analysis, _)| analysis)
+                .collect::<Vec<_>>()
+                .into_py(py),
+        })
+    }
+
+    /// The analysis lattice of `text`, sanitized and folded as `analyze`
+    /// sees it, as a Graphviz DOT graph, with every candidate edge and its
+    /// cost and the winning path highlighted.
+    #[pyo3(signature = (text, layers=None))]
+    fn lattice_dot(&self, text: String, layers: Option<Vec<String>>) -> PyResult<String> {
+        let text = self.prepare(&text, false).text();
+        self.check_length(&text)?;
+        let extra = self.extra_candidates(&text)?;
+        let lattice = Lattice::build_with(
+            &self.view(layers.as_deref())?,
+            &text,
+            self.build_options(extra.as_ref()),
+            check_signals,
+        )?;
+        let best = decoder::decode_with(&lattice, 1, check_signals)?;
+        Ok(dot::render(&lattice, best.first()))
+    }
+
+    /// Noun phrases (determiners, adjective modifiers and noun runs) in the
+    /// best analysis, as `(start, end, text)` with character offsets.
+    #[pyo3(signature = (text, layers=None))]
+    fn noun_phrases(
+        &self,
+        text: String,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<Vec<(usize, usize, String)>> {
+        let located = self.analyze_with_offsets(text, layers, false, 2, 2, false, None)?;
+        let morphs: Vec<Morpheme> = located
+            .iter()
+            .map(|(surface, pos, lemma, _, _)| (surface.clone(), pos.clone(), lemma.clone()))
+            .collect();
+        Ok(chunk::noun_phrases(&morphs)
+            .into_iter()
+            .map(|(a, b)| {
+                let phrase = morphs[a..b].iter().map(|m| m.0.as_str()).collect();
+                (located[a].3, located[b - 1].4, phrase)
+            })
+            .collect())
+    }
+
+    /// Iterates over every dictionary-consistent segmentation of `text`
+    /// (at most `limit`), sanitized and folded as `analyze` sees it, without
+    /// ranking them.
+    #[pyo3(signature = (text, limit=100, layers=None))]
+    fn segmentations(
+        &self,
+        text: String,
+        limit: usize,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<Segmentations> {
+        let text = self.prepare(&text, false).text();
+        self.check_length(&text)?;
+        let extra = self.extra_candidates(&text)?;
+        let view = self.view(layers.as_deref())?;
+        let opts = self.build_options(extra.as_ref());
+        let lattice = Lattice::build_with(&view, &text, opts, check_signals)?.into_owned();
+        Ok(Segmentations::new(lattice, limit))
+    }
+
+    /// Rescores the n-best analyses of `text` with an external language model.
+    ///
+    /// `scorer` is called with a list of candidate analyses and must return one
+    /// log-score per candidate (higher is better). The combined cost is
+    /// `lattice_weight * cost - lm_weight * score`, lowest first.
+    #[pyo3(signature = (text, scorer, n=10, lm_weight=1.0, lattice_weight=1.0, batch_size=32, layers=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn rescore(
+        &self,
+        text: String,
+        scorer: PyObject,
+        n: usize,
+        lm_weight: f64,
+        lattice_weight: f64,
+        batch_size: usize,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<Vec<Analysis>> {
+        let mut out = self.rescore_batch(
+            vec![text],
+            scorer,
+            n,
+            lm_weight,
+            lattice_weight,
+            batch_size,
+            layers,
+        )?;
+        Ok(out.pop().unwrap_or_default())
+    }
+
+    /// Like `rescore`, but pools the candidates of every text into shared
+    /// scorer batches. Texts are sanitized and folded as `analyze` does.
+    #[pyo3(signature = (texts, scorer, n=10, lm_weight=1.0, lattice_weight=1.0, batch_size=32, layers=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn rescore_batch(
+        &self,
+        texts: Vec<String>,
+        scorer: PyObject,
+        n: usize,
+        lm_weight: f64,
+        lattice_weight: f64,
+        batch_size: usize,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<Vec<Vec<Analysis>>> {
+        let nbests = texts
+            .iter()
+            .map(|t| {
+                check_signals()?;
+                self.decode_nbest(&self.prepare(t, false), n, layers.as_deref())
+            })
+            .collect::<PyResult<_>>()?;
+        let weights = ScoreWeights {
+            lattice: lattice_weight,
+            lm: lm_weight,
+        };
+        rescoring::rescore(&PyCallableScorer::new(scorer), nbests, weights, batch_size)
+    }
+
+    /// Replaces the post-processing rewrite rules with those in `path`.
+    fn load_rewrite_rules(&mut self, path: String) -> PyResult<usize> {
+        self.rules = rewrite::load_rules(&path).map_err(PyValueError::new_err)?;
+        Ok(self.rules.len())
+    }
+
+    /// Appends a single rule, e.g. `"*/NNG */NNG => merge NNG"`.
+    fn add_rewrite_rule(&mut self, rule: String) -> PyResult<()> {
+        let rule = RewriteRule::parse(&rule).map_err(PyValueError::new_err)?;
+        self.rules.push(rule);
+        Ok(())
+    }
+
+    fn clear_rewrite_rules(&mut self) {
+        self.rules.clear();
+    }
+
+    /// Registers `callback` as a source of candidate words. It is called with
+    /// each piece of text the decoder analyzes (a whole input, or one chunk
+    /// of a long one) and returns `(start, end, pos, lemma, cost)` tuples in
+    /// character offsets of that piece; they compete with dictionary entries
+    /// under the same tag constraints. Use it for pattern matchers (product
+    /// codes, dates) or rules the dictionary cannot express.
+    fn add_candidate_source(&mut self, callback: PyObject) {
+        self.sources.push(callback);
+    }
+
+    fn clear_candidate_sources(&mut self) {
+        self.sources.clear();
+    }
+
+    /// Attaches `trie`'s dictionary as the named domain layer (replacing any
+    /// layer with the same name). The dictionary is shared, not copied.
+    #[pyo3(signature = (name, trie, enabled=true))]
+    fn attach_layer(&mut self, name: String, trie: &RustTrie, enabled: bool) {
+        let layer = match &trie.overlay {
+            // A compiled base stays shared (and mapped) rather than copied
+            None => Layer {
+                name,
+                frozen: trie.frozen.clone(),
+                data: Arc::clone(&trie.data),
+                enabled,
+            },
+            Some(_) => Layer {
+                name,
+                frozen: None,
+                data: trie.snapshot(),
+                enabled,
+            },
+        };
+        match self.layers.iter_mut().find(|l| l.name == layer.name) {
+            Some(existing) => *existing = layer,
+            None => self.layers.push(layer),
+        }
+        self.suffixes = OnceLock::new();
+        self.folded = OnceLock::new();
+        self.spaced = OnceLock::new();
+    }
+
+    /// Loads a saved dictionary from `path` as the named domain layer.
+    #[pyo3(signature = (name, path, enabled=true))]
+    fn load_layer(&mut self, name: String, path: String, enabled: bool) -> PyResult<()> {
+        let trie = load_trie(path.into(), true)?;
+        self.attach_layer(name, &trie, enabled);
+        Ok(())
+    }
+
+    fn detach_layer(&mut self, name: String) -> PyResult<()> {
+        let before = self.layers.len();
+        self.layers.retain(|l| l.name != name);
+        self.suffixes = OnceLock::new();
+        self.folded = OnceLock::new();
+        self.spaced = OnceLock::new();
+        if self.layers.len() == before {
+            return Err(PyValueError::new_err(format!("unknown layer '{}'", name)));
+        }
+        Ok(())
+    }
+
+    /// Sets whether the layer is used when `analyze` is called without `layers`.
+    fn set_layer_enabled(&mut self, name: String, enabled: bool) -> PyResult<()> {
+        self.layer_mut(&name)?.enabled = enabled;
+        self.suffixes = OnceLock::new();
+        Ok(())
+    }
+
+    /// `(name, enabled)` for every attached layer, in attach order.
+    fn layers(&self) -> Vec<(String, bool)> {
+        self.layers
+            .iter()
+            .map(|l| (l.name.clone(), l.enabled))
+            .collect()
+    }
+
+    /// Turns the emoticon / internet-slang table (ㅇㅋ, ㄱㄱ, 갠톡, ^^, ...)
+    /// on or off. It is consulted after the regular dictionaries, before
+    /// unknown-character fallback.
+    #[pyo3(signature = (enabled=true))]
+    fn enable_slang(&mut self, enabled: bool) {
+        self.suffixes = OnceLock::new();
+        self.folded = OnceLock::new();
+        self.spaced = OnceLock::new();
+        match (enabled, &self.slang) {
+            (true, None) => self.slang = Some(Arc::new(slang::builtin())),
+            (false, _) => self.slang = None,
+            _ => {}
+        }
+    }
+
+    fn slang_enabled(&self) -> bool {
+        self.slang.is_some()
+    }
+
+    /// Adds an entry to the slang table, enabling it if needed. Emoticons
+    /// conventionally use `pos="SW"`.
+    #[pyo3(signature = (surface, pos="SW".to_string(), lemma=None))]
+    fn add_slang(&mut self, surface: String, pos: String, lemma: Option<String>) {
+        self.enable_slang(true);
+        if let Some(table) = &mut self.slang {
+            let lemma = lemma.unwrap_or_else(|| surface.clone());
+            Arc::make_mut(table).insert(surface, pos, lemma, None);
+        }
+    }
+
+    /// Turns recognition of `#태그` hashtags and `@아이디` mentions on or off.
+    /// Each becomes one morpheme with the given tag and is not decoded
+    /// further.
+    #[pyo3(signature = (enabled=true, hashtag_tag="W_HASHTAG".to_string(), mention_tag="W_MENTION".to_string()))]
+    fn enable_social_tokens(&mut self, enabled: bool, hashtag_tag: String, mention_tag: String) {
+        self.social = enabled.then_some(SocialTags {
+            hashtag: hashtag_tag,
+            mention: mention_tag,
+        });
+    }
+
+    fn social_tokens_enabled(&self) -> bool {
+        self.social.is_some()
+    }
+
+    /// Turns code-switching support on or off: every whole Latin word in
+    /// Korean text is a candidate SL token that takes part in tag
+    /// transitions like a dictionary word, so that particles and suffixes
+    /// attach to it (미팅 reschedule 해줘, PPT를, check해줘) instead of
+    /// the English falling back to unknown characters. Dictionary entries
+    /// for a Latin span, whatever their lemma, are preferred to the plain
+    /// SL token.
+    #[pyo3(signature = (enabled=true))]
+    fn set_code_switching(&mut self, enabled: bool) {
+        self.code_switching = enabled;
+    }
+
+    /// Turns the language pre-filter on or off. Each paragraph (line) gets
+    /// a language from the scripts of its letters: "ko" when at least
+    /// `hangul_ratio` of them are Hangul, else "ja" (kana), "zh" (hanja) or
+    /// "en" (Latin). `actions` maps "en", "ja" and "zh" to what is done
+    /// with their paragraphs: "analyze" as usual, "passthrough" with every
+    /// whitespace-separated word as one SL (SH for Chinese) token, or
+    /// "skip" as a single token; unmapped languages are passed through.
+    #[pyo3(signature = (enabled=true, actions=None, hangul_ratio=0.1))]
+    fn set_language_filter(
+        &mut self,
+        enabled: bool,
+        actions: Option<HashMap<String, String>>,
+        hangul_ratio: f64,
+    ) -> PyResult<()> {
+        if !(0.0..=1.0).contains(&hangul_ratio) {
+            return Err(PyValueError::new_err(format!(
+                "hangul ratio {} is not between 0 and 1",
+                hangul_ratio
+            )));
+        }
+        let mut filter = LanguageFilter {
+            actions: [Action::Passthrough; 3],
+            hangul_ratio,
+        };
+        for (code, name) in actions.unwrap_or_default() {
+            let k = Language::parse(&code)
+                .and_then(|l| Language::FOREIGN.iter().position(|&f| f == l))
+                .ok_or_else(|| {
+                    PyValueError::new_err(format!(
+                        "unknown language {:?}; expected en, ja or zh",
+                        code
+                    ))
+                })?;
+            filter.actions[k] = Action::parse(&name).ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "unknown action {:?}; expected analyze, passthrough or skip",
+                    name
+                ))
+            })?;
+        }
+        self.language = enabled.then_some(filter);
+        Ok(())
+    }
+
+    /// The paragraphs of `text` as `(start, end, language)` with character
+    /// offsets, languages detected as `set_language_filter` does ("und"
+    /// for paragraphs without letters).
+    #[pyo3(signature = (text, hangul_ratio=None))]
+    fn detect_languages(
+        &self,
+        text: &str,
+        hangul_ratio: Option<f64>,
+    ) -> Vec<(usize, usize, &'static str)> {
+        let ratio =
+            hangul_ratio.unwrap_or_else(|| self.language.as_ref().map_or(0.1, |f| f.hangul_ratio));
+        let chars: Vec<char> = text.chars().collect();
+        language::paragraphs(&chars, ratio)
+            .into_iter()
+            .map(|(start, end, language)| (start, end, language.code()))
+            .collect()
+    }
+
+    /// Turns recognition of phone numbers (010-1234-5678, 02.123.4567,
+    /// +82 10 1234 5678, 1588-1234), resident registration numbers
+    /// (900101-1234567, masked or not), business registration numbers
+    /// (123-45-67890) and email addresses on or off, each kind on its own.
+    /// Each becomes one morpheme with its tag, W_PHONE, W_RRN, W_BIZNO and
+    /// W_EMAIL unless `tags` maps "phone", "rrn", "business_number" or
+    /// "email" to another.
+    #[pyo3(signature = (phone=true, rrn=true, business_number=true, email=true, tags=None))]
+    fn enable_structured_tokens(
+        &mut self,
+        phone: bool,
+        rrn: bool,
+        business_number: bool,
+        email: bool,
+        tags: Option<HashMap<String, String>>,
+    ) -> PyResult<()> {
+        let tags = tags.unwrap_or_default();
+        if let Some(kind) = tags
+            .keys()
+            .find(|k| !["phone", "rrn", "business_number", "email"].contains(&k.as_str()))
+        {
+            return Err(PyValueError::new_err(format!(
+                "unknown structured token kind {:?}",
+                kind
+            )));
+        }
+        let tag = |on: bool, kind: &str, default: &str| {
+            on.then(|| tags.get(kind).map_or(default, String::as_str).to_string())
+        };
+        let tokens = StructuredTokens {
+            phone: tag(phone, "phone", "W_PHONE"),
+            rrn: tag(rrn, "rrn", "W_RRN"),
+            business: tag(business_number, "business_number", "W_BIZNO"),
+            email: tag(email, "email", "W_EMAIL"),
+        };
+        self.structured = (!tokens.is_empty()).then_some(tokens);
+        Ok(())
+    }
+
+    fn structured_tokens_enabled(&self) -> bool {
+        self.structured.is_some()
+    }
+
+    /// Analyzes every non-empty line of `corpus_path` and counts how often
+    /// each dictionary entry is used by the best analyses, as
+    /// `{(surface, pos, lemma): count}`; this is what `prune` expects.
+    #[pyo3(signature = (corpus_path, layers=None))]
+    fn count_entries(
+        &self,
+        corpus_path: String,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<HashMap<Morpheme, u64>> {
+        use std::io::BufRead;
+
+        let file = File::open(&corpus_path).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let view = self.view(layers.as_deref())?;
+        let mut counts = HashMap::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| PyValueError::new_err(e.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            check_signals()?;
+            for m in self.analyze_best(&self.prepare(&line, false), layers.as_deref())? {
+                let known = view
+                    .lookup(&m.0)
+                    .iter()
+                    .any(|p| p.pos == m.1 && p.lemma == m.2);
+                if known {
+                    *counts.entry(m).or_insert(0) += 1;
+                }
+            }
+        }
+        Ok(counts)
+    }
+
+    /// `n` synthetic sentences for fuzzing and benchmarks, built from the
+    /// dictionary (the default view, or the given layers): one to
+    /// `max_words` eojeols, each a noun, predicate stem, adverb or
+    /// determiner followed by the particles and endings the transition
+    /// rules reward after it, the last a predicate with a final ending when
+    /// the dictionary has one. The same dictionary and `seed` give the same
+    /// sentences. With `with_morphemes` each is `(sentence, morphemes)`,
+    /// the morphemes it was built from.
+    #[pyo3(signature = (n, seed=0, max_words=6, layers=None, with_morphemes=false))]
+    fn generate_sentences(
+        &self,
+        py: Python,
+        n: usize,
+        seed: u64,
+        max_words: usize,
+        layers: Option<Vec<String>>,
+        with_morphemes: bool,
+    ) -> PyResult<PyObject> {
+        if max_words == 0 {
+            return Err(PyValueError::new_err("max_words must be at least 1"));
+        }
+        let view = self.view(layers.as_deref())?;
+        let mut surfaces = view.surfaces();
+        surfaces.sort_unstable();
+        let entries = surfaces.iter().flat_map(|surface| {
+            view.lookup(surface)
+                .into_iter()
+                .map(move |p| (surface.to_string(), p.pos.to_string(), p.lemma.to_string()))
+        });
+        let lexicon = Lexicon::new(entries).ok_or_else(|| {
+            PyValueError::new_err("the dictionary has no nouns, predicates, adverbs or determiners to build sentences from")
+        })?;
+        let sentences = lexicon.sentences(&*self.grammar, n, seed, max_words);
+        Ok(match with_morphemes {
+            true => sentences.into_py(py),
+            false => sentences
+                .into_iter()
+                .map(|(text, _)| text)
+                .collect::<Vec<_>>()
+                .into_py(py),
+        })
+    }
+
+    /// Analyzes every non-empty line of `corpus_path` and reports how much
+    /// of it the dictionary covers, as a dict: `lines`, `tokens`
+    /// (non-whitespace morphemes), `unknown` (tokens matching no dictionary
+    /// entry, as `count_entries` tells them apart, nor any gazetteer name),
+    /// `entities` (tokens known from a gazetteer only), `oov_rate`, `pos` and
+    /// `unknown_pos` (`{tag: tokens}`), and `top_unknown`, the `top_n` most
+    /// frequent unknown surfaces as `(surface, count)`.
+    #[pyo3(signature = (corpus_path, top_n=50, layers=None))]
+    fn coverage(
+        &self,
+        py: Python,
+        corpus_path: String,
+        top_n: usize,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<HashMap<&'static str, PyObject>> {
+        use std::io::BufRead;
+
+        let file = File::open(&corpus_path)
+            .map_err(|e| PyValueError::new_err(format!("{}: {}", corpus_path, e)))?;
+        let view = self.view(layers.as_deref())?;
+        let mut coverage = coverage::Coverage::default();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| PyValueError::new_err(e.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            check_signals()?;
+            let morphs = self.analyze_best(&self.prepare(&line, false), layers.as_deref())?;
+            coverage.add(morphs, |m| {
+                let entry = view.lookup(&m.0);
+                if entry.iter().any(|p| p.pos == m.1 && p.lemma == m.2) {
+                    return coverage::Source::Dictionary;
+                }
+                let named = |g: &Arc<Gazetteer>| g.tag == m.1 && g.contains(&m.0);
+                match self.gazetteers.iter().any(named) {
+                    true => coverage::Source::Gazetteer,
+                    false => coverage::Source::Unknown,
+                }
+            });
+        }
+        let mut out = HashMap::new();
+        out.insert("lines", coverage.lines.into_py(py));
+        out.insert("tokens", coverage.tokens.into_py(py));
+        out.insert("unknown", coverage.unknown.into_py(py));
+        out.insert("entities", coverage.entities.into_py(py));
+        out.insert("oov_rate", coverage.oov_rate().into_py(py));
+        out.insert("top_unknown", coverage.top_unknown(top_n).into_py(py));
+        out.insert("pos", coverage.pos.into_py(py));
+        out.insert("unknown_pos", coverage.unknown_pos.into_py(py));
+        Ok(out)
+    }
+
+    /// Removes entries used fewer than `min_freq` times according to
+    /// `counts` (as returned by `count_entries`; missing entries count 0)
+    /// and returns the dropped `(surface, pos, lemma)`, sorted. Layers are
+    /// untouched; on a fork the entries are banned. Save or compile the
+    /// result for a compact dictionary.
+    fn prune(&mut self, min_freq: u64, counts: HashMap<Morpheme, u64>) -> PyResult<Vec<Morpheme>> {
+        self.prune_where(|surface, pos, lemma| {
+            let key = (surface.to_string(), pos.to_string(), lemma.to_string());
+            Ok(counts.get(&key).copied().unwrap_or(0) < min_freq)
+        })
+    }
+
+    /// Like `prune`, removing the entries for which
+    /// `predicate(surface, pos, lemma)` is true.
+    fn prune_predicate(&mut self, predicate: &PyAny) -> PyResult<Vec<Morpheme>> {
+        self.prune_where(|surface, pos, lemma| predicate.call1((surface, pos, lemma))?.is_true())
+    }
+
+    /// Analyzes every non-empty line of `corpus_path` and counts POS
+    /// transitions, with `BOS`/`EOS` at line edges and whitespace skipped.
+    /// Returns `{(prev, next): count}`; with `output` set, also writes a
+    /// `prev<TAB>next<TAB>count` table there, most frequent first.
+    #[pyo3(signature = (corpus_path, output=None, layers=None))]
+    fn collect_bigram_stats(
+        &self,
+        corpus_path: String,
+        output: Option<String>,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<HashMap<(String, String), u64>> {
+        use std::io::BufRead;
+
+        let file = File::open(&corpus_path).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let mut stats = BigramCounts::default();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| PyValueError::new_err(e.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            check_signals()?;
+            stats.add(&self.analyze_best(&self.prepare(&line, false), layers.as_deref())?);
+        }
+        if let Some(path) = output {
+            stats.write_tsv(&path).map_err(PyValueError::new_err)?;
+        }
+        Ok(stats.counts)
+    }
+
+    /// Proposes new nouns from a raw corpus by substring cohesion.
+    ///
+    /// Returns `(word, cohesion, count)` for candidates not already in the
+    /// dictionary, most cohesive first. With `insert_threshold` set, the
+    /// candidates scoring at least that much are inserted as `pos`.
+    #[pyo3(signature = (texts, min_count=5, min_cohesion=0.1, max_len=10, insert_threshold=None, pos="NNG".to_string()))]
+    fn discover_words(
+        &mut self,
+        texts: Vec<String>,
+        min_count: u32,
+        min_cohesion: f64,
+        max_len: usize,
+        insert_threshold: Option<f64>,
+        pos: String,
+    ) -> PyResult<Vec<(String, f64, u32)>> {
+        let mut stats = CorpusStats::new(max_len);
+        for text in &texts {
+            check_signals()?;
+            stats.add(text);
+        }
+        let found: Vec<_> = {
+            let view = self.default_view();
+            stats
+                .candidates(min_count, min_cohesion)
+                .into_iter()
+                .filter(|(word, _, _)| !view.contains(word))
+                .collect()
+        };
+        if let Some(threshold) = insert_threshold {
+            for (word, score, _) in &found {
+                if *score >= threshold {
+                    self.insert_entry(word.clone(), pos.clone(), word.clone(), None, None);
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    /// Experimental: decode single-best analyses with a right-to-left pass
+    /// as well, so an early choice can use what follows it. This finds the
+    /// cheapest path where the forward decoder's one-history-per-position
+    /// pruning can miss it on long eojeols. `analyze_nbest` with `n > 1`
+    /// keeps the forward beam.
+    #[pyo3(signature = (enabled=true))]
+    fn set_bidirectional(&mut self, enabled: bool) {
+        self.bidirectional = enabled;
+    }
+
+    /// Experimental: decode with second-order context. Paths reaching a
+    /// position are kept apart by the tags of their last two morphemes
+    /// rather than competing for one slot, and three-tag bonuses apply
+    /// (a stem, a pre-final ending and an ending: 가/VV 았/EP 다/EF), which
+    /// fixes taggings the first-order decoder gets wrong around EP/EF
+    /// sequences. Each position keeps its `max_states` cheapest hypotheses,
+    /// trading accuracy for speed. Takes precedence over `set_bidirectional`.
+    #[pyo3(signature = (enabled=true, max_states=64))]
+    fn set_second_order(&mut self, enabled: bool, max_states: usize) -> PyResult<()> {
+        if max_states == 0 {
+            return Err(PyValueError::new_err("max_states must be positive"));
+        }
+        self.second_order = enabled.then_some(max_states);
+        Ok(())
+    }
+
+    /// Segments a chunk that no analysis crosses (tag constraints and
+    /// rewrites can leave none) by longest dictionary match, with unknown
+    /// words in between, instead of failing with `AnalysisError`: output is
+    /// never empty for non-empty input. Off by default. A fallback is
+    /// flagged by an infinite cost from `analyze_nbest` (`null` in JSON
+    /// Lines output) and by `True` from a timed `analyze`.
+    #[pyo3(signature = (enabled=true))]
+    fn set_dead_end_fallback(&mut self, enabled: bool) {
+        self.dead_end_fallback = enabled;
+    }
+
+    /// Checks every analysis before returning it: the surfaces of its
+    /// morphemes must concatenate to exactly the text that was analyzed,
+    /// spaces included (the normalized text, which offsets map back to the
+    /// original). An analysis that would break this raises `AnalysisError`
+    /// with `stage="verify"` at the first character that differs, instead
+    /// of reaching an aligner that relies on it. Off by default.
+    #[pyo3(signature = (enabled=true))]
+    fn set_verify_round_trip(&mut self, enabled: bool) {
+        self.verify = enabled;
+    }
+
+    /// Configures cleaning of invisible characters before analysis (off by
+    /// default). Each class takes "strip", "space" (replace with an
+    /// ordinary space, so it separates words) or "keep": `zero_width` covers
+    /// ZWSP, ZWNJ, ZWJ, word joiner and soft hyphen; `bom` is U+FEFF; `nbsp`
+    /// covers no-break spaces; `control` is control characters other than
+    /// tab and newlines. Offsets from `analyze_with_offsets` and `tokenize`
+    /// still point into the original text.
+    #[pyo3(signature = (enabled=true, zero_width="strip", bom="strip", nbsp="space", control="strip"))]
+    fn set_sanitize(
+        &mut self,
+        enabled: bool,
+        zero_width: &str,
+        bom: &str,
+        nbsp: &str,
+        control: &str,
+    ) -> PyResult<()> {
+        let action = |name: &str| {
+            normalize::Action::parse(name)
+                .ok_or_else(|| PyValueError::new_err(format!("unknown sanitize action '{}'", name)))
+        };
+        let opts = Sanitize {
+            zero_width: action(zero_width)?,
+            bom: action(bom)?,
+            nbsp: action(nbsp)?,
+            control: action(control)?,
+        };
+        self.sanitize = enabled.then_some(opts);
+        Ok(())
+    }
+
+    /// Configures folding of the text before analysis, after `set_sanitize`
+    /// (off by default): `width` folds fullwidth ASCII and halfwidth jamo,
+    /// `nfc` composes conjoining Hangul jamo into syllables, and
+    /// `max_repeat` (when set) collapses repeated jamo and stretched vowels
+    /// as `normalize_text` does. Surfaces are the folded forms; offsets from
+    /// `analyze_with_offsets`, `tokenize` and errors still point into the
+    /// original text, a folded character spanning all it replaced.
+    #[pyo3(signature = (enabled=true, nfc=true, width=true, max_repeat=None, min_elongation=2))]
+    fn set_normalization(
+        &mut self,
+        enabled: bool,
+        nfc: bool,
+        width: bool,
+        max_repeat: Option<usize>,
+        min_elongation: usize,
+    ) {
+        self.folding = enabled.then_some(Folding {
+            width,
+            nfc,
+            repeats: max_repeat.map(|max| (max, min_elongation)),
+        });
+    }
+
+    /// Sets the POS tags `insert`, `insert_batch` and `insert_iter` accept
+    /// and what happens to others: `unknown` is "warn" (the default; a
+    /// `UserWarning`, the entry is still inserted), "reject" (`DictEntryError`
+    /// at the entry's index, nothing of the call is inserted) or "allow". `tags=None` restores the
+    /// standard TTA tag set; pass a list to add custom tags to it, e.g.
+    /// `tag_inventory() + ["NNBC"]`.
+    #[pyo3(signature = (tags=None, unknown="warn"))]
+    fn set_tag_inventory(&mut self, tags: Option<Vec<String>>, unknown: &str) -> PyResult<()> {
+        let unknown = UnknownTag::parse(unknown)
+            .ok_or_else(|| PyValueError::new_err(format!("unknown tag policy '{}'", unknown)))?;
+        self.tags = match tags {
+            Some(tags) => TagSet::new(tags, unknown),
+            None => TagSet::new(tags::STANDARD_TAGS.iter().map(|t| t.to_string()), unknown),
+        };
+        Ok(())
+    }
+
+    /// The accepted POS tags, sorted.
+    fn tag_inventory(&self) -> Vec<String> {
+        self.tags.tags().into_iter().map(str::to_string).collect()
+    }
+
+    /// Turns case-insensitive matching of dictionary keys containing Latin
+    /// letters on or off: with it, "IPHONE" and "iphone" in the text match
+    /// an "iPhone" entry. Morphemes keep the text's surface and take the
+    /// entry's lemma. Only Latin letters are folded.
+    #[pyo3(signature = (enabled=true))]
+    fn set_case_folding(&mut self, enabled: bool) {
+        self.case_fold = enabled;
+    }
+
+    /// Configures unknown-text grouping. When enabled (the default), unknown
+    /// text splits at script changes (갤럭시S24 → 갤럭시 / S / 24); numbers and
+    /// foreign letters are tagged SN / SL / SH. `merge` lists class pairs to
+    /// keep together, e.g. `[("latin", "digit")]` for S24; classes are
+    /// hangul, latin, digit, han and kana. Disabled, every unknown character
+    /// is its own NNG.
+    #[pyo3(signature = (enabled=true, merge=Vec::new()))]
+    fn set_oov_grouping(&mut self, enabled: bool, merge: Vec<(String, String)>) -> PyResult<()> {
+        let class = |name: &str| {
+            CharClass::parse(name)
+                .ok_or_else(|| PyValueError::new_err(format!("unknown character class '{}'", name)))
+        };
+        let merge = merge
+            .iter()
+            .map(|(a, b)| Ok((class(a)?, class(b)?)))
+            .collect::<PyResult<Vec<_>>>()?;
+        self.grouping = enabled.then_some(OovGrouping { merge });
+        Ok(())
+    }
+
+    /// Loads a name list (one name per line, e.g. people, organizations or
+    /// places) searched alongside the dictionary: the longest name starting
+    /// at each position is a candidate tagged `tag`, its length-based cost
+    /// lowered by `cost_bonus` per character. Gazetteers are stored apart
+    /// from the dictionary, in little more than the text of their names, so
+    /// millions of names fit. `label` is the entity type `extract_entities`
+    /// reports for the names; a line `name<TAB>TYPE` gives a name its own.
+    /// Returns how many distinct names were loaded.
+    #[pyo3(signature = (path, tag="NNP", cost_bonus=10.0, label="MISC"))]
+    fn load_gazetteer(
+        &mut self,
+        py: Python,
+        path: String,
+        tag: &str,
+        cost_bonus: f64,
+        label: &str,
+    ) -> PyResult<usize> {
+        if !cost_bonus.is_finite() {
+            return Err(PyValueError::new_err(format!(
+                "cost bonus {} is not finite",
+                cost_bonus
+            )));
+        }
+        let source = std::fs::read_to_string(&path)
+            .map_err(|e| PyValueError::new_err(format!("{}: {}", path, e)))?;
+        let gazetteer = py
+            .allow_threads(|| {
+                Gazetteer::parse(&source, tag.to_string(), cost_bonus, label.to_string())
+            })
+            .map_err(|e| PyValueError::new_err(format!("{}: {}", path, e)))?;
+        let count = gazetteer.len();
+        self.gazetteers.push(Arc::new(gazetteer));
+        Ok(count)
+    }
+
+    /// Drops every gazetteer loaded with `load_gazetteer`.
+    fn clear_gazetteers(&mut self) {
+        self.gazetteers.clear();
+    }
+
+    /// Entities of `text` as `(surface, type, start, end)` with character
+    /// offsets: the longest gazetteer name at each point, left to right,
+    /// among those starting and ending on morpheme boundaries of the best
+    /// analysis, so an entity is always whole morphemes.
+    #[pyo3(signature = (text, layers=None))]
+    fn extract_entities(
+        &self,
+        text: String,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<Vec<(String, String, usize, usize)>> {
+        let norm = self.prepare(&text, false);
+        let (morphs, _) = self
+            .decode_nbest(&norm, 1, layers.as_deref())?
+            .pop()
+            .unwrap_or_default();
+        let mut boundaries = vec![false; norm.chars.len() + 1];
+        boundaries[0] = true;
+        let mut at = 0;
+        for (surface, _, _) in &morphs {
+            at += surface.chars().count();
+            if let Some(boundary) = boundaries.get_mut(at) {
+                *boundary = true;
+            }
+        }
+        let chars: Vec<char> = text.chars().collect();
+        let found = gazetteer::entities(&self.gazetteers, &norm.chars, &boundaries);
+        Ok(found
+            .into_iter()
+            .map(|(start, end, kind)| {
+                let (start, end) = norm.original_span(start, end);
+                (
+                    chars[start..end].iter().collect(),
+                    kind.to_string(),
+                    start,
+                    end,
+                )
+            })
+            .collect())
+    }
+
+    /// `text` with personal data replaced by placeholders, and the offset
+    /// map `[(start, end, new_start, new_end, type)]` of the replacements.
+    /// `targets` are the types to replace: PER (person names from the
+    /// gazetteers, else proper nouns shaped like a Korean name), PHONE,
+    /// EMAIL, RRN (resident registration numbers), BIZNO (business
+    /// registration numbers), or any other gazetteer entity type. The
+    /// placeholder's `{}` becomes the type. Analysis is skipped when only
+    /// structured types are asked for.
+    #[pyo3(signature = (text, targets=None, placeholder="[{}]", layers=None))]
+    fn anonymize(
+        &self,
+        text: String,
+        targets: Option<Vec<String>>,
+        placeholder: &str,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<(String, Vec<anonymize::Replacement>)> {
+        const STRUCTURED: [&str; 4] = ["PHONE", "EMAIL", "RRN", "BIZNO"];
+        let targets = targets
+            .unwrap_or_else(|| vec!["PER".to_string(), "PHONE".to_string(), "EMAIL".to_string()]);
+        let wants = |kind: &str| targets.iter().any(|t| t == kind);
+        let norm = self.prepare(&text, false);
+        let tag = |kind: &str| wants(kind).then(|| kind.to_string());
+        let tokens = StructuredTokens {
+            phone: tag("PHONE"),
+            rrn: tag("RRN"),
+            business: tag("BIZNO"),
+            email: tag("EMAIL"),
+        };
+        let mut spans: Vec<(usize, usize, String)> = structured::find_spans(&norm.chars, &tokens)
+            .into_iter()
+            .map(|(start, end, kind)| (start, end, kind.to_string()))
+            .collect();
+        if targets.iter().any(|t| !STRUCTURED.contains(&t.as_str())) {
+            let (morphs, _) = self
+                .decode_nbest(&norm, 1, layers.as_deref())?
+                .pop()
+                .unwrap_or_default();
+            let mut boundaries = vec![false; norm.chars.len() + 1];
+            boundaries[0] = true;
+            let mut names = Vec::new();
+            let mut at = 0;
+            for (surface, pos, _) in &morphs {
+                let start = at;
+                at += surface.chars().count();
+                if let Some(boundary) = boundaries.get_mut(at) {
+                    *boundary = true;
+                }
+                if pos == "NNP" && anonymize::is_person_name(surface) {
+                    names.push((start, at, "PER"));
+                }
+            }
+            let mut found = gazetteer::entities(&self.gazetteers, &norm.chars, &boundaries);
+            if wants("PER") {
+                found.extend(names);
+            }
+            // Structured tokens first, then gazetteer entities, then names
+            for (start, end, kind) in found {
+                if wants(kind) && !spans.iter().any(|&(s, e, _)| start < e && s < end) {
+                    spans.push((start, end, kind.to_string()));
+                }
+            }
+            spans.sort_by_key(|&(start, _, _)| start);
+        }
+        for span in &mut spans {
+            (span.0, span.1) = norm.original_span(span.0, span.1);
+        }
+        let chars: Vec<char> = text.chars().collect();
+        Ok(anonymize::redact(&chars, &spans, placeholder))
+    }
+
+    /// Adds the words of `path` (one per line) to the profanity list
+    /// `mask_profanity` uses, or with `replace=True` uses them instead of
+    /// the built-in list. Returns how many words were new.
+    #[pyo3(signature = (path, replace=false))]
+    fn load_profanity(&mut self, path: String, replace: bool) -> PyResult<usize> {
+        let source = std::fs::read_to_string(&path)
+            .map_err(|e| PyValueError::new_err(format!("{}: {}", path, e)))?;
+        let mut profanity = match (replace, self.profanity.take()) {
+            (true, _) => Profanity::default(),
+            (false, Some(current)) => Arc::unwrap_or_clone(current),
+            (false, None) => Profanity::builtin(),
+        };
+        let added = profanity.extend(Profanity::parse(&source));
+        self.profanity = Some(Arc::new(profanity));
+        Ok(added)
+    }
+
+    /// `text` with its profanity masked: every character of a profane word
+    /// is replaced by `replacement` when that is one character, or the
+    /// whole word by it otherwise. Words are found through digits and
+    /// symbols wedged into them (시1발), jamo spellings (ㅅㅣ발) and initial
+    /// consonants (ㅅㅂ); a match inside a longer word of the best analysis
+    /// (시발점) is left alone.
+    #[pyo3(signature = (text, replacement="*", layers=None))]
+    fn mask_profanity(
+        &self,
+        text: String,
+        replacement: &str,
+        layers: Option<Vec<String>>,
+    ) -> PyResult<String> {
+        let builtin;
+        let profanity = match &self.profanity {
+            Some(profanity) => &**profanity,
+            None => {
+                builtin = Profanity::builtin();
+                &builtin
+            }
+        };
+        let norm = self.prepare(&text, false);
+        let mut found = profanity.find(&norm.chars);
+        if found.is_empty() {
+            return Ok(text);
+        }
+        let (morphs, _) = self
+            .decode_nbest(&norm, 1, layers.as_deref())?
+            .pop()
+            .unwrap_or_default();
+        let mut at = 0;
+        for (surface, pos, lemma) in &morphs {
+            let (start, end) = (at, at + surface.chars().count());
+            at = end;
+            let word = lemma != "UNKNOWN" && !pos.starts_with("W_") && !profanity.contains(surface);
+            if word {
+                found.retain(|&(s, e)| !(start <= s && e <= end && (start, end) != (s, e)));
+            }
+        }
+        let chars: Vec<char> = text.chars().collect();
+        let mut masked = String::with_capacity(text.len());
+        let mut next = 0;
+        let single = replacement.chars().count() == 1;
+        for (start, end) in found {
+            let (start, end) = norm.original_span(start, end);
+            masked.extend(&chars[next..start]);
+            match single {
+                true => (start..end).for_each(|_| masked.push_str(replacement)),
+                false => masked.push_str(replacement),
+            }
+            next = end;
+        }
+        masked.extend(&chars[next..]);
+        Ok(masked)
+    }
+
+    /// Turns recognition of numeral + counter constructions on or off: after
+    /// a number (3, 1,000), a native numeral (세, 스무, 열두) or a
+    /// Sino-Korean one (이십), a counter word (개, 명, 마리, 번, ...) is a
+    /// bound noun (NNB) bound to the number, as in 3/SN 개/NNB and 세/MM
+    /// 명/NNB. `words` replaces the built-in counters. With `composite`, the
+    /// numeral and its counter come out as one quantity token, e.g.
+    /// `("세 명", "MM+NNB", "세+명")`.
+    #[pyo3(signature = (enabled=true, words=None, composite=false))]
+    fn set_counters(&mut self, enabled: bool, words: Option<Vec<String>>, composite: bool) {
+        let words =
+            words.unwrap_or_else(|| counter::COUNTERS.iter().map(|w| w.to_string()).collect());
+        self.counters = enabled.then(|| Counters::new(words, composite));
+    }
+
+    /// Inputs longer than `chunk_chars` are analyzed in pieces cut at
+    /// sentence starts (or whitespace), bounding memory on large documents.
+    /// Inputs longer than `max_chars` raise `InputTooLongError`, carrying
+    /// `length` and `limit` attributes besides the `stage`, `offset` and
+    /// `substring` of every `AnalysisError`.
+    #[pyo3(signature = (chunk_chars=10_000, max_chars=None))]
+    fn set_input_limits(&mut self, chunk_chars: usize, max_chars: Option<usize>) -> PyResult<()> {
+        if chunk_chars == 0 {
+            return Err(PyValueError::new_err("chunk_chars must be positive"));
+        }
+        self.limits = InputLimits {
+            chunk_chars,
+            max_chars,
+        };
+        Ok(())
+    }
+
+    /// Uses corpus branching entropy to place unknown-word boundaries: letter
+    /// runs seen in the corpus become candidate unknown words, discounted by
+    /// `weight` times their left plus right entropy. `None` turns it off.
+    #[pyo3(signature = (entropy, weight=10.0))]
+    fn set_boundary_entropy(&mut self, entropy: Option<&BranchingEntropy>, weight: f64) {
+        self.entropy = entropy.map(|e| (Arc::clone(&e.stats), weight));
+    }
+
+    /// The tag transition rules as `(prev, curr, allowed, bonus)`, in the
+    /// order they are tried: the first rule matching a pair of tags decides
+    /// whether the second may follow the first and the bonus subtracted
+    /// from its cost. A trailing `*` matches any tag with that prefix.
+    fn get_transition_rules(&self) -> Vec<(String, String, bool, f64)> {
+        self.grammar
+            .transitions()
+            .rules()
+            .iter()
+            .map(|r| (r.prev.to_string(), r.curr.to_string(), r.allowed, r.bonus))
+            .collect()
+    }
+
+    /// Sets the rule for `prev` followed by `curr` (tags, or prefixes ending
+    /// in `*`), replacing the rule for the same pair or else taking
+    /// precedence over every existing one. Forks keep their own rules.
+    #[pyo3(signature = (prev, curr, allowed=true, bonus=0.0))]
+    fn set_transition_rule(
+        &mut self,
+        prev: &str,
+        curr: &str,
+        allowed: bool,
+        bonus: f64,
+    ) -> PyResult<()> {
+        if !bonus.is_finite() {
+            return Err(PyValueError::new_err(format!(
+                "bonus {} is not finite",
+                bonus
+            )));
+        }
+        let rule = TransitionRule {
+            prev: TagPattern::parse(prev).map_err(PyValueError::new_err)?,
+            curr: TagPattern::parse(curr).map_err(PyValueError::new_err)?,
+            allowed,
+            bonus,
+            name: None,
+        };
+        let mut transitions = self.grammar.transitions().clone();
+        transitions.set(rule);
+        self.grammar = self.grammar.with_transitions(transitions);
+        Ok(())
+    }
+
+    /// Compares the current best analysis of `text` with an annotator's
+    /// `gold` analysis and accumulates the differences for
+    /// `apply_corrections`. Gold surfaces must spell out `text` as
+    /// `analyze` sees it, sanitized and folded (whitespace between them is
+    /// skipped). Returns whether the analysis was already correct.
+    fn record_correction(&mut self, text: String, gold: Vec<Morpheme>) -> PyResult<bool> {
+        let text = self.prepare(&text, false).text();
+        self.check_length(&text)?;
+        let gold = corrections::align(&text, &gold).map_err(PyValueError::new_err)?;
+        let predicted: Vec<Span> = {
+            let view = self.view(None)?;
+            let extra = self.extra_candidates(&text)?;
+            let lattice = Lattice::build(&view, &text, self.build_options(extra.as_ref()));
+            let best = decoder::decode(&lattice, 1);
+            best.first()
+                .map(|path| {
+                    path.edges
+                        .iter()
+                        .map(|&e| &lattice.edges[e])
+                        .filter(|edge| edge.kind == EdgeKind::Dict)
+                        .map(|edge| {
+                            let surface = lattice.surface(edge);
+                            let m = (surface, edge.pos.to_string(), edge.lemma.to_string());
+                            (edge.start, edge.end, m)
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let correct = gold.iter().all(|g| predicted.contains(g))
+            && predicted.iter().all(|p| gold.contains(p));
+        self.corrections.record(&predicted, &gold);
+        Ok(correct)
+    }
+
+    /// Accumulated `(surface, pos, lemma, missed, spurious)` counts.
+    fn pending_corrections(&self) -> Vec<(String, String, String, u32, u32)> {
+        let mut out: Vec<_> = self
+            .corrections
+            .counts
+            .iter()
+            .map(|(m, c)| (m.0.clone(), m.1.clone(), m.2.clone(), c.missed, c.spurious))
+            .collect();
+        out.sort();
+        out
+    }
+
+    /// Turns the recorded corrections into dictionary changes and clears them.
+    ///
+    /// Each entry's cost moves by `step` per net occurrence (missed minus
+    /// spurious); entries seen at least `min_count` times net are changed.
+    /// Missed entries absent from the dictionary are inserted. Returns
+    /// `(added, adjusted)`.
+    #[pyo3(signature = (step=5.0, min_count=1))]
+    fn apply_corrections(&mut self, step: f64, min_count: u32) -> (usize, usize) {
+        let (mut added, mut adjusted) = (0, 0);
+        for ((surface, pos, lemma), delta) in self.corrections.deltas(step, min_count) {
+            let len = surface.chars().count();
+            if len > MAX_WORD_LEN {
+                continue;
+            }
+            let current = self
+                .own_view()
+                .lookup(&surface)
+                .into_iter()
+                .find(|p| p.pos == pos && p.lemma == lemma)
+                .map(|p| p.cost.unwrap_or_else(|| self.grammar.word_cost(len, p.pos)));
+            match current {
+                Some(cost) => {
+                    self.insert_entry(surface, pos, lemma, Some(cost + delta), None);
+                    adjusted += 1;
+                }
+                None if delta < 0.0 => {
+                    let cost = self.grammar.word_cost(len, &pos) + delta;
+                    self.insert_entry(surface, pos, lemma, Some(cost), None);
+                    added += 1;
+                }
+                None => {}
+            }
+        }
+        self.corrections = Corrections::default();
+        (added, adjusted)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Serialization Wrappers
+// -----------------------------------------------------------------------------
+
+/// Saves `trie` to `path` (a `str` or `os.PathLike`) with a checksum that
+/// `load_trie` verifies. Entries, and the patterns of each, are written in
+/// sorted order, so equal dictionaries give byte-identical files.
+#[pyfunction]
+fn save_trie(trie: &RustTrie, path: PathBuf) -> PyResult<()> {
+    serial::write(&trie.snapshot(), &path).map_err(PyValueError::new_err)
+}
+
+fn read_dict(path: &Path) -> PyResult<(TrieData, Option<Legacy>)> {
+    serial::read(path).map_err(|err| match err {
+        LoadError::Io(e) => PyValueError::new_err(e.to_string()),
+        LoadError::Format(msg) => DictFormatError::new_err(format!("{}: {}", path.display(), msg)),
+    })
+}
+
+/// Loads a dictionary saved by `save_trie` from `path`, a `str` or
+/// `os.PathLike`. A truncated or corrupted file raises `DictFormatError`.
+///
+/// Files from before the checksummed format are upgraded in memory; with
+/// `allow_legacy=False` they raise `DictFormatError` naming the detected
+/// format instead (convert them once with `migrate_dict`).
+#[pyfunction]
+#[pyo3(signature = (path, allow_legacy=true))]
+fn load_trie(path: PathBuf, allow_legacy: bool) -> PyResult<RustTrie> {
+    match read_dict(&path)? {
+        (_, Some(legacy)) if !allow_legacy => Err(DictFormatError::new_err(format!(
+            "{}: {}; run migrate_dict(\"{}\", new_path) to convert it",
+            path.display(),
+            legacy.describe(),
+            path.display()
+        ))),
+        (data, legacy) => {
+            let mut trie = RustTrie::from_data(data);
+            let version = serial::file_version(&path).filter(|_| legacy.is_none());
+            trie.source = Some(DictSource::new(&path, "saved", version));
+            Ok(trie)
+        }
+    }
+}
+
+/// `save_trie` to a `bytes` object, serialized straight into it.
+#[pyfunction]
+fn dumps(py: Python, trie: &RustTrie) -> PyResult<Py<PyBytes>> {
+    let data = trie.snapshot();
+    let len = serial::encoded_len(&data).map_err(PyValueError::new_err)?;
+    let bytes = PyBytes::new_with(py, len, |buf| {
+        py.allow_threads(|| serial::encode_into(&data, buf))
+            .map(drop)
+            .map_err(PyValueError::new_err)
+    })?;
+    Ok(bytes.into())
+}
+
+/// How many bytes `dumps_into` needs for `trie`.
+#[pyfunction]
+fn dumps_size(trie: &RustTrie) -> PyResult<usize> {
+    serial::encoded_len(&trie.snapshot()).map_err(PyValueError::new_err)
+}
+
+/// `dumps` into the front of a preallocated writable buffer (`bytearray`,
+/// writable `memoryview` or `mmap`, ...). Returns how many bytes were
+/// written; a buffer smaller than `dumps_size(trie)` raises `ValueError`.
+#[pyfunction]
+fn dumps_into(py: Python, trie: &RustTrie, buffer: &PyAny) -> PyResult<usize> {
+    let data = trie.snapshot();
+    let mut buffer = Buffer::get_mut(buffer)?;
+    let buf = buffer.as_mut_slice();
+    py.allow_threads(|| serial::encode_into(&data, buf))
+        .map_err(PyValueError::new_err)
+}
+
+/// Loads a dictionary from `dumps` output held by any object exporting a
+/// buffer (`bytes`, `memoryview`, `mmap`, ...), read in place. Corrupted
+/// data raises `DictFormatError`.
+#[pyfunction]
+fn loads(py: Python, data: &PyAny) -> PyResult<RustTrie> {
+    let buffer = Buffer::get(data)?;
+    let bytes = buffer.as_slice();
+    match py.allow_threads(|| serial::decode(bytes)) {
+        Ok(data) => Ok(RustTrie::from_data(data)),
+        Err(LoadError::Io(e)) => Err(PyValueError::new_err(e.to_string())),
+        Err(LoadError::Format(msg)) => Err(DictFormatError::new_err(msg)),
+    }
+}
+
+/// Writes `trie` as `save_trie` does to `file`, any binary stream with a
+/// `write` method (an open file, `io.BytesIO`, an object-store upload
+/// stream). Short writes are continued; a stream that would block (`write`
+/// returning None) or reports more bytes than it was given raises
+/// `ValueError`. The stream is not flushed or closed. Returns the number of
+/// bytes written.
+#[pyfunction]
+fn save_trie_to(py: Python, trie: &RustTrie, file: &PyAny) -> PyResult<usize> {
+    let bytes = dumps(py, trie)?;
+    let total = bytes.as_ref(py).as_bytes().len();
+    let view = py
+        .import("builtins")?
+        .getattr("memoryview")?
+        .call1((bytes,))?;
+    let mut written = 0;
+    while written < total {
+        let rest = view.get_item(PySlice::new(py, written as isize, total as isize, 1))?;
+        let n = match file
+            .call_method1("write", (rest,))?
+            .extract::<Option<usize>>()?
+        {
+            // A raw stream in non-blocking mode returns None when it took
+            // nothing; waiting for it is the caller's business
+            None => {
+                return Err(PyValueError::new_err(
+                    "the stream would block; pass a blocking or buffered stream",
+                ))
+            }
+            Some(0) => return Err(PyValueError::new_err("the stream accepted no bytes")),
+            Some(n) if n > total - written => {
+                return Err(PyValueError::new_err(format!(
+                    "write() reported {} bytes written of the {} given",
+                    n,
+                    total - written
+                )))
+            }
+            Some(n) => n,
+        };
+        written += n;
+    }
+    Ok(written)
+}
+
+/// Loads a dictionary from `file`, a binary stream with a `read` method,
+/// read to its end. The stream must hold `save_trie` output in the current
+/// format; corrupted data raises `DictFormatError`.
+#[pyfunction]
+fn load_trie_from(py: Python, file: &PyAny) -> PyResult<RustTrie> {
+    let data = file.call_method0("read")?;
+    loads(py, data)
+}
+
+/// Rewrites the dictionary file `old` in the current format at `new`.
+///
+/// Returns the detected format of `old`, e.g. "legacy headerless format
+/// with entry costs", or "current format" when it was already up to date.
+#[pyfunction]
+fn migrate_dict(old: String, new: String) -> PyResult<&'static str> {
+    let (data, legacy) = read_dict(Path::new(&old))?;
+    serial::write(&data, Path::new(&new)).map_err(PyValueError::new_err)?;
+    Ok(legacy.map_or("current format", Legacy::describe))
+}
+
+/// Writes `trie` in the compiled layout used by `load_compiled`.
+///
+/// `profile="small"` targets devices short of memory: pattern records are
+/// half the size, entry costs are quantized to 255 steps over their range
+/// (entries without a cost keep the length-based default exactly) and
+/// metadata is dropped. With `max_cost`, entries costing more (by their
+/// own cost or the default) are left out, pruning rare words first.
+#[pyfunction]
+#[pyo3(signature = (trie, path, profile="full", max_cost=None))]
+fn compile_trie(
+    trie: &RustTrie,
+    path: String,
+    profile: &str,
+    max_cost: Option<f64>,
+) -> PyResult<()> {
+    let profile = frozen::Profile::parse(profile).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "unknown profile '{}' (expected 'full' or 'small')",
+            profile
+        ))
+    })?;
+    let mut data = trie.snapshot();
+    if let Some(max) = max_cost {
+        Arc::make_mut(&mut data).prune(max, |len, pos| trie.grammar.word_cost(len, pos));
+    }
+    frozen::write(&data, &path, profile).map_err(PyValueError::new_err)
+}
+
+/// Loads a compiled dictionary without deserializing it.
+///
+/// With `shared=True` (the default) the file is memory-mapped with
+/// `MAP_SHARED`, so every worker process that loads the same file — or that
+/// inherits it across `fork()` — reads one physical copy from the page cache.
+/// Inserts land in a small in-memory map above the compiled base.
+///
+/// With `lazy=True` opening reads only the header and the first-syllable
+/// index, so start-up does not depend on the dictionary size; each part of
+/// the dictionary is checked when a lookup first reaches it, and a corrupt
+/// part reads as empty instead of failing the load.
+#[pyfunction]
+#[pyo3(signature = (path, shared=true, lazy=false))]
+fn load_compiled(path: String, shared: bool, lazy: bool) -> PyResult<RustTrie> {
+    let frozen = FrozenDict::open(&path, shared, lazy).map_err(PyValueError::new_err)?;
+    let mut trie = RustTrie::from_data(TrieData::default());
+    trie.source = Some(DictSource::new(
+        Path::new(&path),
+        "compiled",
+        Some(frozen.version()),
+    ));
+    trie.frozen = Some(Arc::new(frozen));
+    Ok(trie)
+}
+
+/// Collapses repeated jamo and stretched vowels (ㅋㅋㅋㅋ → ㅋㅋ, 좋아아아아 → 좋아),
+/// after folding widths and composing Hangul jamo with `width` and `nfc`
+/// (see `RustTrie.set_normalization`).
+///
+/// Returns the normalized text and, for each of its characters plus one
+/// trailing entry, the corresponding character offset in `text`.
+#[pyfunction]
+#[pyo3(signature = (text, max_repeat=2, min_elongation=2, nfc=false, width=false))]
+fn normalize_text(
+    text: String,
+    max_repeat: usize,
+    min_elongation: usize,
+    nfc: bool,
+    width: bool,
+) -> (String, Vec<usize>) {
+    let opts = Folding {
+        width,
+        nfc,
+        repeats: Some((max_repeat, min_elongation)),
+    };
+    let norm = normalize::fold(Normalized::identity(&text), &opts);
+    (norm.text(), norm.map)
+}
+
+/// Strips zero-width characters, BOMs and controls and turns no-break
+/// spaces into spaces, with the defaults of `RustTrie.set_sanitize`.
+///
+/// Returns the cleaned text and, for each of its characters plus one
+/// trailing entry, the corresponding character offset in `text`.
+#[pyfunction]
+fn sanitize_text(text: String) -> (String, Vec<usize>) {
+    let norm = normalize::sanitize(Normalized::identity(&text), &Sanitize::default());
+    (norm.text(), norm.map)
+}
+
+/// Splits `text` into sentences, aware of quoted speech and brackets.
+///
+/// Returns `(start, end, depth)` character spans; depth 0 spans are the
+/// sentences of `text`, deeper ones are sentences inside quotes.
+#[pyfunction]
+fn split_sentences(text: String) -> Vec<(usize, usize, usize)> {
+    sentence::split(&text)
+}
+
+/// Analyzes every text with both tries and returns `(index, text, a, b)`
+/// for each text they analyze differently, the analyses in the regression
+/// form (see `run_regression`); an empty list means no differences. A
+/// configuration is compared by changing a `fork()` of the other trie.
+#[pyfunction]
+#[pyo3(signature = (texts, trie_a, trie_b, layers=None))]
+fn compare(
+    py: Python,
+    texts: Vec<String>,
+    trie_a: &RustTrie,
+    trie_b: &RustTrie,
+    layers: Option<Vec<String>>,
+) -> PyResult<Vec<(usize, String, String, String)>> {
+    trie_a.view(layers.as_deref())?;
+    trie_b.view(layers.as_deref())?;
+    let mut diffs = Vec::new();
+    for (index, text) in texts.into_iter().enumerate() {
+        check_signals()?;
+        let a = trie_a.tagged_line(py, &text, layers.as_deref())?;
+        let b = trie_b.tagged_line(py, &text, layers.as_deref())?;
+        if a != b {
+            diffs.push((index, text, a, b));
+        }
+    }
+    Ok(diffs)
+}
+
+// -----------------------------------------------------------------------------
+// Module Definition
+// -----------------------------------------------------------------------------
+
+// Free-threaded Python (3.13t) calls methods of one object from several
+// threads at once without a lock, so every class must be shareable: caches
+// are `OnceLock`s and `Mutex`es, not `OnceCell`s and `RefCell`s. The module
+// is not declared GIL-free until the pyo3 upgrade (see Cargo.toml).
+const _: fn() = || {
+    fn shared<T: Send + Sync>() {}
+    shared::<RustTrie>();
+    shared::<AnalyzeOptions>();
+    shared::<BatchError>();
+    shared::<BranchingEntropy>();
+    shared::<PatternMatches>();
+    shared::<Segmentations>();
+    shared::<arrow::ArrowResultWriter>();
+};
+
 #[pymodule]
-fn kulim_rust(_py: Python, m: &PyModule) -> PyResult<()> {
+fn kulim_rust(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<RustTrie>()?;
+    m.add_class::<AnalyzeOptions>()?;
+    m.add_class::<Segmentations>()?;
+    m.add_class::<BranchingEntropy>()?;
+    m.add_class::<BatchError>()?;
+    m.add_class::<PatternMatches>()?;
+    m.add_class::<ArrowResultWriter>()?;
+    m.add("AnalysisError", py.get_type::<AnalysisError>())?;
+    m.add("InputTooLongError", py.get_type::<InputTooLongError>())?;
+    m.add("DictFormatError", py.get_type::<DictFormatError>())?;
+    m.add("DictEntryError", py.get_type::<DictEntryError>())?;
     m.add_function(wrap_pyfunction!(save_trie, m)?)?;
     m.add_function(wrap_pyfunction!(load_trie, m)?)?;
+    m.add_function(wrap_pyfunction!(save_trie_to, m)?)?;
+    m.add_function(wrap_pyfunction!(load_trie_from, m)?)?;
+    m.add_function(wrap_pyfunction!(dumps, m)?)?;
+    m.add_function(wrap_pyfunction!(dumps_size, m)?)?;
+    m.add_function(wrap_pyfunction!(dumps_into, m)?)?;
+    m.add_function(wrap_pyfunction!(loads, m)?)?;
+    m.add_function(wrap_pyfunction!(migrate_dict, m)?)?;
+    m.add_function(wrap_pyfunction!(compile_trie, m)?)?;
+    m.add_function(wrap_pyfunction!(load_compiled, m)?)?;
+    m.add_function(wrap_pyfunction!(normalize_text, m)?)?;
+    m.add_function(wrap_pyfunction!(sanitize_text, m)?)?;
+    m.add_function(wrap_pyfunction!(split_sentences, m)?)?;
+    m.add_function(wrap_pyfunction!(compare, m)?)?;
     Ok(())
 }
@@ -0,0 +1,92 @@
+use pyo3::prelude::*;
+
+use crate::candidates::char_offsets;
+use crate::dict::MAX_WORD_LEN;
+use crate::view::{DictView, PatternRef};
+use crate::{Pattern, RustTrie};
+
+// -----------------------------------------------------------------------------
+// Dictionary Match Scanning
+// -----------------------------------------------------------------------------
+
+/// Every dictionary word inside `chars[..end]` starting at or after a
+/// position, in (start, length) order; resumable between calls.
+pub(crate) struct Scanner {
+    text: String,
+    // Byte offset of each character, then the text's length
+    bytes: Vec<usize>,
+    end: usize,
+    // Next candidate: chars[at..at + len]
+    at: usize,
+    len: usize,
+}
+
+impl Scanner {
+    /// Scans the words lying entirely within `chars[start..end]`.
+    pub fn new(text: String, start: usize, end: Option<usize>) -> Self {
+        let bytes = char_offsets(&text);
+        let n = bytes.len() - 1;
+        let end = end.map_or(n, |e| e.min(n));
+        Scanner {
+            text,
+            bytes,
+            end,
+            at: start,
+            len: 1,
+        }
+    }
+
+    pub fn next_match(&mut self, view: &DictView) -> Option<(usize, usize, Vec<Pattern>)> {
+        self.next_match_with(view, |p| (p.pos.to_string(), p.lemma.to_string()))
+    }
+
+    /// The next match with each of its patterns converted by `f`.
+    pub fn next_match_with<'a, T>(
+        &mut self,
+        view: &DictView<'a>,
+        mut f: impl FnMut(PatternRef<'a>) -> T,
+    ) -> Option<(usize, usize, Vec<T>)> {
+        while self.at < self.end {
+            if self.len > MAX_WORD_LEN || self.at + self.len > self.end {
+                self.at += 1;
+                self.len = 1;
+                continue;
+            }
+            let (at, len) = (self.at, self.len);
+            self.len += 1;
+            let sub = &self.text[self.bytes[at]..self.bytes[at + len]];
+            let mut found = Vec::new();
+            view.for_each(sub, |p| found.push(f(p)));
+            if !found.is_empty() {
+                return Some((at, len, found));
+            }
+        }
+        None
+    }
+}
+
+/// Lazy `search_all_patterns`: yields `(start, length, patterns)` one match
+/// at a time. The trie cannot be modified while an iterator is mid-step.
+#[pyclass]
+pub(crate) struct PatternMatches {
+    trie: Py<RustTrie>,
+    scanner: Scanner,
+}
+
+impl PatternMatches {
+    pub fn new(trie: Py<RustTrie>, scanner: Scanner) -> Self {
+        PatternMatches { trie, scanner }
+    }
+}
+
+#[pymethods]
+impl PatternMatches {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> Option<(usize, usize, Vec<Pattern>)> {
+        let trie = self.trie.borrow(py);
+        self.scanner.next_match(&trie.default_view())
+    }
+}
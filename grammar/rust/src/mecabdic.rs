@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::josa;
+use crate::tags::STANDARD_TAGS;
+use crate::view::PatternRef;
+
+// -----------------------------------------------------------------------------
+// mecab-ko-dic CSV Export
+// -----------------------------------------------------------------------------
+//
+// mecab-ko-dic keeps one CSV file per tag (NNG.csv, VV.csv, ...) plus
+// Inflect.csv for contracted and conjugated forms, with rows of
+//
+//     surface,left_id,right_id,cost,POS,semantic,has_jongseong,reading,
+//     type,first_pos,last_pos,expression
+//
+// Context ids come from the tag set (the first tag on the left, the last
+// on the right) and costs from ours, scaled into mecab's short range; both
+// are starting points for mecab-dict-index, which can reassign them from a
+// trained model.
+
+/// mecab cost of an entry with cost 0; ours are centered on it
+const COST_OFFSET: f64 = 3000.0;
+/// mecab cost units per unit of ours
+const COST_SCALE: f64 = 50.0;
+
+/// The context id of `tag`: its place in the TTA tag set, after 0 for
+/// BOS/EOS, and one past the set for other tags.
+fn context_id(tag: &str) -> usize {
+    STANDARD_TAGS
+        .iter()
+        .position(|t| *t == tag)
+        .map_or(STANDARD_TAGS.len() + 1, |i| i + 1)
+}
+
+fn mecab_cost(cost: f64) -> i16 {
+    (COST_OFFSET + cost * COST_SCALE)
+        .round()
+        .clamp(i16::MIN as f64, i16::MAX as f64) as i16
+}
+
+/// A CSV field, quoted when it holds a comma or a quote.
+fn field(s: &str) -> String {
+    if s.contains([',', '"']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// One mecab-ko-dic row for `surface` with entry `pat` at `cost`.
+fn row(surface: &str, pat: &PatternRef, cost: f64) -> String {
+    let tags: Vec<&str> = pat.pos.split('+').collect();
+    let (first, last) = (tags[0], tags[tags.len() - 1]);
+    let hangul = surface.chars().all(|c| josa::batchim(c).is_some());
+    let jongseong = match surface.chars().last().and_then(josa::batchim) {
+        Some(0) => "F",
+        Some(_) => "T",
+        None => "*",
+    };
+    let reading = if hangul { surface } else { "*" };
+    let (kind, first_pos, last_pos, expression) = if tags.len() > 1 {
+        // A compound entry's lemma lists its morphemes: 사귀+었+다
+        let forms: Vec<&str> = pat.lemma.split('+').collect();
+        let expression = if forms.len() == tags.len() {
+            let parts: Vec<String> = forms
+                .iter()
+                .zip(&tags)
+                .map(|(f, t)| format!("{}/{}/*", f, t))
+                .collect();
+            parts.join("+")
+        } else {
+            "*".to_string()
+        };
+        ("Inflect", first, last, expression)
+    } else {
+        ("*", "*", "*", "*".to_string())
+    };
+    [
+        field(surface),
+        context_id(first).to_string(),
+        context_id(last).to_string(),
+        mecab_cost(cost).to_string(),
+        field(pat.pos),
+        "*".to_string(),
+        jongseong.to_string(),
+        field(reading),
+        kind.to_string(),
+        first_pos.to_string(),
+        last_pos.to_string(),
+        field(&expression),
+    ]
+    .join(",")
+}
+
+/// Writes `entries` as mecab-ko-dic CSV files in `dir`, creating it: one
+/// file per tag, Inflect.csv for compound tags and Other.csv for tags that
+/// are not plain names. `default_cost` prices entries without a cost of
+/// their own. Returns the file names and their row counts.
+pub(crate) fn export<'a>(
+    dir: &Path,
+    entries: impl IntoIterator<Item = (&'a str, PatternRef<'a>)>,
+    default_cost: impl Fn(usize, &str) -> f64,
+) -> Result<Vec<(String, usize)>, String> {
+    let mut files: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (surface, pat) in entries {
+        let cost = pat
+            .cost
+            .unwrap_or_else(|| default_cost(surface.chars().count(), pat.pos));
+        let name = match pat.pos {
+            pos if pos.contains('+') => "Inflect",
+            // A tag is a file name; anything else goes to one catch-all file
+            pos if pos.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => pos,
+            _ => "Other",
+        };
+        files
+            .entry(format!("{}.csv", name))
+            .or_default()
+            .push(row(surface, &pat, cost));
+    }
+    fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let mut written = Vec::with_capacity(files.len());
+    for (name, rows) in files {
+        let path = dir.join(&name);
+        let fail = |e: std::io::Error| format!("{}: {}", path.display(), e);
+        let mut w = BufWriter::new(File::create(&path).map_err(fail)?);
+        for r in &rows {
+            writeln!(w, "{}", r).map_err(fail)?;
+        }
+        w.flush().map_err(fail)?;
+        written.push((name, rows.len()));
+    }
+    Ok(written)
+}
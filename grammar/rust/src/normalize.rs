@@ -0,0 +1,292 @@
+// -----------------------------------------------------------------------------
+// Input Normalization
+// -----------------------------------------------------------------------------
+//
+// Every step rewrites a character sequence and records, for each output
+// character, the span of input characters it stands for. Characters dropped
+// by a folding step (stretched vowels) join the span before them; characters
+// that are merely removed (spaces) belong to no span.
+
+const HANGUL_BASE: u32 = 0xAC00;
+const HANGUL_LAST: u32 = 0xD7A3;
+const ONSET_IEUNG: u32 = 11;
+// Conjoining jamo: leading consonants, vowels and trailing consonants
+const JAMO_L: u32 = 0x1100;
+const JAMO_V: u32 = 0x1161;
+const JAMO_T: u32 = 0x11A7;
+
+/// Normalized text plus its mapping back to the original characters.
+#[derive(Clone, Debug)]
+pub(crate) struct Normalized {
+    pub chars: Vec<char>,
+    // map[i]: original index of chars[i]; map[len] is the original length
+    pub map: Vec<usize>,
+    // ends[i]: original end of the span chars[i] stands for
+    ends: Vec<usize>,
+}
+
+impl Normalized {
+    pub fn identity(text: &str) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        let map = (0..=chars.len()).collect();
+        let ends = (1..=chars.len()).collect();
+        Normalized { chars, map, ends }
+    }
+
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    /// Original character span of the normalized span [start, end).
+    pub fn original_span(&self, start: usize, end: usize) -> (usize, usize) {
+        let from = self.map[start];
+        (
+            from,
+            if end > start {
+                self.ends[end - 1]
+            } else {
+                from
+            },
+        )
+    }
+
+    /// Applies a step that keeps or drops characters by index; with `fold`,
+    /// dropped characters extend the span of the kept one before them.
+    fn retain(self, keep: &[bool], fold: bool) -> Self {
+        let mut chars = Vec::with_capacity(self.chars.len());
+        let mut map = Vec::with_capacity(self.map.len());
+        let mut ends: Vec<usize> = Vec::with_capacity(self.ends.len());
+        for (((&c, &orig), &end), &k) in self.chars.iter().zip(&self.map).zip(&self.ends).zip(keep)
+        {
+            if k {
+                chars.push(c);
+                map.push(orig);
+                ends.push(end);
+            } else if fold {
+                if let Some(last) = ends.last_mut() {
+                    *last = end;
+                }
+            }
+        }
+        map.push(self.map[self.chars.len()]);
+        Normalized { chars, map, ends }
+    }
+}
+
+fn is_compat_jamo(c: char) -> bool {
+    ('\u{3131}'..='\u{318E}').contains(&c)
+}
+
+/// (onset, vowel, coda) indices of a precomposed Hangul syllable.
+fn decompose(c: char) -> Option<(u32, u32, u32)> {
+    let code = c as u32;
+    if !(HANGUL_BASE..=HANGUL_LAST).contains(&code) {
+        return None;
+    }
+    let idx = code - HANGUL_BASE;
+    Some((idx / (21 * 28), (idx / 28) % 21, idx % 28))
+}
+
+/// An open syllable with a silent onset that only stretches the vowel of
+/// the syllable before it (the second 아 in 좋아아).
+fn stretches(prev: char, c: char) -> bool {
+    match (decompose(prev), decompose(c)) {
+        (Some((_, prev_vowel, _)), Some((ONSET_IEUNG, vowel, 0))) => vowel == prev_vowel,
+        _ => false,
+    }
+}
+
+/// Collapses laughter and other repeated jamo (ㅋㅋㅋㅋ → ㅋㅋ) to at most
+/// `max_repeat`, and drops runs of at least `min_elongation` vowel-stretching
+/// syllables (좋아아아아 → 좋아).
+pub(crate) fn collapse_repeats(
+    input: Normalized,
+    max_repeat: usize,
+    min_elongation: usize,
+) -> Normalized {
+    let chars = &input.chars;
+    let n = chars.len();
+    let mut keep = vec![true; n];
+
+    let mut i = 0;
+    while i < n {
+        let c = chars[i];
+        let run = chars[i..].iter().take_while(|&&x| x == c).count();
+        if is_compat_jamo(c) {
+            if run > max_repeat.max(1) {
+                keep[i + max_repeat.max(1)..i + run].fill(false);
+            }
+            i += run;
+            continue;
+        }
+        let stretch = (i + 1..n)
+            .take_while(|&k| stretches(chars[k - 1], chars[k]))
+            .count();
+        if stretch >= min_elongation.max(1) {
+            keep[i + 1..=i + stretch].fill(false);
+        }
+        i += stretch + 1;
+    }
+
+    input.retain(&keep, true)
+}
+
+/// Composes conjoining Hangul jamo into precomposed syllables (ᄒ ᅡ ᆫ →
+/// 한), the part of NFC that Korean text needs; each syllable stands for
+/// the span of its jamo. Other characters are left as they are.
+pub(crate) fn compose_hangul(mut input: Normalized) -> Normalized {
+    let n = input.chars.len();
+    let mut keep = vec![true; n];
+    let index = |c: char, base: u32, count: u32| {
+        let code = c as u32;
+        (base..base + count).contains(&code).then(|| code - base)
+    };
+    let mut i = 0;
+    while i < n {
+        let c = input.chars[i];
+        let next = |k: usize| input.chars.get(i + k).copied().unwrap_or('\0');
+        // A leading consonant and vowel, or a syllable without a coda, that
+        // the next jamo continues
+        let (mut code, mut used) = match (index(c, JAMO_L, 19), index(next(1), JAMO_V, 21)) {
+            (Some(l), Some(v)) => (HANGUL_BASE + (l * 21 + v) * 28, 2),
+            _ => match decompose(c) {
+                Some((_, _, 0)) => (c as u32, 1),
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            },
+        };
+        if let Some(t) = index(next(used), JAMO_T + 1, 27) {
+            code += t + 1;
+            used += 1;
+        }
+        input.chars[i] = char::from_u32(code).unwrap_or(c);
+        keep[i + 1..i + used].fill(false);
+        i += used;
+    }
+    input.retain(&keep, true)
+}
+
+/// Folds fullwidth ASCII (Ａ１！) to ASCII, the ideographic space to a
+/// space and halfwidth Hangul jamo (ﾡ) to compatibility jamo, one
+/// character for one.
+pub(crate) fn fold_width(mut input: Normalized) -> Normalized {
+    for c in input.chars.iter_mut() {
+        let code = *c as u32;
+        let folded = match code {
+            0xFF01..=0xFF5E => code - 0xFEE0,
+            0x3000 => 0x20,
+            // Filler, then consonants and the vowel rows, with gaps between
+            0xFFA0 => 0x3164,
+            0xFFA1..=0xFFBE => code - 0xFFA1 + 0x3131,
+            0xFFC2..=0xFFC7 => code - 0xFFC2 + 0x314F,
+            0xFFCA..=0xFFCF => code - 0xFFCA + 0x3155,
+            0xFFD2..=0xFFD7 => code - 0xFFD2 + 0x315B,
+            0xFFDA..=0xFFDC => code - 0xFFDA + 0x3161,
+            _ => continue,
+        };
+        *c = char::from_u32(folded).unwrap_or(*c);
+    }
+    input
+}
+
+/// The folding steps `set_normalization` turns on, applied in this order.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Folding {
+    pub width: bool,
+    pub nfc: bool,
+    /// (max_repeat, min_elongation) for `collapse_repeats`
+    pub repeats: Option<(usize, usize)>,
+}
+
+pub(crate) fn fold(mut input: Normalized, opts: &Folding) -> Normalized {
+    if opts.width {
+        input = fold_width(input);
+    }
+    if opts.nfc {
+        input = compose_hangul(input);
+    }
+    if let Some((max_repeat, min_elongation)) = opts.repeats {
+        input = collapse_repeats(input, max_repeat, min_elongation);
+    }
+    input
+}
+
+/// Removes all whitespace; the removed characters belong to no span.
+pub(crate) fn strip_spaces(input: Normalized) -> Normalized {
+    let keep: Vec<bool> = input.chars.iter().map(|c| !c.is_whitespace()).collect();
+    input.retain(&keep, false)
+}
+
+/// What sanitization does with one class of invisible characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+    Keep,
+    Strip,
+    /// Replace with an ordinary space, isolating the text on either side
+    Space,
+}
+
+impl Action {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "keep" => Some(Action::Keep),
+            "strip" => Some(Action::Strip),
+            "space" => Some(Action::Space),
+            _ => None,
+        }
+    }
+}
+
+/// Per-class actions for invisible and control characters.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Sanitize {
+    /// ZWSP, ZWNJ, ZWJ, word joiner and soft hyphen
+    pub zero_width: Action,
+    /// U+FEFF, wherever it occurs
+    pub bom: Action,
+    /// No-break, narrow no-break and figure spaces
+    pub nbsp: Action,
+    /// C0/C1 controls other than tab, newline and carriage return
+    pub control: Action,
+}
+
+impl Default for Sanitize {
+    fn default() -> Self {
+        Sanitize {
+            zero_width: Action::Strip,
+            bom: Action::Strip,
+            nbsp: Action::Space,
+            control: Action::Strip,
+        }
+    }
+}
+
+impl Sanitize {
+    fn action(&self, c: char) -> Action {
+        match c {
+            '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{00AD}' => self.zero_width,
+            '\u{FEFF}' => self.bom,
+            '\u{00A0}' | '\u{202F}' | '\u{2007}' => self.nbsp,
+            '\t' | '\n' | '\r' => Action::Keep,
+            c if c.is_control() => self.control,
+            _ => Action::Keep,
+        }
+    }
+}
+
+/// Strips invisible characters or turns them into spaces, per `opts`.
+/// Stripped characters belong to no span; a replacement space keeps the
+/// span of the character it replaces.
+pub(crate) fn sanitize(mut input: Normalized, opts: &Sanitize) -> Normalized {
+    let mut keep = vec![true; input.chars.len()];
+    for (c, k) in input.chars.iter_mut().zip(keep.iter_mut()) {
+        match opts.action(*c) {
+            Action::Keep => {}
+            Action::Strip => *k = false,
+            Action::Space => *c = ' ',
+        }
+    }
+    input.retain(&keep, false)
+}
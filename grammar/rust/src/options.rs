@@ -0,0 +1,268 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::grammar::TagPattern;
+use crate::output;
+use crate::Located;
+
+// -----------------------------------------------------------------------------
+// Per-Call Analysis Options
+// -----------------------------------------------------------------------------
+//
+// The keyword arguments of the analyze methods gathered in one object that
+// is checked once when built, so that a caller can keep a configuration and
+// pass it to any of them as `options=`. Options a method has no use for are
+// ignored.
+
+/// Tags morphemes are reported in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Tagset {
+    /// The analyzer's own tags (Sejong)
+    Sejong,
+    /// Universal POS, as in `to_conllu`
+    Ud,
+}
+
+impl Tagset {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "sejong" => Some(Tagset::Sejong),
+            "ud" => Some(Tagset::Ud),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Tagset::Sejong => "sejong",
+            Tagset::Ud => "ud",
+        }
+    }
+}
+
+/// Options for `analyze`, `analyze_with_offsets`, `analyze_nbest`,
+/// `analyze_batch`, `analyze_readings`, `analyze_parallel`,
+/// `analyze_uncertain`, `tokenize`, `analyze_pretokenize`,
+/// `analyze_batch_jsonl` and `analyze_file`, all given by keyword:
+///
+/// - `layers`, `ignore_spaces`, `timeout_ms`: as for `analyze`
+/// - `normalize`, `max_repeat`, `min_elongation`: as for
+///   `analyze_with_offsets`
+/// - `nbest`: the number of analyses `analyze_nbest` returns
+/// - `pos_filter`: only morphemes whose tag matches one of these (tags, or
+///   prefixes ending in `*`) are returned
+/// - `offsets`: morphemes carry `(start, end)` character offsets into the
+///   input, as from `analyze_with_offsets`
+/// - `strict`: a chunk no analysis crosses raises `AnalysisError` and every
+///   analysis is checked to spell out its input, whatever
+///   `set_dead_end_fallback` and `set_verify_round_trip` say
+/// - `tagset`: `"sejong"` (the analyzer's tags) or `"ud"` (universal POS);
+///   `pos_filter` still matches the analyzer's tags
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub(crate) struct AnalyzeOptions {
+    #[pyo3(get)]
+    pub layers: Option<Vec<String>>,
+    #[pyo3(get)]
+    pub ignore_spaces: bool,
+    #[pyo3(get)]
+    pub timeout_ms: Option<u64>,
+    #[pyo3(get)]
+    pub normalize: bool,
+    #[pyo3(get)]
+    pub max_repeat: usize,
+    #[pyo3(get)]
+    pub min_elongation: usize,
+    #[pyo3(get)]
+    pub nbest: usize,
+    #[pyo3(get)]
+    pub pos_filter: Option<Vec<String>>,
+    #[pyo3(get)]
+    pub offsets: bool,
+    #[pyo3(get)]
+    pub strict: bool,
+    tagset: Tagset,
+    filter: Vec<TagPattern>,
+}
+
+impl Default for AnalyzeOptions {
+    fn default() -> Self {
+        AnalyzeOptions {
+            layers: None,
+            ignore_spaces: false,
+            timeout_ms: None,
+            normalize: false,
+            max_repeat: 2,
+            min_elongation: 2,
+            nbest: 5,
+            pos_filter: None,
+            offsets: false,
+            strict: false,
+            tagset: Tagset::Sejong,
+            filter: Vec::new(),
+        }
+    }
+}
+
+impl AnalyzeOptions {
+    /// The defaults, with `layers`.
+    pub fn with_layers(layers: Option<Vec<String>>) -> Self {
+        AnalyzeOptions {
+            layers,
+            ..Default::default()
+        }
+    }
+
+    /// Whether a morpheme tagged `pos` is returned.
+    pub fn keeps(&self, pos: &str) -> bool {
+        self.filter.is_empty() || self.filter.iter().any(|f| f.matches(pos))
+    }
+
+    /// `pos` in the tagset asked for.
+    pub fn tag(&self, pos: String) -> String {
+        match self.tagset {
+            Tagset::Sejong => pos,
+            Tagset::Ud => output::upos(&pos).to_string(),
+        }
+    }
+
+    /// The morphemes of `morphs` that are kept, in the tagset asked for.
+    pub fn finish(&self, morphs: Vec<Located>) -> Vec<Located> {
+        morphs
+            .into_iter()
+            .filter(|(_, pos, ..)| self.keeps(pos))
+            .map(|(surface, pos, lemma, start, end)| (surface, self.tag(pos), lemma, start, end))
+            .collect()
+    }
+
+    /// `morphs` as Python tuples, with offsets when `offsets` is set.
+    pub fn to_py(&self, py: Python, morphs: Vec<Located>) -> PyObject {
+        if self.offsets {
+            return morphs.into_py(py);
+        }
+        morphs
+            .into_iter()
+            .map(|(surface, pos, lemma, _, _)| (surface, pos, lemma))
+            .collect::<Vec<_>>()
+            .into_py(py)
+    }
+}
+
+#[pymethods]
+impl AnalyzeOptions {
+    #[new]
+    #[pyo3(signature = (
+        *,
+        layers=None,
+        ignore_spaces=false,
+        timeout_ms=None,
+        normalize=false,
+        max_repeat=2,
+        min_elongation=2,
+        nbest=5,
+        pos_filter=None,
+        offsets=false,
+        strict=false,
+        tagset="sejong"
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        layers: Option<Vec<String>>,
+        ignore_spaces: bool,
+        timeout_ms: Option<u64>,
+        normalize: bool,
+        max_repeat: usize,
+        min_elongation: usize,
+        nbest: usize,
+        pos_filter: Option<Vec<String>>,
+        offsets: bool,
+        strict: bool,
+        tagset: &str,
+    ) -> PyResult<Self> {
+        if nbest == 0 {
+            return Err(PyValueError::new_err("nbest must be at least 1"));
+        }
+        if max_repeat == 0 || min_elongation == 0 {
+            return Err(PyValueError::new_err(
+                "max_repeat and min_elongation must be at least 1",
+            ));
+        }
+        let tagset = Tagset::parse(tagset).ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "unknown tagset '{}' (expected 'sejong' or 'ud')",
+                tagset
+            ))
+        })?;
+        let filter = pos_filter
+            .iter()
+            .flatten()
+            .map(|p| TagPattern::parse(p))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(PyValueError::new_err)?;
+        if pos_filter.as_ref().is_some_and(|p| p.is_empty()) {
+            return Err(PyValueError::new_err(
+                "pos_filter is empty; pass None to keep every morpheme",
+            ));
+        }
+        Ok(AnalyzeOptions {
+            layers,
+            ignore_spaces,
+            timeout_ms,
+            normalize,
+            max_repeat,
+            min_elongation,
+            nbest,
+            pos_filter,
+            offsets,
+            strict,
+            tagset,
+            filter,
+        })
+    }
+
+    #[getter]
+    fn tagset(&self) -> &'static str {
+        self.tagset.name()
+    }
+
+    fn __repr__(&self) -> String {
+        let default = AnalyzeOptions::default();
+        let mut fields = Vec::new();
+        if self.layers != default.layers {
+            fields.push(format!(
+                "layers={:?}",
+                self.layers.as_deref().unwrap_or_default()
+            ));
+        }
+        let flags = [
+            ("ignore_spaces", self.ignore_spaces),
+            ("normalize", self.normalize),
+            ("offsets", self.offsets),
+            ("strict", self.strict),
+        ];
+        for (name, on) in flags {
+            if on {
+                fields.push(format!("{}=True", name));
+            }
+        }
+        if let Some(ms) = self.timeout_ms {
+            fields.push(format!("timeout_ms={}", ms));
+        }
+        if (self.max_repeat, self.min_elongation) != (default.max_repeat, default.min_elongation) {
+            fields.push(format!(
+                "max_repeat={}, min_elongation={}",
+                self.max_repeat, self.min_elongation
+            ));
+        }
+        if self.nbest != default.nbest {
+            fields.push(format!("nbest={}", self.nbest));
+        }
+        if let Some(filter) = &self.pos_filter {
+            fields.push(format!("pos_filter={:?}", filter));
+        }
+        if self.tagset != default.tagset {
+            fields.push(format!("tagset={:?}", self.tagset.name()));
+        }
+        format!("AnalyzeOptions({})", fields.join(", "))
+    }
+}
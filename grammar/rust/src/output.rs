@@ -0,0 +1,263 @@
+use std::fmt::Write;
+
+use crate::josa;
+use crate::Located;
+
+// -----------------------------------------------------------------------------
+// Output Formats
+// -----------------------------------------------------------------------------
+//
+// Formatters over analyses with character offsets into the analyzed text.
+// Whitespace morphemes only separate eojeols and are never printed.
+
+/// A sentence of the text: its `(start, end)` character span and morphemes.
+pub(crate) struct Sentence {
+    pub start: usize,
+    pub end: usize,
+    pub morphs: Vec<Located>,
+}
+
+fn is_space(m: &Located) -> bool {
+    m.0.chars().all(char::is_whitespace)
+}
+
+/// Groups the morphemes of a sentence into eojeols: a new one starts after
+/// whitespace in the text.
+pub(crate) fn eojeols<'m>(chars: &[char], morphs: &'m [Located]) -> Vec<Vec<&'m Located>> {
+    let mut out: Vec<Vec<&Located>> = Vec::new();
+    let mut last_end = None;
+    for m in morphs.iter().filter(|m| !is_space(m)) {
+        let spaced = last_end
+            .is_none_or(|end: usize| chars[end.min(m.3)..m.3].iter().any(|c| c.is_whitespace()));
+        match out.last_mut() {
+            Some(eojeol) if !spaced => eojeol.push(m),
+            _ => out.push(vec![m]),
+        }
+        last_end = Some(m.4);
+    }
+    out
+}
+
+/// Universal POS of a Sejong tag; a compound tag (VV+EP) maps by its head.
+pub(crate) fn upos(tag: &str) -> &'static str {
+    let head = tag.split('+').next().unwrap_or(tag);
+    match head {
+        "NNG" | "NNB" | "XR" => "NOUN",
+        "NNP" => "PROPN",
+        "NR" | "SN" => "NUM",
+        "NP" => "PRON",
+        "VV" => "VERB",
+        "VA" => "ADJ",
+        "VX" | "VCP" | "VCN" => "AUX",
+        "MM" => "DET",
+        "MAG" => "ADV",
+        "MAJ" | "JC" => "CCONJ",
+        "IC" => "INTJ",
+        "SF" | "SP" | "SS" | "SE" | "SO" => "PUNCT",
+        "SW" => "SYM",
+        t if t.starts_with('J') => "ADP",
+        t if t.starts_with('E') => "PART",
+        _ => "X",
+    }
+}
+
+/// CoNLL-U with one token per morpheme. MISC gives the 1-based `Eojeol` a
+/// token belongs to, its `TokenRange` in characters of the whole text and
+/// `SpaceAfter=No` inside an eojeol. HEAD and DEPREL are left empty (`_`).
+pub(crate) fn conllu(chars: &[char], sentences: &[Sentence]) -> String {
+    let mut out = String::new();
+    for (i, sentence) in sentences.iter().enumerate() {
+        let text: String = chars[sentence.start..sentence.end]
+            .iter()
+            .map(|&c| if c == '\n' || c == '\r' { ' ' } else { c })
+            .collect();
+        let _ = writeln!(out, "# sent_id = {}\n# text = {}", i + 1, text);
+        let mut id = 0;
+        for (e, eojeol) in eojeols(chars, &sentence.morphs).iter().enumerate() {
+            for (k, (surface, pos, lemma, start, end)) in eojeol.iter().enumerate() {
+                id += 1;
+                let lemma = if lemma == "UNKNOWN" {
+                    "_"
+                } else {
+                    lemma.as_str()
+                };
+                let mut misc = format!("Eojeol={}|TokenRange={}:{}", e + 1, start, end);
+                if k + 1 < eojeol.len() {
+                    misc.push_str("|SpaceAfter=No");
+                }
+                let _ = writeln!(
+                    out,
+                    "{}\t{}\t{}\t{}\t{}\t_\t_\t_\t_\t{}",
+                    id,
+                    surface,
+                    lemma,
+                    upos(pos),
+                    pos,
+                    misc
+                );
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Sejong-style tagged text: one eojeol per line as
+/// `eojeol<TAB>morph/POS+morph/POS`, with the eojeol as written in the text
+/// and a blank line after each sentence.
+pub(crate) fn sejong(chars: &[char], sentences: &[Sentence]) -> String {
+    let mut out = String::new();
+    for sentence in sentences {
+        for eojeol in eojeols(chars, &sentence.morphs) {
+            let (start, end) = (eojeol[0].3, eojeol[eojeol.len() - 1].4);
+            out.extend(&chars[start..end]);
+            out.push('\t');
+            push_tagged(&mut out, &eojeol);
+            out.push('\n');
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Appends the morphemes of an eojeol as `morph/POS+morph/POS`.
+fn push_tagged(out: &mut String, eojeol: &[&Located]) {
+    for (k, m) in eojeol.iter().enumerate() {
+        if k > 0 {
+            out.push('+');
+        }
+        let _ = write!(out, "{}/{}", m.0, m.1);
+    }
+}
+
+/// An analysis on one line: the Sejong form of each eojeol, separated by
+/// spaces.
+pub(crate) fn tagged_line(chars: &[char], morphs: &[Located]) -> String {
+    let mut out = String::new();
+    for (e, eojeol) in eojeols(chars, morphs).iter().enumerate() {
+        if e > 0 {
+            out.push(' ');
+        }
+        push_tagged(&mut out, eojeol);
+    }
+    out
+}
+
+/// `chars` as HTML with each `(start, end)` span wrapped in `<tag>`;
+/// spans are sorted and disjoint, and every character is escaped.
+pub(crate) fn highlight(chars: &[char], spans: &[(usize, usize)], tag: &str) -> String {
+    let mut out = String::with_capacity(chars.len() + spans.len() * (tag.len() * 2 + 5));
+    let mut spans = spans.iter().peekable();
+    for (i, &c) in chars.iter().enumerate() {
+        if spans.peek().is_some_and(|&&(start, _)| start == i) {
+            let _ = write!(out, "<{}>", tag);
+        }
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#x27;"),
+            c => out.push(c),
+        }
+        if spans.peek().is_some_and(|&&(_, end)| end == i + 1) {
+            let _ = write!(out, "</{}>", tag);
+            spans.next();
+        }
+    }
+    out
+}
+
+/// mecab-ko output: one morpheme per line as `surface<TAB>POS,semantic,
+/// has_jongseong,reading,type,first_pos,last_pos,expression` and `EOS`
+/// after each sentence. What KULIM does not know is `*`: the semantic class,
+/// the expression, and the jongseong and reading of non-Hangul surfaces.
+/// A compound tag (VV+EP) is an `Inflect` from its first to its last tag.
+pub(crate) fn mecab(sentences: &[Sentence]) -> String {
+    let mut out = String::new();
+    for sentence in sentences {
+        for m in sentence.morphs.iter().filter(|m| !is_space(m)) {
+            let (surface, pos) = (&m.0, &m.1);
+            let hangul = surface.chars().all(|c| josa::batchim(c).is_some());
+            let jongseong = match surface.chars().last().and_then(josa::batchim) {
+                Some(0) => "F",
+                Some(_) => "T",
+                None => "*",
+            };
+            let reading = if hangul { surface.as_str() } else { "*" };
+            let (kind, first, last) = match (pos.split('+').next(), pos.rsplit('+').next()) {
+                (Some(first), Some(last)) if pos.contains('+') => ("Inflect", first, last),
+                _ => ("*", "*", "*"),
+            };
+            let _ = writeln!(
+                out,
+                "{}\t{},*,{},{},{},{},{},*",
+                surface, pos, jongseong, reading, kind, first, last
+            );
+        }
+        out.push_str("EOS\n");
+    }
+    out
+}
+
+/// Appends `s` as a JSON string literal.
+fn json_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// One JSON Lines record: `{"<key>": id, "text": ..., "cost": ...,
+/// "morphemes": [{"surface", "pos", "lemma", "start", "end"}, ...]}`,
+/// newline-terminated. Offsets are characters of `text`.
+pub(crate) fn jsonl_document(
+    out: &mut String,
+    key: &str,
+    id: usize,
+    text: &str,
+    morphs: &[Located],
+    cost: f64,
+) {
+    let _ = write!(out, "{{\"{}\":{},\"text\":", key, id);
+    json_str(out, text);
+    if cost.is_finite() {
+        let _ = write!(out, ",\"cost\":{}", cost);
+    } else {
+        out.push_str(",\"cost\":null");
+    }
+    out.push_str(",\"morphemes\":[");
+    for (i, m) in morphs.iter().filter(|m| !is_space(m)).enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str("{\"surface\":");
+        json_str(out, &m.0);
+        out.push_str(",\"pos\":");
+        json_str(out, &m.1);
+        out.push_str(",\"lemma\":");
+        json_str(out, &m.2);
+        let _ = write!(out, ",\"start\":{},\"end\":{}}}", m.3, m.4);
+    }
+    out.push_str("]}\n");
+}
+
+/// A JSON Lines record for a document that failed.
+pub(crate) fn jsonl_error(out: &mut String, key: &str, id: usize, kind: &str, message: &str) {
+    let _ = write!(out, "{{\"{}\":{},\"error\":{{\"kind\":", key, id);
+    json_str(out, kind);
+    out.push_str(",\"message\":");
+    json_str(out, message);
+    out.push_str("}}\n");
+}
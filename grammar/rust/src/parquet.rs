@@ -0,0 +1,462 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::{sentence, AnalysisError, Located, RustTrie};
+
+// -----------------------------------------------------------------------------
+// Parquet Output
+// -----------------------------------------------------------------------------
+//
+// Corpus analyses written as Parquet, one row per morpheme, so that a
+// corpus run goes straight into Spark or pandas. Every column is required
+// and PLAIN encoded without compression, one data page per column chunk:
+//
+//     "PAR1" column chunks... FileMetaData len:u32 "PAR1"
+//
+// Page headers and the FileMetaData are Thrift structs in the compact
+// protocol (`Thrift` below): a field header byte holds the id delta and the
+// type, integers are zigzag varints, and every struct ends with a 0 byte.
+
+const MAGIC: &[u8] = b"PAR1";
+// Thrift compact types
+const T_I32: u8 = 5;
+const T_I64: u8 = 6;
+const T_BINARY: u8 = 8;
+const T_LIST: u8 = 9;
+const T_STRUCT: u8 = 12;
+// Parquet enums
+const TYPE_INT64: i32 = 2;
+const TYPE_BYTE_ARRAY: i32 = 6;
+const REPETITION_REQUIRED: i32 = 0;
+const CONVERTED_UTF8: i32 = 0;
+const ENCODING_PLAIN: i32 = 0;
+const ENCODING_RLE: i32 = 3;
+const CODEC_UNCOMPRESSED: i32 = 0;
+const PAGE_DATA: i32 = 0;
+
+/// An output column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Column {
+    /// 1-based line of the document in its input file
+    DocId,
+    /// Top-level sentence of the document, from 0
+    SentenceId,
+    Surface,
+    Pos,
+    Lemma,
+    /// Character offsets into the document
+    Start,
+    End,
+}
+
+impl Column {
+    pub const ALL: [Column; 7] = [
+        Column::DocId,
+        Column::SentenceId,
+        Column::Surface,
+        Column::Pos,
+        Column::Lemma,
+        Column::Start,
+        Column::End,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Column::DocId => "doc_id",
+            Column::SentenceId => "sentence_id",
+            Column::Surface => "surface",
+            Column::Pos => "pos",
+            Column::Lemma => "lemma",
+            Column::Start => "start",
+            Column::End => "end",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Column::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    fn is_string(self) -> bool {
+        matches!(self, Column::Surface | Column::Pos | Column::Lemma)
+    }
+}
+
+/// A Thrift struct in the compact protocol.
+#[derive(Default)]
+struct Thrift {
+    out: Vec<u8>,
+    // Last field id of the current struct, and of the enclosing ones
+    last: i16,
+    outer: Vec<i16>,
+}
+
+impl Thrift {
+    fn varint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.out.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        self.out.push(v as u8);
+    }
+
+    fn zigzag(&mut self, v: i64) {
+        self.varint(((v << 1) ^ (v >> 63)) as u64);
+    }
+
+    fn field(&mut self, id: i16, ty: u8) {
+        match id - self.last {
+            delta @ 1..=15 => self.out.push((delta as u8) << 4 | ty),
+            _ => {
+                self.out.push(ty);
+                self.zigzag(i64::from(id));
+            }
+        }
+        self.last = id;
+    }
+
+    fn i32(&mut self, id: i16, v: i32) {
+        self.field(id, T_I32);
+        self.zigzag(i64::from(v));
+    }
+
+    fn i64(&mut self, id: i16, v: i64) {
+        self.field(id, T_I64);
+        self.zigzag(v);
+    }
+
+    fn string(&mut self, id: i16, s: &str) {
+        self.field(id, T_BINARY);
+        self.varint(s.len() as u64);
+        self.out.extend_from_slice(s.as_bytes());
+    }
+
+    fn list(&mut self, id: i16, elem: u8, len: usize) {
+        self.field(id, T_LIST);
+        match len {
+            0..=14 => self.out.push((len as u8) << 4 | elem),
+            _ => {
+                self.out.push(0xF0 | elem);
+                self.varint(len as u64);
+            }
+        }
+    }
+
+    /// Starts a struct field (with `Some(id)`) or a struct list element.
+    fn begin(&mut self, id: Option<i16>) {
+        if let Some(id) = id {
+            self.field(id, T_STRUCT);
+        }
+        self.outer.push(self.last);
+        self.last = 0;
+    }
+
+    fn end(&mut self) {
+        self.out.push(0);
+        self.last = self.outer.pop().unwrap_or(0);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.out.push(0);
+        self.out
+    }
+}
+
+/// A row: one morpheme of a sentence of a document.
+pub(crate) struct Row<'a> {
+    pub doc_id: usize,
+    pub sentence_id: usize,
+    pub morph: &'a Located,
+}
+
+/// Where a column chunk lies in the file.
+struct Chunk {
+    offset: u64,
+    size: usize,
+}
+
+/// A Parquet file being written, a row group at a time.
+pub(crate) struct ParquetFile {
+    out: BufWriter<File>,
+    pos: u64,
+    columns: Vec<Column>,
+    // PLAIN-encoded values of the row group being filled, per column
+    pages: Vec<Vec<u8>>,
+    rows: usize,
+    group_rows: usize,
+    groups: Vec<(Vec<Chunk>, usize)>,
+}
+
+impl ParquetFile {
+    pub fn create(path: &Path, columns: Vec<Column>, group_rows: usize) -> io::Result<Self> {
+        let mut file = ParquetFile {
+            out: BufWriter::new(File::create(path)?),
+            pos: 0,
+            pages: vec![Vec::new(); columns.len()],
+            columns,
+            rows: 0,
+            group_rows: group_rows.max(1),
+            groups: Vec::new(),
+        };
+        file.write(MAGIC)?;
+        Ok(file)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.pos += bytes.len() as u64;
+        Ok(())
+    }
+
+    pub fn push(&mut self, row: &Row) -> io::Result<()> {
+        let (surface, pos, lemma, start, end) = row.morph;
+        for (&column, page) in self.columns.iter().zip(&mut self.pages) {
+            let int = match column {
+                Column::DocId => row.doc_id,
+                Column::SentenceId => row.sentence_id,
+                Column::Start => *start,
+                Column::End => *end,
+                Column::Surface | Column::Pos | Column::Lemma => {
+                    let s = match column {
+                        Column::Surface => surface,
+                        Column::Pos => pos,
+                        _ => lemma,
+                    };
+                    page.extend_from_slice(&(s.len() as u32).to_le_bytes());
+                    page.extend_from_slice(s.as_bytes());
+                    continue;
+                }
+            };
+            page.extend_from_slice(&(int as i64).to_le_bytes());
+        }
+        self.rows += 1;
+        // Page sizes are 32-bit; a row group never holds 1 GiB in a column
+        let full = self.pages.iter().any(|p| p.len() > i32::MAX as usize / 2);
+        if self.rows >= self.group_rows || full {
+            self.flush_group()?;
+        }
+        Ok(())
+    }
+
+    fn flush_group(&mut self) -> io::Result<()> {
+        if self.rows == 0 {
+            return Ok(());
+        }
+        let mut chunks = Vec::with_capacity(self.columns.len());
+        for page in std::mem::replace(&mut self.pages, vec![Vec::new(); self.columns.len()]) {
+            // Only a single value over 1 GiB can get here
+            let size = i32::try_from(page.len()).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("a page of {} bytes exceeds the Parquet limit", page.len()),
+                )
+            })?;
+            let mut header = Thrift::default();
+            header.i32(1, PAGE_DATA);
+            header.i32(2, size);
+            header.i32(3, size);
+            header.begin(Some(5));
+            header.i32(1, self.rows as i32);
+            header.i32(2, ENCODING_PLAIN);
+            header.i32(3, ENCODING_RLE);
+            header.i32(4, ENCODING_RLE);
+            header.end();
+            let header = header.finish();
+            let offset = self.pos;
+            self.write(&header)?;
+            self.write(&page)?;
+            chunks.push(Chunk {
+                offset,
+                size: header.len() + page.len(),
+            });
+        }
+        self.groups.push((chunks, self.rows));
+        self.rows = 0;
+        Ok(())
+    }
+
+    /// Writes the last row group and the footer.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.flush_group()?;
+        let mut meta = Thrift::default();
+        meta.i32(1, 1);
+        meta.list(2, T_STRUCT, self.columns.len() + 1);
+        meta.begin(None);
+        meta.string(4, "schema");
+        meta.i32(5, self.columns.len() as i32);
+        meta.end();
+        for &column in &self.columns {
+            meta.begin(None);
+            meta.i32(
+                1,
+                if column.is_string() {
+                    TYPE_BYTE_ARRAY
+                } else {
+                    TYPE_INT64
+                },
+            );
+            meta.i32(3, REPETITION_REQUIRED);
+            meta.string(4, column.name());
+            if column.is_string() {
+                meta.i32(6, CONVERTED_UTF8);
+            }
+            meta.end();
+        }
+        meta.i64(3, self.groups.iter().map(|(_, rows)| *rows as i64).sum());
+        meta.list(4, T_STRUCT, self.groups.len());
+        for (chunks, rows) in &self.groups {
+            meta.begin(None);
+            meta.list(1, T_STRUCT, chunks.len());
+            for (&column, chunk) in self.columns.iter().zip(chunks) {
+                meta.begin(None);
+                meta.i64(2, chunk.offset as i64);
+                meta.begin(Some(3));
+                meta.i32(
+                    1,
+                    if column.is_string() {
+                        TYPE_BYTE_ARRAY
+                    } else {
+                        TYPE_INT64
+                    },
+                );
+                meta.list(2, T_I32, 1);
+                meta.zigzag(i64::from(ENCODING_PLAIN));
+                meta.list(3, T_BINARY, 1);
+                meta.varint(column.name().len() as u64);
+                meta.out.extend_from_slice(column.name().as_bytes());
+                meta.i32(4, CODEC_UNCOMPRESSED);
+                meta.i64(5, *rows as i64);
+                meta.i64(6, chunk.size as i64);
+                meta.i64(7, chunk.size as i64);
+                meta.i64(9, chunk.offset as i64);
+                meta.end();
+                meta.end();
+            }
+            meta.i64(2, chunks.iter().map(|c| c.size as i64).sum());
+            meta.i64(3, *rows as i64);
+            meta.end();
+        }
+        meta.string(6, concat!("kulim version ", env!("CARGO_PKG_VERSION")));
+        let meta = meta.finish();
+        self.write(&meta)?;
+        self.write(&(meta.len() as u32).to_le_bytes())?;
+        self.write(MAGIC)?;
+        self.out.flush()
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of
+/// characters and `?` for one.
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|k| matches(rest, &name[k..])),
+        Some((&p, rest)) => name
+            .split_first()
+            .is_some_and(|(&c, tail)| (p == '?' || p == c) && matches(rest, tail)),
+    }
+}
+
+/// The files matching `pattern`, sorted; wildcards may only appear in the
+/// file name.
+pub(crate) fn glob(pattern: &str) -> Result<Vec<PathBuf>, String> {
+    let path = Path::new(pattern);
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if dir.to_string_lossy().contains(['*', '?']) {
+        return Err(format!(
+            "{}: wildcards are only supported in the file name",
+            pattern
+        ));
+    }
+    let name: Vec<char> = path
+        .file_name()
+        .map(|n| n.to_string_lossy().chars().collect())
+        .unwrap_or_default();
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let mut out = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("{}: {}", dir.display(), e))?;
+        let file: Vec<char> = entry.file_name().to_string_lossy().chars().collect();
+        if entry.path().is_file() && matches(&name, &file) {
+            out.push(entry.path());
+        }
+    }
+    out.sort();
+    Ok(out)
+}
+
+/// Documents, rows and failed documents of a corpus run.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Counts {
+    pub documents: usize,
+    pub rows: usize,
+    pub failed: usize,
+}
+
+/// Analyzes every non-empty line of `input` as a document, sentence by
+/// sentence, and writes its morphemes to `output`; a document that fails
+/// to analyze is counted and left out.
+pub(crate) fn analyze_file(
+    trie: &RustTrie,
+    input: &Path,
+    output: &Path,
+    columns: &[Column],
+    group_rows: usize,
+    layers: Option<&[String]>,
+) -> PyResult<Counts> {
+    fn io(path: &Path) -> impl Fn(io::Error) -> PyErr + '_ {
+        move |e| PyValueError::new_err(format!("{}: {}", path.display(), e))
+    }
+    let reader = BufReader::new(File::open(input).map_err(io(input))?);
+    let mut file = ParquetFile::create(output, columns.to_vec(), group_rows).map_err(io(output))?;
+    let mut counts = Counts::default();
+    let mut morphs: Vec<(usize, Located)> = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(io(input))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        crate::check_signals()?;
+        morphs.clear();
+        let chars: Vec<char> = line.chars().collect();
+        let sentences = sentence::split(&line)
+            .into_iter()
+            .filter(|&(_, _, depth)| depth == 0);
+        let analyzed = sentences.enumerate().try_for_each(|(id, (start, end, _))| {
+            let text: String = chars[start..end].iter().collect();
+            let (located, _) = trie.analyze_located(&trie.prepare(&text, false), layers)?;
+            let words = located
+                .into_iter()
+                .filter(|m| !m.0.chars().all(char::is_whitespace));
+            morphs
+                .extend(words.map(|(s, p, l, from, to)| (id, (s, p, l, start + from, start + to))));
+            Ok::<(), PyErr>(())
+        });
+        match analyzed {
+            Ok(()) => {}
+            Err(e) if Python::with_gil(|py| e.is_instance_of::<AnalysisError>(py)) => {
+                counts.failed += 1;
+                continue;
+            }
+            Err(e) => return Err(e),
+        }
+        for (sentence_id, morph) in &morphs {
+            let row = Row {
+                doc_id: i + 1,
+                sentence_id: *sentence_id,
+                morph,
+            };
+            file.push(&row).map_err(io(output))?;
+        }
+        counts.documents += 1;
+        counts.rows += morphs.len();
+    }
+    file.finish().map_err(io(output))?;
+    Ok(counts)
+}
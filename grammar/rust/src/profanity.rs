@@ -0,0 +1,196 @@
+// -----------------------------------------------------------------------------
+// Profanity
+// -----------------------------------------------------------------------------
+//
+// A list of profane words, matched against the text with the usual
+// disguises undone: digits and symbols wedged between syllables (시1발,
+// 시.발), syllables spelled out in compatibility jamo (ㅅㅣ발) and words
+// abbreviated to their initial consonants (ㅅㅂ). Whether a match is meant
+// is left to the analysis: one inside a longer dictionary word (시발점,
+// "starting point") is not masked.
+
+/// The built-in words.
+const BUILTIN: &[&str] = &[
+    "시발",
+    "씨발",
+    "시팔",
+    "씨팔",
+    "씨빨",
+    "씨바",
+    "씨부랄",
+    "썅",
+    "좆",
+    "좆같",
+    "좇같",
+    "존나",
+    "졸라",
+    "개새끼",
+    "개새기",
+    "개색기",
+    "개색히",
+    "개세끼",
+    "병신",
+    "븅신",
+    "빙신",
+    "지랄",
+    "미친놈",
+    "미친년",
+    "또라이",
+    "니미",
+    "느금마",
+    "엠창",
+    "염병",
+    "닥쳐",
+    "꺼져",
+];
+
+// Compatibility jamo in syllable block order (final 0 is none)
+const INITIALS: [char; 19] = [
+    'ㄱ', 'ㄲ', 'ㄴ', 'ㄷ', 'ㄸ', 'ㄹ', 'ㅁ', 'ㅂ', 'ㅃ', 'ㅅ', 'ㅆ', 'ㅇ', 'ㅈ', 'ㅉ', 'ㅊ', 'ㅋ',
+    'ㅌ', 'ㅍ', 'ㅎ',
+];
+const FINALS: [char; 28] = [
+    ' ', 'ㄱ', 'ㄲ', 'ㄳ', 'ㄴ', 'ㄵ', 'ㄶ', 'ㄷ', 'ㄹ', 'ㄺ', 'ㄻ', 'ㄼ', 'ㄽ', 'ㄾ', 'ㄿ', 'ㅀ',
+    'ㅁ', 'ㅂ', 'ㅄ', 'ㅅ', 'ㅆ', 'ㅇ', 'ㅈ', 'ㅊ', 'ㅋ', 'ㅌ', 'ㅍ', 'ㅎ',
+];
+const VOWEL_BASE: u32 = 0x314F;
+
+// At most this many wedged characters between two syllables
+const MAX_NOISE: usize = 3;
+
+/// A syllable of a word, with the jamo it may be spelled in.
+#[derive(Clone, Debug)]
+struct Syllable {
+    text: char,
+    // Initial, vowel and final (if any) as compatibility jamo
+    jamo: Vec<char>,
+}
+
+impl Syllable {
+    fn new(c: char) -> Self {
+        let jamo = match (c as u32).checked_sub(0xAC00).filter(|&code| code < 11172) {
+            Some(code) => {
+                let last = (code % 28) as usize;
+                let mut jamo = vec![INITIALS[(code / 588) as usize]];
+                jamo.extend(char::from_u32(VOWEL_BASE + code % 588 / 28));
+                if last > 0 {
+                    jamo.push(FINALS[last]);
+                }
+                jamo
+            }
+            None => Vec::new(),
+        };
+        Syllable { text: c, jamo }
+    }
+
+    /// The end of this syllable at `chars[i]`, and whether it was only
+    /// its initial consonant.
+    fn at(&self, chars: &[char], i: usize) -> Option<(usize, bool)> {
+        if chars[i] == self.text {
+            return Some((i + 1, false));
+        }
+        let initial = *self.jamo.first()?;
+        if chars[i..].starts_with(&self.jamo) {
+            return Some((i + self.jamo.len(), false));
+        }
+        (chars[i] == initial).then_some((i + 1, true))
+    }
+}
+
+/// Characters that may be wedged into a word: digits and symbols.
+fn is_noise(c: char) -> bool {
+    !c.is_whitespace() && !c.is_alphabetic() && !('ㄱ'..='ㆎ').contains(&c)
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Profanity {
+    words: Vec<Vec<Syllable>>,
+}
+
+impl Profanity {
+    pub fn builtin() -> Self {
+        let mut profanity = Profanity::default();
+        profanity.extend(BUILTIN.iter().map(|w| w.to_string()));
+        profanity
+    }
+
+    /// Adds `words`, skipping blank and repeated ones; returns how many
+    /// were new.
+    pub fn extend(&mut self, words: impl IntoIterator<Item = String>) -> usize {
+        let before = self.words.len();
+        for word in words {
+            let word = word.trim();
+            if !word.is_empty() && !self.contains(word) {
+                self.words.push(word.chars().map(Syllable::new).collect());
+            }
+        }
+        self.words.len() - before
+    }
+
+    /// The words of `source`, one per line; blank lines and lines
+    /// starting with `#` are skipped.
+    pub fn parse(source: &str) -> Vec<String> {
+        source
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect()
+    }
+
+    pub fn contains(&self, surface: &str) -> bool {
+        self.words
+            .iter()
+            .any(|w| w.iter().map(|s| s.text).eq(surface.chars()))
+    }
+
+    /// The end of `word` matched at `chars[i]`, if it is.
+    fn match_at(word: &[Syllable], chars: &[char], i: usize) -> Option<usize> {
+        let mut at = i;
+        let mut abbreviated = true;
+        for (k, syllable) in word.iter().enumerate() {
+            if k > 0 {
+                // Try the syllable right away, then after wedged characters
+                let mut skip = 0;
+                while skip < MAX_NOISE
+                    && at + skip < chars.len()
+                    && syllable.at(chars, at + skip).is_none()
+                    && is_noise(chars[at + skip])
+                {
+                    skip += 1;
+                }
+                at += skip;
+            }
+            if at >= chars.len() {
+                return None;
+            }
+            let (end, initial) = syllable.at(chars, at)?;
+            abbreviated &= initial;
+            at = end;
+        }
+        // A lone initial consonant is not a word
+        (!abbreviated || word.len() >= 2).then_some(at)
+    }
+
+    /// Matched spans `(start, end)` in `chars`, the longest at each
+    /// position, left to right.
+    pub fn find(&self, chars: &[char]) -> Vec<(usize, usize)> {
+        let mut spans = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let longest = self
+                .words
+                .iter()
+                .filter_map(|word| Self::match_at(word, chars, i))
+                .max();
+            match longest {
+                Some(end) => {
+                    spans.push((i, end));
+                    i = end;
+                }
+                None => i += 1,
+            }
+        }
+        spans
+    }
+}
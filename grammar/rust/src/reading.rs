@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+
+use crate::Morpheme;
+
+// -----------------------------------------------------------------------------
+// Readings (Standard Pronunciation)
+// -----------------------------------------------------------------------------
+//
+// The reading of a morpheme is its dictionary reading, or its surface, with
+// the sound changes of standard pronunciation applied across the syllables
+// of its eojeol: 값/NNG 이/JKS → 갑 씨, 읽/VV 다/EF → 익 따. Every rule maps
+// a syllable to one syllable, so the pronounced eojeol splits back into
+// morphemes by syllable count. Text that is not Hangul is read as written
+// and ends the run of syllables the rules look across.
+
+// Initial and final consonants in syllable block order (final 0 is none)
+const INITIALS: [char; 19] = [
+    'ㄱ', 'ㄲ', 'ㄴ', 'ㄷ', 'ㄸ', 'ㄹ', 'ㅁ', 'ㅂ', 'ㅃ', 'ㅅ', 'ㅆ', 'ㅇ', 'ㅈ', 'ㅉ', 'ㅊ', 'ㅋ',
+    'ㅌ', 'ㅍ', 'ㅎ',
+];
+const FINALS: [char; 28] = [
+    ' ', 'ㄱ', 'ㄲ', 'ㄳ', 'ㄴ', 'ㄵ', 'ㄶ', 'ㄷ', 'ㄹ', 'ㄺ', 'ㄻ', 'ㄼ', 'ㄽ', 'ㄾ', 'ㄿ', 'ㅀ',
+    'ㅁ', 'ㅂ', 'ㅄ', 'ㅅ', 'ㅆ', 'ㅇ', 'ㅈ', 'ㅊ', 'ㅋ', 'ㅌ', 'ㅍ', 'ㅎ',
+];
+// The vowel ㅣ, before which ㄷ and ㅌ palatalize
+const VOWEL_I: u32 = 20;
+
+#[derive(Clone, Copy)]
+struct Syllable {
+    initial: char,
+    vowel: u32,
+    last: Option<char>,
+}
+
+impl Syllable {
+    fn parse(c: char) -> Option<Self> {
+        let code = (c as u32)
+            .checked_sub(0xAC00)
+            .filter(|&code| code < 11172)?;
+        let last = (code % 28) as usize;
+        Some(Syllable {
+            initial: INITIALS[(code / 588) as usize],
+            vowel: code % 588 / 28,
+            last: (last > 0).then(|| FINALS[last]),
+        })
+    }
+
+    fn to_char(self) -> char {
+        let initial = INITIALS
+            .iter()
+            .position(|&c| c == self.initial)
+            .unwrap_or(11) as u32;
+        let last = self
+            .last
+            .and_then(|l| FINALS.iter().position(|&c| c == l))
+            .unwrap_or(0) as u32;
+        char::from_u32(0xAC00 + initial * 588 + self.vowel * 28 + last).unwrap_or('?')
+    }
+}
+
+/// The consonants of a final cluster: ㄺ → (ㄹ, ㄱ).
+fn split(last: char) -> (char, Option<char>) {
+    let (first, second) = match last {
+        'ㄳ' => ('ㄱ', 'ㅅ'),
+        'ㄵ' => ('ㄴ', 'ㅈ'),
+        'ㄶ' => ('ㄴ', 'ㅎ'),
+        'ㄺ' => ('ㄹ', 'ㄱ'),
+        'ㄻ' => ('ㄹ', 'ㅁ'),
+        'ㄼ' => ('ㄹ', 'ㅂ'),
+        'ㄽ' => ('ㄹ', 'ㅅ'),
+        'ㄾ' => ('ㄹ', 'ㅌ'),
+        'ㄿ' => ('ㄹ', 'ㅍ'),
+        'ㅀ' => ('ㄹ', 'ㅎ'),
+        'ㅄ' => ('ㅂ', 'ㅅ'),
+        c => return (c, None),
+    };
+    (first, Some(second))
+}
+
+/// The one consonant a final cluster keeps before `next` (or at the end).
+fn simplify(last: char, next: Option<char>) -> char {
+    match last {
+        'ㄺ' if next == Some('ㄱ') => 'ㄹ',
+        'ㄳ' | 'ㄺ' => 'ㄱ',
+        'ㄵ' | 'ㄶ' => 'ㄴ',
+        'ㄻ' => 'ㅁ',
+        'ㄼ' | 'ㄽ' | 'ㄾ' | 'ㅀ' => 'ㄹ',
+        'ㄿ' | 'ㅄ' => 'ㅂ',
+        c => c,
+    }
+}
+
+/// A final consonant as it is pronounced: one of ㄱ ㄴ ㄷ ㄹ ㅁ ㅂ ㅇ.
+fn neutralize(last: char) -> char {
+    match last {
+        'ㄲ' | 'ㅋ' => 'ㄱ',
+        'ㅅ' | 'ㅆ' | 'ㅈ' | 'ㅊ' | 'ㅌ' | 'ㅎ' => 'ㄷ',
+        'ㅍ' => 'ㅂ',
+        c => c,
+    }
+}
+
+fn tense(c: char) -> char {
+    match c {
+        'ㄱ' => 'ㄲ',
+        'ㄷ' => 'ㄸ',
+        'ㅂ' => 'ㅃ',
+        'ㅅ' => 'ㅆ',
+        'ㅈ' => 'ㅉ',
+        c => c,
+    }
+}
+
+fn aspirate(c: char) -> char {
+    match c {
+        'ㄱ' => 'ㅋ',
+        'ㄷ' => 'ㅌ',
+        'ㅂ' => 'ㅍ',
+        'ㅈ' => 'ㅊ',
+        c => c,
+    }
+}
+
+/// Applies the sound changes between the final consonant of `a` and the
+/// initial of the syllable `b` after it.
+fn join(a: &mut Syllable, b: &mut Syllable) {
+    let Some(last) = a.last else {
+        return;
+    };
+    let (first, second) = split(last);
+    let h_final = last == 'ㅎ' || second == Some('ㅎ');
+    if b.initial == 'ㅇ' {
+        // Liaison: the final moves over, ㅎ is dropped, ㄷ ㅌ palatalize
+        if last == 'ㅇ' {
+            return;
+        }
+        a.last = second.filter(|&s| s != 'ㅎ').map(|_| first);
+        b.initial = match (last, second) {
+            ('ㅎ', _) => 'ㅇ',
+            (_, Some('ㅎ')) => first,
+            (_, Some('ㅅ')) => 'ㅆ',
+            (_, Some(s)) => s,
+            ('ㄷ', None) if b.vowel == VOWEL_I => 'ㅈ',
+            ('ㅌ', None) if b.vowel == VOWEL_I => 'ㅊ',
+            (c, None) => c,
+        };
+        return;
+    }
+    if b.initial == 'ㅎ' && !h_final {
+        // Aspiration: ㄱ ㄷ ㅂ ㅈ merge with a following ㅎ
+        let (keep, stop) = match second {
+            Some(s) if matches!(s, 'ㄱ' | 'ㄷ' | 'ㅂ' | 'ㅈ') => (Some(first), s),
+            Some(_) => (None, simplify(last, Some('ㅎ'))),
+            None if last == 'ㅈ' => (None, 'ㅈ'),
+            None => (None, neutralize(last)),
+        };
+        match stop {
+            'ㄱ' | 'ㄷ' | 'ㅂ' | 'ㅈ' => {
+                a.last = keep;
+                b.initial = aspirate(stop);
+            }
+            c => a.last = Some(c),
+        }
+        return;
+    }
+    let mut last = last;
+    if h_final {
+        // ㅎ aspirates ㄱ ㄷ ㅈ, tenses ㅅ, and becomes ㄴ before ㄴ
+        let rest = second.map(|_| first);
+        match b.initial {
+            'ㄱ' | 'ㄷ' | 'ㅈ' | 'ㅅ' => {
+                a.last = rest;
+                b.initial = match b.initial {
+                    'ㅅ' => 'ㅆ',
+                    c => aspirate(c),
+                };
+                return;
+            }
+            'ㄴ' => last = rest.unwrap_or('ㄴ'),
+            _ => last = rest.unwrap_or('ㄷ'),
+        }
+    } else if second.is_some() {
+        // A stem-final cluster tenses what follows it: 앉다 → 안따
+        last = simplify(last, Some(b.initial));
+        b.initial = tense(b.initial);
+    }
+    let mut last = neutralize(last);
+    let stop = matches!(last, 'ㄱ' | 'ㄷ' | 'ㅂ');
+    if stop {
+        b.initial = tense(b.initial);
+    }
+    match (last, b.initial) {
+        // ㄹ becomes ㄴ after consonants other than ㄴ ㄹ: 종로 → 종노
+        (_, 'ㄹ') if stop || matches!(last, 'ㅁ' | 'ㅇ') => b.initial = 'ㄴ',
+        // Liquidization: 신라 → 실라, 설날 → 설랄
+        ('ㄴ', 'ㄹ') => last = 'ㄹ',
+        ('ㄹ', 'ㄴ') => b.initial = 'ㄹ',
+        _ => {}
+    }
+    if stop && matches!(b.initial, 'ㄴ' | 'ㅁ') {
+        // Nasalization: 국물 → 궁물, 독립 → 동닙
+        last = match last {
+            'ㄱ' => 'ㅇ',
+            'ㄷ' => 'ㄴ',
+            _ => 'ㅁ',
+        };
+    }
+    a.last = Some(last);
+}
+
+/// The standard pronunciation of `text`, one syllable per syllable.
+pub(crate) fn pronounce(text: &str) -> String {
+    let mut syllables: Vec<Result<Syllable, char>> =
+        text.chars().map(|c| Syllable::parse(c).ok_or(c)).collect();
+    for i in 0..syllables.len() {
+        let (head, tail) = syllables.split_at_mut(i + 1);
+        let Ok(a) = &mut head[i] else {
+            continue;
+        };
+        match tail.first_mut() {
+            Some(Ok(b)) => join(a, b),
+            // The end of a run: the final as it is pronounced on its own
+            _ => a.last = a.last.map(|l| neutralize(simplify(l, None))),
+        }
+    }
+    syllables
+        .into_iter()
+        .map(|s| s.map_or_else(|c| c, Syllable::to_char))
+        .collect()
+}
+
+/// Readings of dictionary entries by surface, each for one tag or all
+/// (`None`).
+#[derive(Clone, Default)]
+pub(crate) struct Readings {
+    entries: HashMap<String, Vec<(Option<String>, String)>>,
+}
+
+impl Readings {
+    /// Sets the reading of `surface` as `pos`, or as any tag.
+    pub fn insert(&mut self, surface: &str, pos: Option<&str>, reading: &str) {
+        let readings = self.entries.entry(surface.to_string()).or_default();
+        let pos = pos.map(str::to_string);
+        readings.retain(|(p, _)| *p != pos);
+        readings.push((pos, reading.to_string()));
+    }
+
+    /// The reading of `surface` as `pos`, one set for its tag first.
+    fn get(&self, surface: &str, pos: &str) -> Option<&str> {
+        let readings = self.entries.get(surface)?;
+        readings
+            .iter()
+            .find(|(p, _)| p.as_deref() == Some(pos))
+            .or_else(|| readings.iter().find(|(p, _)| p.is_none()))
+            .map(|(_, r)| r.as_str())
+    }
+
+    /// The reading of each of `morphs`, an analysis of running text.
+    pub fn read(&self, morphs: &[Morpheme]) -> Vec<String> {
+        let mut out = Vec::with_capacity(morphs.len());
+        let mut start = 0;
+        while start < morphs.len() {
+            // An eojeol, or a run of whitespace
+            let space = |m: &Morpheme| m.0.chars().all(char::is_whitespace);
+            let kind = space(&morphs[start]);
+            let end = start
+                + morphs[start..]
+                    .iter()
+                    .take_while(|m| space(m) == kind)
+                    .count();
+            let written: Vec<&str> = morphs[start..end]
+                .iter()
+                .map(|(surface, pos, _)| self.get(surface, pos).unwrap_or(surface))
+                .collect();
+            let mut pronounced = pronounce(&written.concat())
+                .chars()
+                .collect::<Vec<_>>()
+                .into_iter();
+            for part in written {
+                out.push(pronounced.by_ref().take(part.chars().count()).collect());
+            }
+            start = end;
+        }
+        out
+    }
+
+    /// Parses a readings file: `surface<TAB>reading` or
+    /// `surface<TAB>pos<TAB>reading` per line, `#` comments.
+    pub fn load(&mut self, source: &str) -> Result<usize, String> {
+        let mut count = 0;
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let columns: Vec<&str> = line.split('\t').map(str::trim).collect();
+            let (surface, pos, reading) = match columns.as_slice() {
+                [surface, reading] => (*surface, None, *reading),
+                [surface, pos, reading] => (*surface, Some(*pos), *reading),
+                _ => {
+                    return Err(format!(
+                        "line {}: expected 'word<TAB>[tag<TAB>]reading'",
+                        i + 1
+                    ))
+                }
+            };
+            if surface.is_empty() || reading.is_empty() || pos == Some("") {
+                return Err(format!("line {}: empty field", i + 1));
+            }
+            self.insert(surface, pos, reading);
+            count += 1;
+        }
+        Ok(count)
+    }
+}
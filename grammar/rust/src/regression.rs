@@ -0,0 +1,58 @@
+// -----------------------------------------------------------------------------
+// Regression Cases
+// -----------------------------------------------------------------------------
+//
+// A cases file pins the analyses a dictionary or weight change must not
+// disturb, one case per line:
+//
+//     나는 학교에 갔다<TAB>나/NP+는/JX 학교/NNG+에/JKB 갔/VV+다/EF
+//
+// The expected analysis is written eojeol by eojeol, separated by spaces,
+// in the Sejong `morph/POS+morph/POS` form. Blank lines and lines starting
+// with `#` are kept as they are when the file is rewritten.
+
+pub(crate) struct Case {
+    /// 1-based line number in the cases file
+    pub line: usize,
+    pub text: String,
+    pub expected: String,
+}
+
+/// The cases in `source`, or the first line that is not `text<TAB>expected`.
+pub(crate) fn parse(source: &str) -> Result<Vec<Case>, String> {
+    let mut cases = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (text, expected) = line
+            .split_once('\t')
+            .ok_or_else(|| format!("line {}: expected 'text<TAB>analysis'", i + 1))?;
+        cases.push(Case {
+            line: i + 1,
+            text: text.to_string(),
+            expected: expected.trim().to_string(),
+        });
+    }
+    Ok(cases)
+}
+
+/// `source` with the expectation of each case at `(line, analysis)`
+/// replaced, everything else unchanged.
+pub(crate) fn rewrite(source: &str, updates: &[(usize, String)]) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut updates = updates.iter().peekable();
+    for (i, line) in source.lines().enumerate() {
+        match updates.next_if(|(at, _)| *at == i + 1) {
+            Some((_, analysis)) => {
+                let text = line.split_once('\t').map_or(line, |(text, _)| text);
+                out.push_str(text);
+                out.push('\t');
+                out.push_str(analysis);
+            }
+            None => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    out
+}
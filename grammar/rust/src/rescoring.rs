@@ -0,0 +1,90 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::{Analysis, Morpheme};
+
+// -----------------------------------------------------------------------------
+// External Language-Model Scoring
+// -----------------------------------------------------------------------------
+
+/// An external model that scores whole analyses.
+///
+/// Implementations receive candidates in batches and return one log-score per
+/// candidate (higher is better). Transport (socket, RPC, Python) is up to the
+/// implementation; batching and score combination are handled by `rescore`.
+pub(crate) trait LmScorer {
+    fn score_batch(&self, batch: &[&[Morpheme]]) -> PyResult<Vec<f64>>;
+}
+
+/// Adapts a Python callable `f(list[list[(surface, pos, lemma)]]) -> list[float]`.
+pub(crate) struct PyCallableScorer {
+    callable: PyObject,
+}
+
+impl PyCallableScorer {
+    pub fn new(callable: PyObject) -> Self {
+        PyCallableScorer { callable }
+    }
+}
+
+impl LmScorer for PyCallableScorer {
+    fn score_batch(&self, batch: &[&[Morpheme]]) -> PyResult<Vec<f64>> {
+        Python::with_gil(|py| {
+            let arg: Vec<Vec<Morpheme>> = batch.iter().map(|c| c.to_vec()).collect();
+            self.callable.call1(py, (arg,))?.extract(py)
+        })
+    }
+}
+
+/// Weights for combining lattice cost (lower is better) with LM score
+/// (higher is better) into a single cost.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ScoreWeights {
+    pub lattice: f64,
+    pub lm: f64,
+}
+
+impl ScoreWeights {
+    pub fn combine(&self, lattice_cost: f64, lm_score: f64) -> f64 {
+        self.lattice * lattice_cost - self.lm * lm_score
+    }
+}
+
+/// Rescores the n-best lists of several inputs, sending at most `batch_size`
+/// candidates per scorer call. Each list is returned sorted by combined cost.
+pub(crate) fn rescore(
+    scorer: &dyn LmScorer,
+    nbests: Vec<Vec<Analysis>>,
+    weights: ScoreWeights,
+    batch_size: usize,
+) -> PyResult<Vec<Vec<Analysis>>> {
+    let batch_size = batch_size.max(1);
+    let flat: Vec<(usize, &[Morpheme], f64)> = nbests
+        .iter()
+        .enumerate()
+        .flat_map(|(doc, list)| list.iter().map(move |(m, c)| (doc, m.as_slice(), *c)))
+        .collect();
+
+    let mut scores = Vec::with_capacity(flat.len());
+    for chunk in flat.chunks(batch_size) {
+        let batch: Vec<&[Morpheme]> = chunk.iter().map(|(_, m, _)| *m).collect();
+        let got = scorer.score_batch(&batch)?;
+        if got.len() != batch.len() {
+            return Err(PyValueError::new_err(format!(
+                "scorer returned {} scores for a batch of {}",
+                got.len(),
+                batch.len()
+            )));
+        }
+        scores.extend(got);
+    }
+
+    let mut combined: Vec<Vec<Analysis>> = vec![Vec::new(); nbests.len()];
+    for ((doc, morphs, cost), lm_score) in flat.into_iter().zip(scores) {
+        combined[doc].push((morphs.to_vec(), weights.combine(cost, lm_score)));
+    }
+    for list in combined.iter_mut() {
+        list.sort_by(|a, b| a.1.total_cmp(&b.1));
+    }
+    Ok(combined)
+}
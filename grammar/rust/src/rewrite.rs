@@ -0,0 +1,174 @@
+use std::fs;
+
+use crate::Morpheme;
+
+// -----------------------------------------------------------------------------
+// Post-processing Rewrite Rules
+// -----------------------------------------------------------------------------
+//
+// One rule per line; lines starting with `#` are comments:
+//
+//     */NNG */NNG => merge NNG
+//     하/XSV/하다 => retag VV
+//
+// Each pattern element is `surface/pos` or `surface/pos/lemma`. `*` matches
+// anything and a trailing `*` on a POS matches by prefix (`N*`). Actions:
+//
+//     merge POS        join the matched morphemes into one tagged POS
+//     retag POS [...]  retag the matched morphemes (one POS, or one per element)
+
+#[derive(Clone, Debug, PartialEq)]
+enum Field {
+    Any,
+    Exact(String),
+    Prefix(String),
+}
+
+impl Field {
+    fn parse(s: &str, allow_prefix: bool) -> Field {
+        if s == "*" {
+            Field::Any
+        } else if allow_prefix && s.len() > 1 && s.ends_with('*') {
+            Field::Prefix(s[..s.len() - 1].to_string())
+        } else {
+            Field::Exact(s.to_string())
+        }
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            Field::Any => true,
+            Field::Exact(s) => s == value,
+            Field::Prefix(p) => value.starts_with(p.as_str()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    surface: Field,
+    pos: Field,
+    lemma: Field,
+}
+
+impl Element {
+    fn matches(&self, m: &Morpheme) -> bool {
+        self.surface.matches(&m.0) && self.pos.matches(&m.1) && self.lemma.matches(&m.2)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Action {
+    Merge(String),
+    Retag(Vec<String>),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct RewriteRule {
+    pattern: Vec<Element>,
+    action: Action,
+}
+
+impl RewriteRule {
+    pub fn parse(line: &str) -> Result<RewriteRule, String> {
+        let (lhs, rhs) = line
+            .split_once("=>")
+            .ok_or_else(|| "missing '=>'".to_string())?;
+
+        let mut pattern = Vec::new();
+        for token in lhs.split_whitespace() {
+            let parts: Vec<&str> = token.split('/').collect();
+            let (surface, pos, lemma) = match parts.as_slice() {
+                [s, p] => (*s, *p, "*"),
+                [s, p, l] => (*s, *p, *l),
+                _ => return Err(format!("bad pattern element '{}'", token)),
+            };
+            if surface.is_empty() || pos.is_empty() || lemma.is_empty() {
+                return Err(format!("bad pattern element '{}'", token));
+            }
+            pattern.push(Element {
+                surface: Field::parse(surface, false),
+                pos: Field::parse(pos, true),
+                lemma: Field::parse(lemma, false),
+            });
+        }
+        if pattern.is_empty() {
+            return Err("empty pattern".to_string());
+        }
+
+        let mut words = rhs.split_whitespace();
+        let action = match (words.next(), words.collect::<Vec<_>>()) {
+            (Some("merge"), args) if args.len() == 1 => Action::Merge(args[0].to_string()),
+            (Some("retag"), args) if args.len() == 1 || args.len() == pattern.len() => {
+                Action::Retag(args.iter().map(|s| s.to_string()).collect())
+            }
+            _ => return Err(format!("bad action '{}'", rhs.trim())),
+        };
+
+        Ok(RewriteRule { pattern, action })
+    }
+
+    fn matches_at(&self, morphs: &[Morpheme], i: usize) -> bool {
+        i + self.pattern.len() <= morphs.len()
+            && self
+                .pattern
+                .iter()
+                .zip(&morphs[i..])
+                .all(|(el, m)| el.matches(m))
+    }
+
+    fn apply(&self, morphs: &mut Vec<Morpheme>) {
+        let width = self.pattern.len();
+        let mut i = 0;
+        while i < morphs.len() {
+            if !self.matches_at(morphs, i) {
+                i += 1;
+                continue;
+            }
+            match &self.action {
+                Action::Merge(pos) => {
+                    let merged: Vec<Morpheme> = morphs.drain(i..i + width).collect();
+                    let surface = merged.iter().map(|m| m.0.as_str()).collect();
+                    let lemma = merged.iter().map(|m| m.2.as_str()).collect();
+                    morphs.insert(i, (surface, pos.clone(), lemma));
+                    // the merged morpheme may start another match (NNG NNG NNG)
+                    if width == 1 {
+                        i += 1;
+                    }
+                }
+                Action::Retag(tags) => {
+                    for (k, m) in morphs[i..i + width].iter_mut().enumerate() {
+                        m.1 = tags[k.min(tags.len() - 1)].clone();
+                    }
+                    i += width;
+                }
+            }
+        }
+    }
+}
+
+/// Parses a rule file, reporting the first bad line.
+pub(crate) fn parse_rules(source: &str) -> Result<Vec<RewriteRule>, String> {
+    let mut rules = Vec::new();
+    for (no, raw) in source.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let rule = RewriteRule::parse(line).map_err(|e| format!("line {}: {}", no + 1, e))?;
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+pub(crate) fn load_rules(path: &str) -> Result<Vec<RewriteRule>, String> {
+    let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    parse_rules(&source)
+}
+
+/// Applies every rule in order over the whole sequence.
+pub(crate) fn apply_rules(rules: &[RewriteRule], morphs: &mut Vec<Morpheme>) {
+    for rule in rules {
+        rule.apply(morphs);
+    }
+}
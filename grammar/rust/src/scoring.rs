@@ -0,0 +1,144 @@
+// -----------------------------------------------------------------------------
+// Scoring Constants
+// -----------------------------------------------------------------------------
+pub(crate) const COST_LONG_WORD: f64 = -40.0;
+pub(crate) const COST_MEDIUM_WORD: f64 = -30.0;
+pub(crate) const COST_SHORT_WORD: f64 = -5.0;
+pub(crate) const PENALTY_SINGLE_VERB_IC: f64 = 20.0;
+pub(crate) const COST_OOV: f64 = 50.0;
+// Preference for an unknown span following one script over the generic fallback
+pub(crate) const BONUS_SCRIPT_RUN: f64 = 1.0;
+pub(crate) const BONUS_NOUN_2PLUS: f64 = 5.0;
+pub(crate) const BONUS_ADVERB_2PLUS: f64 = 10.0;
+
+pub(crate) const BONUS_NOUN_JOSA: f64 = 20.0;
+pub(crate) const BONUS_VERB_EOMI: f64 = 15.0;
+pub(crate) const BONUS_EOMI_EOMI: f64 = 10.0;
+pub(crate) const BONUS_ADVERB_NOUN: f64 = 15.0;
+pub(crate) const BONUS_ADVERB_VERB: f64 = 10.0;
+pub(crate) const BONUS_DETERMINER_NOUN: f64 = 10.0;
+pub(crate) const BONUS_STEM_EP_ENDING: f64 = 5.0;
+// A counting bound noun after a number (3/SN 개/NNB, 이십/NR 명/NNB)
+pub(crate) const BONUS_NUMERAL_COUNTER: f64 = 15.0;
+// A derivational suffix after an English word (check/SL 하/XSV)
+pub(crate) const BONUS_FOREIGN_SUFFIX: f64 = 15.0;
+// A whole Latin word as SL (`set_code_switching`), over a dictionary entry
+// for the same span
+pub(crate) const PENALTY_FOREIGN_WORD: f64 = 1.0;
+
+// Cost per unit of -ln(weight) between lemma candidates of one surface/POS
+pub(crate) const COST_LEMMA_WEIGHT: f64 = 5.0;
+
+// -----------------------------------------------------------------------------
+// Transition Rules
+// -----------------------------------------------------------------------------
+
+/// Tag pairs that never follow each other. Ported from constraints.py
+pub(crate) const IMPOSSIBLE_TRANSITIONS: [(&str, &str); 6] = [
+    ("JKS", "JKS"),
+    ("JKO", "JKO"),
+    ("EF", "JKS"),
+    ("EF", "JKO"),
+    ("EF", "EF"),
+    ("SF", "JKS"),
+];
+
+/// Bonuses subtracted from an edge's cost when it follows an edge of the
+/// first tag, with the name score breakdowns show; a trailing `*` matches
+/// any tag with that prefix. The first matching pair applies.
+pub(crate) const TRANSITION_BONUSES: [(&str, &str, f64, &str); 10] = [
+    ("N*", "J*", BONUS_NOUN_JOSA, "BONUS_NOUN_JOSA"),
+    ("V*", "E*", BONUS_VERB_EOMI, "BONUS_VERB_EOMI"),
+    ("E*", "E*", BONUS_EOMI_EOMI, "BONUS_EOMI_EOMI"),
+    ("MAG", "N*", BONUS_ADVERB_NOUN, "BONUS_ADVERB_NOUN"),
+    ("MAG", "V*", BONUS_ADVERB_VERB, "BONUS_ADVERB_VERB"),
+    ("MM", "N*", BONUS_DETERMINER_NOUN, "BONUS_DETERMINER_NOUN"),
+    ("SN", "NNB", BONUS_NUMERAL_COUNTER, "BONUS_NUMERAL_COUNTER"),
+    ("NR", "NNB", BONUS_NUMERAL_COUNTER, "BONUS_NUMERAL_COUNTER"),
+    ("SL", "J*", BONUS_NOUN_JOSA, "BONUS_NOUN_JOSA"),
+    ("SL", "XS*", BONUS_FOREIGN_SUFFIX, "BONUS_FOREIGN_SUFFIX"),
+];
+
+/// Three-tag bonuses for second-order decoding, on top of the pairwise ones:
+/// a pre-final ending completed by an ending after a predicate stem (갔다 as
+/// 가/VV 았/EP 다/EF), over readings where the EP attaches elsewhere.
+pub(crate) const TRIGRAM_BONUSES: [(&str, &str, &str, f64, &str); 3] = [
+    (
+        "V*",
+        "EP",
+        "EF",
+        BONUS_STEM_EP_ENDING,
+        "BONUS_STEM_EP_ENDING",
+    ),
+    (
+        "V*",
+        "EP",
+        "EC",
+        BONUS_STEM_EP_ENDING,
+        "BONUS_STEM_EP_ENDING",
+    ),
+    (
+        "V*",
+        "EP",
+        "ETM",
+        BONUS_STEM_EP_ENDING,
+        "BONUS_STEM_EP_ENDING",
+    ),
+];
+
+// -----------------------------------------------------------------------------
+// Edge Costs
+// -----------------------------------------------------------------------------
+
+/// Context-free cost of a dictionary edge of `len` characters tagged `pos`.
+pub(crate) fn word_cost(len: usize, pos: &str) -> f64 {
+    word_cost_terms(len, pos).map(|(_, cost)| cost).sum()
+}
+
+/// The named terms `word_cost` adds up, bonuses negated.
+pub(crate) fn word_cost_terms(len: usize, pos: &str) -> impl Iterator<Item = (&'static str, f64)> {
+    let base = match len {
+        l if l >= 3 => ("COST_LONG_WORD", COST_LONG_WORD),
+        2 => ("COST_MEDIUM_WORD", COST_MEDIUM_WORD),
+        _ => ("COST_SHORT_WORD", COST_SHORT_WORD),
+    };
+    let single_verb = len == 1 && (pos.starts_with('V') || pos == "IC");
+    let long_noun = pos.starts_with('N') && len >= 2;
+    let long_adverb = pos == "MAG" && len >= 2;
+    [
+        Some(base),
+        single_verb.then_some(("PENALTY_SINGLE_VERB_IC", PENALTY_SINGLE_VERB_IC)),
+        long_noun.then_some(("BONUS_NOUN_2PLUS", -BONUS_NOUN_2PLUS)),
+        long_adverb.then_some(("BONUS_ADVERB_2PLUS", -BONUS_ADVERB_2PLUS)),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+/// Extra cost of a lemma candidate whose weight is `share` of the best one's.
+pub(crate) fn lemma_penalty(share: f64) -> f64 {
+    -share.ln() * COST_LEMMA_WEIGHT
+}
+
+/// Cost of an unknown span of `len` characters.
+pub(crate) fn oov_cost(len: usize) -> f64 {
+    COST_OOV + (len as f64 * 10.0)
+}
+
+// -----------------------------------------------------------------------------
+// Normalization
+// -----------------------------------------------------------------------------
+
+/// Softmax over negated path costs: the share of probability mass each
+/// analysis gets among the candidates. Higher `temperature` flattens it;
+/// it must be finite and positive, which callers check.
+pub(crate) fn softmax(costs: &[f64], temperature: f64) -> Vec<f64> {
+    debug_assert!(temperature.is_finite() && temperature > 0.0);
+    let best = costs.iter().copied().fold(f64::INFINITY, f64::min);
+    let weights: Vec<f64> = costs
+        .iter()
+        .map(|c| (-(c - best) / temperature).exp())
+        .collect();
+    let total: f64 = weights.iter().sum();
+    weights.iter().map(|w| w / total).collect()
+}
@@ -0,0 +1,109 @@
+use pyo3::prelude::*;
+
+use crate::decoder::{self, EdgeKind, Lattice};
+use crate::Morpheme;
+
+// -----------------------------------------------------------------------------
+// Segmentation Enumeration
+// -----------------------------------------------------------------------------
+
+/// Lazily walks every dictionary-consistent segmentation of a lattice.
+///
+/// Only dictionary edges are used, except that whitespace may be covered by
+/// its unknown edge; transition constraints are honored. Segmentations come
+/// out depth-first in lattice order, at most `limit` of them.
+#[pyclass]
+pub(crate) struct Segmentations {
+    lattice: Lattice<'static>,
+    // reachable[i]: some usable edge sequence leads from i to the end
+    reachable: Vec<bool>,
+    // (position, next edge offset to try) per depth; path[d] leads to stack[d + 1]
+    stack: Vec<(usize, usize)>,
+    path: Vec<usize>,
+    remaining: usize,
+}
+
+impl Segmentations {
+    pub fn new(lattice: Lattice<'static>, limit: usize) -> Self {
+        let n = lattice.len();
+        let mut reachable = vec![false; n + 1];
+        reachable[n] = true;
+        for i in (0..n).rev() {
+            reachable[i] = lattice
+                .edges_from(i)
+                .iter()
+                .any(|e| Self::usable(&lattice, e) && reachable[e.end]);
+        }
+        Segmentations {
+            lattice,
+            reachable,
+            stack: vec![(0, 0)],
+            path: Vec::new(),
+            remaining: limit,
+        }
+    }
+
+    fn usable(lattice: &Lattice, edge: &decoder::Edge) -> bool {
+        edge.kind != EdgeKind::Oov || lattice.chars[edge.start].is_whitespace()
+    }
+
+    fn next_path(&mut self) -> Option<Vec<usize>> {
+        let n = self.lattice.len();
+        while self.remaining > 0 {
+            let depth = self.stack.len();
+            let (pos, cursor) = *self.stack.last()?;
+            if pos == n {
+                let found = self.path.clone();
+                self.stack.pop();
+                self.path.pop();
+                self.remaining -= 1;
+                return Some(found);
+            }
+
+            let prev_pos = self
+                .path
+                .last()
+                .map(|&e| self.lattice.edges[e].pos.as_ref());
+            let candidates = self.lattice.edges_from(pos);
+            let next = candidates[cursor..].iter().position(|e| {
+                Self::usable(&self.lattice, e)
+                    && self.reachable[e.end]
+                    && self.lattice.step_cost(prev_pos, e).is_some()
+            });
+            match next {
+                Some(k) => {
+                    let edge = self.lattice.first_edge(pos) + cursor + k;
+                    self.stack[depth - 1].1 = cursor + k + 1;
+                    self.stack.push((self.lattice.edges[edge].end, 0));
+                    self.path.push(edge);
+                }
+                None => {
+                    self.stack.pop();
+                    self.path.pop();
+                }
+            }
+        }
+        None
+    }
+}
+
+#[pymethods]
+impl Segmentations {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> Option<Vec<Morpheme>> {
+        let edges = self.next_path()?;
+        Some(
+            edges
+                .iter()
+                .map(|&e| {
+                    let edge = &self.lattice.edges[e];
+                    let surface = self.lattice.surface(edge);
+                    (surface, edge.pos.to_string(), edge.lemma.to_string())
+                })
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,238 @@
+// -----------------------------------------------------------------------------
+// Sentence Boundaries
+// -----------------------------------------------------------------------------
+//
+// A terminator run (다. / ?! / …) ends a sentence only when followed by
+// whitespace or the end of the text, and only at the nesting level it
+// appears in: `"가자." 라고 했다.` is one sentence containing one quoted
+// sentence. A blank line closes everything, so an unbalanced quote cannot
+// swallow the rest of a document.
+
+use crate::grapheme;
+
+/// A sentence as `(start, end, depth)` in characters; depth 0 is top level,
+/// depth `d` lies inside `d` quotes or brackets.
+pub(crate) type SentenceSpan = (usize, usize, usize);
+
+fn is_terminator(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | '…' | '。' | '！' | '？')
+}
+
+fn closer_of(c: char) -> Option<char> {
+    Some(match c {
+        '“' => '”',
+        '‘' => '’',
+        '「' => '」',
+        '『' => '』',
+        '(' => ')',
+        '[' => ']',
+        '（' => '）',
+        '《' => '》',
+        '〈' => '〉',
+        '"' => '"',
+        '\'' => '\'',
+        _ => return None,
+    })
+}
+
+/// Words a quotation runs on into, reporting it: `"가자." 라고 했다`,
+/// `"정말요?" 하고 물었다`.
+const QUOTATIVES: [&str; 17] = [
+    "라고",
+    "이라고",
+    "라며",
+    "이라며",
+    "라면서",
+    "이라면서",
+    "라는",
+    "이라는",
+    "하고",
+    "하며",
+    "하면서",
+    "하는",
+    "하니",
+    "하자",
+    "하더니",
+    "고",
+    "며",
+];
+
+/// Whether the first word of `rest` (after whitespace) is a quotative:
+/// one of `QUOTATIVES` on its own or followed by more of the word, except
+/// 고 and 며, which must stand alone (고양이 does not report anything).
+fn is_quotative(rest: &[char]) -> bool {
+    let word: String = rest
+        .iter()
+        .skip_while(|c| c.is_whitespace())
+        .take_while(|c| c.is_alphanumeric())
+        .collect();
+    QUOTATIVES.iter().any(|q| match q.chars().count() {
+        1 => word == *q,
+        _ => word.starts_with(q),
+    })
+}
+
+struct Level {
+    close: char,
+    // Start of the pending sentence inside this level
+    start: usize,
+}
+
+/// Splits `text` into sentences, including the sentences of quoted speech.
+/// Spans are ordered by start, outer before inner, and exclude surrounding
+/// whitespace.
+pub(crate) fn split(text: &str) -> Vec<SentenceSpan> {
+    let chars: Vec<char> = text.chars().collect();
+    let n = chars.len();
+    let mut out = Vec::new();
+    let mut emit = |start: usize, end: usize, depth: usize| {
+        let (mut s, mut e) = (start, end);
+        while s < e && chars[s].is_whitespace() {
+            s += 1;
+        }
+        while e > s && chars[e - 1].is_whitespace() {
+            e -= 1;
+        }
+        if s < e {
+            out.push((s, e, depth));
+        }
+    };
+
+    let mut stack: Vec<Level> = Vec::new();
+    let mut top_start = 0;
+    let at_break = |j: usize| j == n || chars[j].is_whitespace();
+
+    let mut i = 0;
+    while i < n {
+        let c = chars[i];
+
+        if c == '\n'
+            && chars[i + 1..]
+                .iter()
+                .take_while(|c| c.is_whitespace())
+                .any(|&c| c == '\n')
+        {
+            while let Some(level) = stack.pop() {
+                emit(level.start, i, stack.len() + 1);
+            }
+            emit(top_start, i, 0);
+            top_start = i;
+            i += 1;
+            continue;
+        }
+
+        if stack.last().is_some_and(|l| l.close == c) {
+            let level = stack.pop().unwrap();
+            emit(level.start, i, stack.len() + 1);
+            // A quote ending in a terminator and followed by a break also
+            // ends the enclosing sentence ("네." "아니요."), unless the next
+            // word goes on to report it ("가자." 라고 했다.)
+            let ended = chars[level.start..i]
+                .iter()
+                .rev()
+                .find(|c| !c.is_whitespace())
+                .is_some_and(|&c| is_terminator(c));
+            i += 1;
+            if ended && at_break(i) && !is_quotative(&chars[i..]) {
+                let depth = stack.len();
+                match stack.last_mut() {
+                    Some(outer) => {
+                        emit(outer.start, i, depth);
+                        outer.start = i;
+                    }
+                    None => {
+                        emit(top_start, i, 0);
+                        top_start = i;
+                    }
+                }
+            }
+            continue;
+        }
+
+        if let Some(close) = closer_of(c) {
+            // An apostrophe inside a word (don't) opens nothing
+            if c != '\'' || i == 0 || !chars[i - 1].is_alphanumeric() {
+                stack.push(Level {
+                    close,
+                    start: i + 1,
+                });
+                i += 1;
+                continue;
+            }
+        }
+
+        if is_terminator(c) {
+            let mut j = i;
+            while j < n && is_terminator(chars[j]) {
+                j += 1;
+            }
+            if at_break(j) {
+                let depth = stack.len();
+                match stack.last_mut() {
+                    Some(level) => {
+                        emit(level.start, j, depth);
+                        level.start = j;
+                    }
+                    None => {
+                        emit(top_start, j, 0);
+                        top_start = j;
+                    }
+                }
+            }
+            i = j;
+            continue;
+        }
+        i += 1;
+    }
+    while let Some(level) = stack.pop() {
+        emit(level.start, n, stack.len() + 1);
+    }
+    emit(top_start, n, 0);
+
+    out.sort_by_key(|&(start, _, depth)| (start, depth));
+    out
+}
+
+/// Cuts `text` into consecutive pieces of at most `max_chars` characters,
+/// preferring sentence starts, then whitespace, then any grapheme cluster
+/// boundary. The pieces concatenate back to `text`; an empty text is one
+/// empty piece.
+pub(crate) fn chunks(text: &str, max_chars: usize) -> Vec<&str> {
+    let max_chars = max_chars.max(1);
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let n = chars.len();
+    if n <= max_chars {
+        return vec![text];
+    }
+    let starts: Vec<usize> = split(text)
+        .into_iter()
+        .filter(|&(s, _, depth)| depth == 0 && s > 0)
+        .map(|(s, _, _)| s)
+        .collect();
+    let byte = |i: usize| chars.get(i).map_or(text.len(), |&(b, _)| b);
+    let clusters = grapheme::boundaries(&chars.iter().map(|&(_, c)| c).collect::<Vec<_>>());
+
+    let mut out = Vec::new();
+    let mut start = 0;
+    while n - start > max_chars {
+        let limit = start + max_chars;
+        let at_sentence = starts
+            .partition_point(|&s| s <= limit)
+            .checked_sub(1)
+            .map(|k| starts[k])
+            .filter(|&s| s > start);
+        let cut = at_sentence
+            .or_else(|| {
+                (start + 1..=limit)
+                    .rev()
+                    .find(|&j| chars[j - 1].1.is_whitespace())
+            })
+            .or_else(|| (start + 1..=limit).rev().find(|&j| clusters[j]))
+            .or_else(|| (limit..n).find(|&j| clusters[j]))
+            .unwrap_or(limit);
+        out.push(&text[byte(start)..byte(cut)]);
+        start = cut;
+    }
+    out.push(&text[byte(start)..]);
+    out
+}
@@ -0,0 +1,381 @@
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::smallstr::SmallStr;
+use crate::{EntryMeta, TrieData, TriePattern};
+
+// -----------------------------------------------------------------------------
+// Saved Dictionary Files
+// -----------------------------------------------------------------------------
+//
+// `save_trie` files are a fixed header followed by the bincode payload:
+//
+//     magic[8] version:u32 crc32:u32 payload_len:u64
+//
+// The CRC-32 (IEEE) covers the payload, so truncated or corrupted files are
+// rejected instead of producing garbage analyses. Version 2 payloads store
+// POS tags and lemmas once in a string pool that patterns refer to by id;
+// version 1 payloads spelled them out in every pattern, and are still read.
+//
+// Files written before the header existed are bare payloads in one of the
+// older entry layouts below. They are recognized by which layout parses
+// the whole file, and read without verification.
+
+const MAGIC: &[u8; 8] = b"KULIMTR\0";
+const VERSION: u32 = 2;
+const HEADER_LEN: u64 = 24;
+
+/// Entry layouts of headerless files, oldest first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Legacy {
+    /// (pos, lemma) only
+    Plain,
+    /// With per-entry costs
+    Costs,
+    /// With costs and metadata, as in version 1 files
+    Headerless,
+}
+
+impl Legacy {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Legacy::Plain => "legacy headerless format without entry costs",
+            Legacy::Costs => "legacy headerless format with entry costs",
+            Legacy::Headerless => "legacy headerless format",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PlainPattern {
+    pos: String,
+    lemma: String,
+}
+
+#[derive(Deserialize)]
+struct CostPattern {
+    pos: String,
+    lemma: String,
+    cost: Option<f64>,
+}
+
+/// A pattern with its strings inline: version 1 files, and headerless ones
+/// from just before the header.
+#[derive(Deserialize)]
+struct InlinePattern {
+    pos: SmallStr,
+    lemma: SmallStr,
+    cost: Option<f64>,
+    meta: Option<Box<EntryMeta>>,
+}
+
+/// Moves the strings of inline patterns into a pool.
+fn pooled(dict: HashMap<SmallStr, Vec<InlinePattern>>) -> TrieData {
+    let mut data = TrieData::default();
+    data.dict.reserve(dict.len());
+    for (surface, patterns) in dict {
+        let patterns = patterns
+            .into_iter()
+            .map(|p| TriePattern {
+                pos: data.strings.intern(&p.pos),
+                lemma: data.strings.intern(&p.lemma),
+                cost: p.cost,
+                meta: p.meta,
+            })
+            .collect();
+        data.dict.insert(surface, patterns);
+    }
+    data
+}
+
+/// Parses `bytes` as a whole map of `P`, converting each pattern.
+fn parse_as<P: DeserializeOwned>(
+    bytes: &[u8],
+    convert: impl Fn(P) -> InlinePattern,
+) -> Option<TrieData> {
+    let dict: HashMap<SmallStr, Vec<P>> = options()
+        .with_limit(bytes.len() as u64)
+        .deserialize(bytes)
+        .ok()?;
+    let dict = dict
+        .into_iter()
+        .map(|(surface, patterns)| (surface, patterns.into_iter().map(&convert).collect()))
+        .collect();
+    Some(pooled(dict))
+}
+
+/// Recognizes a headerless file by its layout. Older layouts come first:
+/// a payload parses under a newer one only by coincidence, and then must
+/// still consume every byte.
+fn read_legacy(path: &Path) -> Result<(TrieData, Legacy), LoadError> {
+    let bytes = fs::read(path)?;
+    let plain = |p: PlainPattern| InlinePattern {
+        pos: p.pos.into(),
+        lemma: p.lemma.into(),
+        cost: None,
+        meta: None,
+    };
+    let costs = |p: CostPattern| InlinePattern {
+        pos: p.pos.into(),
+        lemma: p.lemma.into(),
+        cost: p.cost,
+        meta: None,
+    };
+    if let Some(data) = parse_as(&bytes, plain) {
+        return Ok((data, Legacy::Plain));
+    }
+    if let Some(data) = parse_as(&bytes, costs) {
+        return Ok((data, Legacy::Costs));
+    }
+    if let Some(data) = parse_as(&bytes, |p: InlinePattern| p) {
+        return Ok((data, Legacy::Headerless));
+    }
+    Err(LoadError::Format(
+        "not a KULIM dictionary file (no header, and no known legacy layout parses)".to_string(),
+    ))
+}
+
+/// Why a dictionary file could not be read.
+pub(crate) enum LoadError {
+    Io(io::Error),
+    /// The file is not a valid dictionary (bad checksum, truncated, ...)
+    Format(String),
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+/// Running CRC-32 (IEEE 802.3).
+#[derive(Clone, Copy)]
+pub(crate) struct Crc32(u32);
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Crc32(!0)
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = CRC_TABLE[((self.0 ^ b as u32) & 0xFF) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    pub fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+/// Passes bytes through while checksumming and counting them.
+struct Tee<T> {
+    inner: T,
+    crc: Crc32,
+    len: u64,
+}
+
+impl<T> Tee<T> {
+    fn new(inner: T) -> Self {
+        Tee {
+            inner,
+            crc: Crc32::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<W: Write> Write for Tee<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.crc.update(&buf[..n]);
+        self.len += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<R: Read> Read for Tee<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.crc.update(&buf[..n]);
+        self.len += n as u64;
+        Ok(n)
+    }
+}
+
+fn options() -> impl Options {
+    // Same encoding as `bincode::serialize`, which older files used
+    bincode::DefaultOptions::new().with_fixint_encoding()
+}
+
+/// Writes `data` with a header carrying its length and checksum.
+pub(crate) fn write(data: &TrieData, path: &Path) -> Result<(), String> {
+    let io = |e: io::Error| e.to_string();
+    let mut file = File::create(path).map_err(io)?;
+    file.write_all(&[0; HEADER_LEN as usize]).map_err(io)?;
+    let mut tee = Tee::new(BufWriter::new(&file));
+    options()
+        .serialize_into(&mut tee, data)
+        .map_err(|e| e.to_string())?;
+    tee.flush().map_err(io)?;
+    let (crc, len) = (tee.crc.finish(), tee.len);
+    drop(tee);
+
+    let mut header = Vec::with_capacity(HEADER_LEN as usize);
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&VERSION.to_le_bytes());
+    header.extend_from_slice(&crc.to_le_bytes());
+    header.extend_from_slice(&len.to_le_bytes());
+    file.seek(SeekFrom::Start(0)).map_err(io)?;
+    file.write_all(&header).map_err(io)
+}
+
+/// The version, checksum and payload length of a header, given how many
+/// bytes follow it.
+fn parse_header(header: &[u8], payload: u64) -> Result<(u32, u32, u64), LoadError> {
+    let version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+    if version != 1 && version != VERSION {
+        return Err(LoadError::Format(format!(
+            "unsupported dictionary file version {}",
+            version
+        )));
+    }
+    let crc = u32::from_le_bytes(header[12..16].try_into().unwrap());
+    let len = u64::from_le_bytes(header[16..24].try_into().unwrap());
+    if payload != len {
+        return Err(LoadError::Format(format!(
+            "dictionary file is truncated or padded: expected {} payload bytes, found {}",
+            len, payload
+        )));
+    }
+    Ok((version, crc, len))
+}
+
+fn deserialize<R: Read>(version: u32, reader: R, len: u64) -> bincode::Result<TrieData> {
+    if version == 1 {
+        options()
+            .with_limit(len)
+            .deserialize_from::<_, HashMap<SmallStr, Vec<InlinePattern>>>(reader)
+            .map(pooled)
+    } else {
+        options()
+            .with_limit(len)
+            .deserialize_from::<_, TrieData>(reader)
+    }
+}
+
+/// The version in the header of the dictionary file at `path`; `None` for
+/// headerless legacy files.
+pub(crate) fn file_version(path: &Path) -> Option<u32> {
+    let mut header = [0u8; 12];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .ok()?;
+    (&header[..8] == MAGIC).then(|| u32::from_le_bytes(header[8..12].try_into().unwrap()))
+}
+
+/// Reads a dictionary written by `write`, or a headerless legacy file,
+/// reporting which legacy layout it had.
+pub(crate) fn read(path: &Path) -> Result<(TrieData, Option<Legacy>), LoadError> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut header = [0u8; HEADER_LEN as usize];
+    let has_header = size >= HEADER_LEN && {
+        file.read_exact(&mut header)?;
+        &header[..8] == MAGIC
+    };
+    if !has_header {
+        return read_legacy(path).map(|(data, legacy)| (data, Some(legacy)));
+    }
+
+    let (version, crc, len) = parse_header(&header, size - HEADER_LEN)?;
+    let mut tee = Tee::new(BufReader::new(file));
+    let parsed = deserialize(version, &mut tee, len);
+    // Checksum whatever the parser left unread so the verdict covers the file
+    io::copy(&mut tee, &mut io::sink())?;
+    if tee.crc.finish() != crc {
+        return Err(LoadError::Format(
+            "dictionary file checksum mismatch (corrupted)".to_string(),
+        ));
+    }
+    parsed
+        .map(|data| (data, None))
+        .map_err(|e| LoadError::Format(e.to_string()))
+}
+
+/// How many bytes `encode_into` writes for `data`.
+pub(crate) fn encoded_len(data: &TrieData) -> Result<usize, String> {
+    let payload = options().serialized_size(data).map_err(|e| e.to_string())?;
+    Ok((HEADER_LEN + payload) as usize)
+}
+
+/// Writes `data` in the file layout of `write` to the front of `buf`, which
+/// must hold `encoded_len(data)` bytes, and returns how many were written.
+pub(crate) fn encode_into(data: &TrieData, buf: &mut [u8]) -> Result<usize, String> {
+    let total = encoded_len(data)?;
+    if buf.len() < total {
+        return Err(format!(
+            "buffer holds {} bytes; the dictionary needs {}",
+            buf.len(),
+            total
+        ));
+    }
+    let (header, payload) = buf[..total].split_at_mut(HEADER_LEN as usize);
+    options()
+        .serialize_into(&mut &mut *payload, data)
+        .map_err(|e| e.to_string())?;
+    let mut crc = Crc32::new();
+    crc.update(payload);
+    header[..8].copy_from_slice(MAGIC);
+    header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+    header[12..16].copy_from_slice(&crc.finish().to_le_bytes());
+    header[16..24].copy_from_slice(&(payload.len() as u64).to_le_bytes());
+    Ok(total)
+}
+
+/// Reads a dictionary in the layout of `write` straight from memory.
+/// Headerless legacy layouts are only read from files.
+pub(crate) fn decode(bytes: &[u8]) -> Result<TrieData, LoadError> {
+    if bytes.len() < HEADER_LEN as usize || &bytes[..8] != MAGIC {
+        return Err(LoadError::Format("not a serialized dictionary".to_string()));
+    }
+    let (header, payload) = bytes.split_at(HEADER_LEN as usize);
+    let (version, crc, len) = parse_header(header, payload.len() as u64)?;
+    let mut check = Crc32::new();
+    check.update(payload);
+    if check.finish() != crc {
+        return Err(LoadError::Format(
+            "dictionary file checksum mismatch (corrupted)".to_string(),
+        ));
+    }
+    deserialize(version, payload, len).map_err(|e| LoadError::Format(e.to_string()))
+}
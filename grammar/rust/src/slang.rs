@@ -0,0 +1,101 @@
+use crate::TrieData;
+
+// -----------------------------------------------------------------------------
+// Emoticon and Internet-Slang Table
+// -----------------------------------------------------------------------------
+//
+// Consulted as an extra dictionary after the regular ones, so chat text
+// that would otherwise decay into single-character unknowns gets tagged.
+// Emoticons are SW (other symbols); contractions keep their usual class
+// with the spelled-out form as lemma.
+
+const EMOTICONS: &[&str] = &[
+    "^^",
+    "^^;",
+    "^_^",
+    "^-^",
+    "^o^",
+    "^.^",
+    "(^_^)",
+    "(^^)",
+    "ㅠㅠ",
+    "ㅜㅜ",
+    "ㅠ_ㅠ",
+    "ㅜ_ㅜ",
+    "(ㅠ_ㅠ)",
+    "T_T",
+    "T.T",
+    ";_;",
+    ">_<",
+    "-_-",
+    "-_-;",
+    "-.-",
+    "o_O",
+    "O_o",
+    "@_@",
+    "*^^*",
+    ":)",
+    ":(",
+    ":D",
+    ":P",
+    ";)",
+    ":-)",
+    ":-(",
+    "<3",
+    "♥",
+    "♡",
+    "☆",
+    "★",
+    "¯\\_(ツ)_/¯",
+    "(╯°□°)╯︵ ┻━┻",
+    "(づ｡◕‿‿◕｡)づ",
+    "(｡•́︿•̀｡)",
+];
+
+const SLANG: &[(&str, &str, &str)] = &[
+    ("ㅋㅋ", "IC", "ㅋㅋ"),
+    ("ㅎㅎ", "IC", "ㅎㅎ"),
+    ("ㅇㅋ", "IC", "오케이"),
+    ("ㅇㅇ", "IC", "응응"),
+    ("ㄴㄴ", "IC", "노노"),
+    ("ㄱㄱ", "IC", "고고"),
+    ("ㅎㅇ", "IC", "하이"),
+    ("ㅂㅂ", "IC", "바이바이"),
+    ("ㄳ", "IC", "감사"),
+    ("ㄱㅅ", "IC", "감사"),
+    ("ㅈㅅ", "IC", "죄송"),
+    ("ㅊㅋ", "IC", "축하"),
+    ("ㅅㄱ", "IC", "수고"),
+    ("ㄷㄷ", "IC", "덜덜"),
+    ("ㅇㅈ", "IC", "인정"),
+    ("ㄹㅇ", "MAG", "리얼"),
+    ("갠톡", "NNG", "개인톡"),
+    ("단톡", "NNG", "단체톡"),
+    ("단톡방", "NNG", "단체톡방"),
+    ("생파", "NNG", "생일파티"),
+    ("열공", "NNG", "열심히공부"),
+    ("카톡", "NNG", "카카오톡"),
+    ("셀카", "NNG", "셀프카메라"),
+    ("넘", "MAG", "너무"),
+    ("젤", "MAG", "제일"),
+    ("걍", "MAG", "그냥"),
+    ("넘나", "MAG", "너무나"),
+    ("겜", "NNG", "게임"),
+];
+
+/// The built-in table as a dictionary.
+pub(crate) fn builtin() -> TrieData {
+    let mut data = TrieData::default();
+    for e in EMOTICONS {
+        data.insert(e.to_string(), "SW".to_string(), e.to_string(), None);
+    }
+    for (surface, pos, lemma) in SLANG {
+        data.insert(
+            surface.to_string(),
+            pos.to_string(),
+            lemma.to_string(),
+            None,
+        );
+    }
+    data
+}
@@ -0,0 +1,183 @@
+use serde::de::{Deserializer, Error, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+// -----------------------------------------------------------------------------
+// Small Strings
+// -----------------------------------------------------------------------------
+//
+// Surfaces, POS tags and lemmas are short: up to seven Hangul syllables
+// (three bytes each) fit in the bytes a `String` spends on its pointer,
+// length and capacity, so nearly every entry needs no heap allocation of
+// its own. Serialized exactly like a `String`.
+
+/// Longest string kept inline, in bytes.
+const INLINE: usize = 22;
+
+/// An immutable string stored inline when short, on the heap otherwise.
+#[derive(Clone)]
+pub(crate) enum SmallStr {
+    Inline { len: u8, buf: [u8; INLINE] },
+    Heap(Box<str>),
+}
+
+// No larger than the `String` it replaces
+const _: () = assert!(std::mem::size_of::<SmallStr>() == std::mem::size_of::<String>());
+
+impl SmallStr {
+    pub fn new(s: &str) -> Self {
+        if s.len() <= INLINE {
+            let mut buf = [0; INLINE];
+            buf[..s.len()].copy_from_slice(s.as_bytes());
+            SmallStr::Inline {
+                len: s.len() as u8,
+                buf,
+            }
+        } else {
+            SmallStr::Heap(s.into())
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            // Only ever filled from a `&str`, up to `len`
+            SmallStr::Inline { len, buf } => unsafe {
+                std::str::from_utf8_unchecked(&buf[..*len as usize])
+            },
+            SmallStr::Heap(s) => s,
+        }
+    }
+}
+
+impl Default for SmallStr {
+    fn default() -> Self {
+        SmallStr::new("")
+    }
+}
+
+impl Deref for SmallStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for SmallStr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+// Hashes and compares as the `str` it holds, so maps keyed by `SmallStr`
+// are searched with a `&str`.
+impl Borrow<str> for SmallStr {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Hash for SmallStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialEq for SmallStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for SmallStr {}
+
+impl PartialOrd for SmallStr {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SmallStr {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl PartialEq<str> for SmallStr {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for SmallStr {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for SmallStr {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl From<&str> for SmallStr {
+    fn from(s: &str) -> Self {
+        SmallStr::new(s)
+    }
+}
+
+impl From<String> for SmallStr {
+    fn from(s: String) -> Self {
+        if s.len() <= INLINE {
+            SmallStr::new(&s)
+        } else {
+            SmallStr::Heap(s.into_boxed_str())
+        }
+    }
+}
+
+impl fmt::Display for SmallStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for SmallStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Serialize for SmallStr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for SmallStr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Small;
+
+        impl Visitor<'_> for Small {
+            type Value = SmallStr;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string")
+            }
+
+            fn visit_str<E: Error>(self, s: &str) -> Result<SmallStr, E> {
+                Ok(SmallStr::new(s))
+            }
+
+            fn visit_string<E: Error>(self, s: String) -> Result<SmallStr, E> {
+                Ok(SmallStr::from(s))
+            }
+        }
+
+        deserializer.deserialize_str(Small)
+    }
+}
@@ -0,0 +1,51 @@
+// -----------------------------------------------------------------------------
+// Hashtags and Mentions
+// -----------------------------------------------------------------------------
+
+/// Tags given to `#태그` and `@아이디` tokens.
+#[derive(Clone, Debug)]
+pub(crate) struct SocialTags {
+    pub hashtag: String,
+    pub mention: String,
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.'
+}
+
+/// Locates hashtag and mention spans as `(start, end, tag)`, in order and
+/// non-overlapping. A marker glued to a preceding letter or digit (`C#`,
+/// `a@b.com`) does not start a token; a mention never ends with a dot.
+pub(crate) fn find_spans<'t>(chars: &[char], tags: &'t SocialTags) -> Vec<(usize, usize, &'t str)> {
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let glued = i > 0 && chars[i - 1].is_alphanumeric();
+        let (accept, tag): (fn(char) -> bool, &str) = match chars[i] {
+            '#' if !glued => (is_tag_char, &tags.hashtag),
+            '@' if !glued => (is_id_char, &tags.mention),
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let mut end = i + 1;
+        while end < chars.len() && accept(chars[end]) {
+            end += 1;
+        }
+        while end > i + 1 && chars[end - 1] == '.' {
+            end -= 1;
+        }
+        if end > i + 1 {
+            spans.push((i, end, tag));
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    spans
+}
@@ -0,0 +1,219 @@
+// -----------------------------------------------------------------------------
+// Structured Tokens
+// -----------------------------------------------------------------------------
+//
+// Phone numbers, resident registration numbers, business registration
+// numbers and email addresses, recognized by their shape before the
+// lattice is built. Each becomes one protected token like a hashtag,
+// rather than digits and punctuation split into separate morphemes.
+// Shapes only are checked (an RRN's date, not its check digit), so that
+// typos and masked forms (900101-1******) are still caught.
+
+/// The recognizers that are on, each with the tag of its tokens.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct StructuredTokens {
+    pub phone: Option<String>,
+    pub rrn: Option<String>,
+    pub business: Option<String>,
+    pub email: Option<String>,
+}
+
+impl StructuredTokens {
+    pub fn is_empty(&self) -> bool {
+        self.phone.is_none()
+            && self.rrn.is_none()
+            && self.business.is_none()
+            && self.email.is_none()
+    }
+}
+
+// Area codes after the leading 0: mobile, Seoul, the provinces, VoIP
+const AREA_CODES: &[&str] = &[
+    "10", "11", "16", "17", "18", "19", "2", "31", "32", "33", "41", "42", "43", "44", "51", "52",
+    "53", "54", "55", "61", "62", "63", "64", "70",
+];
+
+// The end of a token of one kind starting at a position, if one does
+type Recognizer = fn(&[char], usize) -> Option<usize>;
+
+fn is_email_local(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '%' | '+' | '-')
+}
+
+fn is_email_domain(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '.' | '-')
+}
+
+/// Runs of ASCII digits separated by one separator, e.g. 010-1234-5678 as
+/// ["010", "1234", "5678"] with '-', starting at `i`.
+fn digit_groups(chars: &[char], i: usize) -> Vec<(Vec<char>, Option<char>, usize)> {
+    // (group, separator before the next group, end of the group)
+    let mut groups = Vec::new();
+    let mut at = i;
+    loop {
+        let start = at;
+        while at < chars.len() && chars[at].is_ascii_digit() {
+            at += 1;
+        }
+        if at == start {
+            break;
+        }
+        let sep = chars
+            .get(at)
+            .copied()
+            .filter(|c| matches!(c, '-' | '.' | ' '));
+        let next_digit = chars.get(at + 1).is_some_and(char::is_ascii_digit);
+        groups.push((chars[start..at].to_vec(), sep.filter(|_| next_digit), at));
+        match sep {
+            Some(_) if next_digit => at += 1,
+            _ => break,
+        }
+    }
+    groups
+}
+
+/// The end of a phone number starting at `i`, if one does.
+fn phone(chars: &[char], i: usize) -> Option<usize> {
+    let (international, from) = match chars[i] {
+        '+' if chars[i + 1..].starts_with(&['8', '2']) => {
+            let skip = match chars.get(i + 3) {
+                Some('-' | ' ' | '.') => 4,
+                _ => 3,
+            };
+            (true, i + skip)
+        }
+        _ => (false, i),
+    };
+    let groups = digit_groups(chars, from);
+    let seps: Vec<Option<char>> = groups.iter().map(|g| g.1).collect();
+    let lens: Vec<usize> = groups.iter().map(|g| g.0.len()).collect();
+    let text = |k: usize| -> String { groups[k].0.iter().collect() };
+    // The number is the first groups joined by one kind of separator
+    let consistent = |n: usize| seps[..n - 1].iter().all(|&s| s.is_some() && s == seps[0]);
+    // Representative numbers: 1588-1234
+    if !international && groups.len() >= 2 && consistent(2) {
+        let head = text(0);
+        if lens[0] == 4 && lens[1] == 4 && ["15", "16", "18"].iter().any(|p| head.starts_with(p)) {
+            return Some(groups[1].2);
+        }
+    }
+    let area_ok = |area: &str| match international {
+        true => AREA_CODES.contains(&area),
+        false => area
+            .strip_prefix('0')
+            .is_some_and(|a| AREA_CODES.contains(&a)),
+    };
+    if groups.len() >= 3
+        && consistent(3)
+        && area_ok(&text(0))
+        && (3..=4).contains(&lens[1])
+        && lens[2] == 4
+    {
+        return Some(groups[2].2);
+    }
+    // Unseparated mobile numbers: 01012345678
+    let digits = text(0);
+    let unseparated =
+        !international && (lens[0] == 10 || lens[0] == 11) && digits.starts_with("01");
+    (unseparated && area_ok(&digits[..3])).then(|| groups[0].2)
+}
+
+/// The end of a resident registration number (YYMMDD-NNNNNNN, the last
+/// six digits possibly masked with `*`) starting at `i`.
+fn rrn(chars: &[char], i: usize) -> Option<usize> {
+    let front = chars.get(i..i + 6)?;
+    if !front.iter().all(char::is_ascii_digit) || chars.get(i + 6) != Some(&'-') {
+        return None;
+    }
+    let number =
+        |a: usize, b: usize| -> u32 { front[a..b].iter().collect::<String>().parse().unwrap_or(0) };
+    if !(1..=12).contains(&number(2, 4)) || !(1..=31).contains(&number(4, 6)) {
+        return None;
+    }
+    let back = chars.get(i + 7..i + 14)?;
+    let ok =
+        matches!(back[0], '1'..='8') && back[1..].iter().all(|&c| c.is_ascii_digit() || c == '*');
+    ok.then_some(i + 14)
+}
+
+/// The end of a business registration number (NNN-NN-NNNNN) at `i`.
+fn business(chars: &[char], i: usize) -> Option<usize> {
+    let groups = digit_groups(chars, i);
+    let lens: Vec<usize> = groups.iter().take(3).map(|g| g.0.len()).collect();
+    let dashed = groups.len() >= 3 && groups[0].1 == Some('-') && groups[1].1 == Some('-');
+    (dashed && lens == [3, 2, 5]).then(|| groups[2].2)
+}
+
+/// The end of an email address starting at `i`.
+fn email(chars: &[char], i: usize) -> Option<usize> {
+    let mut at = i;
+    while at < chars.len() && is_email_local(chars[at]) {
+        at += 1;
+    }
+    if at == i || chars.get(at) != Some(&'@') || chars[i] == '.' || chars[at - 1] == '.' {
+        return None;
+    }
+    let domain = at + 1;
+    let mut end = domain;
+    while end < chars.len() && is_email_domain(chars[end]) {
+        end += 1;
+    }
+    while end > domain && matches!(chars[end - 1], '.' | '-') {
+        end -= 1;
+    }
+    let host: String = chars[domain..end].iter().collect();
+    let labels: Vec<&str> = host.split('.').collect();
+    let tld = labels.last().copied().unwrap_or("");
+    let ok = labels.len() >= 2
+        && labels.iter().all(|l| !l.is_empty() && !l.starts_with('-'))
+        && tld.len() >= 2
+        && tld.chars().all(|c| c.is_ascii_alphabetic());
+    ok.then_some(end)
+}
+
+/// Locates structured tokens as `(start, end, tag)`, in order and
+/// non-overlapping. A token must not be glued to a Latin letter or digit
+/// on either side; Hangul may touch it, as particles do (010-1234-5678로).
+pub(crate) fn find_spans<'t>(
+    chars: &[char],
+    tokens: &'t StructuredTokens,
+) -> Vec<(usize, usize, &'t str)> {
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let glued = i > 0
+            && (chars[i - 1].is_ascii_alphanumeric()
+                || (is_email_local(c) && is_email_local(chars[i - 1])));
+        let free = |end: usize| end >= chars.len() || !chars[end].is_ascii_alphanumeric();
+        let mut found = None;
+        if !glued && (c.is_ascii_digit() || c == '+') {
+            let recognizers: [(&Option<String>, Recognizer); 3] = [
+                (&tokens.rrn, rrn),
+                (&tokens.business, business),
+                (&tokens.phone, phone),
+            ];
+            found = recognizers.iter().find_map(|(tag, recognize)| {
+                let tag = tag.as_deref()?;
+                recognize(chars, i)
+                    .filter(|&end| free(end))
+                    .map(|end| (end, tag))
+            });
+        }
+        if found.is_none() && !glued && is_email_local(c) {
+            if let Some(tag) = tokens.email.as_deref() {
+                found = email(chars, i)
+                    .filter(|&end| free(end))
+                    .map(|end| (end, tag));
+            }
+        }
+        match found {
+            Some((end, tag)) => {
+                spans.push((i, end, tag));
+                i = end;
+            }
+            None => i += 1,
+        }
+    }
+    spans
+}
@@ -0,0 +1,93 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::Located;
+
+// -----------------------------------------------------------------------------
+// Subword Pieces
+// -----------------------------------------------------------------------------
+//
+// A segmentation for neural models whose pieces never cross a morpheme
+// boundary: a morpheme is one piece when the vocabulary has it and falls
+// back to one piece per syllable otherwise. As in WordPiece, pieces after
+// the first of an eojeol carry a continuation prefix (`##`), so the text is
+// recoverable from the pieces and whitespace.
+
+/// A piece with its `(start, end)` character span in the analyzed text.
+pub(crate) type Piece = (String, usize, usize);
+
+/// The pieces of an analysis; `known` says whether a morpheme is one piece,
+/// given the piece (with its continuation prefix, if any) and its surface.
+pub(crate) fn pieces(
+    morphs: &[Located],
+    continuation: &str,
+    known: impl Fn(&str, &str) -> bool,
+) -> Vec<Piece> {
+    let mut out = Vec::new();
+    let mut initial = true;
+    for (surface, _, _, start, end) in morphs {
+        if surface.chars().all(char::is_whitespace) {
+            initial = true;
+            continue;
+        }
+        let prefix = if initial { "" } else { continuation };
+        let piece = format!("{}{}", prefix, surface);
+        initial = false;
+        if known(&piece, surface) {
+            out.push((piece, *start, *end));
+            continue;
+        }
+        // Syllables keep their own spans unless normalization changed the
+        // morpheme's length, when each takes the morpheme's
+        let len = surface.chars().count();
+        for (k, c) in surface.chars().enumerate() {
+            let prefix = if k == 0 { prefix } else { continuation };
+            let span = match len == end - start {
+                true => (start + k, start + k + 1),
+                false => (*start, *end),
+            };
+            out.push((format!("{}{}", prefix, c), span.0, span.1));
+        }
+    }
+    out
+}
+
+/// Piece counts over a corpus, for exporting a vocabulary.
+#[derive(Default)]
+pub(crate) struct VocabCounts {
+    morphemes: HashMap<String, usize>,
+    syllables: HashSet<String>,
+}
+
+impl VocabCounts {
+    /// Counts the morpheme pieces of an analysis and notes its syllable
+    /// pieces.
+    pub fn add(&mut self, morphs: &[Located], continuation: &str) {
+        for (piece, ..) in pieces(morphs, continuation, |_, _| true) {
+            *self.morphemes.entry(piece).or_default() += 1;
+        }
+        for (piece, ..) in pieces(morphs, continuation, |_, _| false) {
+            self.syllables.insert(piece);
+        }
+    }
+
+    /// Every syllable piece seen, so that any analyzed text can be encoded,
+    /// then the morpheme pieces seen `min_count` times or more, most
+    /// frequent first, up to `max_size` pieces in all.
+    pub fn vocabulary(&self, min_count: usize, max_size: Option<usize>) -> Vec<String> {
+        let mut syllables: Vec<&String> = self.syllables.iter().collect();
+        syllables.sort();
+        let mut morphemes: Vec<(&String, usize)> = self
+            .morphemes
+            .iter()
+            .filter(|(piece, &count)| count >= min_count && !self.syllables.contains(*piece))
+            .map(|(piece, &count)| (piece, count))
+            .collect();
+        morphemes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        syllables
+            .into_iter()
+            .chain(morphemes.into_iter().map(|(piece, _)| piece))
+            .take(max_size.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+}
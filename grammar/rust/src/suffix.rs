@@ -0,0 +1,92 @@
+use crate::view::DictView;
+
+// -----------------------------------------------------------------------------
+// Reverse (Suffix) Trie of Endings
+// -----------------------------------------------------------------------------
+//
+// Eomi (E*) and josa (J*) entries keyed by their reversed surface, so every
+// ending of an eojeol is found in one right-to-left walk instead of one hash
+// lookup per candidate length.
+
+#[derive(Default)]
+struct Node {
+    // Sorted by character
+    children: Vec<(char, u32)>,
+    // (pos, lemma) of the endings spelled by the path to this node
+    entries: Vec<(String, String)>,
+}
+
+/// An ending found at the right edge of a word.
+pub(crate) struct EndingMatch<'a> {
+    /// Character length of the ending
+    pub len: usize,
+    pub pos: &'a str,
+    pub lemma: &'a str,
+}
+
+pub(crate) struct SuffixIndex {
+    nodes: Vec<Node>,
+}
+
+pub(crate) fn is_ending(pos: &str) -> bool {
+    pos.starts_with('E') || pos.starts_with('J')
+}
+
+impl SuffixIndex {
+    /// Indexes the ending entries visible in `view`.
+    pub fn build(view: &DictView) -> Self {
+        let mut index = SuffixIndex {
+            nodes: vec![Node::default()],
+        };
+        for surface in view.surfaces() {
+            for pat in view.lookup(&surface) {
+                if is_ending(pat.pos) {
+                    let node = index.path_to(&surface);
+                    index.nodes[node]
+                        .entries
+                        .push((pat.pos.to_string(), pat.lemma.to_string()));
+                }
+            }
+        }
+        index
+    }
+
+    fn path_to(&mut self, surface: &str) -> usize {
+        let mut node = 0;
+        for c in surface.chars().rev() {
+            let children = &self.nodes[node].children;
+            node = match children.binary_search_by_key(&c, |&(k, _)| k) {
+                Ok(i) => children[i].1 as usize,
+                Err(i) => {
+                    let id = self.nodes.len();
+                    self.nodes[node].children.insert(i, (c, id as u32));
+                    self.nodes.push(Node::default());
+                    id
+                }
+            };
+        }
+        node
+    }
+
+    /// Endings of `chars`, longest first.
+    pub fn endings(&self, chars: &[char]) -> Vec<EndingMatch<'_>> {
+        let mut out = Vec::new();
+        let mut node = 0;
+        for (len, c) in chars.iter().rev().enumerate() {
+            let children = &self.nodes[node].children;
+            match children.binary_search_by_key(c, |&(k, _)| k) {
+                Ok(i) => node = children[i].1 as usize,
+                Err(_) => break,
+            }
+            for (pos, lemma) in &self.nodes[node].entries {
+                out.push(EndingMatch {
+                    len: len + 1,
+                    pos,
+                    lemma,
+                });
+            }
+        }
+        out.reverse();
+        out
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+
+// -----------------------------------------------------------------------------
+// POS Tag Inventory
+// -----------------------------------------------------------------------------
+
+/// The TTA tag set (TTAK.KO-11.0010/R1), as listed in `pos_tags.py`.
+pub const STANDARD_TAGS: &[&str] = &[
+    "NNG", "NNP", "NNB", "NR", "NP", "VV", "VA", "VX", "VCP", "VCN", "MM", "MAG", "MAJ", "IC",
+    "JKS", "JKC", "JKG", "JKO", "JKB", "JKV", "JKQ", "JX", "JC", "EP", "EF", "EC", "ETN", "ETM",
+    "XPN", "XSN", "XSV", "XSA", "XR", "SF", "SP", "SS", "SE", "SO", "SW", "SL", "SH", "SN", "NA",
+];
+
+/// What to do with an entry whose tag is not in the inventory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnknownTag {
+    Allow,
+    Warn,
+    Reject,
+}
+
+impl UnknownTag {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(UnknownTag::Allow),
+            "warn" => Some(UnknownTag::Warn),
+            "reject" => Some(UnknownTag::Reject),
+            _ => None,
+        }
+    }
+}
+
+/// The tags entries may carry. Scoring keys on tag prefixes
+/// (`starts_with('N')`), so a stray "NNg" or "VV " silently loses every
+/// bonus its class would get.
+#[derive(Clone, Debug)]
+pub struct TagSet {
+    tags: HashSet<String>,
+    pub(crate) unknown: UnknownTag,
+}
+
+impl Default for TagSet {
+    fn default() -> Self {
+        TagSet::new(
+            STANDARD_TAGS.iter().map(|t| t.to_string()),
+            UnknownTag::Warn,
+        )
+    }
+}
+
+impl TagSet {
+    pub fn new(tags: impl IntoIterator<Item = String>, unknown: UnknownTag) -> Self {
+        TagSet {
+            tags: tags.into_iter().collect(),
+            unknown,
+        }
+    }
+
+    /// The inventory, sorted.
+    pub(crate) fn tags(&self) -> Vec<&str> {
+        let mut tags: Vec<&str> = self.tags.iter().map(String::as_str).collect();
+        tags.sort_unstable();
+        tags
+    }
+
+    /// Why `pos` is not acceptable, or `None` if it is in the inventory.
+    /// Compound tags of contracted forms (VV+EP) need every part known.
+    pub(crate) fn problem(&self, pos: &str) -> Option<String> {
+        if self.tags.contains(pos) || pos.split('+').all(|part| self.tags.contains(part)) {
+            return None;
+        }
+        let fixed = pos.trim().to_ascii_uppercase();
+        Some(if self.tags.contains(&fixed) {
+            format!("unknown POS tag '{}' (did you mean '{}'?)", pos, fixed)
+        } else {
+            format!("unknown POS tag '{}'", pos)
+        })
+    }
+}
@@ -0,0 +1,176 @@
+use crate::grammar::Transitions;
+
+// -----------------------------------------------------------------------------
+// Weight Tuning
+// -----------------------------------------------------------------------------
+//
+// `tune_weights` searches transition bonuses against a gold corpus instead
+// of editing the constants in `scoring` and rebuilding. A parameter is a
+// transition rule, named as in score breakdowns (BONUS_NOUN_JOSA, or
+// `prev -> curr` for rules set from Python), with a list of values to try
+// or a range to sample. Candidates are scored by morpheme F1 over the
+// eojeol-aligned `morph/POS` tokens of each case.
+
+/// The values a parameter may take.
+#[derive(Clone, Debug)]
+pub(crate) enum Values {
+    Choices(Vec<f64>),
+    /// Sampled uniformly between the bounds
+    Range(f64, f64),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Param {
+    pub name: String,
+    pub values: Values,
+}
+
+/// Fails on a parameter that names no rule of `transitions` or has no
+/// usable values.
+pub(crate) fn check(transitions: &Transitions, space: &[Param]) -> Result<(), String> {
+    for param in space {
+        if !transitions.rules().iter().any(|r| r.label() == param.name) {
+            return Err(format!("no transition rule named '{}'", param.name));
+        }
+        let ok = match &param.values {
+            Values::Choices(values) => !values.is_empty() && values.iter().all(|v| v.is_finite()),
+            Values::Range(lo, hi) => lo.is_finite() && hi.is_finite() && lo <= hi,
+        };
+        if !ok {
+            return Err(format!(
+                "'{}' needs a non-empty list of finite values or a (low, high) range",
+                param.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The candidate values of `space`, one per parameter in order: every
+/// combination of the choices without `trials`, otherwise `trials` random
+/// combinations (the whole grid when it is no larger).
+pub(crate) fn candidates(
+    space: &[Param],
+    trials: Option<usize>,
+    seed: u64,
+) -> Result<Vec<Vec<f64>>, String> {
+    let grid = space
+        .iter()
+        .try_fold(1usize, |size, param| match &param.values {
+            Values::Choices(values) => Some(size.saturating_mul(values.len())),
+            Values::Range(..) => None,
+        });
+    match (grid, trials) {
+        (None, None) => Err("ranges are sampled; give the number of trials".to_string()),
+        (_, Some(0)) => Err("trials must be positive".to_string()),
+        (Some(size), Some(trials)) if size <= trials => Ok(product(space)),
+        (Some(_), None) => Ok(product(space)),
+        (_, Some(trials)) => {
+            let mut rng = SplitMix(seed);
+            Ok((0..trials).map(|_| sample(space, &mut rng)).collect())
+        }
+    }
+}
+
+fn product(space: &[Param]) -> Vec<Vec<f64>> {
+    let mut out = vec![Vec::with_capacity(space.len())];
+    for param in space {
+        let Values::Choices(values) = &param.values else {
+            unreachable!("ranges are never enumerated");
+        };
+        out = out
+            .into_iter()
+            .flat_map(|prefix| {
+                values.iter().map(move |&v| {
+                    let mut next = prefix.clone();
+                    next.push(v);
+                    next
+                })
+            })
+            .collect();
+    }
+    out
+}
+
+fn sample(space: &[Param], rng: &mut SplitMix) -> Vec<f64> {
+    space
+        .iter()
+        .map(|param| match &param.values {
+            Values::Choices(values) => values[(rng.next() % values.len() as u64) as usize],
+            Values::Range(lo, hi) => lo + rng.unit() * (hi - lo),
+        })
+        .collect()
+}
+
+/// `transitions` with the bonus of each rule of `space` set to `values`.
+pub(crate) fn apply(transitions: &Transitions, space: &[Param], values: &[f64]) -> Transitions {
+    let mut out = transitions.clone();
+    for (param, &bonus) in space.iter().zip(values) {
+        let rules: Vec<_> = out
+            .rules()
+            .iter()
+            .filter(|r| r.label() == param.name)
+            .cloned()
+            .collect();
+        for mut rule in rules {
+            rule.bonus = bonus;
+            out.set(rule);
+        }
+    }
+    out
+}
+
+/// Token counts behind a morpheme F1.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Counts {
+    pub matched: usize,
+    pub gold: usize,
+    pub predicted: usize,
+}
+
+impl Counts {
+    /// Adds the tokens of one case, both analyses in the regression form
+    /// (`morph/POS+morph/POS` eojeols separated by spaces).
+    pub fn add(&mut self, expected: &str, actual: &str) {
+        let tokens = |line: &str| -> Vec<(usize, String)> {
+            line.split_whitespace()
+                .enumerate()
+                .flat_map(|(e, eojeol)| eojeol.split('+').map(move |t| (e, t.to_string())))
+                .collect()
+        };
+        let (gold, mut predicted) = (tokens(expected), tokens(actual));
+        self.gold += gold.len();
+        self.predicted += predicted.len();
+        for token in gold {
+            if let Some(k) = predicted.iter().position(|p| *p == token) {
+                predicted.swap_remove(k);
+                self.matched += 1;
+            }
+        }
+    }
+
+    pub fn f1(&self) -> f64 {
+        match self.gold + self.predicted {
+            0 => 0.0,
+            total => 2.0 * self.matched as f64 / total as f64,
+        }
+    }
+}
+
+/// A small seeded generator, so that a search can be repeated.
+pub(crate) struct SplitMix(pub u64);
+
+impl SplitMix {
+    pub fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    pub fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
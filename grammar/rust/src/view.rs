@@ -0,0 +1,260 @@
+use std::borrow::Cow;
+
+use crate::dict::{EntryMeta, StringPool};
+use crate::frozen::FrozenDict;
+use crate::hashing::KeyMap;
+use crate::{TrieData, TriePattern};
+
+// -----------------------------------------------------------------------------
+// Dictionary Views
+// -----------------------------------------------------------------------------
+
+/// A borrowed dictionary entry, from either dictionary representation.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PatternRef<'a> {
+    pub pos: &'a str,
+    pub lemma: &'a str,
+    pub cost: Option<f64>,
+    pub meta: Option<MetaRef<'a>>,
+}
+
+/// Borrowed entry metadata.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct MetaRef<'a> {
+    pub source: Option<&'a str>,
+    pub priority: Option<u8>,
+    pub note: Option<&'a str>,
+}
+
+impl<'a> MetaRef<'a> {
+    pub fn to_owned(self) -> EntryMeta {
+        EntryMeta {
+            source: self.source.map(str::to_string),
+            priority: self.priority,
+            note: self.note.map(str::to_string),
+        }
+    }
+}
+
+impl TriePattern {
+    /// The entry with its strings looked up in `strings`, the pool of the
+    /// dictionary holding it.
+    pub(crate) fn as_ref<'a>(&'a self, strings: &'a StringPool) -> PatternRef<'a> {
+        PatternRef {
+            pos: strings.get(self.pos),
+            lemma: strings.get(self.lemma),
+            cost: self.cost,
+            meta: self.meta.as_deref().map(|m| MetaRef {
+                source: m.source.as_deref(),
+                priority: m.priority,
+                note: m.note.as_deref(),
+            }),
+        }
+    }
+}
+
+/// One dictionary in a view: an in-memory map or a compiled buffer.
+#[derive(Clone, Copy)]
+pub(crate) enum DictRef<'a> {
+    Map(&'a TrieData),
+    Frozen(&'a FrozenDict),
+}
+
+impl<'a> DictRef<'a> {
+    fn for_each(self, surface: &str, mut f: impl FnMut(PatternRef<'a>)) {
+        match self {
+            DictRef::Map(data) => {
+                if let Some(patterns) = data.dict.get(surface) {
+                    patterns.iter().for_each(|p| f(data.pattern(p)));
+                }
+            }
+            DictRef::Frozen(frozen) => {
+                if let Some(i) = frozen.find(surface) {
+                    frozen.for_each_pattern(i, f);
+                }
+            }
+        }
+    }
+
+    fn keys(self) -> Box<dyn Iterator<Item = Cow<'a, str>> + 'a> {
+        match self {
+            DictRef::Map(data) => Box::new(data.dict.keys().map(|k| Cow::Borrowed(k.as_str()))),
+            DictRef::Frozen(frozen) => Box::new(frozen.keys()),
+        }
+    }
+}
+
+/// A ban hides base entries of a word; `None` fields match anything.
+#[derive(Clone, Debug)]
+pub(crate) struct Ban {
+    pub pos: Option<String>,
+    pub lemma: Option<String>,
+}
+
+impl Ban {
+    pub fn matches(&self, pat: PatternRef) -> bool {
+        self.pos.as_ref().is_none_or(|p| *p == pat.pos)
+            && self.lemma.as_ref().is_none_or(|l| *l == pat.lemma)
+    }
+}
+
+/// Per-fork modifications layered over a shared base dictionary.
+#[derive(Clone, Default)]
+pub(crate) struct Overlay {
+    pub added: TrieData,
+    pub banned: KeyMap<String, Vec<Ban>>,
+}
+
+impl Overlay {
+    pub fn is_banned(&self, word: &str, pat: PatternRef) -> bool {
+        self.banned
+            .get(word)
+            .is_some_and(|bans| bans.iter().any(|b| b.matches(pat)))
+    }
+}
+
+/// Read-only lookup over a stack of dictionaries.
+///
+/// Dictionaries are consulted in order; when several hold the same
+/// (pos, lemma) for a surface, the later one wins, keeping the earlier
+/// metadata if it has none. Bans from the overlay hide entries of every
+/// dictionary except the overlay's own additions.
+pub(crate) struct DictView<'a> {
+    dicts: Vec<DictRef<'a>>,
+    overlay: Option<&'a Overlay>,
+}
+
+impl<'a> DictView<'a> {
+    pub fn new(dicts: Vec<DictRef<'a>>, overlay: Option<&'a Overlay>) -> Self {
+        DictView { dicts, overlay }
+    }
+
+    fn all_dicts(&self) -> impl Iterator<Item = (DictRef<'a>, bool)> + '_ {
+        let added = self.overlay.map(|o| (DictRef::Map(&o.added), true));
+        self.dicts.iter().map(|d| (*d, false)).chain(added)
+    }
+
+    /// Calls `f` with every visible entry for `surface`.
+    pub fn for_each(&self, surface: &str, mut f: impl FnMut(PatternRef<'a>)) {
+        // Fast path: a plain dictionary needs no merging
+        if self.dicts.len() == 1 && self.overlay.is_none() {
+            self.dicts[0].for_each(surface, f);
+            return;
+        }
+        self.lookup(surface).into_iter().for_each(&mut f);
+    }
+
+    pub fn lookup(&self, surface: &str) -> Vec<PatternRef<'a>> {
+        let mut found: Vec<PatternRef<'a>> = Vec::new();
+        for (dict, is_added) in self.all_dicts() {
+            dict.for_each(surface, |pat| {
+                if !is_added && self.overlay.is_some_and(|o| o.is_banned(surface, pat)) {
+                    return;
+                }
+                match found
+                    .iter_mut()
+                    .find(|p| p.pos == pat.pos && p.lemma == pat.lemma)
+                {
+                    // A later cost change keeps the entry's provenance
+                    Some(slot) => {
+                        *slot = PatternRef {
+                            meta: pat.meta.or(slot.meta),
+                            ..pat
+                        }
+                    }
+                    None => found.push(pat),
+                }
+            });
+        }
+        found
+    }
+
+    pub fn contains(&self, surface: &str) -> bool {
+        let mut any = false;
+        self.for_each(surface, |_| any = true);
+        any
+    }
+
+    /// Every key of every dictionary, unordered and possibly repeated or
+    /// hidden by a ban; cheaper than `surfaces` for building indexes.
+    pub fn keys(&self) -> impl Iterator<Item = Cow<'a, str>> + '_ {
+        self.all_dicts().flat_map(|(dict, _)| dict.keys())
+    }
+
+    /// Every visible surface, unordered.
+    pub fn surfaces(&self) -> Vec<Cow<'a, str>> {
+        // A plain dictionary's keys are unique and all visible
+        if self.dicts.len() == 1 && self.overlay.is_none() {
+            return self.dicts[0].keys().collect();
+        }
+        let mut seen = std::collections::HashSet::new();
+        let mut out = Vec::new();
+        for (dict, _) in self.all_dicts() {
+            for key in dict.keys() {
+                if seen.insert(key.clone()) && self.contains(&key) {
+                    out.push(key);
+                }
+            }
+        }
+        out
+    }
+
+    /// A 64-bit FNV-1a hash of the visible entries, stable across runs,
+    /// platforms and insertion order: surfaces and their patterns are fed
+    /// sorted, with every field length-prefixed or flagged.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash = Fnv64::default();
+        let mut surfaces = self.surfaces();
+        surfaces.sort_unstable();
+        for surface in surfaces {
+            let mut patterns = self.lookup(&surface);
+            patterns.sort_unstable_by(|a, b| (a.pos, a.lemma).cmp(&(b.pos, b.lemma)));
+            hash.str(&surface);
+            hash.write(&(patterns.len() as u64).to_le_bytes());
+            for p in patterns {
+                hash.str(p.pos);
+                hash.str(p.lemma);
+                hash.write(&p.cost.map_or(u64::MAX, f64::to_bits).to_le_bytes());
+                let meta = p.meta.unwrap_or_default();
+                for field in [meta.source, meta.note] {
+                    hash.write(&[field.is_some() as u8]);
+                    hash.str(field.unwrap_or_default());
+                }
+                hash.write(&[meta.priority.is_some() as u8, meta.priority.unwrap_or(0)]);
+            }
+        }
+        hash.0
+    }
+
+    /// Copies the visible entries into a standalone dictionary.
+    pub fn materialize(&self) -> TrieData {
+        let mut data = TrieData::default();
+        for surface in self.surfaces() {
+            self.lookup(&surface)
+                .into_iter()
+                .for_each(|p| data.push(&surface, p));
+        }
+        data
+    }
+}
+
+struct Fnv64(u64);
+
+impl Default for Fnv64 {
+    fn default() -> Self {
+        Fnv64(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv64 {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn str(&mut self, s: &str) {
+        self.write(&(s.len() as u64).to_le_bytes());
+        self.write(s.as_bytes());
+    }
+}
@@ -51,10 +51,19 @@ class RustTrieWrapper:
             except Exception as e:
                 print(f"Rust Trie load failed: {e}")
 
-    def insert(self, word: str, pos: str, lemma: str):
-        """단어 삽입"""
+    def insert(
+        self,
+        word: str,
+        pos: str,
+        lemma: str,
+        cost: Optional[float] = None,
+        source: Optional[str] = None,
+        priority: Optional[int] = None,
+        note: Optional[str] = None,
+    ):
+        """단어 삽입 (cost: 길이 기반 기본 비용 대신 사용할 비용, source/priority/note: 출처 메타데이터)"""
         if self.use_rust:
-            self.rust_trie.insert(word, pos, lemma)
+            self.rust_trie.insert(word, pos, lemma, cost, source, priority, note)
         else:
             if word not in self.py_dict:
                 self.py_dict[word] = []
@@ -129,6 +138,12 @@ class RustTrieWrapper:
                 return []
         return []
 
+    def analyze_batch(self, texts: List[str]) -> list:
+        """배치 분석: 실패한 항목은 BatchError(index, kind, message)로 반환"""
+        if self.use_rust:
+            return self.rust_trie.analyze_batch(texts)
+        return [[] for _ in texts]
+
     def search_batch(self, words: List[str]) -> List[List[Tuple[str, str]]]:
         """배치 검색"""
         if self.use_rust:
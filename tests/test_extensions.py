@@ -1,4 +1,6 @@
+import math
 import pytest
+import struct
 import sys
 
 
@@ -17,6 +19,710 @@ def test_rust_extension_availability():
         pytest.skip("Rust module import failed")
 
 
+def test_compiled_dictionary_shared_across_fork(tmp_path):
+    kulim_rust = pytest.importorskip("kulim_rust")
+    os = pytest.importorskip("os")
+    if not hasattr(os, "fork"):
+        pytest.skip("fork() not available")
+
+    trie = kulim_rust.RustTrie()
+    trie.insert("학교", "NNG", "학교")
+    trie.insert("에", "JKB", "에")
+    path = str(tmp_path / "dict.kfz")
+    kulim_rust.compile_trie(trie, path)
+
+    shared = kulim_rust.load_compiled(path)
+    assert shared.is_shared()
+    expected = shared.analyze("학교에")
+
+    read_fd, write_fd = os.pipe()
+    pid = os.fork()
+    if pid == 0:
+        # Child: use the inherited mapping, then report whether any of its
+        # pages became private (a copy-on-write copy).
+        status = 1
+        try:
+            ok = shared.analyze("학교에") == expected
+            dirty = 0
+            if os.path.exists("/proc/self/smaps"):
+                with open("/proc/self/smaps") as f:
+                    in_dict = False
+                    for line in f:
+                        if "-" in line.split(" ", 1)[0]:
+                            in_dict = line.rstrip().endswith(path)
+                        elif in_dict and line.startswith("Private_Dirty:"):
+                            dirty += int(line.split()[1])
+            os.write(write_fd, f"{int(ok)} {dirty}".encode())
+            status = 0
+        finally:
+            os._exit(status)
+
+    os.close(write_fd)
+    _, status = os.waitpid(pid, 0)
+    ok, dirty = os.read(read_fd, 64).decode().split()
+    os.close(read_fd)
+    assert status == 0
+    assert ok == "1"
+    assert dirty == "0"
+
+
+@pytest.mark.parametrize(
+    "text",
+    [
+        "학교에 갔다",
+        "  학교에\t갔다\n",
+        "학교\u200b에 갔다",
+        "ＡＢＣ 학교에 123 갔다!!",
+        "학교에갔다학교에갔다",
+        "",
+    ],
+)
+def test_surfaces_concatenate_to_input(text):
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    trie.insert("학교", "NNG", "학교")
+    trie.insert("에", "JKB", "에")
+    trie.insert("갔다", "VV+EF", "가+았다")
+    trie.set_sanitize()
+    trie.set_verify_round_trip()
+
+    morphs = trie.analyze(text)
+    assert "".join(m[0] for m in morphs) == text.replace("\u200b", "")
+    for analysis, _ in trie.analyze_nbest(text, 3):
+        assert "".join(m[0] for m in analysis) == text.replace("\u200b", "")
+    spans = trie.analyze_with_offsets(text)
+    assert [text[s:e] for *_, s, e in spans] == [m[0] for m in morphs]
+
+
+def test_readings_follow_pronunciation_rules():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    for word, pos in [("값", "NNG"), ("이", "JKS"), ("읽", "VV"), ("다", "EF"), ("KBS", "NNP")]:
+        trie.insert(word, pos, word)
+    trie.add_reading("KBS", "케이비에스")
+
+    def readings(text):
+        return [m[3] for m in trie.analyze_readings(text)]
+
+    assert readings("값이") == ["갑", "씨"]
+    assert readings("읽다") == ["익", "따"]
+    assert readings("KBS") == ["케이비에스"]
+
+
+def test_dumps_round_trips_through_buffers():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    pickle = pytest.importorskip("pickle")
+    trie = kulim_rust.RustTrie()
+    trie.insert("학교", "NNG", "학교")
+    trie.insert("에", "JKB", "에")
+    expected = trie.analyze("학교에")
+
+    data = kulim_rust.dumps(trie)
+    assert len(data) == kulim_rust.dumps_size(trie)
+    for source in (data, memoryview(data), bytearray(data)):
+        assert kulim_rust.loads(source).analyze("학교에") == expected
+
+    buffer = bytearray(len(data) + 8)
+    assert kulim_rust.dumps_into(trie, buffer) == len(data)
+    assert bytes(buffer[: len(data)]) == data
+    with pytest.raises(ValueError):
+        kulim_rust.dumps_into(trie, bytearray(4))
+    with pytest.raises(kulim_rust.DictFormatError):
+        kulim_rust.loads(data[:-1])
+
+    buffers = []
+    pickled = pickle.dumps(trie, protocol=5, buffer_callback=buffers.append)
+    assert len(buffers) == 1
+    assert pickle.loads(pickled, buffers=buffers).analyze("학교에") == expected
+    assert pickle.loads(pickle.dumps(trie)).analyze("학교에") == expected
+
+
+def test_gazetteer_entities_are_whole_morphemes(tmp_path):
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    for word, pos in [("서울", "NNG"), ("대학교", "NNG"), ("에", "JKB"), ("가", "JKS")]:
+        trie.insert(word, pos, word)
+    names = tmp_path / "names.tsv"
+    names.write_text("김철수\tPER\n서울대학교\tORG\n서울\n", encoding="utf-8")
+    assert trie.load_gazetteer(str(names), label="LOC") == 3
+
+    text = "김철수가 서울대학교에"
+    assert ("서울대학교", "NNP", "서울대학교") in trie.analyze(text)
+    assert trie.extract_entities(text) == [("김철수", "PER", 0, 3), ("서울대학교", "ORG", 5, 10)]
+    assert trie.extract_entities("서울에") == [("서울", "LOC", 0, 2)]
+
+
+def test_anonymize_replaces_names_and_structured_tokens():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    for word, pos in [("김철수", "NNP"), ("서울", "NNP"), ("는", "JX"), ("에", "JKB")]:
+        trie.insert(word, pos, word)
+
+    text = "김철수는 서울에 010-1234-5678"
+    redacted, replaced = trie.anonymize(text)
+    assert redacted == "[PER]는 서울에 [PHONE]"
+    assert replaced == [(0, 3, 0, 5, "PER"), (9, 22, 11, 18, "PHONE")]
+    for start, end, new_start, new_end, kind in replaced:
+        assert redacted[new_start:new_end] == f"[{kind}]"
+
+    trie.enable_structured_tokens()
+    assert ("900101-1******", "W_RRN", "900101-1******") in trie.analyze("900101-1******")
+
+
+def test_analyze_parallel_matches_sequential():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    for word, pos in [("학교", "NNG"), ("에", "JKB"), ("가", "VV"), ("았", "EP"), ("다", "EF"), (".", "SF")]:
+        trie.insert(word, pos, word)
+
+    text = "학교에 가았다. " * 50 + "학교에"
+    sequential = trie.analyze_parallel(text, workers=1)
+    assert trie.analyze_parallel(text, workers=4) == sequential
+    assert "".join(m[0] for m in sequential) == text
+    assert all(text[start:end] == surface for surface, _, _, start, end in sequential)
+
+
+def test_analyze_options_filter_tagset_and_offsets():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    for word, pos in [("학교", "NNG"), ("에", "JKB"), ("가", "VV"), ("다", "EF")]:
+        trie.insert(word, pos, word)
+
+    options = kulim_rust.AnalyzeOptions(pos_filter=["NN*", "V*"], tagset="ud", offsets=True)
+    assert trie.analyze("학교에 가다", options=options) == [
+        ("학교", "NOUN", "학교", 0, 2),
+        (" ", "NOUN", "UNKNOWN", 3, 4),
+        ("가", "VERB", "가", 4, 5),
+    ]
+    nbest = trie.analyze_nbest("학교에", options=kulim_rust.AnalyzeOptions(nbest=1))
+    assert [morphs for morphs, _ in nbest] == [[("학교", "NNG", "학교"), ("에", "JKB", "에")]]
+    with pytest.raises(ValueError):
+        kulim_rust.AnalyzeOptions(tagset="penn")
+
+
+def test_concurrent_access_from_threads():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    from concurrent.futures import ThreadPoolExecutor
+
+    trie = kulim_rust.RustTrie()
+    for word, pos in [("학교", "NNG"), ("에", "JKB"), ("가", "VV"), ("았", "EP"), ("다", "EF"), (".", "SF")]:
+        trie.insert(word, pos, word)
+
+    text = "학교에 가았다. " * 20
+    strict = kulim_rust.AnalyzeOptions(strict=True)
+    expected = (trie.analyze(text), trie.analyze(text, options=strict), trie.search_ids("학교"))
+
+    def work(i):
+        fork = trie.fork()
+        fork.insert(f"단어{i}", "NNG", f"단어{i}")
+        assert fork.analyze(f"단어{i}") == [(f"단어{i}", "NNG", f"단어{i}")]
+        results = []
+        for _ in range(20):
+            results.append((trie.analyze(text), trie.analyze(text, options=strict), trie.search_ids("학교")))
+            trie.get_symbols()
+        return results
+
+    with ThreadPoolExecutor(max_workers=8) as pool:
+        for results in pool.map(work, range(16)):
+            assert all(r == expected for r in results)
+    assert trie.search("단어0") == []
+
+
+def test_analyze_uncertain_flags_close_decisions():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    for word, pos, lemma in [("학교", "NNG", "학교"), ("에", "JKB", "에"), ("눈", "NNG", "눈(雪)"), ("눈", "NNG", "눈(目)")]:
+        trie.insert(word, pos, lemma)
+
+    flagged = trie.analyze_uncertain("학교에 눈")
+    assert [m[:5] for m in flagged] == trie.analyze_with_offsets("학교에 눈")
+    assert [m[5] for m in flagged] == [False, False, False, True]
+    assert not any(m[5] for m in trie.analyze_uncertain("학교에 눈", threshold=-1.0))
+
+
+def test_spaced_key_inserted_after_analysis_is_used():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    for word, pos in [("학교", "NNG"), ("앞", "NNG"), ("에", "JKB")]:
+        trie.insert(word, pos, word)
+    trie.analyze("학교 앞에")
+
+    trie.insert("학교 앞", "NNP", "학교 앞")
+    assert trie.analyze("학교 앞에") == [("학교 앞", "NNP", "학교 앞"), ("에", "JKB", "에")]
+    trie.insert_batch([("정문 앞", "NNP", "정문 앞")])
+    assert trie.analyze("정문 앞에")[0] == ("정문 앞", "NNP", "정문 앞")
+
+
+def test_chunked_analysis_joins_chunks_in_order():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    for word, pos in [("학교", "NNG"), ("에", "JKB"), ("가", "VV"), ("았", "EP"), ("다", "EF"), (".", "SF")]:
+        trie.insert(word, pos, word)
+
+    text = "학교에 가았다. " * 400
+    whole = trie.analyze(text)
+    trie.set_input_limits(chunk_chars=50)
+    assert trie.analyze(text) == whole
+    nbest = trie.analyze_nbest(text, n=3)
+    assert nbest[0][0] == whole
+    assert [cost for _, cost in nbest] == sorted(cost for _, cost in nbest)
+    assert all("".join(m[0] for m in morphs) == text for morphs, _ in nbest)
+
+
+def test_quoted_speech_runs_on_into_quotative():
+    kulim_rust = pytest.importorskip("kulim_rust")
+
+    def top_level(text):
+        return [text[start:end] for start, end, depth in kulim_rust.split_sentences(text) if depth == 0]
+
+    assert top_level('"가자." 라고 했다.') == ['"가자." 라고 했다.']
+    assert top_level("“정말요?” 하고 물었다.") == ["“정말요?” 하고 물었다."]
+    assert top_level('"네." "아니요."') == ['"네."', '"아니요."']
+    assert top_level("“고양이다.” 고양이가 왔다.") == ["“고양이다.”", "고양이가 왔다."]
+
+
+def test_tokenize_spans_match_analyze():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    for word, pos in [("학교", "NNG"), ("친구", "NNG"), ("를", "JKO"), ("에", "JKB"), ("만나", "VV"), ("다", "EF")]:
+        trie.insert(word, pos, word)
+
+    text = "학교에 친구를 만나다 학교친구를 만나다 " * 20
+    spans, at = [], 0
+    for surface, _, _ in trie.analyze(text):
+        spans.append((at, at + len(surface)))
+        at += len(surface)
+    assert trie.tokenize(text, keep_spaces=True) == spans
+
+
+def test_analyze_options_accepted_by_token_and_jsonl_apis():
+    import json
+
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    for word, pos in [("학교", "NNG"), ("에", "JKB"), ("가", "VV"), ("다", "EF")]:
+        trie.insert(word, pos, word)
+
+    options = kulim_rust.AnalyzeOptions(pos_filter=["NN*", "V*"], tagset="ud")
+    assert trie.tokenize("학교에 가다", options=options) == [(0, 2), (4, 5)]
+    assert trie.analyze_pretokenize("학교에 가다", options=options) == [("학교", (0, 6)), ("가", (10, 13))]
+    record = json.loads(trie.analyze_batch_jsonl(["학교에 가다"], options=options))
+    assert [(m["surface"], m["pos"]) for m in record["morphemes"]] == [("학교", "NOUN"), ("가", "VERB")]
+    flagged = trie.analyze_uncertain("학교에 가다", options=options)
+    assert [m[:2] for m in flagged if m[0] != " "] == [("학교", "NOUN"), ("가", "VERB")]
+
+
+def test_save_trie_to_rejects_streams_that_would_block():
+    import io
+
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    trie.insert("학교", "NNG", "학교")
+
+    class WouldBlock(io.RawIOBase):
+        def writable(self):
+            return True
+
+        def write(self, data):
+            return None
+
+    class Trickle(io.RawIOBase):
+        def __init__(self):
+            self.data = bytearray()
+
+        def writable(self):
+            return True
+
+        def write(self, data):
+            chunk = bytes(data[:3])
+            self.data += chunk
+            return len(chunk)
+
+    with pytest.raises(ValueError):
+        kulim_rust.save_trie_to(trie, WouldBlock())
+    stream = Trickle()
+    assert kulim_rust.save_trie_to(trie, stream) == len(stream.data)
+    assert kulim_rust.load_trie_from(io.BytesIO(bytes(stream.data))).search("학교") == [("NNG", "학교")]
+
+
+def test_nbest_probabilities_reject_bad_temperature():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    for word, pos in [("가", "VV"), ("가", "JKS"), ("다", "EF")]:
+        trie.insert(word, pos, word)
+
+    probs = [p for _, p in trie.analyze_nbest("가다", n=3, probabilities=True, temperature=2.0)]
+    assert abs(sum(probs) - 1.0) < 1e-9
+    for temperature in (0.0, -1.0, float("nan"), float("inf")):
+        with pytest.raises(ValueError):
+            trie.analyze_nbest("가다", probabilities=True, temperature=temperature)
+
+
+def test_analyze_batch_reports_dead_ends_per_item():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    trie.insert("학교", "NNG", "학교")
+    trie.enable_social_tokens()
+
+    # The combining mark after the hashtag starts no edge, so nothing continues the path
+    texts = ["학교", "#태그\u0301 학교", "학교"]
+    for options in (None, kulim_rust.AnalyzeOptions()):
+        results = trie.analyze_batch(texts, options=options)
+        assert results[0] == results[2] == [("학교", "NNG", "학교")]
+        assert (results[1].index, results[1].kind) == (1, "AnalysisError")
+        assert results[1].error.stage == "decode"
+
+
+def test_highlight_skips_unknown_markup_and_emoji():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    for word, pos in [("학교", "NNG"), ("에", "JKB")]:
+        trie.insert(word, pos, word)
+
+    html = trie.highlight("<b>학교</b>에 😀 서울", ["NNG"])
+    assert html == "&lt;b&gt;<mark>학교</mark>&lt;/b&gt;에 😀 <mark>서울</mark>"
+
+
+def test_saved_files_do_not_depend_on_insertion_order(tmp_path):
+    kulim_rust = pytest.importorskip("kulim_rust")
+    entries = [("가", "VV", "가다"), ("가", "JKS", "가"), ("가", "NNG", "가")]
+    entries += [(f"단어{i}", "NNG", f"단어{i}") for i in range(200)]
+    tries = []
+    for order in (entries, entries[::-1]):
+        trie = kulim_rust.RustTrie()
+        for word, pos, lemma in order:
+            trie.insert(word, pos, lemma)
+        tries.append(trie)
+    assert tries[0].fingerprint(False) == tries[1].fingerprint(False)
+
+    saves = [
+        kulim_rust.save_trie,
+        kulim_rust.compile_trie,
+        lambda trie, path: kulim_rust.compile_trie(trie, path, "small"),
+    ]
+    for k, save in enumerate(saves):
+        paths = [tmp_path / f"{k}-{i}.bin" for i in range(2)]
+        for trie, path in zip(tries, paths):
+            save(trie, str(path))
+        assert paths[0].read_bytes() == paths[1].read_bytes()
+
+
+def test_coverage_counts_gazetteer_names_as_known(tmp_path):
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    for word, pos in [("가", "JKS"), ("학교", "NNG"), ("에", "JKB")]:
+        trie.insert(word, pos, word)
+    names = tmp_path / "names.txt"
+    names.write_text("김철수\n", encoding="utf-8")
+    trie.load_gazetteer(str(names), label="PER")
+    corpus = tmp_path / "corpus.txt"
+    corpus.write_text("김철수가 학교에 뭐\n", encoding="utf-8")
+
+    report = trie.coverage(str(corpus))
+    assert (report["tokens"], report["entities"], report["unknown"]) == (5, 1, 1)
+    assert report["top_unknown"] == [("뭐", 1)]
+
+
+def test_accented_latin_words_stay_whole():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    trie.insert("에서", "JKB", "에서")
+
+    for word in ["naïve", "café", "cafe\u0301", "Tiếng", "Ørsted"]:
+        assert trie.analyze(word) == [(word, "SL", word)]
+    assert trie.analyze("café에서") == [("café", "SL", "café"), ("에서", "JKB", "에서")]
+    assert [m[0] for m in trie.analyze("2×3")] == ["2", "×", "3"]
+
+
+def test_rescore_prepares_text_like_analyze():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    trie.insert("학교", "NNG", "학교")
+    trie.insert("에", "JKB", "에")
+    trie.set_sanitize()
+    text = "학교\u200b에"
+
+    def scorer(candidates):
+        return [0.0] * len(candidates)
+
+    best, _ = trie.rescore(text, scorer, n=3)[0]
+    assert best == trie.analyze(text)
+    assert [m[0] for m in best] == ["학교", "에"]
+
+
+def test_segmentations_and_lattice_dot_prepare_text_like_analyze():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    trie.insert("학교", "NNG", "학교")
+    trie.insert("에", "JKB", "에")
+    trie.set_sanitize()
+    text = "학교\u200b에"
+
+    segmentations = list(trie.segmentations(text))
+    assert segmentations
+    assert all("".join(m[0] for m in s) == "학교에" for s in segmentations)
+    assert "\u200b" not in trie.lattice_dot(text)
+
+    trie.set_input_limits(max_chars=2)
+    with pytest.raises(kulim_rust.InputTooLongError):
+        trie.segmentations(text)
+    with pytest.raises(kulim_rust.InputTooLongError):
+        trie.lattice_dot(text)
+
+
+def test_record_correction_prepares_text_like_analyze():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    trie.insert("학교", "NNG", "학교")
+    trie.insert("에", "JKB", "에")
+    trie.set_sanitize()
+
+    gold = [("학교", "NNG", "학교"), ("에", "JKB", "에")]
+    assert trie.record_correction("학교\u200b에", gold)
+
+
+def test_detached_layer_keys_no_longer_match():
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie = kulim_rust.RustTrie()
+    trie.insert("에", "JKB", "에")
+    layer = kulim_rust.RustTrie()
+    layer.insert("iPhone", "NNP", "iPhone")
+    layer.insert("뉴 욕", "NNP", "뉴욕")
+    trie.attach_layer("brands", layer)
+    trie.set_case_folding()
+    assert trie.analyze("IPHONE에")[0] == ("IPHONE", "NNP", "iPhone")
+    assert trie.analyze("뉴 욕에")[0] == ("뉴 욕", "NNP", "뉴욕")
+
+    trie.detach_layer("brands")
+    assert trie.analyze("IPHONE에")[0][2] != "iPhone"
+    assert all(m[2] != "뉴욕" for m in trie.analyze("뉴 욕에"))
+    trie.insert("Galaxy", "NNP", "Galaxy")
+    assert trie.analyze("GALAXY에")[0] == ("GALAXY", "NNP", "Galaxy")
+
+
+ARROW_COLUMNS = [("doc", 2), ("surface", 5), ("pos", 5), ("lemma", 5), ("start", 2), ("end", 2)]
+
+
+def _arrow_corpus(kulim_rust, tmp_path):
+    trie = kulim_rust.RustTrie()
+    for word, pos in [("학교", "NNG"), ("에", "JKB"), ("서울", "NNP"), ("갔다", "VV+EF")]:
+        trie.insert(word, pos, word)
+    texts = ["학교에 갔다", "서울에", "서울 학교에 갔다"]
+    path = tmp_path / "out.arrow"
+    with kulim_rust.ArrowResultWriter(str(path), batch_rows=2) as writer:
+        rows = writer.write(trie, texts)
+    expected = [
+        (doc, *m)
+        for doc, text in enumerate(texts)
+        for m in trie.analyze_with_offsets(text)
+        if not m[0].isspace()
+    ]
+    assert rows == len(expected)
+    return path, expected
+
+
+def _fb_field(buf, table, index):
+    """The position of field `index` of the flatbuffer table at `table`."""
+    vtable = table - struct.unpack_from("<i", buf, table)[0]
+    if 4 + 2 * index >= struct.unpack_from("<H", buf, vtable)[0]:
+        return None
+    offset = struct.unpack_from("<H", buf, vtable + 4 + 2 * index)[0]
+    return table + offset if offset else None
+
+
+def _fb_deref(buf, at):
+    return at + struct.unpack_from("<I", buf, at)[0]
+
+
+def _fb_vector(buf, at):
+    start = _fb_deref(buf, at)
+    return start + 4, struct.unpack_from("<I", buf, start)[0]
+
+
+def test_arrow_writer_file_layout(tmp_path):
+    kulim_rust = pytest.importorskip("kulim_rust")
+    path, expected = _arrow_corpus(kulim_rust, tmp_path)
+    data = path.read_bytes()
+    assert data[:8] == b"ARROW1\0\0" and data[-6:] == b"ARROW1"
+    (footer_len,) = struct.unpack_from("<i", data, len(data) - 10)
+    footer_at = len(data) - 10 - footer_len
+
+    pos, schema, batches = 8, None, []
+    while True:
+        marker, meta_len = struct.unpack_from("<Ii", data, pos)
+        assert marker == 0xFFFFFFFF and meta_len % 8 == 0
+        if meta_len == 0:
+            pos += 8
+            break
+        meta = data[pos + 8 : pos + 8 + meta_len]
+        message = _fb_deref(meta, 0)
+        header_type = meta[_fb_field(meta, message, 1)]
+        body_len = struct.unpack_from("<q", meta, _fb_field(meta, message, 3))[0]
+        header = _fb_deref(meta, _fb_field(meta, message, 2))
+        if header_type == 1:
+            assert schema is None and not batches and body_len == 0
+            fields, count = _fb_vector(meta, _fb_field(meta, header, 1))
+            schema = []
+            for i in range(count):
+                field = _fb_deref(meta, fields + 4 * i)
+                name, length = _fb_vector(meta, _fb_field(meta, field, 0))
+                type_id = meta[_fb_field(meta, field, 2)]
+                schema.append((meta[name : name + length].decode(), type_id))
+        else:
+            assert header_type == 3
+            (length,) = struct.unpack_from("<q", meta, _fb_field(meta, header, 0))
+            batches.append((pos, 8 + meta_len, body_len, length))
+        pos += 8 + meta_len + body_len
+    assert pos == footer_at
+    assert schema == ARROW_COLUMNS
+    assert [b[3] for b in batches] == [2] * (len(expected) // 2) + [1] * (len(expected) % 2)
+
+    footer = data[footer_at : footer_at + footer_len]
+    blocks, count = _fb_vector(footer, _fb_field(footer, _fb_deref(footer, 0), 3))
+    assert [
+        struct.unpack_from("<qi4xq", footer, blocks + 24 * i) for i in range(count)
+    ] == [b[:3] for b in batches]
+
+
+def test_arrow_writer_reads_back_with_pyarrow(tmp_path):
+    kulim_rust = pytest.importorskip("kulim_rust")
+    pa = pytest.importorskip("pyarrow")
+    ipc = pytest.importorskip("pyarrow.ipc")
+    path, expected = _arrow_corpus(kulim_rust, tmp_path)
+
+    with pa.memory_map(str(path)) as source:
+        reader = ipc.open_file(source)
+        assert reader.num_record_batches == math.ceil(len(expected) / 2)
+        table = reader.read_all()
+    names = [name for name, _ in ARROW_COLUMNS]
+    assert table.schema.names == names
+    for name, type_id in ARROW_COLUMNS:
+        assert table.schema.field(name).type == (pa.int64() if type_id == 2 else pa.string())
+        assert not table.schema.field(name).nullable
+    assert list(zip(*(table.column(name).to_pylist() for name in names))) == expected
+
+
+PARQUET_COLUMNS = ["doc_id", "sentence_id", "surface", "pos", "lemma", "start", "end"]
+
+
+def _parquet_corpus(kulim_rust, tmp_path):
+    trie = kulim_rust.RustTrie()
+    for word, pos in [("학교", "NNG"), ("에", "JKB"), ("서울", "NNP"), ("갔다", "VV+EF")]:
+        trie.insert(word, pos, word)
+    corpus = tmp_path / "corpus"
+    corpus.mkdir()
+    files = {"a.txt": ["학교에 갔다", "", "서울에"], "b.txt": ["서울 학교에 갔다"]}
+    expected = {}
+    for name, lines in files.items():
+        (corpus / name).write_text("\n".join(lines) + "\n", encoding="utf-8")
+        expected[name] = [
+            (doc_id, 0, *m)
+            for doc_id, line in enumerate(lines, 1)
+            for m in (trie.analyze_with_offsets(line) if line else [])
+            if not m[0].isspace()
+        ]
+    out = tmp_path / "out"
+    counts = trie.analyze_corpus_to_parquet(str(corpus / "*.txt"), str(out), row_group_rows=2)
+    assert counts == (3, sum(len(rows) for rows in expected.values()), 0)
+    return out, expected
+
+
+def test_corpus_parquet_file_layout(tmp_path):
+    kulim_rust = pytest.importorskip("kulim_rust")
+    out, expected = _parquet_corpus(kulim_rust, tmp_path)
+
+    assert sorted(p.name for p in out.iterdir()) == ["source=a.txt", "source=b.txt"]
+    for name in expected:
+        files = list((out / f"source={name}").iterdir())
+        assert [p.name for p in files] == ["part-00000.parquet"]
+        data = files[0].read_bytes()
+        assert data[:4] == b"PAR1" and data[-4:] == b"PAR1"
+        (footer_len,) = struct.unpack_from("<I", data, len(data) - 8)
+        assert 0 < footer_len <= len(data) - 12
+        footer = data[len(data) - 8 - footer_len : len(data) - 8]
+        assert all(column.encode() in footer for column in PARQUET_COLUMNS)
+        assert b"kulim version" in footer
+
+
+def test_corpus_parquet_reads_back_with_pyarrow(tmp_path):
+    kulim_rust = pytest.importorskip("kulim_rust")
+    pq = pytest.importorskip("pyarrow.parquet")
+    out, expected = _parquet_corpus(kulim_rust, tmp_path)
+
+    for name, rows in expected.items():
+        path = out / f"source={name}" / "part-00000.parquet"
+        assert pq.ParquetFile(str(path)).metadata.num_row_groups == math.ceil(len(rows) / 2)
+        table = pq.read_table(str(path), partitioning=None)
+        assert table.schema.names == PARQUET_COLUMNS
+        assert list(zip(*(table.column(c).to_pylist() for c in PARQUET_COLUMNS))) == rows
+
+
+def _frozen_source(kulim_rust):
+    trie = kulim_rust.RustTrie()
+    keys = ["iPhone"]
+    for first in "가나다라마바사":
+        keys.append(first)
+        for second in "고기는다도라리를마면서아야에요으이자지하":
+            keys += [first + second, first + second + "다"]
+    for i, key in enumerate(keys):
+        trie.insert(key, "NNG", key)
+        if key.endswith("다"):
+            trie.insert(key, "VV", key, 2.5)
+        if i % 3 == 0:
+            trie.insert(key, "NNP", key + "씨", float(i % 7))
+    return trie, keys
+
+
+@pytest.mark.parametrize("lazy", [False, True])
+@pytest.mark.parametrize("profile", ["full", "small"])
+def test_compiled_dictionary_matches_its_source(tmp_path, profile, lazy):
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie, keys = _frozen_source(kulim_rust)
+    path = str(tmp_path / "dict.kfz")
+    kulim_rust.compile_trie(trie, path, profile)
+    compiled = kulim_rust.load_compiled(path, lazy=lazy)
+
+    def found(t, text):
+        return [(s, n, sorted(patterns)) for s, n, patterns in t.search_all_patterns(text)]
+
+    absent = ["없는말", "하", "가고다다", "iphone", "zz", "가\u0301", "사요요"]
+    for key in keys + absent:
+        assert sorted(compiled.search(key)) == sorted(trie.search(key))
+        assert found(compiled, key) == found(trie, key)
+    for key in absent:
+        assert compiled.search(key) == []
+    text = " ".join(keys[::5] + absent)
+    assert found(compiled, text) == found(trie, text)
+
+
+def test_load_compiled_rejects_corrupt_files(tmp_path):
+    kulim_rust = pytest.importorskip("kulim_rust")
+    trie, _ = _frozen_source(kulim_rust)
+    path = tmp_path / "dict.kfz"
+    kulim_rust.compile_trie(trie, str(path))
+    data = path.read_bytes()
+    broken = tmp_path / "broken.kfz"
+
+    for bad in [b"", data[:20], data[:60], data[: len(data) // 2], data[:-1], b"X" + data[1:]]:
+        broken.write_bytes(bad)
+        for lazy in (False, True):
+            with pytest.raises(ValueError):
+                kulim_rust.load_compiled(str(broken), lazy=lazy)
+
+    # Overwrite the first pattern records, in the iPhone and 가 buckets
+    header = struct.unpack_from("<8sIIIIIIIIIffI", data)
+    keys, buckets, groups, blocks = header[2], header[6], header[8], header[12]
+    patterns_at = 56 + 8 * (buckets + groups + blocks + 1) + 4 * (2 * keys + 1)
+    corrupt = bytearray(data)
+    corrupt[patterns_at : patterns_at + 16 * 4] = b"\xff" * 64
+    broken.write_bytes(bytes(corrupt))
+    with pytest.raises(ValueError):
+        kulim_rust.load_compiled(str(broken))
+    lazy = kulim_rust.load_compiled(str(broken), lazy=True)
+    assert lazy.search("iPhone") == [] and lazy.search("가고") == []
+    assert sorted(lazy.search("나고")) == sorted(trie.search("나고"))
+
+
 def test_gpu_extension_availability():
     try:
         from grammar.gpu import get_gpu_info